@@ -3,6 +3,7 @@
 //! command appears in the source.
 
 use autotools_parser::ast;
+use autotools_parser::ast::visit::top_level::{walk_simple_command, Visit};
 use autotools_parser::lexer::Lexer;
 use autotools_parser::parse::autoconf::DefaultParser;
 
@@ -16,112 +17,52 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let lex = Lexer::new(input.chars());
     let parser = DefaultParser::new(lex);
 
-    let mut num_echo = 0usize;
+    let mut counter = EchoCounter(0);
     for cmd in parser {
-        num_echo += count_echo_top_level(&cmd?);
+        counter.visit_command(&cmd?);
     }
 
-    println!("total number of echo commands: {}", num_echo);
+    println!("total number of echo commands: {}", counter.0);
     Ok(())
 }
 
-fn count_echo_top_level_array(cmds: &[ast::TopLevelCommand<String>]) -> usize {
-    cmds.iter().map(count_echo_top_level).sum()
-}
-
-fn count_echo_top_level(cmd: &ast::TopLevelCommand<String>) -> usize {
-    match &cmd.0 {
-        ast::Command::Job(list) | ast::Command::List(list) => std::iter::once(&list.first)
-            .chain(list.rest.iter().map(|and_or| match and_or {
-                ast::AndOr::And(cmd) | ast::AndOr::Or(cmd) => cmd,
-            }))
-            .map(|cmd| count_echo_listable(cmd))
-            .sum(),
-    }
-}
-
-fn count_echo_listable(cmd: &ast::DefaultListableCommand) -> usize {
-    match cmd {
-        ast::ListableCommand::Single(cmd) => count_echo_pipeable(cmd),
-        ast::ListableCommand::Pipe(_, cmds) => cmds.iter().map(count_echo_pipeable).sum(),
-    }
-}
+/// Counts the simple commands whose name is "echo". The default walk
+/// reaches the commands nested in compound commands, command
+/// substitutions and macro arguments.
+struct EchoCounter(usize);
 
-fn count_echo_pipeable(cmd: &ast::DefaultPipeableCommand) -> usize {
-    match cmd {
-        ast::PipeableCommand::Simple(cmd) => count_echo_simple(cmd),
-        ast::PipeableCommand::Compound(cmd) => count_echo_compound(cmd),
-        ast::PipeableCommand::FunctionDef(_, cmd) => count_echo_compound(cmd),
+impl<'a> Visit<'a, String> for EchoCounter {
+    fn visit_simple_command(&mut self, cmd: &'a ast::DefaultSimpleCommand) {
+        let name = cmd
+            .redirects_or_cmd_words
+            .iter()
+            .find_map(|redirect_or_word| match redirect_or_word {
+                ast::RedirectOrCmdWord::CmdWord(w) => Some(&w.0),
+                ast::RedirectOrCmdWord::Redirect(_) => None,
+            });
+        if name
+            .and_then(get_word_as_string)
+            .is_some_and(|w| w == "echo")
+        {
+            self.0 += 1;
+        }
+        walk_simple_command(self, cmd);
     }
 }
 
-fn count_echo_compound(cmd: &ast::DefaultCompoundCommand) -> usize {
-    match &cmd.kind {
-        ast::CompoundCommandKind::Brace(cmds) | ast::CompoundCommandKind::Subshell(cmds) => {
-            count_echo_top_level_array(cmds)
-        }
-
-        ast::CompoundCommandKind::While(lp) | ast::CompoundCommandKind::Until(lp) => {
-            count_echo_top_level_array(&lp.guard) + count_echo_top_level_array(&lp.body)
-        }
-
-        ast::CompoundCommandKind::If {
-            conditionals,
-            else_branch,
-        } => {
-            let num_echo_in_conditionals = conditionals
-                .iter()
-                .map(|gbp| {
-                    count_echo_top_level_array(&gbp.guard) + count_echo_top_level_array(&gbp.body)
-                })
-                .sum::<usize>();
-
-            let num_echo_in_else = else_branch
-                .as_ref()
-                .map(|cmds| count_echo_top_level_array(cmds))
-                .unwrap_or(0);
-
-            num_echo_in_conditionals + num_echo_in_else
+fn get_word_as_string(word: &ast::DefaultComplexWord) -> Option<&String> {
+    match word {
+        ast::ComplexWord::Single(ast::Word::SingleQuoted(w)) => Some(w),
+        ast::ComplexWord::Single(ast::Word::Simple(w)) => get_simple_word_as_string(w),
+        ast::ComplexWord::Single(ast::Word::DoubleQuoted(words)) if words.len() == 1 => {
+            get_simple_word_as_string(&words[0])
         }
-
-        ast::CompoundCommandKind::For { body, .. } => count_echo_top_level_array(body),
-
-        ast::CompoundCommandKind::Case { arms, .. } => arms
-            .iter()
-            .map(|pat| count_echo_top_level_array(&pat.body))
-            .sum(),
-
-        // @kui8shi
-        // macro calls skipped
-        ast::CompoundCommandKind::Macro(_) => 0,
+        // We're going to ignore concatenated and multi-word double quoted
+        // strings for simplicity here
+        _ => None,
     }
 }
 
-fn count_echo_simple(cmd: &ast::DefaultSimpleCommand) -> usize {
-    cmd.redirects_or_cmd_words
-        .iter()
-        .filter_map(|redirect_or_word| match redirect_or_word {
-            ast::RedirectOrCmdWord::CmdWord(w) => Some(&w.0),
-            ast::RedirectOrCmdWord::Redirect(_) => None,
-        })
-        .filter_map(|word| match word {
-            ast::ComplexWord::Single(w) => Some(w),
-            // We're going to ignore concatenated words for simplicity here
-            ast::ComplexWord::Concat(_) => None,
-        })
-        .filter_map(|word| match word {
-            ast::Word::SingleQuoted(w) => Some(w),
-            ast::Word::Simple(w) => get_simple_word_as_string(w),
-
-            ast::Word::DoubleQuoted(words) if words.len() == 1 => {
-                get_simple_word_as_string(&words[0])
-            }
-            ast::Word::DoubleQuoted(_) => None, // Ignore all multi-word double quoted strings
-        })
-        .filter(|w| *w == "echo")
-        .count()
-}
-
 fn get_simple_word_as_string(word: &ast::DefaultWordFragment) -> Option<&String> {
     match word {
         ast::MayM4::Shell(ast::SimpleWord::Literal(w)) => Some(w),
@@ -0,0 +1,247 @@
+//! Analyses built on top of parsed autotools sources.
+//!
+//! Automake evaluates most of its inputs under automake conditionals
+//! (`if COND ... else ... endif`). The analyses keep values apart by
+//! the set of conditionals they depend on, represented as a `CondSet`.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::ast::am::{AmAssignment, AmLine, AmRule, AmWord, MakeCondition};
+use crate::ast::node::NodeId;
+use crate::project::AutomakeFile;
+
+pub mod compdb;
+pub mod dist;
+pub mod flags;
+pub mod functions;
+pub mod install;
+pub mod rules;
+pub mod targets;
+pub mod variables;
+
+/// A conjunction of automake conditionals, e.g. `ENABLE_X && !WIN32`.
+///
+/// The empty set holds unconditionally.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub struct CondSet(BTreeMap<String, bool>);
+
+impl CondSet {
+    /// The condition which always holds.
+    pub fn always() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether the condition always holds.
+    pub fn is_always(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the conjunction of `self` and `var == value`,
+    /// or `None` if they contradict each other.
+    pub fn with(&self, var: &str, value: bool) -> Option<Self> {
+        match self.0.get(var) {
+            Some(v) if *v != value => None,
+            _ => {
+                let mut ret = self.clone();
+                ret.0.insert(var.to_string(), value);
+                Some(ret)
+            }
+        }
+    }
+
+    /// Returns the conjunction of two sets, or `None` if they contradict each other.
+    pub fn and(&self, other: &Self) -> Option<Self> {
+        other
+            .iter()
+            .try_fold(self.clone(), |acc, (var, value)| acc.with(var, value))
+    }
+
+    /// Returns whether `self` holds whenever `other` holds.
+    pub fn is_implied_by(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .all(|(var, value)| other.0.get(var) == Some(value))
+    }
+
+    /// Returns whether both sets can hold at the same time.
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.and(other).is_some()
+    }
+
+    /// Returns mutually exclusive sets which together hold exactly when `self` does not.
+    ///
+    /// For `X_TRUE Y_TRUE`, these are `X_FALSE` and `X_TRUE Y_FALSE`.
+    pub fn complement(&self) -> Vec<Self> {
+        let mut prefix = Self::always();
+        let mut ret = Vec::new();
+        for (var, value) in self.iter() {
+            ret.extend(prefix.with(var, !value));
+            prefix = prefix.with(var, value).unwrap();
+        }
+        ret
+    }
+
+    /// Iterate over the conditionals with their expected values.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.0.iter().map(|(var, value)| (var.as_str(), *value))
+    }
+
+    /// Returns the expected value of the conditional if the set depends on it.
+    pub fn get(&self, var: &str) -> Option<bool> {
+        self.0.get(var).copied()
+    }
+}
+
+/// Formats the set as automake does, e.g. `ENABLE_X_TRUE WIN32_FALSE`, or `TRUE`.
+impl fmt::Display for CondSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_always() {
+            return write!(f, "TRUE");
+        }
+        let terms = self
+            .iter()
+            .map(|(var, value)| format!("{}_{}", var, if value { "TRUE" } else { "FALSE" }))
+            .collect::<Vec<_>>();
+        write!(f, "{}", terms.join(" "))
+    }
+}
+
+/// A word which is present only under a set of automake conditionals.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CondWord {
+    /// The conditionals the word depends on.
+    pub cond: CondSet,
+    /// The word itself.
+    pub word: AmWord,
+}
+
+/// Walk the statements of a makefile in the order of appearance, calling `f`
+/// with the conditionals enclosing each of them. Statements in resolved
+/// include fragments appear in place of the include directive.
+fn walk_lines<'a, F: FnMut(&CondSet, &'a AmLine)>(
+    am: &'a AutomakeFile,
+    ids: &[NodeId],
+    cond: &CondSet,
+    f: &mut F,
+) {
+    for id in ids {
+        let line = &am.nodes[*id].cmd;
+        f(cond, line);
+        match line {
+            AmLine::Conditional(c) => {
+                if let Some(then) = cond.with(&c.guard_var, true) {
+                    walk_lines(am, &c.then, &then, f);
+                }
+                if let Some(otherwise) = cond.with(&c.guard_var, false) {
+                    walk_lines(am, &c.otherwise, &otherwise, f);
+                }
+            }
+            // Like automake, GNU make conditionals are not interpreted: their
+            // test stands for a conditional which may hold or not.
+            AmLine::MakeConditional(c) => {
+                let (var, value) = make_condition_term(&c.condition);
+                if let Some(then) = cond.with(&var, value) {
+                    walk_lines(am, &c.then, &then, f);
+                }
+                if let Some(otherwise) = cond.with(&var, !value) {
+                    walk_lines(am, &c.otherwise, &otherwise, f);
+                }
+            }
+            AmLine::Override(id) => walk_lines(am, &[*id], cond, f),
+            // A conditional following a rule is parsed as part of its recipe,
+            // although it may hold assignments and rules.
+            AmLine::Rule(rule) => walk_lines(am, &rule.recipe, cond, f),
+            AmLine::Include(include) => {
+                if let Some(included) = &include.included {
+                    walk_lines(am, &included.body, cond, f);
+                }
+            }
+            AmLine::Export(export) => {
+                if let Some(id) = export.assignment {
+                    walk_lines(am, &[id], cond, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns whether a conditional of a `CondSet` stands for the test of a GNU
+/// make conditional, e.g. `ifeq(a,b)` or `ifdef(VAR)`, rather than for an
+/// automake conditional. Such tests are not evaluated, and their names can
+/// not clash with those of automake conditionals.
+pub fn is_make_test(name: &str) -> bool {
+    name.starts_with("ifeq(") || name.starts_with("ifdef(")
+}
+
+/// The conditional standing for the test of a GNU make conditional, e.g.
+/// `ifeq(a,b)` or `ifdef(VAR)`, with the value under which its first branch
+/// is read: `ifneq (a,b)` reads it when `ifeq(a,b)` is false.
+fn make_condition_term(cond: &MakeCondition<AmWord>) -> (String, bool) {
+    let words = |words: &[AmWord]| {
+        words
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    match cond {
+        MakeCondition::Eq(a, b) => (format!("ifeq({},{})", words(a), words(b)), true),
+        MakeCondition::Neq(a, b) => (format!("ifeq({},{})", words(a), words(b)), false),
+        MakeCondition::Defined(var) => (format!("ifdef({})", var), true),
+        MakeCondition::NotDefined(var) => (format!("ifdef({})", var), false),
+    }
+}
+
+/// Collect all assignments of a makefile in the order of appearance,
+/// paired with the conditionals enclosing them. Assignments in resolved
+/// include fragments appear in place of the include directive.
+pub fn conditional_assignments(am: &AutomakeFile) -> Vec<(CondSet, &AmAssignment)> {
+    let mut ret = Vec::new();
+    walk_lines(am, &am.top_ids, &CondSet::always(), &mut |cond, line| {
+        if let AmLine::Assignment(assignment) = line {
+            ret.push((cond.clone(), assignment));
+        }
+    });
+    ret
+}
+
+/// Collect all rules of a makefile in the order of appearance, paired with
+/// the conditionals enclosing them. Rules in resolved include fragments
+/// appear in place of the include directive.
+pub fn conditional_rules(am: &AutomakeFile) -> Vec<(CondSet, &AmRule)> {
+    let mut ret = Vec::new();
+    walk_lines(am, &am.top_ids, &CondSet::always(), &mut |cond, line| {
+        if let AmLine::Rule(rule) = line {
+            ret.push((cond.clone(), rule));
+        }
+    });
+    ret
+}
+
+/// Collect the words assigned to `var`, keeping the conditionals they depend on.
+///
+/// `+=` appends to the current value, while `=`, `:=` and `!=` discard
+/// the words the new assignment overrides. `?=` is ignored once the
+/// variable has been assigned under any conditionals.
+pub fn conditional_words(am: &AutomakeFile, var: &str) -> Option<Vec<CondWord>> {
+    use crate::ast::am::AmAssignOp::*;
+    let mut ret: Option<Vec<CondWord>> = None;
+    for (cond, assignment) in conditional_assignments(am) {
+        if assignment.lhs != var {
+            continue;
+        }
+        if assignment.op == IfUndefined && ret.is_some() {
+            continue;
+        }
+        let words = ret.get_or_insert_with(Vec::new);
+        if matches!(assignment.op, Lazy | Instant | Shell | IfUndefined) {
+            words.retain(|w| !cond.is_implied_by(&w.cond));
+        }
+        words.extend(assignment.rhs.iter().map(|word| CondWord {
+            cond: cond.clone(),
+            word: word.clone(),
+        }));
+    }
+    ret
+}
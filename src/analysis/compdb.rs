@@ -0,0 +1,310 @@
+//! Compilation databases (`compile_commands.json`) for projects which were
+//! never configured nor built.
+//!
+//! Every program and library of the project contributes the compile commands
+//! of `flags`, expanded with the values `configure` would substitute. Values
+//! which follow from `configure.ac` are derived: the directory variables,
+//! `DEFS` and `DEFAULT_INCLUDES` with a config header from
+//! `AC_CONFIG_HEADERS`, and the literal values of `AC_SUBST`. Others, such as
+//! `CC` or `CFLAGS`, must be given in `CompdbOptions::outputs`. Whatever is
+//! still unknown is kept as written in the arguments and reported, as are the
+//! automake conditionals no value was chosen for.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::project::{AutoconfFile, FileId, Project};
+use crate::util::{normalize, references, top_of};
+
+use super::flags::{BuildFlags, ConfigureOutputs};
+use super::is_make_test;
+use super::targets::collect_targets;
+
+/// The configuration to generate a compilation database for.
+#[derive(Debug, Default, Clone)]
+pub struct CompdbOptions {
+    /// Values of the automake conditionals.
+    pub conds: BTreeMap<String, bool>,
+    /// Values substituted by `configure`, e.g. `CC` or `CFLAGS`. They take
+    /// precedence over the values derived from `configure.ac`.
+    pub outputs: ConfigureOutputs,
+    /// The absolute path of the build directory, or `None` to build in the
+    /// source tree.
+    pub build_dir: Option<PathBuf>,
+}
+
+/// An entry of `compile_commands.json`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CompileEntry {
+    /// The directory the command runs in.
+    pub directory: PathBuf,
+    /// The absolute path of the compiled source.
+    pub file: PathBuf,
+    /// The command line, starting with the compiler.
+    pub arguments: Vec<String>,
+    /// The object file, relative to `directory`.
+    pub output: PathBuf,
+}
+
+/// A value the compilation database could not be completed without.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum UnknownValue {
+    /// A variable with no value, e.g. `CC` when the compiler was not given.
+    Variable {
+        /// The `Makefile.am` referencing the variable.
+        makefile: PathBuf,
+        /// The name of the variable.
+        name: String,
+    },
+    /// An automake conditional without a chosen value. The sources depending
+    /// on it are left out.
+    Conditional {
+        /// The `Makefile.am` depending on the conditional.
+        makefile: PathBuf,
+        /// The name of the conditional.
+        name: String,
+    },
+    /// A GNU make conditional, whose test is not evaluated, without a chosen
+    /// value. The sources depending on it are left out.
+    MakeConditional {
+        /// The `Makefile.am` depending on the conditional.
+        makefile: PathBuf,
+        /// The test standing for the conditional, e.g. `ifdef(DEBUG)`.
+        test: String,
+    },
+}
+
+/// The compile commands of a project.
+#[derive(Debug, Default, Clone)]
+pub struct CompilationDatabase {
+    /// The entries, in the order of the makefiles, targets and sources.
+    pub entries: Vec<CompileEntry>,
+    /// The values which are missing, in the order they were found.
+    pub unknown: Vec<UnknownValue>,
+}
+
+impl CompilationDatabase {
+    /// Compute the compile commands of every makefile of a project.
+    pub fn new(project: &Project, options: &CompdbOptions) -> Self {
+        let root = std::path::absolute(project.root()).unwrap_or_else(|_| project.root().into());
+        let build_root = options.build_dir.clone().unwrap_or_else(|| root.clone());
+        let in_tree = build_root == root;
+
+        let mut db = Self::default();
+        for id in project.automake_files() {
+            let file = project.file(id);
+            let am = file.as_automake().unwrap();
+            let src_dir = root.join(file.dir());
+            let build_dir = build_root.join(file.dir());
+            let srcdir = if in_tree {
+                ".".to_string()
+            } else {
+                src_dir.display().to_string()
+            };
+            let mut outputs = makefile_outputs(project, id, (!in_tree).then_some(&root));
+            outputs.extend(options.outputs.clone());
+
+            let flags = BuildFlags::new(am, &outputs);
+            for target in collect_targets(am) {
+                for command in flags.compile_commands(&target) {
+                    let mut chosen = true;
+                    for (name, value) in command.cond.iter() {
+                        match options.conds.get(name) {
+                            Some(v) => chosen &= *v == value,
+                            None => {
+                                chosen = false;
+                                let makefile = file.path.clone();
+                                db.report(if is_make_test(name) {
+                                    UnknownValue::MakeConditional {
+                                        makefile,
+                                        test: name.to_string(),
+                                    }
+                                } else {
+                                    UnknownValue::Conditional {
+                                        makefile,
+                                        name: name.to_string(),
+                                    }
+                                });
+                            }
+                        }
+                    }
+                    if !chosen {
+                        continue;
+                    }
+
+                    // Libtool runs the compiler itself, which is what tools
+                    // reading the database expect.
+                    let mut arguments = command.arguments;
+                    if let Some(i) = arguments.iter().position(|a| a == "--mode=compile") {
+                        arguments.drain(..=i);
+                    }
+                    let source = Path::new(&command.source);
+                    let file_path = if in_tree || command.built {
+                        normalize(&build_dir.join(source))
+                    } else {
+                        if let Some(last) = arguments.last_mut() {
+                            *last = format!("{}/{}", srcdir, command.source);
+                        }
+                        normalize(&src_dir.join(source))
+                    };
+                    for argument in &arguments {
+                        for name in references(argument) {
+                            db.report(UnknownValue::Variable {
+                                makefile: file.path.clone(),
+                                name: name.to_string(),
+                            });
+                        }
+                    }
+                    db.entries.push(CompileEntry {
+                        directory: build_dir.clone(),
+                        file: file_path,
+                        arguments,
+                        output: PathBuf::from(command.object),
+                    });
+                }
+            }
+        }
+        db
+    }
+
+    /// Render the database as `compile_commands.json`.
+    pub fn to_json(&self) -> String {
+        let mut ret = "[".to_string();
+        for (i, entry) in self.entries.iter().enumerate() {
+            let arguments = entry
+                .arguments
+                .iter()
+                .map(|a| json_string(a))
+                .collect::<Vec<_>>();
+            ret.push_str(if i == 0 { "\n" } else { ",\n" });
+            ret.push_str(&format!(
+                "  {{\n    \"directory\": {},\n    \"file\": {},\n    \"output\": {},\n    \"arguments\": [{}]\n  }}",
+                json_string(&entry.directory.display().to_string()),
+                json_string(&entry.file.display().to_string()),
+                json_string(&entry.output.display().to_string()),
+                arguments.join(", ")
+            ));
+        }
+        ret.push_str("\n]\n");
+        ret
+    }
+
+    /// Write the database to a file, usually `compile_commands.json`.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    fn report(&mut self, unknown: UnknownValue) {
+        if !self.unknown.contains(&unknown) {
+            self.unknown.push(unknown);
+        }
+    }
+}
+
+/// The values `configure` gives to the directory variables, `DEFS` and
+/// `DEFAULT_INCLUDES` of a makefile, along with the literal `AC_SUBST` values
+/// of its package. `source_root` is the source tree as seen from the build
+/// root, relative or absolute, or `None` when building in the source tree.
+pub(crate) fn makefile_outputs(
+    project: &Project,
+    id: FileId,
+    source_root: Option<&Path>,
+) -> ConfigureOutputs {
+    let file = project.file(id);
+    let package = project.package_of(id).map(|ac| project.file(ac));
+    let package_dir = package.map_or(Path::new(""), |ac| ac.dir());
+    let top = top_of(
+        file.dir()
+            .strip_prefix(package_dir)
+            .unwrap_or(Path::new("")),
+    );
+    let (srcdir, top_srcdir) = match source_root {
+        None => (".".to_string(), top.clone()),
+        Some(source_root) => {
+            let source_root = if source_root.is_absolute() {
+                source_root.to_path_buf()
+            } else {
+                Path::new(&top_of(file.dir())).join(source_root)
+            };
+            (
+                normalize(&source_root.join(file.dir()))
+                    .display()
+                    .to_string(),
+                normalize(&source_root.join(package_dir))
+                    .display()
+                    .to_string(),
+            )
+        }
+    };
+
+    let mut outputs = ConfigureOutputs::new();
+    let mut default_includes = vec!["-I.".to_string()];
+    if source_root.is_some() {
+        default_includes.push(format!("-I{}", srcdir));
+    }
+    if let Some(ac) = package.and_then(|ac| ac.as_autoconf()) {
+        let headers = config_headers(ac);
+        if !headers.is_empty() {
+            outputs.insert("DEFS".to_string(), "-DHAVE_CONFIG_H".to_string());
+        }
+        for header in headers {
+            let dir = header.parent().unwrap_or(Path::new(""));
+            if package_dir.join(dir) == file.dir() {
+                continue;
+            }
+            let include = if dir.as_os_str().is_empty() {
+                format!("-I{}", top)
+            } else {
+                format!("-I{}/{}", top, dir.display())
+            };
+            if !default_includes.contains(&include) {
+                default_includes.push(include);
+            }
+        }
+        outputs.extend(literal_substs(ac));
+    }
+    outputs.insert("DEFAULT_INCLUDES".to_string(), default_includes.join(" "));
+    outputs.insert("srcdir".to_string(), srcdir);
+    outputs.insert("top_srcdir".to_string(), top_srcdir);
+    outputs.insert("builddir".to_string(), ".".to_string());
+    outputs.insert("top_builddir".to_string(), top);
+    outputs
+}
+
+/// The headers of `AC_CONFIG_HEADERS`, relative to the package root.
+fn config_headers(ac: &AutoconfFile) -> Vec<PathBuf> {
+    ac.macro_array_args("AC_CONFIG_HEADERS")
+        .iter()
+        .chain(&ac.macro_array_args("AM_CONFIG_HEADER"))
+        .map(|header| PathBuf::from(header.split(':').next().unwrap()))
+        .collect()
+}
+
+/// The values of `AC_SUBST([VAR], [VALUE])` which do not depend on the shell.
+fn literal_substs(ac: &AutoconfFile) -> Vec<(String, String)> {
+    ac.macro_args("AC_SUBST")
+        .into_iter()
+        .filter_map(|args| match args.as_slice() {
+            [Some(name), Some(value), ..] => Some((name.clone(), value.clone())),
+            _ => None,
+        })
+        .filter(|(_, value)| !value.contains(['$', '`', '@']))
+        .collect()
+}
+
+fn json_string(s: &str) -> String {
+    let mut ret = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\t' => ret.push_str("\\t"),
+            c if (c as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
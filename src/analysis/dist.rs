@@ -0,0 +1,494 @@
+//! The files `make dist` would put in the distribution tarball.
+//!
+//! Automake distributes the makefiles and the scripts of the package, the
+//! auxiliary files its macros require, and the files listed in the makefiles:
+//! `EXTRA_DIST`, the sources of every program and library, and the variables
+//! of primaries which are distributed by default (`_HEADERS`, `_TEXINFOS`,
+//! `_PYTHON`) or with a `dist_` prefix. Files listed with a `nodist_` prefix
+//! or only in `BUILT_SOURCES` are left out. Like automake, the manifest covers
+//! all conditionals at once, but every file keeps the conditionals it is
+//! listed under.
+//!
+//! Subdirectories are followed through `DIST_SUBDIRS`, or `SUBDIRS` if the
+//! former is not defined, including nested packages of `AC_CONFIG_SUBDIRS`.
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use crate::ast::am::AmLine;
+use crate::ast::node::NodeId;
+use crate::project::{AutoconfFile, AutomakeFile, FileId, Project};
+use crate::util::{normalize, top_of};
+
+use super::targets::collect_targets;
+use super::variables::{CondValues, Evaluator};
+use super::CondSet;
+
+/// Files automake distributes from any directory where they exist.
+const COMMON_FILES: &[&str] = &[
+    "ABOUT-GNU",
+    "ABOUT-NLS",
+    "AUTHORS",
+    "BACKLOG",
+    "COPYING",
+    "COPYING.DOC",
+    "COPYING.LIB",
+    "COPYING.LESSER",
+    "ChangeLog",
+    "INSTALL",
+    "NEWS",
+    "README",
+    "README-alpha",
+    "THANKS",
+    "TODO",
+];
+
+/// Primaries distributed unless listed with a `nodist_` prefix.
+const DIST_PRIMARIES: &[&str] = &["HEADERS", "TEXINFOS", "PYTHON"];
+
+/// Primaries distributed only when listed with a `dist_` prefix.
+const CANDIST_PRIMARIES: &[&str] = &["DATA", "SCRIPTS", "LISP", "JAVA", "MANS"];
+
+/// Macros requiring `config.guess` and `config.sub`.
+const CANONICAL_MACROS: &[&str] = &[
+    "AC_CANONICAL_BUILD",
+    "AC_CANONICAL_HOST",
+    "AC_CANONICAL_TARGET",
+    "AC_CANONICAL_SYSTEM",
+];
+
+/// Macros setting up libtool, which require `ltmain.sh`.
+const LIBTOOL_MACROS: &[&str] = &["LT_INIT", "AC_PROG_LIBTOOL", "AM_PROG_LIBTOOL"];
+
+/// Why a file is distributed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DistOrigin {
+    /// An input of the autotools: `configure.ac`, a `Makefile.am`, or
+    /// a fragment included by one.
+    Input,
+    /// A file the autotools generate, e.g. `configure`, `Makefile.in` or `config.h.in`.
+    Generated,
+    /// An auxiliary script added by `automake --add-missing` or `libtoolize`,
+    /// e.g. `install-sh`.
+    AuxFile,
+    /// A file automake distributes whenever it exists, e.g. `README`.
+    Common,
+    /// An input of `AC_CONFIG_FILES` other than a makefile.
+    ConfigInput,
+    /// A file listed in `EXTRA_DIST`.
+    ExtraDist,
+    /// A file listed in the variable of a primary, e.g. `dist_pkgdata_DATA`.
+    Primary(String),
+    /// A source of the program or library of that name.
+    Source(String),
+}
+
+/// A distributed file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DistFile {
+    /// Path relative to the project root. Entries of `EXTRA_DIST` may be directories.
+    pub path: PathBuf,
+    /// The file is distributed because it is listed under any of these conditions.
+    pub conds: Vec<CondSet>,
+    /// Why the file is distributed, from the first place it was found.
+    pub origin: DistOrigin,
+}
+
+impl DistFile {
+    /// Returns whether the file is generated by the autotools rather than
+    /// kept under version control.
+    pub fn is_generated(&self) -> bool {
+        matches!(self.origin, DistOrigin::Generated | DistOrigin::AuxFile)
+    }
+}
+
+/// A word of a makefile which could not be expanded to a file name,
+/// e.g. a reference to a variable set by `configure`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct UnresolvedWord {
+    /// The makefile listing the word, relative to the project root.
+    pub makefile: PathBuf,
+    /// The word after expansion.
+    pub word: String,
+    /// The conditions the word is listed under.
+    pub cond: CondSet,
+}
+
+/// The files of the distribution of a project.
+#[derive(Debug, Default, Clone)]
+pub struct DistManifest {
+    /// The distributed files, in the order they were found.
+    pub files: Vec<DistFile>,
+    /// Words which could not be resolved to file names.
+    pub unresolved: Vec<UnresolvedWord>,
+}
+
+impl DistManifest {
+    /// Compute the distribution of the top-level package of a project.
+    pub fn new(project: &Project) -> Self {
+        let mut manifest = Self::default();
+        let mut packages: Vec<Package<'_>> = Vec::new();
+        let mut visited = Vec::new();
+        let mut queue = VecDeque::from([(PathBuf::new(), None)]);
+        while let Some((dir, package)) = queue.pop_front() {
+            if visited.contains(&dir) {
+                continue;
+            }
+            visited.push(dir.clone());
+            let package = match find_autoconf(project, &dir) {
+                Some(id) => {
+                    manifest.add_package_files(project, &dir, id);
+                    packages.push(Package {
+                        dir: dir.clone(),
+                        ac: project.file(id).as_autoconf().unwrap(),
+                        aux: Vec::new(),
+                    });
+                    packages.len() - 1
+                }
+                None => match package {
+                    Some(package) => package,
+                    None => continue,
+                },
+            };
+            let Some(id) = project.find(dir.join("Makefile.am")) else {
+                continue;
+            };
+            let file = project.file(id);
+            let am = file.as_automake().unwrap();
+            let subdirs =
+                manifest.add_makefile_files(project, &file.path, am, &mut packages[package]);
+            for sub in subdirs {
+                queue.push_back((normalize(&dir.join(sub)), Some(package)));
+            }
+        }
+        for package in &packages {
+            manifest.add_aux_files(package);
+        }
+        manifest
+    }
+
+    /// Returns the entry of a path relative to the project root.
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&DistFile> {
+        let path = normalize(path.as_ref());
+        self.files.iter().find(|f| f.path == path)
+    }
+
+    /// Returns the distributed files which are neither generated nor among
+    /// `tracked`, e.g. the output of `git ls-files`.
+    pub fn missing<I, P>(&self, tracked: I) -> Vec<&DistFile>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let tracked = tracked
+            .into_iter()
+            .map(|p| normalize(p.as_ref()))
+            .collect::<Vec<_>>();
+        self.files
+            .iter()
+            .filter(|f| !f.is_generated())
+            .filter(|f| {
+                !tracked
+                    .iter()
+                    .any(|t| t == &f.path || t.starts_with(&f.path))
+            })
+            .collect()
+    }
+
+    fn add(&mut self, path: PathBuf, cond: &CondSet, origin: DistOrigin) {
+        let path = normalize(&path);
+        match self.files.iter_mut().find(|f| f.path == path) {
+            Some(file) => {
+                if !file.conds.contains(cond) {
+                    file.conds.push(cond.clone());
+                    merge_conds(&mut file.conds);
+                }
+            }
+            None => self.files.push(DistFile {
+                path,
+                conds: vec![cond.clone()],
+                origin,
+            }),
+        }
+    }
+
+    /// Add `configure.ac`, its outputs and the inputs of `AC_CONFIG_FILES`.
+    fn add_package_files(&mut self, project: &Project, dir: &Path, id: FileId) {
+        let always = CondSet::always();
+        let ac = project.file(id).as_autoconf().unwrap();
+        self.add(project.file(id).path.clone(), &always, DistOrigin::Input);
+        for generated in ["configure", "aclocal.m4"] {
+            self.add(dir.join(generated), &always, DistOrigin::Generated);
+        }
+        for tag in ac.macro_array_args("AC_CONFIG_FILES") {
+            let mut split = tag.split(':');
+            let output = split.next().unwrap();
+            let inputs = split.map(str::to_string).collect::<Vec<_>>();
+            let inputs = if inputs.is_empty() {
+                vec![format!("{}.in", output)]
+            } else {
+                inputs
+            };
+            for input in inputs {
+                let path = dir.join(&input);
+                let from_automake = input
+                    .strip_suffix(".in")
+                    .is_some_and(|stem| project.find(dir.join(format!("{}.am", stem))).is_some());
+                let origin = if from_automake {
+                    DistOrigin::Generated
+                } else {
+                    DistOrigin::ConfigInput
+                };
+                self.add(path, &always, origin);
+            }
+        }
+        for tag in ac.macro_array_args("AC_CONFIG_HEADERS") {
+            let mut split = tag.split(':');
+            let output = split.next().unwrap();
+            let input = split
+                .next()
+                .map_or(format!("{}.in", output), str::to_string);
+            self.add(dir.join(input), &always, DistOrigin::Generated);
+        }
+    }
+
+    /// Add the files listed by a makefile, returning the subdirectories to distribute.
+    fn add_makefile_files(
+        &mut self,
+        project: &Project,
+        path: &Path,
+        am: &AutomakeFile,
+        package: &mut Package<'_>,
+    ) -> Vec<String> {
+        let always = CondSet::always();
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        self.add(path.to_path_buf(), &always, DistOrigin::Input);
+        self.add(path.with_extension("in"), &always, DistOrigin::Generated);
+        for fragment in included_fragments(am) {
+            self.add(package.dir.join(fragment), &always, DistOrigin::Input);
+        }
+        for name in COMMON_FILES {
+            if project.root().join(&dir).join(name).is_file() {
+                self.add(dir.join(name), &always, DistOrigin::Common);
+            }
+        }
+
+        let mut eval = Evaluator::new(am);
+        eval.substitute("srcdir", ".");
+        eval.substitute(
+            "top_srcdir",
+            &top_of(dir.strip_prefix(&package.dir).unwrap_or(Path::new(""))),
+        );
+
+        let mut listed = Vec::new();
+        if let Some(values) = eval.eval("EXTRA_DIST") {
+            listed.push((values, DistOrigin::ExtraDist));
+        }
+        for var in eval.variables() {
+            if let Some(primary) = distributed_primary(var) {
+                match primary {
+                    "TEXINFOS" => package.require("texinfo.tex"),
+                    "PYTHON" => package.require("py-compile"),
+                    _ => {}
+                }
+                listed.push((
+                    eval.eval(var).unwrap(),
+                    DistOrigin::Primary(var.to_string()),
+                ));
+            }
+        }
+        if eval.is_defined("TESTS") {
+            package.require("test-driver");
+        }
+        for target in collect_targets(am) {
+            package.require("depcomp");
+            let sources = target.sources.iter().chain(&target.extra_sources);
+            for source in sources {
+                let values = eval
+                    .eval_words(std::slice::from_ref(&source.word))
+                    .into_iter()
+                    .filter_map(|(cond, words)| Some((cond.and(&source.cond)?, words)))
+                    .collect::<CondValues>();
+                let lex_yacc = [".y", ".yy", ".l", ".ll"];
+                if values
+                    .values()
+                    .flatten()
+                    .any(|w| lex_yacc.iter().any(|ext| w.ends_with(ext)))
+                {
+                    package.require("ylwrap");
+                }
+                listed.push((values, DistOrigin::Source(target.name.clone())));
+            }
+        }
+
+        for (values, origin) in listed {
+            for (cond, words) in values {
+                for word in words {
+                    if word.contains('$') || word.contains('@') {
+                        self.unresolved.push(UnresolvedWord {
+                            makefile: path.to_path_buf(),
+                            word,
+                            cond: cond.clone(),
+                        });
+                    } else {
+                        self.add(dir.join(word), &cond, origin.clone());
+                    }
+                }
+            }
+        }
+
+        let subdirs = match eval.eval("DIST_SUBDIRS") {
+            Some(values) => values,
+            None => eval.eval("SUBDIRS").unwrap_or_default(),
+        };
+        let mut ret = Vec::new();
+        for sub in subdirs.into_values().flatten() {
+            if sub != "." && !ret.contains(&sub) {
+                ret.push(sub);
+            }
+        }
+        ret
+    }
+
+    /// Add the auxiliary files required by the macros of a package and its makefiles.
+    fn add_aux_files(&mut self, package: &Package<'_>) {
+        let ac = package.ac;
+        let aux_dir = package.dir.join(
+            ac.macro_array_args("AC_CONFIG_AUX_DIR")
+                .first()
+                .map_or(".", String::as_str),
+        );
+        let mut aux = Vec::new();
+        if ac.calls_macro("AM_INIT_AUTOMAKE") {
+            aux.extend(["install-sh", "missing"]);
+        }
+        if ["AC_PROG_CC", "AM_PROG_CC_C_O"]
+            .iter()
+            .any(|m| ac.calls_macro(m))
+        {
+            aux.push("compile");
+        }
+        if CANONICAL_MACROS.iter().any(|m| ac.calls_macro(m)) {
+            aux.extend(["config.guess", "config.sub"]);
+        }
+        if LIBTOOL_MACROS.iter().any(|m| ac.calls_macro(m)) {
+            aux.extend(["config.guess", "config.sub", "ltmain.sh"]);
+        }
+        if ac.calls_macro("AM_PROG_AR") {
+            aux.push("ar-lib");
+        }
+        let no_dependencies = ac
+            .macro_array_args("AM_INIT_AUTOMAKE")
+            .iter()
+            .any(|option| option == "no-dependencies");
+        for name in &package.aux {
+            if !(no_dependencies && *name == "depcomp") {
+                aux.push(name);
+            }
+        }
+        let always = CondSet::always();
+        for name in aux {
+            self.add(aux_dir.join(name), &always, DistOrigin::AuxFile);
+        }
+    }
+}
+
+/// A package of the distribution, with the auxiliary files its makefiles require.
+struct Package<'a> {
+    dir: PathBuf,
+    ac: &'a AutoconfFile,
+    aux: Vec<&'static str>,
+}
+
+impl Package<'_> {
+    fn require(&mut self, name: &'static str) {
+        if !self.aux.contains(&name) {
+            self.aux.push(name);
+        }
+    }
+}
+
+/// Merge conditions differing only in the value of one conditional,
+/// e.g. `X_TRUE Y_TRUE` and `X_TRUE Y_FALSE` into `X_TRUE`.
+fn merge_conds(conds: &mut Vec<CondSet>) {
+    'merge: loop {
+        for i in 0..conds.len() {
+            for j in i + 1..conds.len() {
+                let (a, b) = (&conds[i], &conds[j]);
+                if a.iter().count() != b.iter().count() {
+                    continue;
+                }
+                let differing = a
+                    .iter()
+                    .filter(|(var, value)| b.get(var) != Some(*value))
+                    .collect::<Vec<_>>();
+                let [(var, _)] = differing[..] else {
+                    continue;
+                };
+                if b.get(var).is_none() {
+                    continue;
+                }
+                let merged = a
+                    .iter()
+                    .filter(|(v, _)| *v != var)
+                    .try_fold(CondSet::always(), |acc, (v, value)| acc.with(v, value))
+                    .unwrap();
+                conds.remove(j);
+                conds[i] = merged;
+                conds.dedup();
+                continue 'merge;
+            }
+        }
+        break;
+    }
+}
+
+/// Returns the autoconf script of the package rooted at `dir`.
+fn find_autoconf(project: &Project, dir: &Path) -> Option<FileId> {
+    project
+        .autoconf_files()
+        .find(|id| project.file(*id).dir() == dir)
+}
+
+/// Returns the primary of a variable if it lists distributed files,
+/// e.g. `HEADERS` for `include_HEADERS` and `DATA` for `dist_pkgdata_DATA`.
+fn distributed_primary(var: &str) -> Option<&'static str> {
+    let (prefix, primary) = var.rsplit_once('_')?;
+    let words = prefix.split('_').collect::<Vec<_>>();
+    if words.contains(&"nodist") || words.iter().all(|w| *w == "dist" || *w == "nobase") {
+        return None;
+    }
+    if let Some(primary) = DIST_PRIMARIES.iter().find(|p| **p == primary) {
+        return Some(primary);
+    }
+    let primary = CANDIST_PRIMARIES.iter().find(|p| **p == primary)?;
+    words.contains(&"dist").then_some(primary)
+}
+
+/// Paths of the fragments included by a makefile, relative to the package root.
+fn included_fragments(am: &AutomakeFile) -> Vec<PathBuf> {
+    fn walk(am: &AutomakeFile, ids: &[NodeId], ret: &mut Vec<PathBuf>) {
+        for id in ids {
+            match &am.nodes[*id].cmd {
+                AmLine::Include(include) => {
+                    if let Some(included) = &include.included {
+                        ret.push(included.path.clone());
+                        walk(am, &included.body, ret);
+                    }
+                }
+                AmLine::Conditional(c) => {
+                    walk(am, &c.then, ret);
+                    walk(am, &c.otherwise, ret);
+                }
+                AmLine::MakeConditional(c) => {
+                    walk(am, &c.then, ret);
+                    walk(am, &c.otherwise, ret);
+                }
+                AmLine::Rule(rule) => walk(am, &rule.recipe, ret),
+                _ => {}
+            }
+        }
+    }
+    let mut ret = Vec::new();
+    walk(am, &am.top_ids, &mut ret);
+    ret
+}
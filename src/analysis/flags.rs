@@ -0,0 +1,508 @@
+//! Effective compile and link commands of the programs and libraries of a
+//! `Makefile.am`.
+//!
+//! Automake compiles every source with a fixed sequence of variables, e.g.
+//! `$(CC) $(DEFS) $(DEFAULT_INCLUDES) $(INCLUDES) $(AM_CPPFLAGS) $(CPPFLAGS)
+//! $(AM_CFLAGS) $(CFLAGS)` for C. A per-target variable such as `foo_CPPFLAGS`
+//! takes the place of the `AM_` variable, and since the objects of `foo` are
+//! then compiled differently from those of other targets, they are renamed
+//! to `foo-main.o`. The user variables (`CPPFLAGS`, `CFLAGS`, ...) always come
+//! last so that they take precedence over the makefile.
+//!
+//! Commands are given as written in the generated `Makefile.in`, and expanded
+//! with the values `configure` substitutes. Values which were not given are
+//! kept as references, e.g. `$(CC)`.
+use std::collections::BTreeMap;
+
+use crate::ast::am::AmWord;
+use crate::project::AutomakeFile;
+
+use super::targets::{Primary, Target};
+use super::variables::Evaluator;
+use super::CondSet;
+
+/// Values `configure` substitutes into the makefiles, e.g. `CC` or `CFLAGS`.
+pub type ConfigureOutputs = BTreeMap<String, String>;
+
+/// Variables which expand to their automake default unless the makefile or
+/// `configure` defines them. Variables only the makefile sets are empty.
+const AUTOMAKE_DEFAULTS: &[(&str, &str)] = &[
+    ("DEFAULT_INCLUDES", "-I."),
+    ("ARFLAGS", "cru"),
+    ("INCLUDES", ""),
+    ("LDADD", ""),
+    ("LIBTOOLFLAGS", ""),
+    ("AM_CPPFLAGS", ""),
+    ("AM_CFLAGS", ""),
+    ("AM_CXXFLAGS", ""),
+    ("AM_OBJCFLAGS", ""),
+    ("AM_FFLAGS", ""),
+    ("AM_CCASFLAGS", ""),
+    ("AM_LDFLAGS", ""),
+    ("AM_LIBTOOLFLAGS", ""),
+];
+
+/// Suffixes of per-target variables which make automake rename the objects
+/// of a target.
+const RENAMING_VARS: &[&str] = &[
+    "CPPFLAGS",
+    "CFLAGS",
+    "CXXFLAGS",
+    "OBJCFLAGS",
+    "FFLAGS",
+    "CCASFLAGS",
+    "YFLAGS",
+    "LFLAGS",
+    "SHORTNAME",
+];
+
+/// Languages automake knows how to compile.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Language {
+    /// `.c`
+    C,
+    /// `.cc`, `.cpp`, `.cxx`, `.C` and `.c++`
+    Cxx,
+    /// `.m`
+    ObjC,
+    /// `.f` and `.for`
+    Fortran77,
+    /// `.s`
+    Assembler,
+    /// `.S` and `.sx`, preprocessed assembler.
+    PreprocessedAssembler,
+}
+
+impl Language {
+    /// Returns the language of a source by its extension, without the dot.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "c" => Some(Self::C),
+            "cc" | "cpp" | "cxx" | "C" | "c++" | "cp" => Some(Self::Cxx),
+            "m" => Some(Self::ObjC),
+            "f" | "for" => Some(Self::Fortran77),
+            "s" => Some(Self::Assembler),
+            "S" | "sx" => Some(Self::PreprocessedAssembler),
+            _ => None,
+        }
+    }
+
+    /// The variable naming the compiler, e.g. `CC`.
+    pub fn compiler(&self) -> &'static str {
+        match self {
+            Self::C => "CC",
+            Self::Cxx => "CXX",
+            Self::ObjC => "OBJC",
+            Self::Fortran77 => "F77",
+            Self::Assembler | Self::PreprocessedAssembler => "CCAS",
+        }
+    }
+
+    /// The user variable holding the compiler flags, e.g. `CFLAGS`.
+    pub fn flags_var(&self) -> &'static str {
+        match self {
+            Self::C => "CFLAGS",
+            Self::Cxx => "CXXFLAGS",
+            Self::ObjC => "OBJCFLAGS",
+            Self::Fortran77 => "FFLAGS",
+            Self::Assembler | Self::PreprocessedAssembler => "CCASFLAGS",
+        }
+    }
+
+    /// The variable naming the linker for targets in this language, e.g. `CCLD`.
+    pub fn linker(&self) -> &'static str {
+        match self {
+            Self::Cxx => "CXXLD",
+            Self::ObjC => "OBJCLD",
+            Self::Fortran77 => "F77LD",
+            Self::C | Self::Assembler | Self::PreprocessedAssembler => "CCLD",
+        }
+    }
+
+    /// The `--tag` libtool is given for this language.
+    pub fn libtool_tag(&self) -> &'static str {
+        match self {
+            Self::Cxx => "CXX",
+            Self::ObjC => "OBJC",
+            Self::Fortran77 => "F77",
+            Self::C | Self::Assembler | Self::PreprocessedAssembler => "CC",
+        }
+    }
+
+    /// Whether the sources are run through the preprocessor, and so take `CPPFLAGS`.
+    pub fn uses_cpp(&self) -> bool {
+        !matches!(self, Self::Fortran77 | Self::Assembler)
+    }
+
+    /// Automake links a target with the linker of the language of highest
+    /// priority among its sources.
+    fn link_priority(&self) -> u8 {
+        match self {
+            Self::Cxx => 3,
+            Self::Fortran77 => 2,
+            Self::ObjC => 1,
+            Self::C | Self::Assembler | Self::PreprocessedAssembler => 0,
+        }
+    }
+}
+
+/// The extension of the source yacc or lex generate from a grammar.
+fn generated_extension(ext: &str) -> Option<&'static str> {
+    match ext {
+        "y" | "l" => Some("c"),
+        "yy" | "ypp" | "y++" | "yxx" | "ll" | "lpp" | "l++" | "lxx" => Some("cc"),
+        _ => None,
+    }
+}
+
+/// The command compiling a source of a target.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CompileCommand {
+    /// The compiled source relative to the makefile directory. For a yacc or
+    /// lex grammar, this is the generated source.
+    pub source: String,
+    /// The object file relative to the makefile directory.
+    pub object: String,
+    /// The language of the source.
+    pub language: Language,
+    /// Whether the source is built in the build directory rather than
+    /// distributed: listed in `nodist_` `_SOURCES` or generated from a grammar.
+    pub built: bool,
+    /// The conditionals under which the command applies.
+    pub cond: CondSet,
+    /// The command as written in `Makefile.in`, e.g. `$(CC)`, `$(DEFS)`, ....
+    pub template: Vec<String>,
+    /// The command with the variables expanded.
+    pub arguments: Vec<String>,
+}
+
+/// The command linking or archiving a target.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LinkCommand {
+    /// The conditionals under which the command applies.
+    pub cond: CondSet,
+    /// The command as written in `Makefile.in`, with the objects as `$(foo_OBJECTS)`.
+    pub template: Vec<String>,
+    /// The command with the variables and objects expanded.
+    pub arguments: Vec<String>,
+}
+
+/// Computes the commands building the targets of a makefile.
+#[derive(Debug)]
+pub struct BuildFlags<'a> {
+    eval: Evaluator<'a>,
+    subdir_objects: bool,
+    libtool: bool,
+}
+
+impl<'a> BuildFlags<'a> {
+    /// Evaluate the makefile with the values substituted by `configure`.
+    ///
+    /// Objects are placed next to their sources if `AUTOMAKE_OPTIONS` holds
+    /// `subdir-objects`.
+    pub fn new(am: &'a AutomakeFile, outputs: &ConfigureOutputs) -> Self {
+        let mut eval = Evaluator::new(am);
+        for (name, value) in AUTOMAKE_DEFAULTS {
+            eval.substitute(name, value);
+        }
+        // `CCLD = $(CC)`, as substituted values are not expanded again.
+        for (linker, compiler) in [
+            ("CCLD", "CC"),
+            ("CXXLD", "CXX"),
+            ("OBJCLD", "OBJC"),
+            ("F77LD", "F77"),
+        ] {
+            let value = outputs
+                .get(compiler)
+                .cloned()
+                .unwrap_or_else(|| format!("$({})", compiler));
+            eval.substitute(linker, &value);
+        }
+        for (name, value) in outputs {
+            eval.substitute(name, value);
+        }
+        let subdir_objects = eval.eval("AUTOMAKE_OPTIONS").is_some_and(|values| {
+            values
+                .values()
+                .flatten()
+                .any(|option| option == "subdir-objects")
+        });
+        Self {
+            eval,
+            subdir_objects,
+            libtool: false,
+        }
+    }
+
+    /// Place objects next to their sources, as with the `subdir-objects`
+    /// option of `AM_INIT_AUTOMAKE`.
+    pub fn subdir_objects(mut self, enable: bool) -> Self {
+        self.subdir_objects |= enable;
+        self
+    }
+
+    /// Link programs through libtool, as automake does once `LT_INIT` is called.
+    /// Libtool libraries always are.
+    pub fn libtool(mut self, enable: bool) -> Self {
+        self.libtool = enable;
+        self
+    }
+
+    /// The commands compiling the sources of a target, in the order of the
+    /// sources. Headers and sources in unknown languages are skipped.
+    pub fn compile_commands(&self, target: &Target) -> Vec<CompileCommand> {
+        let renamed = RENAMING_VARS
+            .iter()
+            .any(|suffix| self.is_per_target(target, suffix));
+        let prefix = match self.eval.eval(&format!("{}_SHORTNAME", target.canonical)) {
+            Some(values) if renamed => values
+                .into_values()
+                .flatten()
+                .next()
+                .unwrap_or_else(|| target.canonical.clone()),
+            _ => target.canonical.clone(),
+        };
+        let libtool = target.primary == Primary::LtLibraries;
+        let object_ext = if libtool { "lo" } else { "o" };
+
+        let mut ret: Vec<CompileCommand> = Vec::new();
+        let sources = target.sources.iter().map(|s| (s, false));
+        let nodist_sources = target.nodist_sources.iter().map(|s| (s, true));
+        for (source, nodist) in sources.chain(nodist_sources) {
+            for (source_cond, words) in self.eval.eval_words(std::slice::from_ref(&source.word)) {
+                let Some(source_cond) = source.cond.and(&source_cond) else {
+                    continue;
+                };
+                for word in words {
+                    let (dir, stem, ext) = split_source(&word, self.subdir_objects);
+                    let renamed_stem = if renamed {
+                        format!("{}-{}", prefix, stem)
+                    } else {
+                        stem.to_string()
+                    };
+                    let (source, language, built) = match generated_extension(ext) {
+                        Some(gen_ext) => (
+                            format!("{}{}.{}", dir, renamed_stem, gen_ext),
+                            Language::from_extension(gen_ext),
+                            true,
+                        ),
+                        None => (word.clone(), Language::from_extension(ext), nodist),
+                    };
+                    let Some(language) = language else {
+                        continue;
+                    };
+                    let object = format!("{}{}.{}", dir, renamed_stem, object_ext);
+                    let mut template = self.compile_template(target, language, libtool);
+                    template.extend(["-c".to_string(), "-o".to_string(), object.clone()]);
+                    template.push(source.clone());
+                    let words = template
+                        .iter()
+                        .map(|token| template_word(token))
+                        .collect::<Vec<_>>();
+                    let alternatives = self.eval.eval_words(&words);
+                    for target_cond in &target.conds {
+                        for (flags_cond, arguments) in &alternatives {
+                            let cond = source_cond
+                                .and(target_cond)
+                                .and_then(|cond| cond.and(flags_cond));
+                            let Some(cond) = cond else {
+                                continue;
+                            };
+                            if ret.iter().any(|c| c.object == object && c.cond == cond) {
+                                continue;
+                            }
+                            ret.push(CompileCommand {
+                                source: source.clone(),
+                                object: object.clone(),
+                                language,
+                                built,
+                                cond,
+                                template: template.clone(),
+                                arguments: arguments.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    /// The commands linking a program or libtool library, or archiving a
+    /// static library, one for each combination of conditionals which changes
+    /// the objects or flags.
+    pub fn link_commands(&self, target: &Target) -> Vec<LinkCommand> {
+        let compiles = self.compile_commands(target);
+        let language = compiles
+            .iter()
+            .map(|c| c.language)
+            .max_by_key(Language::link_priority)
+            .unwrap_or(Language::C);
+        let c = &target.canonical;
+        let var = |name: &str| format!("$({})", name);
+
+        let mut head = Vec::new();
+        let mut tail = Vec::new();
+        match target.primary {
+            Primary::Libraries => {
+                if self.eval.is_defined(&format!("{}_AR", c)) {
+                    head.push(var(&format!("{}_AR", c)));
+                } else {
+                    head.extend([var("AR"), var("ARFLAGS")]);
+                }
+                head.push(target.name.clone());
+                if self.eval.is_defined(&format!("{}_LIBADD", c)) {
+                    tail.push(var(&format!("{}_LIBADD", c)));
+                }
+            }
+            Primary::Programs | Primary::LtLibraries => {
+                if target.primary == Primary::LtLibraries || self.libtool {
+                    head.extend(self.libtool_prefix(target, language, "link"));
+                }
+                head.extend([
+                    var(language.linker()),
+                    self.per_target_var(target, language.flags_var()),
+                    var(language.flags_var()),
+                    self.per_target_var(target, "LDFLAGS"),
+                    var("LDFLAGS"),
+                    "-o".to_string(),
+                    target.name.clone(),
+                ]);
+                let installed = !matches!(target.dir.as_str(), "noinst" | "check" | "EXTRA");
+                if target.primary == Primary::LtLibraries && installed {
+                    head.extend(["-rpath".to_string(), var(&format!("{}dir", target.dir))]);
+                }
+                let link_var = format!("{}_{}", c, target.primary.link_var());
+                if self.eval.is_defined(&link_var) {
+                    tail.push(var(&link_var));
+                } else if target.primary == Primary::Programs {
+                    tail.push(var("LDADD"));
+                }
+                tail.push(var("LIBS"));
+            }
+        }
+        let mut template = head.clone();
+        template.push(var(&format!("{}_OBJECTS", c)));
+        template.extend(tail.iter().cloned());
+
+        let expand = |tokens: &[String]| {
+            let words = tokens.iter().map(|t| template_word(t)).collect::<Vec<_>>();
+            self.eval.eval_words(&words)
+        };
+        let head = expand(&head);
+        let tail = expand(&tail);
+        let mut conds = target.conds.clone();
+        for by in head
+            .keys()
+            .chain(tail.keys())
+            .chain(compiles.iter().map(|c| &c.cond))
+        {
+            conds = split_conds(conds, by);
+        }
+
+        let mut ret = Vec::new();
+        for cond in conds {
+            let part = |values: &BTreeMap<CondSet, Vec<String>>| {
+                values
+                    .iter()
+                    .find(|(key, _)| key.is_implied_by(&cond))
+                    .map(|(_, words)| words.clone())
+            };
+            let (Some(mut arguments), Some(tail)) = (part(&head), part(&tail)) else {
+                continue;
+            };
+            for compile in compiles.iter().filter(|c| c.cond.is_implied_by(&cond)) {
+                if !arguments.contains(&compile.object) {
+                    arguments.push(compile.object.clone());
+                }
+            }
+            arguments.extend(tail);
+            ret.push(LinkCommand {
+                cond,
+                template: template.clone(),
+                arguments,
+            });
+        }
+        ret
+    }
+
+    fn compile_template(&self, target: &Target, language: Language, libtool: bool) -> Vec<String> {
+        let mut template = Vec::new();
+        if libtool {
+            template.extend(self.libtool_prefix(target, language, "compile"));
+        }
+        template.push(format!("$({})", language.compiler()));
+        if language.uses_cpp() {
+            template.extend([
+                "$(DEFS)".to_string(),
+                "$(DEFAULT_INCLUDES)".to_string(),
+                "$(INCLUDES)".to_string(),
+                self.per_target_var(target, "CPPFLAGS"),
+                "$(CPPFLAGS)".to_string(),
+            ]);
+        }
+        template.extend([
+            self.per_target_var(target, language.flags_var()),
+            format!("$({})", language.flags_var()),
+        ]);
+        template
+    }
+
+    fn libtool_prefix(&self, target: &Target, language: Language, mode: &str) -> Vec<String> {
+        vec![
+            "$(LIBTOOL)".to_string(),
+            format!("--tag={}", language.libtool_tag()),
+            self.per_target_var(target, "LIBTOOLFLAGS"),
+            "$(LIBTOOLFLAGS)".to_string(),
+            format!("--mode={}", mode),
+        ]
+    }
+
+    fn is_per_target(&self, target: &Target, suffix: &str) -> bool {
+        self.eval
+            .is_defined(&format!("{}_{}", target.canonical, suffix))
+    }
+
+    /// `$(foo_CFLAGS)` if the target defines it, `$(AM_CFLAGS)` otherwise.
+    fn per_target_var(&self, target: &Target, suffix: &str) -> String {
+        if self.is_per_target(target, suffix) {
+            format!("$({}_{})", target.canonical, suffix)
+        } else {
+            format!("$(AM_{})", suffix)
+        }
+    }
+}
+
+/// Convert a token of a command template to a word, `$(NAME)` being a
+/// reference to a variable.
+fn template_word(token: &str) -> AmWord {
+    match token.strip_prefix("$(").and_then(|t| t.strip_suffix(')')) {
+        Some(name) => AmWord::variable(name),
+        None => AmWord::literal(token),
+    }
+}
+
+/// Split a source into the directory its objects go to, the stem of its file
+/// name and its extension, e.g. `("sub/", "x", "c")` with `subdir-objects`.
+fn split_source(source: &str, subdir_objects: bool) -> (&str, &str, &str) {
+    let (dir, file) = match source.rfind('/') {
+        Some(i) => source.split_at(i + 1),
+        None => ("", source),
+    };
+    let (stem, ext) = file.rsplit_once('.').unwrap_or((file, ""));
+    (if subdir_objects { dir } else { "" }, stem, ext)
+}
+
+/// Split every set into sets which either imply `by` or contradict it.
+fn split_conds(conds: Vec<CondSet>, by: &CondSet) -> Vec<CondSet> {
+    let mut ret = Vec::new();
+    for cond in conds {
+        if by.is_implied_by(&cond) || !by.is_compatible(&cond) {
+            ret.push(cond);
+            continue;
+        }
+        ret.extend(cond.and(by));
+        ret.extend(by.complement().iter().filter_map(|c| cond.and(c)));
+    }
+    ret
+}
@@ -0,0 +1,163 @@
+//! Evaluation of the GNU make functions which only transform text.
+//!
+//! Functions depending on the file system or the environment (`wildcard`,
+//! `shell`, `origin`, ...) and those binding variables (`foreach`, `call`,
+//! `let`) are not evaluated.
+
+/// Call a text function with expanded arguments, or return `None`
+/// if the function can not be evaluated statically.
+pub fn call(name: &str, args: &[&str]) -> Option<String> {
+    let arg = |i: usize| args.get(i).copied().unwrap_or("");
+    let ret = match name {
+        "subst" => {
+            // An empty FROM matches at the end of the text only.
+            if arg(0).is_empty() {
+                format!("{}{}", arg(2), arg(1))
+            } else {
+                arg(2).replace(arg(0), arg(1))
+            }
+        }
+        "patsubst" => map_words(arg(2), |w| patsubst(arg(0), arg(1), w)),
+        "strip" => words(arg(0)).join(" "),
+        "findstring" => {
+            if arg(1).contains(arg(0)) {
+                arg(0).to_string()
+            } else {
+                String::new()
+            }
+        }
+        "filter" | "filter-out" => {
+            let patterns = words(arg(0));
+            let keep = name == "filter";
+            words(arg(1))
+                .into_iter()
+                .filter(|w| patterns.iter().any(|p| pattern_stem(p, w).is_some()) == keep)
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+        "sort" => {
+            let mut words = words(arg(0));
+            words.sort_unstable();
+            words.dedup();
+            words.join(" ")
+        }
+        "word" => {
+            let n = arg(0).trim().parse::<usize>().ok().filter(|n| *n > 0)?;
+            words(arg(1)).get(n - 1).copied().unwrap_or("").to_string()
+        }
+        "wordlist" => {
+            let start = arg(0).trim().parse::<usize>().ok().filter(|n| *n > 0)?;
+            let end = arg(1).trim().parse::<usize>().ok()?;
+            let words = words(arg(2));
+            let end = end.min(words.len());
+            if start > end {
+                String::new()
+            } else {
+                words[start - 1..end].join(" ")
+            }
+        }
+        "words" => words(arg(0)).len().to_string(),
+        "firstword" => words(arg(0)).first().copied().unwrap_or("").to_string(),
+        "lastword" => words(arg(0)).last().copied().unwrap_or("").to_string(),
+        "dir" => map_words(arg(0), |w| match w.rfind('/') {
+            Some(i) => w[..=i].to_string(),
+            None => "./".to_string(),
+        }),
+        "notdir" => map_words(arg(0), |w| match w.rfind('/') {
+            Some(i) => w[i + 1..].to_string(),
+            None => w.to_string(),
+        }),
+        "suffix" => words(arg(0))
+            .into_iter()
+            .filter_map(|w| suffix_index(w).map(|i| &w[i..]))
+            .collect::<Vec<_>>()
+            .join(" "),
+        "basename" => map_words(arg(0), |w| match suffix_index(w) {
+            Some(i) => w[..i].to_string(),
+            None => w.to_string(),
+        }),
+        "addsuffix" => map_words(arg(1), |w| format!("{}{}", w, arg(0))),
+        "addprefix" => map_words(arg(1), |w| format!("{}{}", arg(0), w)),
+        "join" => {
+            let (lhs, rhs) = (words(arg(0)), words(arg(1)));
+            (0..lhs.len().max(rhs.len()))
+                .map(|i| {
+                    let l = lhs.get(i).copied().unwrap_or("");
+                    let r = rhs.get(i).copied().unwrap_or("");
+                    format!("{}{}", l, r)
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+        "if" => {
+            if arg(0).trim().is_empty() {
+                arg(2).to_string()
+            } else {
+                arg(1).to_string()
+            }
+        }
+        "or" => args
+            .iter()
+            .find(|a| !a.trim().is_empty())
+            .map_or(String::new(), |a| a.to_string()),
+        "and" => {
+            if args.iter().all(|a| !a.trim().is_empty()) {
+                args.last().map_or(String::new(), |a| a.to_string())
+            } else {
+                String::new()
+            }
+        }
+        _ => return None,
+    };
+    Some(ret)
+}
+
+/// Apply a substitution reference `$(var:pattern=replacement)` to a value.
+///
+/// Without `%`, the pattern is a suffix, as in `$(SRCS:.c=.o)`.
+pub fn substitution_reference(value: &str, pattern: &str, replacement: &str) -> String {
+    if pattern.contains('%') {
+        map_words(value, |w| patsubst(pattern, replacement, w))
+    } else {
+        map_words(value, |w| match w.strip_suffix(pattern) {
+            Some(stem) if !pattern.is_empty() => format!("{}{}", stem, replacement),
+            _ => w.to_string(),
+        })
+    }
+}
+
+fn words(text: &str) -> Vec<&str> {
+    text.split_whitespace().collect()
+}
+
+fn map_words<F: Fn(&str) -> String>(text: &str, f: F) -> String {
+    words(text).into_iter().map(f).collect::<Vec<_>>().join(" ")
+}
+
+/// Returns the part of `word` matched by `%` in `pattern`, if the pattern matches.
+/// A pattern without `%` only matches itself.
+pub(crate) fn pattern_stem<'a>(pattern: &str, word: &'a str) -> Option<&'a str> {
+    match pattern.split_once('%') {
+        Some((prefix, suffix)) => {
+            if word.len() < prefix.len() + suffix.len() {
+                return None;
+            }
+            word.strip_prefix(prefix)?.strip_suffix(suffix)
+        }
+        None => (pattern == word).then_some(""),
+    }
+}
+
+fn patsubst(pattern: &str, replacement: &str, word: &str) -> String {
+    match pattern_stem(pattern, word) {
+        Some(stem) if pattern.contains('%') => replacement.replacen('%', stem, 1),
+        Some(_) => replacement.to_string(),
+        None => word.to_string(),
+    }
+}
+
+/// Index of the last `.` of the file name part of `word`.
+fn suffix_index(word: &str) -> Option<usize> {
+    let start = word.rfind('/').map_or(0, |i| i + 1);
+    word[start..].rfind('.').map(|i| start + i)
+}
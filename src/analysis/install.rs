@@ -0,0 +1,402 @@
+//! Where `make install` puts the programs, libraries and data of a project.
+//!
+//! Every installable primary variable names its installation directory by its
+//! prefix: `bin_PROGRAMS` installs to `$(bindir)`, `pkgdata_DATA` to
+//! `$(pkgdatadir)` and `foo_DATA` to a `foodir` the makefile defines. The
+//! directories are resolved with the values `configure` would give to its
+//! `--prefix`, `--libdir`, ... options, as described by `InstallDirs`.
+//! Files keep their directory part with the `nobase_` prefix and are installed
+//! by their base name otherwise.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::ast::am::AmWord;
+use crate::project::{AutoconfFile, AutomakeFile, Project};
+use crate::util::normalize;
+
+use super::dist::UnresolvedWord;
+use super::variables::Evaluator;
+use super::CondSet;
+
+/// Installation directories of the GNU Coding Standards and automake,
+/// with their default values.
+const DEFAULT_DIRS: &[(&str, &str)] = &[
+    ("prefix", "/usr/local"),
+    ("exec_prefix", "${prefix}"),
+    ("bindir", "${exec_prefix}/bin"),
+    ("sbindir", "${exec_prefix}/sbin"),
+    ("libexecdir", "${exec_prefix}/libexec"),
+    ("libdir", "${exec_prefix}/lib"),
+    ("sysconfdir", "${prefix}/etc"),
+    ("sharedstatedir", "${prefix}/com"),
+    ("localstatedir", "${prefix}/var"),
+    ("runstatedir", "${localstatedir}/run"),
+    ("includedir", "${prefix}/include"),
+    ("oldincludedir", "/usr/include"),
+    ("datarootdir", "${prefix}/share"),
+    ("datadir", "${datarootdir}"),
+    ("infodir", "${datarootdir}/info"),
+    ("localedir", "${datarootdir}/locale"),
+    ("mandir", "${datarootdir}/man"),
+    ("docdir", "${datarootdir}/doc/${PACKAGE_TARNAME}"),
+    ("htmldir", "${docdir}"),
+    ("dvidir", "${docdir}"),
+    ("pdfdir", "${docdir}"),
+    ("psdir", "${docdir}"),
+    ("lispdir", "${datarootdir}/emacs/site-lisp"),
+    ("pkgdatadir", "${datadir}/${PACKAGE}"),
+    ("pkgincludedir", "${includedir}/${PACKAGE}"),
+    ("pkglibdir", "${libdir}/${PACKAGE}"),
+    ("pkglibexecdir", "${libexecdir}/${PACKAGE}"),
+];
+
+/// Sections of manual pages with a `manNdir` directory.
+const MAN_SECTIONS: &str = "0123456789ln";
+
+/// Primaries whose files are installed.
+const INSTALL_PRIMARIES: &[&str] = &[
+    "PROGRAMS",
+    "LIBRARIES",
+    "LTLIBRARIES",
+    "SCRIPTS",
+    "DATA",
+    "HEADERS",
+    "MANS",
+    "TEXINFOS",
+    "PYTHON",
+    "LISP",
+    "JAVA",
+];
+
+/// Values of the installation directories, as given to `configure`.
+///
+/// Values may refer to other directories as `${prefix}` or `$(prefix)`.
+#[derive(Debug, Clone)]
+pub struct InstallDirs {
+    values: BTreeMap<String, String>,
+}
+
+impl Default for InstallDirs {
+    fn default() -> Self {
+        let mut values = DEFAULT_DIRS
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<BTreeMap<_, _>>();
+        for section in MAN_SECTIONS.chars() {
+            values.insert(
+                format!("man{}dir", section),
+                format!("${{mandir}}/man{}", section),
+            );
+        }
+        Self { values }
+    }
+}
+
+impl InstallDirs {
+    /// The default directories under `prefix`, as with `configure --prefix`.
+    pub fn with_prefix(prefix: &str) -> Self {
+        let mut dirs = Self::default();
+        dirs.set("prefix", prefix);
+        dirs
+    }
+
+    /// Set a directory, as with `configure --name=value`.
+    pub fn set(&mut self, name: &str, value: &str) -> &mut Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Returns the value of a directory as given, without expanding references.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Returns the names of the directories and package variables.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.values.keys().map(String::as_str)
+    }
+
+    /// Returns the value of a directory with the references to other
+    /// directories expanded. Unknown references are kept verbatim.
+    pub fn resolve(&self, name: &str) -> Option<String> {
+        let value = self.values.get(name)?;
+        Some(self.expand(value, &mut vec![name]))
+    }
+
+    /// Returns the directories of a package, adding the package name of
+    /// `AC_INIT` and the directories of `PKG_INSTALLDIR` and
+    /// `PKG_NOARCH_INSTALLDIR` unless they were set.
+    pub fn for_package(&self, ac: &AutoconfFile) -> Self {
+        let mut dirs = self.clone();
+        let mut default = |name: &str, value: String| {
+            dirs.values.entry(name.to_string()).or_insert(value);
+        };
+        if let Some(name) = ac.macro_arg("AC_INIT", 0) {
+            let tarname = ac
+                .macro_arg("AC_INIT", 3)
+                .filter(|tarname| !tarname.is_empty())
+                .unwrap_or_else(|| tarname_of(&name));
+            default("PACKAGE_NAME", name);
+            default("PACKAGE", tarname.clone());
+            default("PACKAGE_TARNAME", tarname);
+        }
+        if let Some(version) = ac.macro_arg("AC_INIT", 1) {
+            default("PACKAGE_VERSION", version.clone());
+            default("VERSION", version);
+        }
+        let installdirs = [
+            ("PKG_INSTALLDIR", "pkgconfigdir", "${libdir}/pkgconfig"),
+            (
+                "PKG_NOARCH_INSTALLDIR",
+                "noarch_pkgconfigdir",
+                "${datadir}/pkgconfig",
+            ),
+        ];
+        for (macro_name, var, value) in installdirs {
+            if ac.calls_macro(macro_name) {
+                let value = ac
+                    .macro_arg(macro_name, 0)
+                    .filter(|dir| !dir.is_empty())
+                    .unwrap_or_else(|| value.to_string());
+                default(var, value);
+            }
+        }
+        dirs
+    }
+
+    fn expand<'a>(&'a self, text: &str, stack: &mut Vec<&'a str>) -> String {
+        let mut ret = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('$') {
+            ret.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let close = match after.chars().next() {
+                Some('{') => '}',
+                Some('(') => ')',
+                _ => {
+                    ret.push('$');
+                    rest = after;
+                    continue;
+                }
+            };
+            let Some(end) = after.find(close) else {
+                ret.push_str(&rest[start..]);
+                return ret;
+            };
+            let name = &after[1..end];
+            match self.values.get_key_value(name) {
+                Some((key, value)) if !stack.contains(&name) => {
+                    stack.push(key);
+                    ret.push_str(&self.expand(value, stack));
+                    stack.pop();
+                }
+                _ => ret.push_str(&rest[start..start + end + 2]),
+            }
+            rest = &after[end + 1..];
+        }
+        ret.push_str(rest);
+        ret
+    }
+}
+
+/// The default tarball name autoconf derives from a package name:
+/// lowercased, without any `GNU `, with other characters than letters,
+/// digits and `_` replaced by `-`.
+pub(crate) fn tarname_of(name: &str) -> String {
+    name.replace("GNU ", "")
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// A file installed by `make install`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InstallEntry {
+    /// The installed artefact, relative to the project root.
+    pub source: PathBuf,
+    /// The installation path, without `$(DESTDIR)`.
+    pub dest: PathBuf,
+    /// The permission bits the file is installed with.
+    pub mode: u32,
+    /// The variable listing the file, e.g. `bin_PROGRAMS`.
+    pub var: String,
+    /// The conditionals under which the file is installed.
+    pub cond: CondSet,
+}
+
+/// The files installed by a project.
+#[derive(Debug, Default, Clone)]
+pub struct InstallLayout {
+    /// The installed files, in the order of the makefiles and variables.
+    pub entries: Vec<InstallEntry>,
+    /// Files or directories which could not be resolved, e.g. `$(pythondir)`
+    /// when no value was given for it.
+    pub unresolved: Vec<UnresolvedWord>,
+}
+
+impl InstallLayout {
+    /// Compute the files installed by every makefile of a project. Each
+    /// makefile uses the package name of the nearest enclosing package.
+    pub fn new(project: &Project, dirs: &InstallDirs) -> Self {
+        let mut layout = Self::default();
+        for id in project.automake_files() {
+            let file = project.file(id);
+            let package = project.package_of(id).map(|ac| project.file(ac));
+            let dirs = match package.and_then(|ac| ac.as_autoconf()) {
+                Some(ac) => dirs.for_package(ac),
+                None => dirs.clone(),
+            };
+            let am = file.as_automake().unwrap();
+            layout.add_makefile(am, &file.path, &dirs);
+        }
+        layout
+    }
+
+    /// Compute the files installed by a single makefile, whose path is
+    /// relative to the project root.
+    pub fn of_makefile<P: AsRef<Path>>(am: &AutomakeFile, path: P, dirs: &InstallDirs) -> Self {
+        let mut layout = Self::default();
+        layout.add_makefile(am, path.as_ref(), dirs);
+        layout
+    }
+
+    /// Returns the entry installed to `dest`.
+    pub fn find<P: AsRef<Path>>(&self, dest: P) -> Option<&InstallEntry> {
+        self.entries.iter().find(|e| e.dest == dest.as_ref())
+    }
+
+    fn add_makefile(&mut self, am: &AutomakeFile, path: &Path, dirs: &InstallDirs) {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut eval = Evaluator::new(am);
+        for name in dirs.values.keys() {
+            eval.substitute(name, &dirs.resolve(name).unwrap());
+        }
+        eval.substitute("srcdir", ".");
+        eval.substitute("EXEEXT", "");
+
+        let vars = eval.variables().to_vec();
+        for var in vars {
+            let Some((nobase, prefix, primary)) = parse_install_var(var) else {
+                continue;
+            };
+            for (cond, words) in eval.eval(var).unwrap() {
+                for word in words {
+                    self.add_file(&eval, path, dir, var, &cond, &word, nobase, prefix, primary);
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_file(
+        &mut self,
+        eval: &Evaluator<'_>,
+        makefile: &Path,
+        dir: &Path,
+        var: &str,
+        cond: &CondSet,
+        word: &str,
+        nobase: bool,
+        prefix: &str,
+        primary: &str,
+    ) {
+        let mut unresolved = |word: String, cond: &CondSet| {
+            self.unresolved.push(UnresolvedWord {
+                makefile: makefile.to_path_buf(),
+                word,
+                cond: cond.clone(),
+            })
+        };
+        if word.contains('$') || word.contains('@') {
+            return unresolved(word.to_string(), cond);
+        }
+        let mut name = normalize(Path::new(word));
+        let mut dir_var = format!("{}dir", prefix);
+        match primary {
+            "MANS" => {
+                let (stem, ext) = split_extension(word);
+                if prefix == "man" {
+                    if let Some(section) = ext.chars().next() {
+                        dir_var = format!("man{}dir", section);
+                    }
+                } else if let Some(section) = prefix.strip_prefix("man") {
+                    // `man1_MANS = foo.man` installs `foo.1`.
+                    if !ext.starts_with(section) {
+                        name = PathBuf::from(format!("{}.{}", stem, section));
+                    }
+                }
+            }
+            "TEXINFOS" => {
+                name = PathBuf::from(format!("{}.info", split_extension(word).0));
+            }
+            _ => {}
+        }
+        let source = normalize(&dir.join(&name));
+        if !nobase {
+            name = PathBuf::from(name.file_name().unwrap_or_default());
+        }
+        let mode = match primary {
+            "PROGRAMS" | "SCRIPTS" => 0o755,
+            _ => 0o644,
+        };
+
+        for (dir_cond, values) in eval.eval_words(&[AmWord::variable(&dir_var)]) {
+            let Some(cond) = cond.and(&dir_cond) else {
+                continue;
+            };
+            let value = values.join(" ");
+            if value.contains('$') || value.contains('@') || !value.starts_with('/') {
+                unresolved(value, &cond);
+                continue;
+            }
+            self.entries.push(InstallEntry {
+                source: source.clone(),
+                dest: Path::new(&value).join(&name),
+                mode,
+                var: var.to_string(),
+                cond,
+            });
+        }
+    }
+}
+
+/// Split a variable such as `nobase_dist_pkgdata_DATA` into
+/// `(nobase, prefix, primary)`, or return `None` if it lists no installed files.
+fn parse_install_var(var: &str) -> Option<(bool, &str, &str)> {
+    let (mut rest, primary) = var.rsplit_once('_')?;
+    let primary = INSTALL_PRIMARIES.iter().find(|p| **p == primary)?;
+    let mut nobase = false;
+    loop {
+        if let Some(r) = rest.strip_prefix("nobase_") {
+            nobase = true;
+            rest = r;
+        } else if let Some(r) = ["dist_", "nodist_", "notrans_"]
+            .iter()
+            .find_map(|p| rest.strip_prefix(p))
+        {
+            rest = r;
+        } else {
+            break;
+        }
+    }
+    match rest {
+        "" | "noinst" | "check" | "EXTRA" => None,
+        _ => Some((nobase, rest, primary)),
+    }
+}
+
+/// Split the extension from the file name of a path, e.g. `("doc/foo", "1")`.
+fn split_extension(path: &str) -> (&str, &str) {
+    let start = path.rfind('/').map_or(0, |i| i + 1);
+    match path[start..].rfind('.') {
+        Some(i) => (&path[..start + i], &path[start + i + 1..]),
+        None => (path, ""),
+    }
+}
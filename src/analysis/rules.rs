@@ -0,0 +1,618 @@
+//! Dependency graph of the rules of a `Makefile.am`.
+//!
+//! Explicit rules give the edges of the graph directly. Pattern rules
+//! (`%.o: %.c`) and suffix rules (`.c.o:`) are kept as inference rules,
+//! which add edges to the targets they can build whenever the makefile
+//! mentions the prerequisites they derive. The `-local` and `-hook` targets
+//! automake recognises are attached to the standard targets invoking them,
+//! e.g. `clean` depends on `clean-local`.
+//!
+//! Targets and prerequisites are expanded with the variable evaluator. Rules
+//! under automake conditionals contribute their edges regardless of the
+//! conditionals, which are recorded on the targets.
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::ast::am::AmWord;
+use crate::ast::node::NodeId;
+use crate::project::AutomakeFile;
+
+use super::functions::pattern_stem;
+use super::variables::Evaluator;
+use super::{conditional_rules, CondSet};
+
+/// Standard targets which run a `-local` target, e.g. `clean-local`.
+const LOCAL_TARGETS: &[&str] = &[
+    "all",
+    "info",
+    "dvi",
+    "pdf",
+    "ps",
+    "html",
+    "check",
+    "installcheck",
+    "installdirs",
+    "install-data",
+    "install-exec",
+    "install-dvi",
+    "install-html",
+    "install-info",
+    "install-pdf",
+    "install-ps",
+    "uninstall",
+    "mostlyclean",
+    "clean",
+    "distclean",
+    "maintainer-clean",
+];
+
+/// Standard targets which run a `-hook` target once done, e.g. `install-data-hook`.
+const HOOK_TARGETS: &[&str] = &[
+    "install-data",
+    "install-exec",
+    "uninstall",
+    "dist",
+    "distcheck",
+];
+
+/// How a standard target runs a user-provided extension target.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ExtensionKind {
+    /// A `-local` target, run along with the work of the standard target.
+    Local,
+    /// A `-hook` target, run after the work of the standard target.
+    Hook,
+}
+
+/// A target automake invokes from one of its standard targets.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Extension {
+    /// Whether the target is a `-local` or a `-hook` target.
+    pub kind: ExtensionKind,
+    /// The standard target invoking it, e.g. `clean` for `clean-local`.
+    pub base: &'static str,
+}
+
+impl Extension {
+    /// Returns the extension named by a target, e.g. `install-exec-hook`.
+    pub fn from_target(name: &str) -> Option<Self> {
+        let (base, kind, known) = if let Some(base) = name.strip_suffix("-local") {
+            (base, ExtensionKind::Local, LOCAL_TARGETS)
+        } else if let Some(base) = name.strip_suffix("-hook") {
+            (base, ExtensionKind::Hook, HOOK_TARGETS)
+        } else {
+            return None;
+        };
+        let base = known.iter().find(|known| **known == base)?;
+        Some(Self { kind, base })
+    }
+}
+
+/// A rule building targets matching a pattern, from a pattern rule such
+/// as `%.o: %.c` or a suffix rule such as `.c.o:`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InferenceRule {
+    /// The target pattern, e.g. `%.o`.
+    pub target: String,
+    /// The prerequisite patterns, e.g. `%.c`.
+    pub prerequisites: Vec<String>,
+    /// Whether the rule was written as a suffix rule.
+    pub suffix_rule: bool,
+    /// The conditionals the rule is defined under.
+    pub cond: CondSet,
+    /// The recipe of the rule.
+    pub recipe: Vec<NodeId>,
+}
+
+impl InferenceRule {
+    /// Returns the prerequisites of `name` if the rule applies to it.
+    pub fn apply(&self, name: &str) -> Option<Vec<String>> {
+        let stem = pattern_stem(&self.target, name)?;
+        Some(
+            self.prerequisites
+                .iter()
+                .map(|p| p.replacen('%', stem, 1))
+                .collect(),
+        )
+    }
+
+    /// Returns the targets the rule builds from the prerequisite `name`.
+    pub fn targets_from(&self, name: &str) -> Vec<String> {
+        self.prerequisites
+            .iter()
+            .filter(|p| p.contains('%'))
+            .filter_map(|p| pattern_stem(p, name))
+            .map(|stem| self.target.replacen('%', stem, 1))
+            .collect()
+    }
+}
+
+/// A target of explicit rules.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RuleTarget {
+    /// The target name.
+    pub name: String,
+    /// The prerequisites given by the rules of the target.
+    pub prerequisites: Vec<String>,
+    /// The target is defined whenever any of these conditions holds.
+    pub conds: Vec<CondSet>,
+    /// The recipes of the rules of the target.
+    pub recipe: Vec<NodeId>,
+    /// Whether the target is defined by `::` rules.
+    pub double_colon: bool,
+    /// The standard target invoking it, if it is an extension target.
+    pub extension: Option<Extension>,
+}
+
+/// The dependency graph of the rules of a makefile.
+#[derive(Debug, Clone, Default)]
+pub struct RuleGraph {
+    targets: Vec<RuleTarget>,
+    phony: BTreeSet<String>,
+    suffixes: Vec<String>,
+    inference: Vec<InferenceRule>,
+    /// All names mentioned by the graph, in sorted order.
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    /// Prerequisites of each name, by index.
+    edges: Vec<Vec<usize>>,
+}
+
+impl RuleGraph {
+    /// Build the graph of the rules of a makefile and its included fragments.
+    pub fn new(am: &AutomakeFile) -> Self {
+        let eval = Evaluator::new(am);
+        let expand = |words: &[AmWord], cond: &CondSet| {
+            let mut ret: Vec<String> = Vec::new();
+            for (alt, words) in eval.eval_words(words) {
+                if !alt.is_compatible(cond) {
+                    continue;
+                }
+                for word in words {
+                    if !ret.contains(&word) {
+                        ret.push(word);
+                    }
+                }
+            }
+            ret
+        };
+
+        let mut graph = Self::default();
+        let rules = conditional_rules(am)
+            .into_iter()
+            .map(|(cond, rule)| {
+                let targets = expand(&rule.target, &cond);
+                let mut prerequisites = expand(&rule.dependency, &cond);
+                let double_colon = prerequisites.first().is_some_and(|p| p == ":");
+                if double_colon {
+                    prerequisites.remove(0);
+                }
+                (cond, targets, prerequisites, double_colon, &rule.recipe)
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(values) = eval.eval("SUFFIXES") {
+            for word in values.into_values().flatten() {
+                graph.add_suffix(word);
+            }
+        }
+        for (_, targets, prerequisites, _, _) in &rules {
+            if targets.iter().any(|t| t == ".SUFFIXES") {
+                if prerequisites.is_empty() {
+                    graph.suffixes.clear();
+                }
+                for p in prerequisites {
+                    graph.add_suffix(p.clone());
+                }
+            }
+            if targets.iter().any(|t| t == ".PHONY") {
+                graph.phony.extend(prerequisites.iter().cloned());
+            }
+        }
+
+        for (cond, targets, prerequisites, double_colon, recipe) in rules {
+            for target in targets {
+                if is_special_target(&target) {
+                    continue;
+                }
+                if let Some(rule) = graph.inference_rule(&target, &prerequisites, &cond, recipe) {
+                    graph.inference.push(rule);
+                    continue;
+                }
+                graph.add_target(target, &prerequisites, &cond, double_colon, recipe);
+            }
+        }
+
+        let extensions = graph
+            .targets
+            .iter()
+            .filter_map(|t| {
+                t.extension
+                    .map(|ext| (ext.base, t.name.clone(), t.conds.clone()))
+            })
+            .collect::<Vec<_>>();
+        for (base, name, conds) in extensions {
+            graph.phony.insert(base.to_string());
+            graph.phony.insert(name.clone());
+            for cond in conds {
+                graph.add_target(
+                    base.to_string(),
+                    std::slice::from_ref(&name),
+                    &cond,
+                    false,
+                    &[],
+                );
+            }
+        }
+
+        graph.build_edges();
+        graph
+    }
+
+    fn add_suffix(&mut self, suffix: String) {
+        if !self.suffixes.contains(&suffix) {
+            self.suffixes.push(suffix);
+        }
+    }
+
+    /// Returns the inference rule defined by a rule, if it is a pattern or a suffix rule.
+    fn inference_rule(
+        &mut self,
+        target: &str,
+        prerequisites: &[String],
+        cond: &CondSet,
+        recipe: &[NodeId],
+    ) -> Option<InferenceRule> {
+        let (target, prerequisites, suffix_rule) = if target.contains('%') {
+            (target.to_string(), prerequisites.to_vec(), false)
+        } else {
+            // Suffix rules with prerequisites are ordinary rules.
+            if !prerequisites.is_empty() {
+                return None;
+            }
+            let (from, to) = self.split_suffix_rule(target)?;
+            self.add_suffix(from.to_string());
+            if !to.is_empty() {
+                self.add_suffix(to.to_string());
+            }
+            (format!("%{}", to), vec![format!("%{}", from)], true)
+        };
+        Some(InferenceRule {
+            target,
+            prerequisites,
+            suffix_rule,
+            cond: cond.clone(),
+            recipe: recipe.to_vec(),
+        })
+    }
+
+    /// Split a suffix rule target such as `.c.o` into its suffixes.
+    ///
+    /// Like automake, any target made of two suffixes is a suffix rule,
+    /// while single suffix rules must use a suffix declared in `.SUFFIXES`.
+    fn split_suffix_rule<'t>(&self, target: &'t str) -> Option<(&'t str, &'t str)> {
+        let rest = target.strip_prefix('.')?;
+        if rest.is_empty() || rest.contains(['/', '%', '$']) {
+            return None;
+        }
+        match rest.find('.') {
+            Some(i) if !rest[i + 1..].is_empty() && !rest[i + 1..].contains('.') => {
+                Some(target.split_at(i + 1))
+            }
+            Some(_) => None,
+            None => self
+                .suffixes
+                .iter()
+                .any(|s| s == target)
+                .then_some((target, "")),
+        }
+    }
+
+    fn add_target(
+        &mut self,
+        name: String,
+        prerequisites: &[String],
+        cond: &CondSet,
+        double_colon: bool,
+        recipe: &[NodeId],
+    ) {
+        let target = match self.targets.iter_mut().find(|t| t.name == name) {
+            Some(target) => target,
+            None => {
+                self.targets.push(RuleTarget {
+                    extension: Extension::from_target(&name),
+                    name,
+                    prerequisites: Vec::new(),
+                    conds: Vec::new(),
+                    recipe: Vec::new(),
+                    double_colon,
+                });
+                self.targets.last_mut().unwrap()
+            }
+        };
+        for p in prerequisites {
+            if !target.prerequisites.contains(p) {
+                target.prerequisites.push(p.clone());
+            }
+        }
+        if !target.conds.contains(cond) {
+            target.conds.push(cond.clone());
+        }
+        target.recipe.extend_from_slice(recipe);
+    }
+
+    fn build_edges(&mut self) {
+        let mut edges: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for target in &self.targets {
+            let prerequisites = edges.entry(&target.name).or_default();
+            prerequisites.extend(target.prerequisites.iter().map(String::as_str));
+        }
+        for target in &self.targets {
+            for p in &target.prerequisites {
+                edges.entry(p).or_default();
+            }
+        }
+
+        // Infer prerequisites of the names built without an explicit recipe,
+        // using the first inference rule whose prerequisites are all mentioned.
+        let mut inferred = Vec::new();
+        for name in edges.keys() {
+            let explicit = self.target(name).is_some_and(|t| !t.recipe.is_empty());
+            if explicit || self.phony.contains(*name) {
+                continue;
+            }
+            let prerequisites = self
+                .inference
+                .iter()
+                .filter_map(|rule| rule.apply(name))
+                .find(|p| {
+                    p.iter()
+                        .all(|p| p != name && edges.contains_key(p.as_str()))
+                });
+            if let Some(prerequisites) = prerequisites {
+                inferred.push((*name, prerequisites));
+            }
+        }
+
+        self.names = edges.keys().map(|name| name.to_string()).collect();
+        self.index = self
+            .names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), i))
+            .collect();
+        let mut adjacency = vec![Vec::new(); self.names.len()];
+        let explicit = edges
+            .iter()
+            .flat_map(|(name, ps)| ps.iter().map(move |p| (*name, *p)));
+        let inferred = inferred
+            .iter()
+            .flat_map(|(name, ps)| ps.iter().map(move |p| (*name, p.as_str())));
+        for (name, p) in explicit.chain(inferred) {
+            let (from, to) = (self.index[name], self.index[p]);
+            if !adjacency[from].contains(&to) {
+                adjacency[from].push(to);
+            }
+        }
+        self.edges = adjacency;
+    }
+
+    /// The targets of explicit rules, in the order of their first rule.
+    pub fn targets(&self) -> &[RuleTarget] {
+        &self.targets
+    }
+
+    /// Returns the explicit rules of a target, merged together.
+    pub fn target(&self, name: &str) -> Option<&RuleTarget> {
+        self.targets.iter().find(|t| t.name == name)
+    }
+
+    /// The pattern and suffix rules, in the order of definition.
+    pub fn inference_rules(&self) -> &[InferenceRule] {
+        &self.inference
+    }
+
+    /// The known suffixes, from `SUFFIXES`, `.SUFFIXES` and the suffix rules.
+    pub fn suffixes(&self) -> &[String] {
+        &self.suffixes
+    }
+
+    /// Returns whether a target is phony, either listed in `.PHONY`
+    /// or a standard target with `-local` or `-hook` extensions.
+    pub fn is_phony(&self, name: &str) -> bool {
+        self.phony.contains(name)
+    }
+
+    /// All targets and prerequisites of the graph, in sorted order.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns the prerequisites of a name, including inferred and extension targets.
+    pub fn prerequisites(&self, name: &str) -> Vec<&str> {
+        match self.index.get(name) {
+            Some(i) => self.edges[*i]
+                .iter()
+                .map(|p| self.names[*p].as_str())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the names depending directly on `name`.
+    pub fn dependents(&self, name: &str) -> Vec<&str> {
+        let Some(i) = self.index.get(name) else {
+            return Vec::new();
+        };
+        self.edges
+            .iter()
+            .enumerate()
+            .filter(|(_, ps)| ps.contains(i))
+            .map(|(n, _)| self.names[n].as_str())
+            .collect()
+    }
+
+    /// Returns the dependency cycles of the graph, each in the order of the edges.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        self.components()
+            .into_iter()
+            .filter(|c| c.len() > 1 || self.edges[c[0]].contains(&c[0]))
+            .map(|c| self.cycle_path(&c))
+            .collect()
+    }
+
+    /// Returns the names with prerequisites ordered before their dependents,
+    /// or one dependency cycle if there is any.
+    pub fn topological_order(&self) -> Result<Vec<&str>, Vec<String>> {
+        let mut missing = self.edges.iter().map(Vec::len).collect::<Vec<_>>();
+        let mut dependents = vec![Vec::new(); self.names.len()];
+        for (n, ps) in self.edges.iter().enumerate() {
+            for p in ps {
+                dependents[*p].push(n);
+            }
+        }
+        let mut ready = (0..self.names.len())
+            .filter(|n| missing[*n] == 0)
+            .collect::<BTreeSet<_>>();
+        let mut ret = Vec::new();
+        while let Some(n) = ready.pop_first() {
+            ret.push(self.names[n].as_str());
+            for d in &dependents[n] {
+                missing[*d] -= 1;
+                if missing[*d] == 0 {
+                    ready.insert(*d);
+                }
+            }
+        }
+        if ret.len() == self.names.len() {
+            Ok(ret)
+        } else {
+            Err(self.cycles().into_iter().next().unwrap_or_default())
+        }
+    }
+
+    /// Returns the targets rebuilt when `name` changes, following the
+    /// dependents in the graph and the targets inference rules build from them.
+    pub fn rebuilt_by(&self, name: &str) -> BTreeSet<String> {
+        let mut ret = BTreeSet::new();
+        // The number of inference steps taken since the last name of the graph,
+        // bounded to stop rules such as `%.x: %` from producing names forever.
+        let mut queue = vec![(name.to_string(), 0)];
+        while let Some((name, steps)) = queue.pop() {
+            let mut next = self
+                .dependents(&name)
+                .into_iter()
+                .map(|d| (d.to_string(), 0))
+                .collect::<Vec<_>>();
+            if steps < self.inference.len() {
+                for rule in &self.inference {
+                    for target in rule.targets_from(&name) {
+                        let steps = if self.index.contains_key(&target) {
+                            0
+                        } else {
+                            steps + 1
+                        };
+                        next.push((target, steps));
+                    }
+                }
+            }
+            for (target, steps) in next {
+                if ret.insert(target.clone()) {
+                    queue.push((target, steps));
+                }
+            }
+        }
+        ret.remove(name);
+        ret
+    }
+
+    /// Strongly connected components, with Tarjan's algorithm.
+    fn components(&self) -> Vec<Vec<usize>> {
+        struct State {
+            index: Vec<Option<usize>>,
+            low: Vec<usize>,
+            on_stack: Vec<bool>,
+            stack: Vec<usize>,
+            next: usize,
+            ret: Vec<Vec<usize>>,
+        }
+        fn visit(edges: &[Vec<usize>], n: usize, s: &mut State) {
+            s.index[n] = Some(s.next);
+            s.low[n] = s.next;
+            s.next += 1;
+            s.stack.push(n);
+            s.on_stack[n] = true;
+            for p in &edges[n] {
+                match s.index[*p] {
+                    None => {
+                        visit(edges, *p, s);
+                        s.low[n] = s.low[n].min(s.low[*p]);
+                    }
+                    Some(i) if s.on_stack[*p] => s.low[n] = s.low[n].min(i),
+                    Some(_) => {}
+                }
+            }
+            if Some(s.low[n]) == s.index[n] {
+                let mut component = Vec::new();
+                while let Some(m) = s.stack.pop() {
+                    s.on_stack[m] = false;
+                    component.push(m);
+                    if m == n {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                s.ret.push(component);
+            }
+        }
+        let len = self.names.len();
+        let mut state = State {
+            index: vec![None; len],
+            low: vec![0; len],
+            on_stack: vec![false; len],
+            stack: Vec::new(),
+            next: 0,
+            ret: Vec::new(),
+        };
+        for n in 0..len {
+            if state.index[n].is_none() {
+                visit(&self.edges, n, &mut state);
+            }
+        }
+        state.ret.sort();
+        state.ret
+    }
+
+    /// Returns a cycle through members of a strongly connected component.
+    fn cycle_path(&self, component: &[usize]) -> Vec<String> {
+        let start = component[0];
+        let mut path = vec![start];
+        let mut seen = BTreeSet::from([start]);
+        loop {
+            let n = *path.last().unwrap();
+            let next = self.edges[n]
+                .iter()
+                .copied()
+                .filter(|p| component.contains(p))
+                .min_by_key(|p| (*p != start, seen.contains(p)))
+                .unwrap();
+            if next == start {
+                break;
+            }
+            if !seen.insert(next) {
+                // Restart from the repeated member to keep the path a cycle.
+                let i = path.iter().position(|p| *p == next).unwrap();
+                path.drain(..i);
+                break;
+            }
+            path.push(next);
+        }
+        path.into_iter().map(|n| self.names[n].clone()).collect()
+    }
+}
+
+/// Returns whether a target is a special target of make, e.g. `.PHONY`.
+fn is_special_target(name: &str) -> bool {
+    name.strip_prefix('.').is_some_and(|rest| {
+        !rest.is_empty() && rest.chars().all(|c| c.is_ascii_uppercase() || c == '_')
+    })
+}
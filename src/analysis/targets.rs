@@ -0,0 +1,194 @@
+//! Typed model of the programs and libraries declared in a `Makefile.am`.
+//!
+//! Automake derives everything about a target from variable names:
+//! `bin_PROGRAMS = foo` declares the program `foo` installed to `bindir`,
+//! and `foo_SOURCES`, `foo_CPPFLAGS`, `foo_LDADD` etc. describe how it is built.
+//! Names are canonicalized before use as a prefix, so the sources of
+//! `libfoo.la` are listed in `libfoo_la_SOURCES`.
+use crate::ast::am::{AmWord, MayAm};
+use crate::ast::minimal::{Word, WordFragment};
+use crate::project::AutomakeFile;
+
+pub use crate::util::canonicalize;
+
+use super::{conditional_assignments, conditional_words, CondSet, CondWord};
+
+/// Kinds of compiled targets.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Primary {
+    /// `PROGRAMS`
+    Programs,
+    /// `LIBRARIES`, static libraries.
+    Libraries,
+    /// `LTLIBRARIES`, libtool libraries.
+    LtLibraries,
+}
+
+impl Primary {
+    /// Returns the primary named by the suffix of a variable, e.g. `PROGRAMS`.
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        match suffix {
+            "PROGRAMS" => Some(Self::Programs),
+            "LIBRARIES" => Some(Self::Libraries),
+            "LTLIBRARIES" => Some(Self::LtLibraries),
+            _ => None,
+        }
+    }
+
+    /// The variable suffix listing the link dependencies of targets of this primary.
+    pub fn link_var(&self) -> &'static str {
+        match self {
+            Self::Programs => "LDADD",
+            Self::Libraries | Self::LtLibraries => "LIBADD",
+        }
+    }
+}
+
+/// A program or library declared in a `Makefile.am`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Target {
+    /// The target name, e.g. `libfoo.la`.
+    pub name: String,
+    /// The canonicalized name used as a variable prefix, e.g. `libfoo_la`.
+    pub canonical: String,
+    /// The kind of the target.
+    pub primary: Primary,
+    /// The install directory prefix, e.g. `bin`, `pkglib`, `noinst`, `check` or `EXTRA`.
+    pub dir: String,
+    /// Whether the `nobase_` prefix was given.
+    pub nobase: bool,
+    /// The target is declared whenever any of these conditions holds.
+    pub conds: Vec<CondSet>,
+    /// Sources from `_SOURCES` and `dist_` `_SOURCES`, or the default source
+    /// if none of the source variables is defined.
+    pub sources: Vec<CondWord>,
+    /// Whether `sources` is the default derived from the target name.
+    pub default_sources: bool,
+    /// Sources from `nodist_` `_SOURCES`.
+    pub nodist_sources: Vec<CondWord>,
+    /// Sources from `EXTRA_` `_SOURCES`.
+    pub extra_sources: Vec<CondWord>,
+    /// `_CPPFLAGS` if defined.
+    pub cppflags: Option<Vec<CondWord>>,
+    /// `_CFLAGS` if defined.
+    pub cflags: Option<Vec<CondWord>>,
+    /// `_CXXFLAGS` if defined.
+    pub cxxflags: Option<Vec<CondWord>>,
+    /// `_LDFLAGS` if defined.
+    pub ldflags: Option<Vec<CondWord>>,
+    /// `_LDADD` for programs and `_LIBADD` for libraries, if defined.
+    pub ldadd: Option<Vec<CondWord>>,
+    /// `_DEPENDENCIES` if defined.
+    pub dependencies: Option<Vec<CondWord>>,
+}
+
+/// Split a variable such as `nobase_pkglib_LTLIBRARIES` into `(nobase, dir, primary)`.
+fn parse_primary_var(var: &str) -> Option<(bool, &str, Primary)> {
+    let (rest, suffix) = var.rsplit_once('_')?;
+    let primary = Primary::from_suffix(suffix)?;
+    let (nobase, rest) = match rest.strip_prefix("nobase_") {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    if rest.is_empty() {
+        None
+    } else {
+        Some((nobase, rest, primary))
+    }
+}
+
+/// Collect the programs and libraries of a makefile in the order of declaration.
+///
+/// Target names which are not literals (e.g. `$(EXTRA_PROGS)` or `@PROGS@`)
+/// can not be resolved without evaluating the makefile and are skipped.
+pub fn collect_targets(am: &AutomakeFile) -> Vec<Target> {
+    let default_ext = conditional_words(am, "AM_DEFAULT_SOURCE_EXT")
+        .and_then(|words| words.first().and_then(|w| w.word.as_literal()))
+        .unwrap_or_else(|| ".c".to_string());
+
+    let mut targets: Vec<Target> = Vec::new();
+    for (cond, assignment) in conditional_assignments(am) {
+        let Some((nobase, dir, primary)) = parse_primary_var(&assignment.lhs) else {
+            continue;
+        };
+        for name in assignment.rhs.iter().filter_map(AmWord::as_literal) {
+            let existing = targets
+                .iter_mut()
+                .find(|t| t.name == name && t.dir == dir && t.primary == primary);
+            if let Some(target) = existing {
+                if !target.conds.contains(&cond) {
+                    target.conds.push(cond.clone());
+                }
+                continue;
+            }
+            targets.push(new_target(
+                am,
+                name,
+                primary,
+                dir,
+                nobase,
+                &cond,
+                &default_ext,
+            ));
+        }
+    }
+    targets
+}
+
+fn new_target(
+    am: &AutomakeFile,
+    name: String,
+    primary: Primary,
+    dir: &str,
+    nobase: bool,
+    cond: &CondSet,
+    default_ext: &str,
+) -> Target {
+    let canonical = canonicalize(&name);
+    let var = |suffix: &str| conditional_words(am, &format!("{}_{}", canonical, suffix));
+    // Sources in `dist_` are built like those in `_SOURCES`.
+    let dist_sources = conditional_words(am, &format!("dist_{}_SOURCES", canonical));
+    let nodist_sources = conditional_words(am, &format!("nodist_{}_SOURCES", canonical));
+    let extra_sources = conditional_words(am, &format!("EXTRA_{}_SOURCES", canonical));
+    let (sources, default_sources) = match (var("SOURCES"), dist_sources) {
+        (None, None) if nodist_sources.is_none() => {
+            let stem = match primary {
+                Primary::Programs => name.as_str(),
+                Primary::Libraries | Primary::LtLibraries => name
+                    .rsplit_once('.')
+                    .map_or(name.as_str(), |(stem, _)| stem),
+            };
+            let word = AmWord(Word::Single(MayAm::Shell(WordFragment::Literal(format!(
+                "{}{}",
+                stem, default_ext
+            )))));
+            let source = CondWord {
+                cond: CondSet::always(),
+                word,
+            };
+            (vec![source], true)
+        }
+        (sources, dist_sources) => (
+            sources.into_iter().chain(dist_sources).flatten().collect(),
+            false,
+        ),
+    };
+    Target {
+        canonical: canonical.clone(),
+        primary,
+        dir: dir.to_string(),
+        nobase,
+        conds: vec![cond.clone()],
+        sources,
+        default_sources,
+        nodist_sources: nodist_sources.unwrap_or_default(),
+        extra_sources: extra_sources.unwrap_or_default(),
+        cppflags: var("CPPFLAGS"),
+        cflags: var("CFLAGS"),
+        cxxflags: var("CXXFLAGS"),
+        ldflags: var("LDFLAGS"),
+        ldadd: var(primary.link_var()),
+        dependencies: var("DEPENDENCIES"),
+        name,
+    }
+}
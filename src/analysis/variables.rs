@@ -0,0 +1,425 @@
+//! Conditional-aware evaluation of `Makefile.am` variables.
+//!
+//! Like automake itself, the evaluator gives the possible values of a variable
+//! as a map from the set of automake conditionals to the list of words the
+//! variable expands to under them. Recursively expanded (`=`) and simply
+//! expanded (`:=`) variables follow the semantics of make: the former are
+//! expanded when referenced, the latter when assigned. `+=` keeps the flavor
+//! of the variable it appends to, and `?=` only assigns where the variable is
+//! still undefined. The value of `!=` is the output of a shell command, which
+//! is represented as `$(shell ...)`.
+//!
+//! References to variables the makefile does not define (e.g. `$(top_srcdir)`)
+//! and `@VAR@` substitutions are kept verbatim unless a value was given with
+//! `Evaluator::substitute`. Substitution references and the text functions of
+//! `functions` are evaluated, while other function calls are kept verbatim
+//! with their arguments expanded. So are the substitution references and
+//! function calls with an argument holding such a verbatim reference, whose
+//! value is not known.
+use std::collections::{BTreeMap, HashMap};
+
+use crate::ast::am::{AmAssignOp, AmVar, AmWord, AmWordFragment, MakeParameter, MayAm};
+use crate::ast::minimal::{Word, WordFragment};
+use crate::ast::node::{NodeId, NodePool};
+use crate::project::AutomakeFile;
+
+use super::{conditional_assignments, functions, CondSet};
+
+/// Possible values of a variable, keyed by the conditionals they depend on.
+pub type CondValues = BTreeMap<CondSet, Vec<String>>;
+
+/// Expanded text alternatives, each holding under its own conditionals.
+type CondTexts = Vec<(CondSet, Text)>;
+
+/// An expanded text.
+#[derive(Debug, Clone)]
+struct Text {
+    value: String,
+    /// Whether the value holds references kept verbatim, e.g. to undefined
+    /// variables, whose expansion is not known.
+    unknown: bool,
+}
+
+impl Text {
+    fn known(value: String) -> Self {
+        Text {
+            value,
+            unknown: false,
+        }
+    }
+
+    fn unknown(value: String) -> Self {
+        Text {
+            value,
+            unknown: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Definition<'a> {
+    /// Position of the assignment in the makefile.
+    pos: usize,
+    cond: CondSet,
+    op: AmAssignOp,
+    rhs: &'a [AmWord],
+}
+
+/// A part of a variable value.
+#[derive(Debug, Clone)]
+enum Piece<'a> {
+    /// A word to be expanded when the variable is referenced.
+    Word(&'a AmWord),
+    /// Text already expanded at the assignment.
+    Text(CondTexts),
+}
+
+/// Evaluates the variables of a `Makefile.am`.
+#[derive(Debug)]
+pub struct Evaluator<'a> {
+    am: &'a AutomakeFile,
+    names: Vec<&'a str>,
+    defs: HashMap<&'a str, Vec<Definition<'a>>>,
+    substs: HashMap<String, String>,
+}
+
+impl<'a> Evaluator<'a> {
+    /// Collect the variable definitions of a makefile.
+    pub fn new(am: &'a AutomakeFile) -> Self {
+        let mut names = Vec::new();
+        let mut defs: HashMap<&str, Vec<Definition<'_>>> = HashMap::new();
+        for (pos, (cond, assignment)) in conditional_assignments(am).into_iter().enumerate() {
+            let name = assignment.lhs.as_str();
+            if !defs.contains_key(name) {
+                names.push(name);
+            }
+            defs.entry(name).or_default().push(Definition {
+                pos,
+                cond,
+                op: assignment.op,
+                rhs: &assignment.rhs,
+            });
+        }
+        Self {
+            am,
+            names,
+            defs,
+            substs: HashMap::new(),
+        }
+    }
+
+    /// Give a value to `@name@` substitutions and to references of `name`
+    /// which the makefile does not define.
+    pub fn substitute(&mut self, name: &str, value: &str) {
+        self.substs.insert(name.to_string(), value.to_string());
+    }
+
+    /// Names of the variables defined in the makefile, in the order of the first definition.
+    pub fn variables(&self) -> &[&'a str] {
+        &self.names
+    }
+
+    /// Returns whether the makefile defines the variable under any conditionals.
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.defs.contains_key(name)
+    }
+
+    /// Evaluate a variable, or return `None` if the makefile does not define it.
+    pub fn eval(&self, name: &str) -> Option<CondValues> {
+        let texts = self.eval_text(name, usize::MAX, &mut Vec::new())?;
+        Some(split_words(texts))
+    }
+
+    /// Expand arbitrary words in the context of the makefile.
+    pub fn eval_words(&self, words: &[AmWord]) -> CondValues {
+        let pieces = words.iter().map(Piece::Word).collect::<Vec<_>>();
+        let texts = self.expand_pieces(&pieces, CondSet::always(), usize::MAX, &mut Vec::new());
+        split_words(texts)
+    }
+
+    /// Expand a variable considering only the assignments before `limit`.
+    fn eval_text(
+        &self,
+        name: &str,
+        limit: usize,
+        stack: &mut Vec<(String, usize)>,
+    ) -> Option<CondTexts> {
+        let defs = self.defs.get(name)?;
+        if defs.first().is_none_or(|def| def.pos >= limit) {
+            return None;
+        }
+        let key = (name.to_string(), limit);
+        if stack.contains(&key) {
+            // make refuses recursive variables; treat them as empty.
+            return Some(vec![(CondSet::always(), Text::known(String::new()))]);
+        }
+        stack.push(key);
+        // `None` marks the alternatives under which the variable is still undefined.
+        let mut alternatives = vec![(CondSet::always(), None)];
+        let mut simple = false;
+        for def in defs.iter().take_while(|def| def.pos < limit) {
+            let immediate = match def.op {
+                AmAssignOp::Lazy | AmAssignOp::IfUndefined => false,
+                AmAssignOp::Instant | AmAssignOp::Shell => true,
+                AmAssignOp::Append => simple,
+            };
+            let pieces = if immediate {
+                let words = def.rhs.iter().map(Piece::Word).collect::<Vec<_>>();
+                let mut texts = self.expand_pieces(&words, def.cond.clone(), def.pos, stack);
+                if def.op == AmAssignOp::Shell {
+                    // The output of the command is not known without running it.
+                    for (_, text) in texts.iter_mut() {
+                        *text = Text::unknown(format!("$(shell {})", text.value));
+                    }
+                }
+                vec![Piece::Text(texts)]
+            } else {
+                def.rhs.iter().map(Piece::Word).collect()
+            };
+            match def.op {
+                AmAssignOp::Append => {}
+                AmAssignOp::IfUndefined => {
+                    if alternatives.iter().all(|(_, old)| old.is_none()) {
+                        simple = false;
+                    }
+                }
+                op => simple = op == AmAssignOp::Instant,
+            }
+            alternatives = apply(alternatives, &def.cond, |old| match (def.op, old) {
+                (AmAssignOp::Append, Some(old)) => {
+                    Some(old.iter().cloned().chain(pieces.iter().cloned()).collect())
+                }
+                (AmAssignOp::IfUndefined, Some(old)) => Some(old.to_vec()),
+                _ => Some(pieces.clone()),
+            });
+        }
+        let mut ret = Vec::new();
+        for (cond, pieces) in alternatives {
+            let pieces = pieces.unwrap_or_default();
+            ret.extend(self.expand_pieces(&pieces, cond, limit, stack));
+        }
+        stack.pop();
+        Some(ret)
+    }
+
+    /// Expand pieces separated by spaces under the conditionals `cond`.
+    fn expand_pieces(
+        &self,
+        pieces: &[Piece<'_>],
+        cond: CondSet,
+        limit: usize,
+        stack: &mut Vec<(String, usize)>,
+    ) -> CondTexts {
+        let mut ret = vec![(cond, Text::known(String::new()))];
+        for piece in pieces {
+            let texts = match piece {
+                Piece::Word(word) => self.expand_word(word, limit, stack),
+                Piece::Text(texts) => texts.clone(),
+            };
+            ret = product(&ret, &texts, " ");
+        }
+        ret
+    }
+
+    fn expand_word(
+        &self,
+        word: &AmWord,
+        limit: usize,
+        stack: &mut Vec<(String, usize)>,
+    ) -> CondTexts {
+        let frags = match &word.0 {
+            Word::Single(frag) => std::slice::from_ref(frag),
+            Word::Concat(frags) => frags.as_slice(),
+            Word::Empty => &[],
+        };
+        let mut ret = vec![(CondSet::always(), Text::known(String::new()))];
+        for frag in frags {
+            let texts = self.expand_fragment(frag, limit, stack);
+            ret = product(&ret, &texts, "");
+        }
+        ret
+    }
+
+    fn expand_fragment(
+        &self,
+        frag: &AmWordFragment,
+        limit: usize,
+        stack: &mut Vec<(String, usize)>,
+    ) -> CondTexts {
+        let text = match frag {
+            MayAm::Automake(AmVar::Param(MakeParameter::Var(name))) => {
+                return self.expand_var(name, limit, stack)
+            }
+            MayAm::Automake(AmVar::Computed(name)) => return self.expand_name(name, limit, stack),
+            MayAm::Automake(AmVar::SubstRef(r)) => {
+                let lists = [
+                    self.expand_name(&r.var, limit, stack),
+                    self.expand_word(&r.pattern, limit, stack),
+                    self.expand_word(&r.replacement, limit, stack),
+                ];
+                return combine(&lists, |args, unknown| {
+                    if unknown {
+                        Text::unknown(format!("$({}:{}={})", r.var, args[1], args[2]))
+                    } else {
+                        Text::known(functions::substitution_reference(args[0], args[1], args[2]))
+                    }
+                });
+            }
+            MayAm::Automake(AmVar::Function(func)) => {
+                let lists = func
+                    .args
+                    .iter()
+                    .map(|arg| {
+                        let pieces = arg.iter().map(Piece::Word).collect::<Vec<_>>();
+                        self.expand_pieces(&pieces, CondSet::always(), limit, stack)
+                    })
+                    .collect::<Vec<_>>();
+                return combine(&lists, |args, unknown| {
+                    match functions::call(&func.name, args).filter(|_| !unknown) {
+                        Some(value) => Text::known(value),
+                        None => Text::unknown(format!("$({} {})", func.name, args.join(","))),
+                    }
+                });
+            }
+            MayAm::Automake(AmVar::Template(name)) => match self.substs.get(name) {
+                Some(value) => Text::known(value.clone()),
+                None => Text::unknown(format!("@{}@", name)),
+            },
+            MayAm::Automake(var) => Text::unknown(var.to_string()),
+            // Command substitutions refer to nodes of the makefile.
+            MayAm::Shell(shell) if has_subst(shell) => {
+                Text::known(self.am.shell_word_to_string(shell))
+            }
+            MayAm::Shell(_) => Text::known(AmWord(Word::Single(frag.clone())).to_string()),
+        };
+        vec![(CondSet::always(), text)]
+    }
+
+    /// Expand a reference to the variable `name`.
+    fn expand_var(&self, name: &str, limit: usize, stack: &mut Vec<(String, usize)>) -> CondTexts {
+        if let Some(texts) = self.eval_text(name, limit, stack) {
+            return texts;
+        }
+        let text = match self.substs.get(name) {
+            Some(value) => Text::known(value.clone()),
+            None => Text::unknown(format!("$({})", name)),
+        };
+        vec![(CondSet::always(), text)]
+    }
+
+    /// Expand a reference to the variable whose name is the expansion of `name`.
+    fn expand_name(
+        &self,
+        name: &AmWord,
+        limit: usize,
+        stack: &mut Vec<(String, usize)>,
+    ) -> CondTexts {
+        let mut ret = Vec::new();
+        for (cond, name) in self.expand_word(name, limit, stack) {
+            if name.unknown {
+                let text = Text::unknown(format!("$({})", name.value));
+                ret.push((cond, text));
+                continue;
+            }
+            for (value_cond, text) in self.expand_var(name.value.trim(), limit, stack) {
+                ret.extend(cond.and(&value_cond).map(|cond| (cond, text)));
+            }
+        }
+        ret
+    }
+}
+
+/// Replace (or extend) the pieces of every alternative overlapping `cond`,
+/// splitting alternatives which only partially overlap it.
+fn apply<'a, F>(
+    alternatives: Vec<(CondSet, Option<Vec<Piece<'a>>>)>,
+    cond: &CondSet,
+    f: F,
+) -> Vec<(CondSet, Option<Vec<Piece<'a>>>)>
+where
+    F: Fn(Option<&[Piece<'a>]>) -> Option<Vec<Piece<'a>>>,
+{
+    let mut ret = Vec::new();
+    for (alt, pieces) in alternatives {
+        match alt.and(cond) {
+            None => ret.push((alt, pieces)),
+            Some(both) => {
+                for part in cond.complement() {
+                    if let Some(rest) = alt.and(&part) {
+                        ret.push((rest, pieces.clone()));
+                    }
+                }
+                ret.push((both, f(pieces.as_deref())));
+            }
+        }
+    }
+    ret
+}
+
+/// Concatenate every compatible pair of alternatives.
+fn product(lhs: &[(CondSet, Text)], rhs: &[(CondSet, Text)], sep: &str) -> CondTexts {
+    let mut ret = Vec::new();
+    for (lcond, ltext) in lhs {
+        for (rcond, rtext) in rhs {
+            if let Some(cond) = lcond.and(rcond) {
+                let (lvalue, rvalue) = (&ltext.value, &rtext.value);
+                let value = if lvalue.is_empty() || rvalue.is_empty() {
+                    format!("{}{}", lvalue, rvalue)
+                } else {
+                    format!("{}{}{}", lvalue, sep, rvalue)
+                };
+                let text = Text {
+                    value,
+                    unknown: ltext.unknown || rtext.unknown,
+                };
+                ret.push((cond, text));
+            }
+        }
+    }
+    ret
+}
+
+/// Call `f` with every compatible combination of alternatives, one from each
+/// list, and whether any of them is not known.
+fn combine<F>(lists: &[CondTexts], f: F) -> CondTexts
+where
+    F: Fn(&[&str], bool) -> Text,
+{
+    let mut tuples = vec![(CondSet::always(), Vec::new(), false)];
+    for list in lists {
+        let mut next = Vec::new();
+        for (cond, args, unknown) in &tuples {
+            for (alt, text) in list {
+                if let Some(both) = cond.and(alt) {
+                    let mut args: Vec<&str> = args.clone();
+                    args.push(&text.value);
+                    next.push((both, args, *unknown || text.unknown));
+                }
+            }
+        }
+        tuples = next;
+    }
+    tuples
+        .into_iter()
+        .map(|(cond, args, unknown)| (cond, f(&args, unknown)))
+        .collect()
+}
+
+fn split_words(texts: CondTexts) -> CondValues {
+    let mut ret = CondValues::new();
+    for (cond, text) in texts {
+        ret.entry(cond)
+            .or_default()
+            .extend(text.value.split_whitespace().map(|s| s.to_string()));
+    }
+    ret
+}
+
+/// Whether a shell fragment holds a parameter or command substitution.
+fn has_subst(frag: &WordFragment<String, NodeId, AmWord>) -> bool {
+    match frag {
+        WordFragment::Subst(_) => true,
+        WordFragment::DoubleQuoted(frags) => frags.iter().any(has_subst),
+        _ => false,
+    }
+}
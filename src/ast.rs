@@ -6,8 +6,10 @@ use std::{fmt, ops};
 
 pub mod am;
 pub mod builder;
+pub mod edit;
 pub mod minimal;
 pub mod node;
+pub mod visit;
 
 /// Type alias for the default `Parameter` representation.
 pub type DefaultParameter = Parameter<String>;
@@ -16,6 +18,7 @@ pub type DefaultParameter = Parameter<String>;
 ///
 /// Generic over the representation of variable names.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Parameter<T> {
     /// $@
     At,
@@ -65,6 +68,7 @@ pub type DefaultParameterSubstitution = ParameterSubstitution<
 /// Generic over the representations of parameters, shell words and
 /// commands, and arithmetic expansions.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ParameterSubstitution<P, C, W, A> {
     /// Returns the standard output of running a command, e.g. `$(cmd)`
     Command(Vec<C>),
@@ -120,6 +124,7 @@ pub type DefaultComplexWord = ComplexWord<DefaultWord>;
 ///
 /// Generic over the representation of a whitespace delimited word.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ComplexWord<W> {
     /// Several distinct words concatenated together.
     Concat(Vec<W>),
@@ -134,6 +139,7 @@ pub type DefaultWord = Word<String, M4Word<String, TopLevelCommand<String>, TopL
 ///
 /// Generic over the representation of single-quoted literals, and non-quoted words.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Word<L, W> {
     /// A regular word.
     Simple(W),
@@ -152,6 +158,7 @@ pub type DefaultSimpleWord =
 ///
 /// Generic over the representation of a literals, parameters, and substitutions.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SimpleWord<L, P, S> {
     /// A non-special literal word.
     Literal(L),
@@ -186,6 +193,7 @@ pub type DefaultRedirect = Redirect<TopLevelWord<String>>;
 ///
 /// Generic over the representation of a shell word.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Redirect<W> {
     /// Open a file for reading, e.g. `[n]< file`.
     Read(Option<u16>, W),
@@ -207,6 +215,7 @@ pub enum Redirect<W> {
 
 /// A grouping of guard and body commands.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GuardBodyPair<C> {
     /// The guard commands, which if successful, should lead to the
     /// execution of the body commands.
@@ -217,6 +226,7 @@ pub struct GuardBodyPair<C> {
 
 /// A grouping of patterns and body commands.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PatternBodyPair<C, W> {
     /// Pattern alternatives to match against.
     pub patterns: Vec<W>,
@@ -232,6 +242,7 @@ pub type DefaultCommand = Command<DefaultAndOrList>;
 /// @kui8shi
 /// Or, a m4 macro call which will be expanded to commands.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Command<T> {
     /// A command that runs asynchronously, that is, the shell will not wait
     /// for it to exit before running the next command, e.g. `foo &`.
@@ -273,6 +284,7 @@ pub type AtomicShellPipeableCommand<T, C, W> = PipeableCommand<
 
 /// A command which conditionally runs based on the exit status of the previous command.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AndOr<T> {
     /// A compound command which should run only if the previously run command succeeded.
     And(T),
@@ -285,6 +297,7 @@ pub type DefaultAndOrList = AndOrList<DefaultListableCommand>;
 
 /// A nonempty list of `AndOr` commands, e.g. `foo && bar || baz`.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AndOrList<T> {
     /// The first command that always runs.
     pub first: T,
@@ -297,6 +310,7 @@ pub type DefaultListableCommand = ListableCommand<DefaultPipeableCommand>;
 
 /// Commands that can be used within an and/or list.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ListableCommand<T> {
     /// A chain of concurrent commands where the standard output of the
     /// previous becomes the standard input of the next, e.g.
@@ -318,6 +332,7 @@ pub type DefaultPipeableCommand =
 /// Generic over the representations of function names, simple commands,
 /// compound commands, and function bodies.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PipeableCommand<N, S, C, F> {
     /// The simplest possible command: an executable with arguments,
     /// environment variable assignments, and redirections.
@@ -341,6 +356,7 @@ pub type DefaultCompoundCommand =
 /// Generic over the representation of a type of compound command, and the
 /// representation of a redirect.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CompoundCommand<T, R> {
     /// The specific kind of compound command.
     pub kind: T,
@@ -356,6 +372,7 @@ pub type DefaultCompoundCommandKind =
 ///
 /// Generic over the representation of shell words and commands.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompoundCommandKind<V, C, W> {
     /// A group of commands that should be executed in the current environment.
     Brace(Vec<C>),
@@ -408,6 +425,7 @@ pub enum CompoundCommandKind<V, C, W> {
 /// Thus we need a wrapper like this to disambiguate what was encountered in
 /// the source program.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RedirectOrEnvVar<R, V, W> {
     /// A parsed redirect before a command was encountered.
     Redirect(R),
@@ -422,6 +440,7 @@ pub enum RedirectOrEnvVar<R, V, W> {
 /// Thus we need a wrapper like this to disambiguate what was encountered in
 /// the source program.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RedirectOrCmdWord<R, W> {
     /// A parsed redirect after a command was encountered.
     Redirect(R),
@@ -438,6 +457,7 @@ pub type DefaultSimpleCommand =
 ///
 /// Generic over representations of variable names, shell words, and redirects.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SimpleCommand<V, W, R> {
     /// Redirections or environment variables that occur before any command
     /// in the order they were parsed.
@@ -453,6 +473,7 @@ pub type DefaultArithmetic = Arithmetic<String>;
 ///
 /// Generic over the representation of a variable name.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Arithmetic<T> {
     /// The value of a variable, e.g. `$var` or `var`.
     Var(T),
@@ -567,6 +588,7 @@ pub(crate) fn map_arith<T: From<String>>(kind: DefaultArithmetic) -> Arithmetic<
 
 /// Represents the nature of m4 macro which can be replaced to arbitrary shell components
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MayM4<S, M> {
     /// Normal syntax node
     Shell(S),
@@ -578,6 +600,7 @@ macro_rules! impl_top_level_cmd {
     ($(#[$attr:meta])* pub struct $Cmd:ident, $CmdList:ident, $Word:ident) => {
         $(#[$attr])*
         #[derive(Debug, PartialEq, Eq, Clone)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct $Cmd<T>(pub Command<$CmdList<T, $Cmd<T>, $Word<T>>>);
 
         impl<T> ops::Deref for $Cmd<T> {
@@ -661,6 +684,7 @@ macro_rules! impl_top_level_word {
     ($(#[$attr:meta])* $Cmd:ident, pub struct $Word:ident) => {
         $(#[$attr])*
         #[derive(Debug, PartialEq, Eq, Clone)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct $Word<T>(pub ShellWord<T, $Cmd<T>, $Word<T>>);
 
         impl<T> ops::Deref for $Word<T> {
@@ -1,5 +1,6 @@
 //! Defines abstract representations of the automake source.
 use std::fmt::Display;
+use std::path::PathBuf;
 
 use super::minimal::{Word, WordFragment};
 use super::node::{NodeId, ShellCommand};
@@ -9,8 +10,85 @@ pub type AmWordFragment = MayAm<WordFragment<String, NodeId, AmWord>, AmVar>;
 
 /// Wraps minimal word with fixing generics
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AmWord(pub Word<AmWordFragment>);
 
+impl AmWord {
+    /// A word consisting of literal text.
+    pub fn literal(text: &str) -> Self {
+        AmWord(Word::Single(MayAm::Shell(WordFragment::Literal(
+            text.to_string(),
+        ))))
+    }
+
+    /// A reference to a make variable, e.g. `$(CFLAGS)`.
+    pub fn variable(name: &str) -> Self {
+        AmWord(Word::Single(MayAm::Automake(AmVar::Param(
+            MakeParameter::Var(name.to_string()),
+        ))))
+    }
+
+    /// Returns the string of the word if it consists only of literals.
+    pub fn as_literal(&self) -> Option<String> {
+        fn frag_literal(frag: &AmWordFragment) -> Option<&str> {
+            match frag {
+                MayAm::Shell(WordFragment::Literal(s)) => Some(s),
+                _ => None,
+            }
+        }
+        match &self.0 {
+            Word::Single(frag) => frag_literal(frag).map(|s| s.to_string()),
+            Word::Concat(frags) => frags.iter().map(frag_literal).collect(),
+            Word::Empty => None,
+        }
+    }
+}
+
+/// Formats the word as it is written in a makefile.
+impl Display for AmWord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn fmt_shell(
+            frag: &WordFragment<String, NodeId, AmWord>,
+            f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result {
+            use WordFragment::*;
+            match frag {
+                Literal(s) => write!(f, "{}", s),
+                DoubleQuoted(frags) => {
+                    write!(f, "\"")?;
+                    for frag in frags {
+                        fmt_shell(frag, f)?;
+                    }
+                    write!(f, "\"")
+                }
+                Escaped(s) => write!(f, "\\{}", s),
+                Param(param) => write!(f, "${}", param),
+                // Command substitutions refer to nodes outside of the word,
+                // which `AutomakeFile` renders as a `DisplayNode`.
+                Subst(_) => write!(f, "$${{...}}"),
+                Star => write!(f, "*"),
+                Question => write!(f, "?"),
+                SquareOpen => write!(f, "["),
+                SquareClose => write!(f, "]"),
+                Tilde => write!(f, "~"),
+                Colon => write!(f, ":"),
+            }
+        }
+        let frags = match &self.0 {
+            Word::Single(frag) => std::slice::from_ref(frag),
+            Word::Concat(frags) => frags.as_slice(),
+            Word::Empty => &[],
+        };
+        for frag in frags {
+            match frag {
+                MayAm::Shell(frag) => fmt_shell(frag, f)?,
+                MayAm::Automake(var) => write!(f, "{}", var)?,
+            }
+        }
+        Ok(())
+    }
+}
+
 impl From<Word<AmWordFragment>> for AmWord {
     fn from(value: Word<AmWordFragment>) -> Self {
         Self(value)
@@ -56,6 +134,7 @@ impl Into<Option<String>> for AmWordFragment {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents different types of automake statements.
 pub enum AmLine {
     /// Represents a make rule statement in automake.
@@ -66,6 +145,21 @@ pub enum AmLine {
     Assignment(AmAssignment),
     /// Represents a shell command in a rule. Note that this can NOT be the top-level statement
     Shell(ShellCommand<AmWord>),
+    /// Represents a GNU make conditional (ifeq/ifneq/ifdef/ifndef).
+    MakeConditional(AmMakeConditional),
+    /// Represents a multi-line variable definition (define/endef).
+    Define(AmDefine),
+    /// Represents an export or unexport directive.
+    Export(AmExport),
+    /// Represents an override directive applied to an assignment or definition.
+    Override(NodeId),
+    /// Represents an include directive.
+    Include(AmInclude),
+    /// Represents a vpath directive.
+    Vpath(AmVpath),
+    /// Represents a line made of configure substitutions, e.g. `@SET_MAKE@`,
+    /// which configure replaces with arbitrary makefile text.
+    Substitution(Vec<AmWord>),
 }
 
 impl From<ShellCommand<AmWord>> for AmLine {
@@ -85,6 +179,7 @@ impl Into<Option<ShellCommand<AmWord>>> for AmLine {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents a make rule in automake with target, dependencies, and recipe.
 pub struct AmRule {
     /// The target of the rule (e.g., 'all', 'install', 'clean').
@@ -96,6 +191,7 @@ pub struct AmRule {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents a conditional statement in automake (if/else).
 pub struct AmConditional {
     /// The variable to test in the conditional.
@@ -107,17 +203,19 @@ pub struct AmConditional {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents a variable assignment in automake.
 pub struct AmAssignment {
     /// The left-hand side (variable name) of the assignment.
     pub lhs: String,
-    /// The assignment operator (=, :=, +=).
+    /// The assignment operator (=, :=, +=, ?=, !=).
     pub op: AmAssignOp,
     /// The right-hand side (value) of the assignment.
     pub rhs: Vec<AmWord>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents different assignment operators in automake.
 pub enum AmAssignOp {
     /// =
@@ -126,9 +224,98 @@ pub enum AmAssignOp {
     Instant,
     /// +=
     Append,
+    /// ?=
+    IfUndefined,
+    /// !=
+    Shell,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Represents the condition tested by a GNU make conditional.
+pub enum MakeCondition<W> {
+    /// ifeq (a,b)
+    Eq(Vec<W>, Vec<W>),
+    /// ifneq (a,b)
+    Neq(Vec<W>, Vec<W>),
+    /// ifdef VAR
+    Defined(W),
+    /// ifndef VAR
+    NotDefined(W),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Represents a GNU make conditional, which make evaluates when reading the makefile.
+pub struct AmMakeConditional {
+    /// The condition to test.
+    pub condition: MakeCondition<AmWord>,
+    /// The statements to read if the condition holds.
+    pub then: Vec<NodeId>,
+    /// The statements to read otherwise. An `else ifeq ...` chain
+    /// is represented by a single nested conditional.
+    pub otherwise: Vec<NodeId>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Represents a multi-line variable definition.
+pub struct AmDefine {
+    /// The name of the variable.
+    pub name: String,
+    /// The assignment operator following the name (= if omitted).
+    pub op: AmAssignOp,
+    /// The lines between `define` and `endef`, kept verbatim.
+    pub body: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Represents an export or unexport directive.
+pub struct AmExport {
+    /// Whether the variables are exported (`export`) or not (`unexport`).
+    pub export: bool,
+    /// The variables named by the directive. Empty if it applies to all variables.
+    pub vars: Vec<AmWord>,
+    /// The assignment given in the form of `export VAR = value`.
+    pub assignment: Option<NodeId>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Represents an include directive.
+pub struct AmInclude {
+    /// The files to include.
+    pub paths: Vec<AmWord>,
+    /// Whether missing files are ignored (`-include` or `sinclude`).
+    pub optional: bool,
+    /// The fragment read in place of the directive, if includes were resolved.
+    pub included: Option<IncludedFile<NodeId>>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Represents a makefile fragment spliced in place of an include directive.
+pub struct IncludedFile<S> {
+    /// Path of the fragment relative to the top source directory.
+    pub path: PathBuf,
+    /// Top-level statements of the fragment.
+    pub body: Vec<S>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Represents a vpath directive.
+pub struct AmVpath {
+    /// The pattern the search path applies to. `None` clears all search paths.
+    pub pattern: Option<AmWord>,
+    /// The directories to search, separated by blanks or colons. Empty clears
+    /// the search paths of the pattern.
+    pub dirs: Vec<AmWord>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents a value that can be either a shell command or automake construct.
 pub enum MayAm<S, A> {
     /// A shell command.
@@ -145,6 +332,7 @@ impl From<WordFragment<String, NodeId, AmWord>> for AmWordFragment {
 
 /// Some of the special makefile parameters could have suffix of "D" or "F".
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MakeDF {
     /// e.g. $(@D)
     Dir,
@@ -164,6 +352,7 @@ impl Display for MakeDF {
 
 /// Used to indicate what kind of makefile parameter could be parsed next.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MakeParameter {
     /// $@ or $(@)
     Target(Option<MakeDF>),
@@ -209,12 +398,19 @@ impl Display for MakeParameter {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents different types of automake variables.
 pub enum AmVar {
     /// A regular variable reference.
     Param(MakeParameter),
     /// A template variable reference.
     Template(String),
+    /// A reference to a variable whose name is computed, e.g. $($(x)_SOURCES)
+    Computed(Box<AmWord>),
+    /// A substitution reference, e.g. $(foo:.c=.o)
+    SubstRef(Box<MakeSubstRef>),
+    /// A call of a GNU make function, e.g. $(patsubst %.c,%.o,$(SRCS))
+    Function(MakeFunction),
 }
 
 impl Display for AmVar {
@@ -223,6 +419,96 @@ impl Display for AmVar {
         match self {
             Param(s) => write!(f, "{}", s),
             Template(s) => write!(f, "@{}@", s),
+            Computed(name) => write!(f, "$({})", name),
+            SubstRef(r) => write!(f, "$({}:{}={})", r.var, r.pattern, r.replacement),
+            Function(func) => {
+                let args = func
+                    .args
+                    .iter()
+                    .map(|arg| {
+                        let words = arg.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+                        words.join(" ")
+                    })
+                    .collect::<Vec<_>>();
+                write!(f, "$({} {})", func.name, args.join(","))
+            }
         }
     }
 }
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Represents a substitution reference `$(var:pattern=replacement)`.
+pub struct MakeSubstRef {
+    /// The name of the variable, which may be computed.
+    pub var: AmWord,
+    /// The suffix (or `%` pattern) to replace in every word of the value.
+    pub pattern: AmWord,
+    /// The replacement of the pattern.
+    pub replacement: AmWord,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Represents a call of a GNU make function.
+pub struct MakeFunction {
+    /// The name of the function, e.g. `patsubst`.
+    pub name: String,
+    /// The comma separated arguments, each consisting of blank separated words.
+    pub args: Vec<Vec<AmWord>>,
+}
+
+/// GNU make built-in functions with the maximum number of arguments they take.
+/// Commas in the last argument are a part of it.
+const MAKE_FUNCTIONS: &[(&str, usize)] = &[
+    ("subst", 3),
+    ("patsubst", 3),
+    ("strip", 1),
+    ("findstring", 2),
+    ("filter", 2),
+    ("filter-out", 2),
+    ("sort", 1),
+    ("word", 2),
+    ("wordlist", 3),
+    ("words", 1),
+    ("firstword", 1),
+    ("lastword", 1),
+    ("dir", 1),
+    ("notdir", 1),
+    ("suffix", 1),
+    ("basename", 1),
+    ("addsuffix", 2),
+    ("addprefix", 2),
+    ("join", 2),
+    ("wildcard", 1),
+    ("realpath", 1),
+    ("abspath", 1),
+    ("if", 3),
+    ("or", usize::MAX),
+    ("and", usize::MAX),
+    ("intcmp", 5),
+    ("foreach", 3),
+    ("let", 3),
+    ("file", 2),
+    ("call", usize::MAX),
+    ("value", 1),
+    ("eval", 1),
+    ("origin", 1),
+    ("flavor", 1),
+    ("shell", 1),
+    ("error", 1),
+    ("warning", 1),
+    ("info", 1),
+    ("guile", 1),
+];
+
+impl MakeFunction {
+    /// Returns the maximum number of arguments of a built-in function,
+    /// or `None` if there is no such function.
+    pub fn max_args(name: &str) -> Option<usize> {
+        MAKE_FUNCTIONS
+            .iter()
+            .find(|(func, _)| *func == name)
+            .map(|(_, max)| *max)
+    }
+}
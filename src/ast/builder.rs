@@ -9,6 +9,7 @@
 //! the `Builder` trait for your AST. Otherwise you can provide the `DefaultBuilder`
 //! struct to the parser if you wish to use the default AST implementation.
 
+use std::error::Error;
 use std::fmt::Display;
 
 use crate::ast::{
@@ -25,7 +26,7 @@ pub use self::minimal_builder::MinimalBuilder;
 pub use self::node_builder::AutoconfNodeBuilder;
 pub use self::node_builder::AutomakeNodeBuilder;
 
-use super::am::{AmAssignOp, AmVar};
+use super::am::{AmAssignOp, AmVar, IncludedFile, MakeCondition};
 
 /// An indicator to the builder of how complete commands are separated.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -555,11 +556,74 @@ pub trait MakeBuilder: BuilderBase {
     /// Interpret a shell command as a part of automake recipe.
     fn shell_as_recipe(&mut self, cmd: Self::Command) -> Result<Self::Statement, Self::Error>;
 
-    /// Construct an automake include statement.
-    fn include(&mut self, path: Self::Word) -> Result<Self::Statement, Self::Error>;
+    /// Construct an include statement. `optional` is set for `-include` and `sinclude`,
+    /// and `included` holds the parsed fragment if the include was resolved.
+    fn include(
+        &mut self,
+        paths: Vec<Self::Word>,
+        optional: bool,
+        included: Option<IncludedFile<Self::Statement>>,
+    ) -> Result<Self::Statement, Self::Error>;
+
+    /// Constructs a GNU make conditional.
+    fn make_conditional(
+        &mut self,
+        condition: MakeCondition<Self::Word>,
+        then: Vec<Self::Statement>,
+        otherwise: Vec<Self::Statement>,
+    ) -> Result<Self::Statement, Self::Error>;
+
+    /// Constructs a multi-line variable definition.
+    fn define(
+        &mut self,
+        name: String,
+        op: AmAssignOp,
+        body: String,
+    ) -> Result<Self::Statement, Self::Error>;
+
+    /// Constructs an export (or unexport) directive.
+    fn export(
+        &mut self,
+        export: bool,
+        vars: Vec<Self::Word>,
+        assignment: Option<Self::Statement>,
+    ) -> Result<Self::Statement, Self::Error>;
+
+    /// Constructs an override directive of an assignment or a definition.
+    fn override_directive(&mut self, stmt: Self::Statement)
+        -> Result<Self::Statement, Self::Error>;
+
+    /// Constructs a vpath directive.
+    fn vpath(
+        &mut self,
+        pattern: Option<Self::Word>,
+        dirs: Vec<Self::Word>,
+    ) -> Result<Self::Statement, Self::Error>;
+
+    /// Constructs a line made of configure substitutions, e.g. `@SET_MAKE@`.
+    fn substitution_line(&mut self, words: Vec<Self::Word>)
+        -> Result<Self::Statement, Self::Error>;
 
     /// Construct an automake styled variable.
     fn variable(&mut self, var: AmVar) -> Result<Self::WordFragment, Self::Error>;
+
+    /// Construct a reference to a variable whose name is computed, e.g. `$($(x)_SOURCES)`.
+    fn computed_variable(&mut self, name: Self::Word) -> Result<Self::WordFragment, Self::Error>;
+
+    /// Construct a substitution reference, e.g. `$(foo:.c=.o)`.
+    fn substitution_reference(
+        &mut self,
+        var: Self::Word,
+        pattern: Self::Word,
+        replacement: Self::Word,
+    ) -> Result<Self::WordFragment, Self::Error>;
+
+    /// Construct a call of a GNU make function, e.g. `$(addprefix -I,$(DIRS))`.
+    fn function_call(
+        &mut self,
+        name: String,
+        args: Vec<Vec<Self::Word>>,
+    ) -> Result<Self::WordFragment, Self::Error>;
 }
 
 macro_rules! impl_builder_body {
@@ -778,6 +842,8 @@ impl Display for BuilderError {
     }
 }
 
+impl Error for BuilderError {}
+
 #[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
 struct Coalesce<I: Iterator, F> {
     iter: I,
@@ -625,7 +625,10 @@ where
                 rhs = word.clone();
             }
         }
-        let operator = match operator_kind.unwrap() {
+        let Some(operator_kind) = operator_kind else {
+            return Err(BuilderError::UnsupportedSyntax);
+        };
+        let operator = match operator_kind {
             Neq => Operator::Neq(lhs, rhs),
             Eq => Operator::Eq(lhs, rhs),
             Ge => Operator::Ge(lhs, rhs),
@@ -656,7 +659,8 @@ where
                 if let Word::Single(w) = &first_word.0 {
                     match w {
                         Shell(WordFragment::Literal(v)) if v.clone().into() == "test" => {
-                            Some(Condition::Cond(self.parse_condition(&words[1..])?))
+                            // A test without a known operator is left as a command.
+                            self.parse_condition(&words[1..]).ok().map(Condition::Cond)
                         }
                         Shell(WordFragment::Literal(v)) if v.clone().into() == "eval" => Some(
                             Condition::Eval(vec![AcCommand::new_cmd(words[1..].to_owned())]),
@@ -3,7 +3,9 @@ use std::fmt::Debug;
 use std::marker::PhantomData;
 
 use crate::ast::am::{
-    AmAssignOp, AmAssignment, AmConditional, AmLine, AmRule, AmVar, AmWord, AmWordFragment, MayAm,
+    AmAssignOp, AmAssignment, AmConditional, AmDefine, AmExport, AmInclude, AmLine,
+    AmMakeConditional, AmRule, AmVar, AmVpath, AmWord, AmWordFragment, IncludedFile, MakeCondition,
+    MakeFunction, MakeSubstRef, MayAm,
 };
 use crate::ast::builder::QuoteWordKind;
 use crate::ast::minimal::{Word, WordFragment};
@@ -51,6 +53,18 @@ impl<C, W, F, U> Default for NodeBuilder<C, W, F, U> {
     }
 }
 
+impl<C, W, F, U> NodeBuilder<C, W, F, U> {
+    /// Creates a builder which adds the nodes it builds to `nodes`,
+    /// leaving the nodes already there untouched.
+    pub(crate) fn with_nodes(nodes: Slab<Node<C, U>>) -> Self {
+        Self {
+            nodes,
+            _word: PhantomData,
+            _fragment: PhantomData,
+        }
+    }
+}
+
 impl<C, W, F, U: Default> NodeBuilder<C, W, F, U> {
     fn new_node(&mut self, cmd: C) -> NodeId {
         self.new_node_with_comment(cmd, None)
@@ -206,13 +220,107 @@ impl<U: Default> MakeBuilder for NodeBuilder<AmLine, AmWord, AmWordFragment, U>
         Ok(cmd)
     }
 
-    fn include(&mut self, path: Self::Word) -> Result<Self::Statement, Self::Error> {
-        Err(BuilderError::UnsupportedSyntax)
+    fn include(
+        &mut self,
+        paths: Vec<Self::Word>,
+        optional: bool,
+        included: Option<IncludedFile<Self::Statement>>,
+    ) -> Result<Self::Statement, Self::Error> {
+        Ok(self.new_node(AmLine::Include(AmInclude {
+            paths,
+            optional,
+            included,
+        })))
+    }
+
+    fn make_conditional(
+        &mut self,
+        condition: MakeCondition<Self::Word>,
+        then: Vec<Self::Statement>,
+        otherwise: Vec<Self::Statement>,
+    ) -> Result<Self::Statement, Self::Error> {
+        Ok(self.new_node(AmLine::MakeConditional(AmMakeConditional {
+            condition,
+            then,
+            otherwise,
+        })))
+    }
+
+    fn define(
+        &mut self,
+        name: String,
+        op: AmAssignOp,
+        body: String,
+    ) -> Result<Self::Statement, Self::Error> {
+        Ok(self.new_node(AmLine::Define(AmDefine { name, op, body })))
+    }
+
+    fn export(
+        &mut self,
+        export: bool,
+        vars: Vec<Self::Word>,
+        assignment: Option<Self::Statement>,
+    ) -> Result<Self::Statement, Self::Error> {
+        Ok(self.new_node(AmLine::Export(AmExport {
+            export,
+            vars,
+            assignment,
+        })))
+    }
+
+    fn override_directive(
+        &mut self,
+        stmt: Self::Statement,
+    ) -> Result<Self::Statement, Self::Error> {
+        Ok(self.new_node(AmLine::Override(stmt)))
+    }
+
+    fn vpath(
+        &mut self,
+        pattern: Option<Self::Word>,
+        dirs: Vec<Self::Word>,
+    ) -> Result<Self::Statement, Self::Error> {
+        Ok(self.new_node(AmLine::Vpath(AmVpath { pattern, dirs })))
+    }
+
+    fn substitution_line(
+        &mut self,
+        words: Vec<Self::Word>,
+    ) -> Result<Self::Statement, Self::Error> {
+        Ok(self.new_node(AmLine::Substitution(words)))
     }
 
     fn variable(&mut self, var: AmVar) -> Result<Self::WordFragment, Self::Error> {
         Ok(MayAm::Automake(var))
     }
+
+    fn computed_variable(&mut self, name: Self::Word) -> Result<Self::WordFragment, Self::Error> {
+        Ok(MayAm::Automake(AmVar::Computed(Box::new(name))))
+    }
+
+    fn substitution_reference(
+        &mut self,
+        var: Self::Word,
+        pattern: Self::Word,
+        replacement: Self::Word,
+    ) -> Result<Self::WordFragment, Self::Error> {
+        Ok(MayAm::Automake(AmVar::SubstRef(Box::new(MakeSubstRef {
+            var,
+            pattern,
+            replacement,
+        }))))
+    }
+
+    fn function_call(
+        &mut self,
+        name: String,
+        args: Vec<Vec<Self::Word>>,
+    ) -> Result<Self::WordFragment, Self::Error> {
+        Ok(MayAm::Automake(AmVar::Function(MakeFunction {
+            name,
+            args,
+        })))
+    }
 }
 
 impl<C, W, F, U> ShellBuilder for NodeBuilder<C, W, F, U>
@@ -678,7 +786,8 @@ where
                 if let Word::Single(f) = first_word.clone().into() {
                     match f.into() {
                         Some(WordFragment::Literal(v)) if v.clone() == "test" => {
-                            Some(Condition::Cond(self.parse_condition(&words[1..])?))
+                            // A test without a known operator is left as a command.
+                            self.parse_condition(&words[1..]).ok().map(Condition::Cond)
                         }
                         Some(WordFragment::Literal(v)) if v.clone() == "eval" => {
                             Some(Condition::Eval(vec![
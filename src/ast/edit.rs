@@ -0,0 +1,651 @@
+//! Structural edits of the nodes of an `AutoconfPool`.
+//!
+//! A `PoolEditor` borrows a pool together with the ids of its top-level
+//! nodes and keeps the parent of every node reachable from them, so that
+//! nodes can be inserted, replaced, removed, wrapped or moved in place of
+//! editing the source text.
+//!
+//! The ids of the nodes do not change while editing. A removed or replaced
+//! node is only detached from the tree: it stays in the pool, along with
+//! its children, and may be put back with `move_subtree` or reused by a
+//! replacement. `compact` drops the detached nodes once the edits are done
+//! and renumbers the remaining ones densely.
+//!
+//! ```
+//! use autotools_parser::ast::minimal::{Condition, Operator, Word, WordFragment};
+//! use autotools_parser::ast::node::{AcWord, AutoconfPool, DisplayNode};
+//! use autotools_parser::ast::MayM4;
+//! use autotools_parser::lexer::Lexer;
+//! use autotools_parser::parse::autoconf::NodeParser;
+//!
+//! let source = "AC_PROG_CC\nAC_CHECK_LIB([m], [cos])\nAC_OUTPUT\n";
+//! let (nodes, mut top_ids) = NodeParser::<_, ()>::new(Lexer::new(source.chars())).parse_all();
+//! let mut pool = AutoconfPool::new(nodes, None);
+//!
+//! let mut editor = pool.edit(&mut top_ids);
+//! let var = AcWord(Word::Single(MayM4::Shell(WordFragment::Param(
+//!     autotools_parser::ast::Parameter::Var("with_m".to_string()),
+//! ))));
+//! let check = editor.top_ids()[1];
+//! let wrapper = editor.wrap_in_if(check, Condition::Cond(Operator::NonEmpty(var))).unwrap();
+//! assert_eq!(editor.parent(check), Some(wrapper));
+//! editor.remove(editor.top_ids()[0]).unwrap();
+//! editor.compact();
+//!
+//! assert_eq!(top_ids.len(), 2);
+//! assert!(pool.display_node(top_ids[0], 0).starts_with("if test -n"));
+//! ```
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::iter;
+use std::mem;
+
+use super::minimal::GuardBodyPair;
+use super::node::{
+    AcCommand, AcWord, AutoconfPool, Condition, M4Argument, M4Macro, Node, NodeId, ShellCommand,
+    WordFragment,
+};
+use super::visit::node::{walk_node, Visit};
+use super::visit::{fragments_mut, operands_mut, redirect_word_mut, subst_parts_mut};
+use super::MayM4;
+
+/// The error type returned when an edit can not be made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditError {
+    /// The node is not in the pool.
+    UnknownNode(NodeId),
+    /// The node is neither a top-level node nor the child of a node.
+    Detached(NodeId),
+    /// The node is the only command of its parent at its place, e.g. the
+    /// command run by `&&`, so that it can not be removed or have a sibling.
+    NotInList(NodeId),
+    /// A node given as the child of a new command is already in the tree.
+    InUse(NodeId),
+    /// A node would be moved into its own subtree.
+    Cycle(NodeId),
+    /// The node is not an m4 macro call.
+    NotAMacro(NodeId),
+    /// The macro call has no argument at the index.
+    ArgumentOutOfRange(NodeId, usize),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownNode(id) => write!(f, "node {} is not in the pool", id),
+            Self::Detached(id) => write!(f, "node {} is not in the tree", id),
+            Self::NotInList(id) => write!(f, "node {} is not in a list of commands", id),
+            Self::InUse(id) => write!(f, "node {} is already in the tree", id),
+            Self::Cycle(id) => write!(f, "node {} can not be moved into itself", id),
+            Self::NotAMacro(id) => write!(f, "node {} is not a macro call", id),
+            Self::ArgumentOutOfRange(id, index) => {
+                write!(f, "the macro call of node {} has no argument {}", id, index)
+            }
+        }
+    }
+}
+
+impl Error for EditError {}
+
+/// Where a node is put, relative to a node of a list of commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    /// Right before the node.
+    Before(NodeId),
+    /// Right after the node.
+    After(NodeId),
+}
+
+impl Position {
+    fn target(self) -> NodeId {
+        match self {
+            Position::Before(id) | Position::After(id) => id,
+        }
+    }
+}
+
+/// An editing session over a pool and its top-level nodes.
+///
+/// Created by `AutoconfPool::edit`.
+#[derive(Debug)]
+pub struct PoolEditor<'a, U = ()> {
+    pool: &'a mut AutoconfPool<U>,
+    top_ids: &'a mut Vec<NodeId>,
+    /// The parent of each node below the top level.
+    parents: HashMap<NodeId, NodeId>,
+}
+
+impl<U> AutoconfPool<U> {
+    /// Start editing the tree of nodes rooted at `top_ids`.
+    pub fn edit<'a>(&'a mut self, top_ids: &'a mut Vec<NodeId>) -> PoolEditor<'a, U> {
+        let parents = parents(self, top_ids);
+        PoolEditor {
+            pool: self,
+            top_ids,
+            parents,
+        }
+    }
+}
+
+impl<U> PoolEditor<'_, U> {
+    /// The edited pool.
+    pub fn pool(&self) -> &AutoconfPool<U> {
+        self.pool
+    }
+
+    /// The ids of the top-level nodes.
+    pub fn top_ids(&self) -> &[NodeId] {
+        self.top_ids
+    }
+
+    /// The parent of a node, or `None` for a top-level or detached node.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.parents.get(&id).copied()
+    }
+
+    /// The ancestors of a node, from its parent up to its top-level node.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        iter::successors(self.parent(id), move |id| self.parent(*id))
+    }
+
+    /// The children of a node in the order of the source, including the
+    /// commands in its conditions, words and macro arguments.
+    pub fn children(&self, id: NodeId) -> Vec<NodeId> {
+        let mut children = Children(Vec::new());
+        if self.pool.nodes.contains(id) {
+            walk_node(&mut children, self.pool, id);
+        }
+        children.0
+    }
+
+    /// Whether a node is a top-level node or one of their descendants.
+    pub fn is_attached(&self, id: NodeId) -> bool {
+        let root = self.ancestors(id).last().unwrap_or(id);
+        self.top_ids.contains(&root)
+    }
+
+    /// Insert a node right before the node `id`, returning its id.
+    pub fn insert_before(
+        &mut self,
+        id: NodeId,
+        node: Node<AcCommand, U>,
+    ) -> Result<NodeId, EditError> {
+        self.insert(Position::Before(id), node)
+    }
+
+    /// Insert a node right after the node `id`, returning its id.
+    pub fn insert_after(
+        &mut self,
+        id: NodeId,
+        node: Node<AcCommand, U>,
+    ) -> Result<NodeId, EditError> {
+        self.insert(Position::After(id), node)
+    }
+
+    /// Insert a node at a position, returning its id.
+    ///
+    /// The children of the node must be detached nodes of the pool.
+    pub fn insert(
+        &mut self,
+        position: Position,
+        mut node: Node<AcCommand, U>,
+    ) -> Result<NodeId, EditError> {
+        self.check_list(position.target())?;
+        self.check_children(command_children(&mut node.cmd), &[])?;
+        let id = self.pool.nodes.insert(node);
+        self.place(id, position)?;
+        self.relink(id, Vec::new());
+        Ok(id)
+    }
+
+    /// Replace the node `id` by another node, which keeps its id, returning
+    /// the replaced node.
+    ///
+    /// The children of the new node must be detached nodes of the pool or
+    /// children of the replaced node. The other children of the replaced
+    /// node are detached.
+    pub fn replace(
+        &mut self,
+        id: NodeId,
+        mut node: Node<AcCommand, U>,
+    ) -> Result<Node<AcCommand, U>, EditError> {
+        if !self.pool.nodes.contains(id) {
+            return Err(EditError::UnknownNode(id));
+        }
+        let old_children = self.children(id);
+        self.check_children(command_children(&mut node.cmd), &old_children)?;
+        let old = mem::replace(&mut self.pool.nodes[id], node);
+        self.relink(id, old_children);
+        Ok(old)
+    }
+
+    /// Detach the node `id` from the list of commands holding it.
+    pub fn remove(&mut self, id: NodeId) -> Result<(), EditError> {
+        self.check_list(id)?;
+        self.with_slot(id, |slot| {
+            if let Slot::List(ids) = slot {
+                ids.retain(|child| *child != id);
+            }
+        })?;
+        self.parents.remove(&id);
+        Ok(())
+    }
+
+    /// Wrap the node `id` in an `if` running it when `condition` holds,
+    /// returning the id of the `if`.
+    pub fn wrap_in_if(
+        &mut self,
+        id: NodeId,
+        condition: Condition<AcWord>,
+    ) -> Result<NodeId, EditError>
+    where
+        U: Default,
+    {
+        let range = match self.pool.nodes.get(id) {
+            Some(node) => node.range.clone(),
+            None => return Err(EditError::UnknownNode(id)),
+        };
+        let mut cmd = AcCommand::new_cmd(ShellCommand::If {
+            conditionals: vec![GuardBodyPair {
+                condition,
+                body: vec![id],
+            }],
+            else_branch: Vec::new(),
+        });
+        self.check_children(command_children(&mut cmd), &[id])?;
+        self.with_slot(id, |_| ())?;
+        let wrapper = self.pool.nodes.insert(Node {
+            comment: None,
+            range,
+            cmd,
+            info: U::default(),
+        });
+        self.with_slot(id, |slot| match slot {
+            Slot::List(ids) => ids
+                .iter_mut()
+                .filter(|child| **child == id)
+                .for_each(|child| *child = wrapper),
+            Slot::One(child) => *child = wrapper,
+        })?;
+        if let Some(parent) = self.parent(id) {
+            self.parents.insert(wrapper, parent);
+        }
+        self.relink(wrapper, Vec::new());
+        Ok(wrapper)
+    }
+
+    /// Move the node `id` with its subtree to a position.
+    pub fn move_subtree(&mut self, id: NodeId, position: Position) -> Result<(), EditError> {
+        let target = position.target();
+        if target == id || self.ancestors(target).any(|ancestor| ancestor == id) {
+            return Err(EditError::Cycle(id));
+        }
+        self.check_list(target)?;
+        self.remove(id)?;
+        self.place(id, position)
+    }
+
+    /// Replace the argument `index` of the macro call of the node `id`,
+    /// returning the replaced argument.
+    pub fn replace_argument(
+        &mut self,
+        id: NodeId,
+        index: usize,
+        arg: M4Argument,
+    ) -> Result<M4Argument, EditError> {
+        self.edit_arguments(id, index, arg, |args, index, arg| {
+            (index < args.len()).then(|| mem::replace(&mut args[index], arg))
+        })
+    }
+
+    /// Insert an argument at `index` in the macro call of the node `id`.
+    pub fn insert_argument(
+        &mut self,
+        id: NodeId,
+        index: usize,
+        arg: M4Argument,
+    ) -> Result<(), EditError> {
+        self.edit_arguments(id, index, arg, |args, index, arg| {
+            (index <= args.len()).then(|| args.insert(index, arg))
+        })
+    }
+
+    /// Remove the argument `index` of the macro call of the node `id`,
+    /// returning it.
+    pub fn remove_argument(&mut self, id: NodeId, index: usize) -> Result<M4Argument, EditError> {
+        let arg = M4Argument::Literal(String::new());
+        self.edit_arguments(id, index, arg, |args, index, _| {
+            (index < args.len()).then(|| args.remove(index))
+        })
+    }
+
+    /// Drop the nodes which are not reachable from the top-level nodes and
+    /// renumber the others densely, returning the new id of each of them.
+    pub fn compact(&mut self) -> HashMap<NodeId, NodeId> {
+        let mut reachable = HashSet::new();
+        let mut stack = self.top_ids.to_vec();
+        while let Some(id) = stack.pop() {
+            if reachable.insert(id) {
+                stack.extend(self.children(id));
+            }
+        }
+        let detached = self
+            .pool
+            .nodes
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| !reachable.contains(id))
+            .collect::<Vec<_>>();
+        for id in detached {
+            self.pool.nodes.remove(id);
+        }
+
+        let mut moved = HashMap::new();
+        self.pool.nodes.compact(|_, from, to| {
+            moved.insert(from, to);
+            true
+        });
+        if !moved.is_empty() {
+            let rekey = |id: &mut NodeId| *id = moved.get(id).copied().unwrap_or(*id);
+            for (_, node) in self.pool.nodes.iter_mut() {
+                let mut slots = Vec::new();
+                command_slots(&mut node.cmd, &mut slots);
+                for slot in slots {
+                    match slot {
+                        Slot::List(ids) => ids.iter_mut().for_each(rekey),
+                        Slot::One(id) => rekey(id),
+                    }
+                }
+            }
+            self.top_ids.iter_mut().for_each(rekey);
+        }
+        self.parents = parents(self.pool, self.top_ids);
+        reachable
+            .into_iter()
+            .map(|id| (id, moved.get(&id).copied().unwrap_or(id)))
+            .collect()
+    }
+
+    /// Call `f` with the list or the single child holding the node `id`.
+    fn with_slot<R, F>(&mut self, id: NodeId, f: F) -> Result<R, EditError>
+    where
+        F: FnOnce(Slot<'_>) -> R,
+    {
+        if !self.pool.nodes.contains(id) {
+            return Err(EditError::UnknownNode(id));
+        }
+        match self.parents.get(&id) {
+            Some(&parent) => {
+                let mut slots = Vec::new();
+                command_slots(&mut self.pool.nodes[parent].cmd, &mut slots);
+                slots
+                    .into_iter()
+                    .find(|slot| match slot {
+                        Slot::List(ids) => ids.contains(&id),
+                        Slot::One(child) => **child == id,
+                    })
+                    .map(f)
+                    .ok_or(EditError::Detached(id))
+            }
+            None if self.top_ids.contains(&id) => Ok(f(Slot::List(self.top_ids))),
+            None => Err(EditError::Detached(id)),
+        }
+    }
+
+    /// Check that the node `id` is in a list of commands.
+    fn check_list(&mut self, id: NodeId) -> Result<(), EditError> {
+        match self.with_slot(id, |slot| matches!(slot, Slot::List(_)))? {
+            true => Ok(()),
+            false => Err(EditError::NotInList(id)),
+        }
+    }
+
+    /// Check that the children of a new command are in the pool and are
+    /// either detached or `reusable`.
+    fn check_children(&self, children: Vec<NodeId>, reusable: &[NodeId]) -> Result<(), EditError> {
+        for child in children {
+            if !self.pool.nodes.contains(child) {
+                return Err(EditError::UnknownNode(child));
+            }
+            if self.is_attached(child) && !reusable.contains(&child) {
+                return Err(EditError::InUse(child));
+            }
+        }
+        Ok(())
+    }
+
+    /// Put the detached node `id` at a position.
+    fn place(&mut self, id: NodeId, position: Position) -> Result<(), EditError> {
+        let target = position.target();
+        self.with_slot(target, |slot| {
+            if let Slot::List(ids) = slot {
+                let index = ids.iter().position(|child| *child == target).unwrap();
+                match position {
+                    Position::Before(_) => ids.insert(index, id),
+                    Position::After(_) => ids.insert(index + 1, id),
+                }
+            }
+        })?;
+        match self.parent(target) {
+            Some(parent) => self.parents.insert(id, parent),
+            None => self.parents.remove(&id),
+        };
+        Ok(())
+    }
+
+    /// Update the parents after the command of the node `id` changed from
+    /// having `old_children`.
+    fn relink(&mut self, id: NodeId, old_children: Vec<NodeId>) {
+        for child in old_children {
+            if self.parents.get(&child) == Some(&id) {
+                self.parents.remove(&child);
+            }
+        }
+        for child in self.children(id) {
+            self.parents.insert(child, id);
+        }
+    }
+
+    fn edit_arguments<R, F>(
+        &mut self,
+        id: NodeId,
+        index: usize,
+        mut arg: M4Argument,
+        f: F,
+    ) -> Result<R, EditError>
+    where
+        F: FnOnce(&mut Vec<M4Argument>, usize, M4Argument) -> Option<R>,
+    {
+        match self.pool.nodes.get(id).map(|node| &node.cmd.0) {
+            None => return Err(EditError::UnknownNode(id)),
+            Some(MayM4::Shell(_)) => return Err(EditError::NotAMacro(id)),
+            Some(MayM4::Macro(_)) => {}
+        }
+        let old_children = self.children(id);
+        let mut slots = Vec::new();
+        argument_slots(&mut arg, &mut slots);
+        self.check_children(slot_ids(slots), &old_children)?;
+        let MayM4::Macro(m4_macro) = &mut self.pool.nodes[id].cmd.0 else {
+            unreachable!()
+        };
+        let ret = f(&mut m4_macro.args, index, arg);
+        self.relink(id, old_children);
+        ret.ok_or(EditError::ArgumentOutOfRange(id, index))
+    }
+}
+
+/// Collects the children of a node without descending into them.
+struct Children(Vec<NodeId>);
+
+impl<'a, U: 'a> Visit<'a, U> for Children {
+    fn visit_node(&mut self, _pool: &'a AutoconfPool<U>, id: NodeId) {
+        self.0.push(id);
+    }
+}
+
+/// Collects the parent of each node below the top level.
+struct Parents {
+    parent: Option<NodeId>,
+    parents: HashMap<NodeId, NodeId>,
+}
+
+impl<'a, U: 'a> Visit<'a, U> for Parents {
+    fn visit_node(&mut self, pool: &'a AutoconfPool<U>, id: NodeId) {
+        if let Some(parent) = self.parent {
+            self.parents.insert(id, parent);
+        }
+        let outer = self.parent.replace(id);
+        walk_node(self, pool, id);
+        self.parent = outer;
+    }
+}
+
+fn parents<U>(pool: &AutoconfPool<U>, top_ids: &[NodeId]) -> HashMap<NodeId, NodeId> {
+    let mut parents = Parents {
+        parent: None,
+        parents: HashMap::new(),
+    };
+    for id in top_ids {
+        if pool.nodes.contains(*id) {
+            parents.visit_node(pool, *id);
+        }
+    }
+    parents.parents
+}
+
+/// A place in a command holding child nodes.
+enum Slot<'s> {
+    /// A list of commands, e.g. the body of a loop.
+    List(&'s mut Vec<NodeId>),
+    /// A single command, e.g. the command run by `&&`.
+    One(&'s mut NodeId),
+}
+
+/// The ids held by places, in order.
+fn slot_ids(slots: Vec<Slot<'_>>) -> Vec<NodeId> {
+    slots
+        .into_iter()
+        .flat_map(|slot| match slot {
+            Slot::List(ids) => ids.to_vec(),
+            Slot::One(id) => vec![*id],
+        })
+        .collect()
+}
+
+/// The children of a command which is not in a pool.
+fn command_children(cmd: &mut AcCommand) -> Vec<NodeId> {
+    let mut slots = Vec::new();
+    command_slots(cmd, &mut slots);
+    slot_ids(slots)
+}
+
+/// Collect the places holding the children of a command.
+fn command_slots<'s>(cmd: &'s mut AcCommand, slots: &mut Vec<Slot<'s>>) {
+    use ShellCommand::*;
+    let cmd = match &mut cmd.0 {
+        MayM4::Shell(cmd) => cmd,
+        MayM4::Macro(m4_macro) => return macro_slots(m4_macro, slots),
+    };
+    match cmd {
+        Assignment(_, word) => word_slots(word, slots),
+        Cmd(words) => words.iter_mut().for_each(|word| word_slots(word, slots)),
+        Brace(ids) | Subshell(ids) | Pipe(_, ids) => slots.push(Slot::List(ids)),
+        While(pair) | Until(pair) => {
+            condition_slots(&mut pair.condition, slots);
+            slots.push(Slot::List(&mut pair.body));
+        }
+        If {
+            conditionals,
+            else_branch,
+        } => {
+            for pair in conditionals {
+                condition_slots(&mut pair.condition, slots);
+                slots.push(Slot::List(&mut pair.body));
+            }
+            slots.push(Slot::List(else_branch));
+        }
+        For { words, body, .. } => {
+            words.iter_mut().for_each(|word| word_slots(word, slots));
+            slots.push(Slot::List(body));
+        }
+        Case { word, arms } => {
+            word_slots(word, slots);
+            for arm in arms {
+                arm.patterns
+                    .iter_mut()
+                    .for_each(|word| word_slots(word, slots));
+                slots.push(Slot::List(&mut arm.body));
+            }
+        }
+        And(cond, id) | Or(cond, id) => {
+            condition_slots(cond, slots);
+            slots.push(Slot::One(id));
+        }
+        Redirect(id, redirects) => {
+            slots.push(Slot::One(id));
+            for redirect in redirects {
+                word_slots(redirect_word_mut(redirect), slots);
+            }
+        }
+        Background(id) | FunctionDef { body: id, .. } => slots.push(Slot::One(id)),
+    }
+}
+
+fn macro_slots<'s>(m4_macro: &'s mut M4Macro, slots: &mut Vec<Slot<'s>>) {
+    for arg in &mut m4_macro.args {
+        argument_slots(arg, slots);
+    }
+}
+
+fn argument_slots<'s>(arg: &'s mut M4Argument, slots: &mut Vec<Slot<'s>>) {
+    match arg {
+        M4Argument::Word(word) => word_slots(word, slots),
+        M4Argument::Array(words) => words.iter_mut().for_each(|word| word_slots(word, slots)),
+        M4Argument::Commands(ids) => slots.push(Slot::List(ids)),
+        M4Argument::Literal(_) | M4Argument::Program(_) | M4Argument::Unknown(_) => {}
+    }
+}
+
+fn condition_slots<'s>(cond: &'s mut Condition<AcWord>, slots: &mut Vec<Slot<'s>>) {
+    match cond {
+        Condition::Cond(op) => {
+            for word in operands_mut(op).into_iter().flatten() {
+                word_slots(word, slots);
+            }
+        }
+        Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+            condition_slots(lhs, slots);
+            condition_slots(rhs, slots);
+        }
+        Condition::Eval(ids) => slots.push(Slot::List(ids)),
+        Condition::ReturnZero(id) => slots.push(Slot::One(id)),
+    }
+}
+
+fn word_slots<'s>(word: &'s mut AcWord, slots: &mut Vec<Slot<'s>>) {
+    for fragment in fragments_mut(&mut word.0) {
+        match fragment {
+            MayM4::Shell(fragment) => fragment_slots(fragment, slots),
+            MayM4::Macro(m4_macro) => macro_slots(m4_macro, slots),
+        }
+    }
+}
+
+fn fragment_slots<'s>(fragment: &'s mut WordFragment<AcWord>, slots: &mut Vec<Slot<'s>>) {
+    match fragment {
+        WordFragment::DoubleQuoted(fragments) => fragments
+            .iter_mut()
+            .for_each(|fragment| fragment_slots(fragment, slots)),
+        WordFragment::Subst(subst) => {
+            let (ids, word) = subst_parts_mut(subst);
+            if let Some(ids) = ids {
+                slots.push(Slot::List(ids));
+            }
+            if let Some(Some(word)) = word {
+                word_slots(word, slots);
+            }
+        }
+        _ => {}
+    }
+}
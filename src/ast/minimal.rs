@@ -7,6 +7,7 @@ use std::fmt;
 ///
 /// Generic over the representation of a literals, parameters, and substitutions.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WordFragment<L, C, W> {
     /// A non-special literal word.
     Literal(L),
@@ -49,6 +50,7 @@ where
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Wraps minimal Word with fixing generics
 pub struct AcWord<L>(pub Word<MinimalWordFragment<L>>);
 
@@ -96,6 +98,7 @@ impl<L> From<AcWord<L>> for Word<MinimalWordFragment<L>> {
 
 /// A collection of simple words, wrapping a vector of `WordFragment`
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Word<X> {
     /// A word composed of multiple fragments concatenated together
     Concat(Vec<X>),
@@ -107,6 +110,7 @@ pub enum Word<X> {
 
 /// Operators used to compare words or check file properties.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operator<W> {
     /// Equality operator (==) between two words.
     Eq(W, W),
@@ -135,6 +139,7 @@ pub enum Operator<W> {
 /// Represents a condition for control flow, which can be a single operator-based
 /// condition, a logical combination of conditions, or an evaluated word.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Condition<C, W> {
     /// A single condition based on an operator.
     Cond(Operator<W>),
@@ -150,6 +155,7 @@ pub enum Condition<C, W> {
 
 /// A pairing of a condition (guard) with a block of commands (body).
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GuardBodyPair<C, W> {
     /// The condition to evaluate.
     pub condition: Condition<C, W>,
@@ -160,6 +166,7 @@ pub struct GuardBodyPair<C, W> {
 /// Represents a compound command which includes control flow constructs such as loops,
 /// conditionals, case statements, and background execution.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompoundCommand<C, W> {
     /// A group of commands that should be executed in the current environment.
     Brace(Vec<C>),
@@ -213,6 +220,7 @@ pub enum CompoundCommand<C, W> {
 
 /// Complete the parsed command with additional information such as comment, line numbers, etc.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AcCommand<L, W> {
     /// trailing comments
     pub comment: Option<String>,
@@ -272,6 +280,7 @@ impl<L, W> AcCommand<L, W> {
 /// Represents a command, which can be an assignment, compound command, simple command,
 /// or macro command.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Command<L, C, W> {
     /// An assignment command that associates a value with a variable.
     Assignment(L, W),
@@ -30,6 +30,7 @@ pub type AcWordFragment = super::MayM4<WordFragment<AcWord>, M4Macro>;
 
 /// Wraps minimal Word with fixing generics
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AcWord(pub super::minimal::Word<AcWordFragment>);
 
 impl From<WordFragment<AcWord>> for AcWordFragment {
@@ -68,6 +69,7 @@ impl Into<Option<WordFragment<AcWord>>> for AcWordFragment {
 
 /// Wraps command with fixing generics
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AcCommand(pub super::MayM4<ShellCommand<AcWord>, M4Macro>);
 
 impl AcCommand {
@@ -101,6 +103,7 @@ impl From<m4_macro::M4Macro<NodeId, AcWord>> for AcCommand {
 
 /// Complete the parsed command with additional information such as comment, line numbers, etc.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node<C, U> {
     /// trailing comments
     pub comment: Option<String>,
@@ -136,6 +139,7 @@ impl<C, U> Node<C, U> {
 
 /// represents any kinds of commands
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ShellCommand<W> {
     /// An assignment command that associates a value with a variable.
     Assignment(String, W),
@@ -204,6 +208,11 @@ pub trait DisplayNode {
 }
 
 /// A pool of autoconf commands stored as nodes.
+///
+/// With the `serde` feature, a pool is written as an object with the
+/// `version` of its schema and the list of its `nodes`, each with its `id`.
+/// The schema is `schema/autoconf-pool.schema.json` at the root of the
+/// repository, and changes to it bump `POOL_SCHEMA_VERSION`.
 pub struct AutoconfPool<U = ()> {
     /// Contains all nodes. `NodeId` represents indexes of nodes in this slab.
     pub nodes: Slab<Node<AcCommand, U>>,
@@ -262,6 +271,76 @@ impl<U> AutoconfPool<U> {
     }
 }
 
+/// The version of the JSON schema of `AutoconfPool`.
+#[cfg(feature = "serde")]
+pub const POOL_SCHEMA_VERSION: u32 = 1;
+
+#[cfg(feature = "serde")]
+mod serde_pool {
+    use super::{AcCommand, AutoconfPool, Node, NodeId, POOL_SCHEMA_VERSION};
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use slab::Slab;
+
+    #[derive(Serialize)]
+    struct PoolRef<'a, U> {
+        version: u32,
+        nodes: Vec<NodeRef<'a, U>>,
+    }
+
+    #[derive(Serialize)]
+    struct NodeRef<'a, U> {
+        id: NodeId,
+        #[serde(flatten)]
+        node: &'a Node<AcCommand, U>,
+    }
+
+    #[derive(Deserialize)]
+    struct Pool<U> {
+        version: u32,
+        nodes: Vec<NodeEntry<U>>,
+    }
+
+    #[derive(Deserialize)]
+    struct NodeEntry<U> {
+        id: NodeId,
+        #[serde(flatten)]
+        node: Node<AcCommand, U>,
+    }
+
+    impl<U: Serialize> Serialize for AutoconfPool<U> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            PoolRef {
+                version: POOL_SCHEMA_VERSION,
+                nodes: self
+                    .nodes
+                    .iter()
+                    .map(|(id, node)| NodeRef { id, node })
+                    .collect(),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, U: Deserialize<'de>> Deserialize<'de> for AutoconfPool<U> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let pool = Pool::<U>::deserialize(deserializer)?;
+            if pool.version != POOL_SCHEMA_VERSION {
+                return Err(D::Error::custom(format!(
+                    "unsupported schema version {}, expected {}",
+                    pool.version, POOL_SCHEMA_VERSION
+                )));
+            }
+            let nodes = pool
+                .nodes
+                .into_iter()
+                .map(|entry| (entry.id, entry.node))
+                .collect::<Slab<_>>();
+            Ok(AutoconfPool::new(nodes, None))
+        }
+    }
+}
+
 impl<U> DisplayNode for AutoconfPool<U> {
     type Word = AcWord;
 
@@ -0,0 +1,224 @@
+//! Defines traversals over each representation of the source.
+//!
+//! Every submodule offers a `Visit` trait walking a tree by shared reference
+//! and a `VisitMut` trait walking it by mutable reference. The trees owning
+//! their children, `minimal` and `top_level`, also offer a `Fold` trait
+//! which consumes a tree and rebuilds it.
+//!
+//! Each trait method defaults to the free function of the same module which
+//! descends into the children of the visited value: `walk_*` for `Visit`,
+//! `walk_*_mut` for `VisitMut` and `fold_*` for `Fold`. An overriding method
+//! calls that function to keep descending, or returns early to prune the
+//! subtree. The walks enter the arguments of m4 macro calls as well as the
+//! fragments of words, so commands nested in a command substitution or in
+//! a macro argument are visited like any other command.
+//!
+//! ```
+//! use autotools_parser::ast::node::AutoconfPool;
+//! use autotools_parser::ast::node::M4Macro;
+//! use autotools_parser::ast::visit::node::{walk_macro, Visit};
+//! use autotools_parser::lexer::Lexer;
+//! use autotools_parser::parse::autoconf::NodeParser;
+//!
+//! struct Macros(Vec<String>);
+//!
+//! impl<'a> Visit<'a> for Macros {
+//!     fn visit_macro(&mut self, pool: &'a AutoconfPool, m4_macro: &'a M4Macro) {
+//!         self.0.push(m4_macro.name.clone());
+//!         walk_macro(self, pool, m4_macro);
+//!     }
+//! }
+//!
+//! let source = "AC_CHECK_HEADERS([zlib.h], [], [AC_MSG_ERROR([no zlib])])\n";
+//! let (nodes, top_ids) = NodeParser::<_, ()>::new(Lexer::new(source.chars())).parse_all();
+//! let pool = AutoconfPool::new(nodes, None);
+//! let mut macros = Macros(Vec::new());
+//! for id in top_ids {
+//!     macros.visit_node(&pool, id);
+//! }
+//! assert_eq!(macros.0, ["AC_CHECK_HEADERS", "AC_MSG_ERROR"]);
+//! ```
+
+use super::minimal::{Operator, Word};
+use super::{ParameterSubstitution, Redirect};
+use std::slice;
+
+pub mod am;
+pub mod minimal;
+pub mod node;
+pub mod top_level;
+
+/// Get the fragments of a minimal word.
+fn fragments<X>(word: &Word<X>) -> &[X] {
+    match word {
+        Word::Concat(frags) => frags,
+        Word::Single(frag) => slice::from_ref(frag),
+        Word::Empty => &[],
+    }
+}
+
+/// Get the fragments of a minimal word mutably.
+pub(crate) fn fragments_mut<X>(word: &mut Word<X>) -> &mut [X] {
+    match word {
+        Word::Concat(frags) => frags,
+        Word::Single(frag) => slice::from_mut(frag),
+        Word::Empty => &mut [],
+    }
+}
+
+/// Rebuild a minimal word by mapping its fragments.
+fn map_fragments<X, F: FnMut(X) -> X>(word: Word<X>, mut f: F) -> Word<X> {
+    match word {
+        Word::Concat(frags) => Word::Concat(frags.into_iter().map(f).collect()),
+        Word::Single(frag) => Word::Single(f(frag)),
+        Word::Empty => Word::Empty,
+    }
+}
+
+/// Get the operands of an operator in order.
+fn operands<W>(op: &Operator<W>) -> [Option<&W>; 2] {
+    use Operator::*;
+    match op {
+        Eq(lhs, rhs)
+        | Neq(lhs, rhs)
+        | Ge(lhs, rhs)
+        | Gt(lhs, rhs)
+        | Le(lhs, rhs)
+        | Lt(lhs, rhs) => [Some(lhs), Some(rhs)],
+        Empty(w) | NonEmpty(w) | Dir(w) | File(w) | NoExists(w) => [Some(w), None],
+    }
+}
+
+/// Get the operands of an operator in order, mutably.
+pub(crate) fn operands_mut<W>(op: &mut Operator<W>) -> [Option<&mut W>; 2] {
+    use Operator::*;
+    match op {
+        Eq(lhs, rhs)
+        | Neq(lhs, rhs)
+        | Ge(lhs, rhs)
+        | Gt(lhs, rhs)
+        | Le(lhs, rhs)
+        | Lt(lhs, rhs) => [Some(lhs), Some(rhs)],
+        Empty(w) | NonEmpty(w) | Dir(w) | File(w) | NoExists(w) => [Some(w), None],
+    }
+}
+
+/// Rebuild an operator by mapping its operands.
+fn map_operands<W, F: FnMut(W) -> W>(op: Operator<W>, mut f: F) -> Operator<W> {
+    use Operator::*;
+    match op {
+        Eq(lhs, rhs) => {
+            let lhs = f(lhs);
+            Eq(lhs, f(rhs))
+        }
+        Neq(lhs, rhs) => {
+            let lhs = f(lhs);
+            Neq(lhs, f(rhs))
+        }
+        Ge(lhs, rhs) => {
+            let lhs = f(lhs);
+            Ge(lhs, f(rhs))
+        }
+        Gt(lhs, rhs) => {
+            let lhs = f(lhs);
+            Gt(lhs, f(rhs))
+        }
+        Le(lhs, rhs) => {
+            let lhs = f(lhs);
+            Le(lhs, f(rhs))
+        }
+        Lt(lhs, rhs) => {
+            let lhs = f(lhs);
+            Lt(lhs, f(rhs))
+        }
+        Empty(w) => Empty(f(w)),
+        NonEmpty(w) => NonEmpty(f(w)),
+        Dir(w) => Dir(f(w)),
+        File(w) => File(f(w)),
+        NoExists(w) => NoExists(f(w)),
+    }
+}
+
+/// Get the word a redirect reads from or writes to.
+fn redirect_word<W>(redirect: &Redirect<W>) -> &W {
+    use Redirect::*;
+    match redirect {
+        Read(_, w)
+        | Write(_, w)
+        | ReadWrite(_, w)
+        | Append(_, w)
+        | Clobber(_, w)
+        | Heredoc(_, w)
+        | DupRead(_, w)
+        | DupWrite(_, w) => w,
+    }
+}
+
+/// Get the word a redirect reads from or writes to, mutably.
+pub(crate) fn redirect_word_mut<W>(redirect: &mut Redirect<W>) -> &mut W {
+    use Redirect::*;
+    match redirect {
+        Read(_, w)
+        | Write(_, w)
+        | ReadWrite(_, w)
+        | Append(_, w)
+        | Clobber(_, w)
+        | Heredoc(_, w)
+        | DupRead(_, w)
+        | DupWrite(_, w) => w,
+    }
+}
+
+/// Rebuild a redirect by mapping its word.
+fn map_redirect<W, F: FnOnce(W) -> W>(redirect: Redirect<W>, f: F) -> Redirect<W> {
+    use Redirect::*;
+    match redirect {
+        Read(fd, w) => Read(fd, f(w)),
+        Write(fd, w) => Write(fd, f(w)),
+        ReadWrite(fd, w) => ReadWrite(fd, f(w)),
+        Append(fd, w) => Append(fd, f(w)),
+        Clobber(fd, w) => Clobber(fd, f(w)),
+        Heredoc(fd, w) => Heredoc(fd, f(w)),
+        DupRead(fd, w) => DupRead(fd, f(w)),
+        DupWrite(fd, w) => DupWrite(fd, f(w)),
+    }
+}
+
+/// Get the commands and the word held by a parameter substitution.
+fn subst_parts<P, C, W, A>(subst: &ParameterSubstitution<P, C, W, A>) -> (&[C], Option<&W>) {
+    use ParameterSubstitution::*;
+    match subst {
+        Command(cmds) => (cmds, None),
+        Len(_) | Arith(_) => (&[], None),
+        Default(_, _, w)
+        | Assign(_, _, w)
+        | Error(_, _, w)
+        | Alternative(_, _, w)
+        | RemoveSmallestSuffix(_, w)
+        | RemoveLargestSuffix(_, w)
+        | RemoveSmallestPrefix(_, w)
+        | RemoveLargestPrefix(_, w) => (&[], w.as_ref()),
+    }
+}
+
+/// Get the commands and the word held by a parameter substitution, mutably.
+///
+/// The word is returned as its `Option` so that a fold may take it.
+#[allow(clippy::type_complexity)]
+pub(crate) fn subst_parts_mut<P, C, W, A>(
+    subst: &mut ParameterSubstitution<P, C, W, A>,
+) -> (Option<&mut Vec<C>>, Option<&mut Option<W>>) {
+    use ParameterSubstitution::*;
+    match subst {
+        Command(cmds) => (Some(cmds), None),
+        Len(_) | Arith(_) => (None, None),
+        Default(_, _, w)
+        | Assign(_, _, w)
+        | Error(_, _, w)
+        | Alternative(_, _, w)
+        | RemoveSmallestSuffix(_, w)
+        | RemoveLargestSuffix(_, w)
+        | RemoveSmallestPrefix(_, w)
+        | RemoveLargestPrefix(_, w) => (None, Some(w)),
+    }
+}
@@ -0,0 +1,644 @@
+//! Traversals over the statements of a makefile, stored as `AmLine` nodes.
+//!
+//! Statements refer to their children by `NodeId`, so every method also
+//! receives the nodes through which the walk resolves them. Both branches of
+//! a conditional are visited, as well as the statements of a resolved include
+//! file.
+use super::{fragments, fragments_mut, operands, operands_mut, redirect_word, redirect_word_mut};
+use super::{subst_parts, subst_parts_mut};
+use crate::ast::am::{AmLine, AmVar, AmWord, AmWordFragment, MakeCondition, MayAm};
+use crate::ast::node::{
+    Condition, Node, NodeId, Operator, ParameterSubstitution, Redirect, ShellCommand, WordFragment,
+};
+use slab::Slab;
+use std::mem;
+
+/// The nodes holding the statements of a makefile.
+pub type AmNodes<U = ()> = Slab<Node<AmLine, U>>;
+
+/// A read-only traversal of the statements of a makefile.
+///
+/// Start the walk with `visit_node` on each top-level statement.
+pub trait Visit<'a, U: 'a = ()> {
+    /// Visit the statement `id`.
+    fn visit_node(&mut self, nodes: &'a AmNodes<U>, id: NodeId) {
+        walk_node(self, nodes, id)
+    }
+
+    /// Visit a statement.
+    fn visit_line(&mut self, nodes: &'a AmNodes<U>, line: &'a AmLine) {
+        walk_line(self, nodes, line)
+    }
+
+    /// Visit a shell command, either a statement or a line of a recipe.
+    fn visit_shell_command(&mut self, nodes: &'a AmNodes<U>, cmd: &'a ShellCommand<AmWord>) {
+        walk_shell_command(self, nodes, cmd)
+    }
+
+    /// Visit the condition of an `if`, a loop or an and/or command.
+    fn visit_condition(&mut self, nodes: &'a AmNodes<U>, cond: &'a Condition<AmWord>) {
+        walk_condition(self, nodes, cond)
+    }
+
+    /// Visit a test operator.
+    fn visit_operator(&mut self, nodes: &'a AmNodes<U>, op: &'a Operator<AmWord>) {
+        walk_operator(self, nodes, op)
+    }
+
+    /// Visit a redirect.
+    fn visit_redirect(&mut self, nodes: &'a AmNodes<U>, redirect: &'a Redirect<AmWord>) {
+        walk_redirect(self, nodes, redirect)
+    }
+
+    /// Visit a word.
+    fn visit_word(&mut self, nodes: &'a AmNodes<U>, word: &'a AmWord) {
+        walk_word(self, nodes, word)
+    }
+
+    /// Visit a fragment of a word, either shell or make.
+    fn visit_fragment(&mut self, nodes: &'a AmNodes<U>, fragment: &'a AmWordFragment) {
+        walk_fragment(self, nodes, fragment)
+    }
+
+    /// Visit a shell fragment of a word.
+    fn visit_shell_fragment(&mut self, nodes: &'a AmNodes<U>, fragment: &'a WordFragment<AmWord>) {
+        walk_shell_fragment(self, nodes, fragment)
+    }
+
+    /// Visit a make variable reference or function call, e.g. `$(CC)`.
+    fn visit_variable(&mut self, nodes: &'a AmNodes<U>, var: &'a AmVar) {
+        walk_variable(self, nodes, var)
+    }
+
+    /// Visit a parameter substitution, e.g. `${foo:-bar}` or `$(cmd)`.
+    fn visit_substitution(
+        &mut self,
+        nodes: &'a AmNodes<U>,
+        subst: &'a ParameterSubstitution<AmWord>,
+    ) {
+        walk_substitution(self, nodes, subst)
+    }
+}
+
+fn visit_nodes<'a, U, V>(v: &mut V, nodes: &'a AmNodes<U>, ids: &[NodeId])
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    for id in ids {
+        v.visit_node(nodes, *id);
+    }
+}
+
+fn visit_words<'a, U, V>(v: &mut V, nodes: &'a AmNodes<U>, words: &'a [AmWord])
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    for word in words {
+        v.visit_word(nodes, word);
+    }
+}
+
+/// Visit the statement of a node.
+pub fn walk_node<'a, U, V>(v: &mut V, nodes: &'a AmNodes<U>, id: NodeId)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    v.visit_line(nodes, &nodes[id].cmd)
+}
+
+/// Visit the words and child statements of a statement.
+pub fn walk_line<'a, U, V>(v: &mut V, nodes: &'a AmNodes<U>, line: &'a AmLine)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    match line {
+        AmLine::Rule(rule) => {
+            visit_words(v, nodes, &rule.target);
+            visit_words(v, nodes, &rule.dependency);
+            visit_nodes(v, nodes, &rule.recipe);
+        }
+        AmLine::Conditional(cond) => {
+            visit_nodes(v, nodes, &cond.then);
+            visit_nodes(v, nodes, &cond.otherwise);
+        }
+        AmLine::Assignment(assignment) => visit_words(v, nodes, &assignment.rhs),
+        AmLine::Shell(cmd) => v.visit_shell_command(nodes, cmd),
+        AmLine::MakeConditional(cond) => {
+            match &cond.condition {
+                MakeCondition::Eq(lhs, rhs) | MakeCondition::Neq(lhs, rhs) => {
+                    visit_words(v, nodes, lhs);
+                    visit_words(v, nodes, rhs);
+                }
+                MakeCondition::Defined(word) | MakeCondition::NotDefined(word) => {
+                    v.visit_word(nodes, word)
+                }
+            }
+            visit_nodes(v, nodes, &cond.then);
+            visit_nodes(v, nodes, &cond.otherwise);
+        }
+        AmLine::Define(_) => {}
+        AmLine::Export(export) => {
+            visit_words(v, nodes, &export.vars);
+            if let Some(id) = export.assignment {
+                v.visit_node(nodes, id);
+            }
+        }
+        AmLine::Override(id) => v.visit_node(nodes, *id),
+        AmLine::Include(include) => {
+            visit_words(v, nodes, &include.paths);
+            if let Some(included) = &include.included {
+                visit_nodes(v, nodes, &included.body);
+            }
+        }
+        AmLine::Vpath(vpath) => {
+            if let Some(pattern) = &vpath.pattern {
+                v.visit_word(nodes, pattern);
+            }
+            visit_words(v, nodes, &vpath.dirs);
+        }
+        AmLine::Substitution(words) => visit_words(v, nodes, words),
+    }
+}
+
+/// Visit the words, conditions, redirects and child nodes of a shell command.
+pub fn walk_shell_command<'a, U, V>(v: &mut V, nodes: &'a AmNodes<U>, cmd: &'a ShellCommand<AmWord>)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    use ShellCommand::*;
+    match cmd {
+        Assignment(_, word) => v.visit_word(nodes, word),
+        Cmd(words) => visit_words(v, nodes, words),
+        Brace(ids) | Subshell(ids) | Pipe(_, ids) => visit_nodes(v, nodes, ids),
+        While(pair) | Until(pair) => {
+            v.visit_condition(nodes, &pair.condition);
+            visit_nodes(v, nodes, &pair.body);
+        }
+        If {
+            conditionals,
+            else_branch,
+        } => {
+            for pair in conditionals {
+                v.visit_condition(nodes, &pair.condition);
+                visit_nodes(v, nodes, &pair.body);
+            }
+            visit_nodes(v, nodes, else_branch);
+        }
+        For { words, body, .. } => {
+            visit_words(v, nodes, words);
+            visit_nodes(v, nodes, body);
+        }
+        Case { word, arms } => {
+            v.visit_word(nodes, word);
+            for arm in arms {
+                visit_words(v, nodes, &arm.patterns);
+                visit_nodes(v, nodes, &arm.body);
+            }
+        }
+        And(cond, id) | Or(cond, id) => {
+            v.visit_condition(nodes, cond);
+            v.visit_node(nodes, *id);
+        }
+        Redirect(id, redirects) => {
+            v.visit_node(nodes, *id);
+            for redirect in redirects {
+                v.visit_redirect(nodes, redirect);
+            }
+        }
+        Background(id) | FunctionDef { body: id, .. } => v.visit_node(nodes, *id),
+    }
+}
+
+/// Visit the operators, sub-conditions or commands of a condition.
+pub fn walk_condition<'a, U, V>(v: &mut V, nodes: &'a AmNodes<U>, cond: &'a Condition<AmWord>)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    match cond {
+        Condition::Cond(op) => v.visit_operator(nodes, op),
+        Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+            v.visit_condition(nodes, lhs);
+            v.visit_condition(nodes, rhs);
+        }
+        Condition::Eval(ids) => visit_nodes(v, nodes, ids),
+        Condition::ReturnZero(id) => v.visit_node(nodes, **id),
+    }
+}
+
+/// Visit the operands of a test operator.
+pub fn walk_operator<'a, U, V>(v: &mut V, nodes: &'a AmNodes<U>, op: &'a Operator<AmWord>)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    for word in operands(op).into_iter().flatten() {
+        v.visit_word(nodes, word);
+    }
+}
+
+/// Visit the word of a redirect.
+pub fn walk_redirect<'a, U, V>(v: &mut V, nodes: &'a AmNodes<U>, redirect: &'a Redirect<AmWord>)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    v.visit_word(nodes, redirect_word(redirect))
+}
+
+/// Visit the fragments of a word.
+pub fn walk_word<'a, U, V>(v: &mut V, nodes: &'a AmNodes<U>, word: &'a AmWord)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    for fragment in fragments(&word.0) {
+        v.visit_fragment(nodes, fragment);
+    }
+}
+
+/// Visit the shell fragment or the make variable of a word fragment.
+pub fn walk_fragment<'a, U, V>(v: &mut V, nodes: &'a AmNodes<U>, fragment: &'a AmWordFragment)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    match fragment {
+        MayAm::Shell(fragment) => v.visit_shell_fragment(nodes, fragment),
+        MayAm::Automake(var) => v.visit_variable(nodes, var),
+    }
+}
+
+/// Visit the double quoted fragments or the substitution of a shell fragment.
+pub fn walk_shell_fragment<'a, U, V>(
+    v: &mut V,
+    nodes: &'a AmNodes<U>,
+    fragment: &'a WordFragment<AmWord>,
+) where
+    V: Visit<'a, U> + ?Sized,
+{
+    match fragment {
+        WordFragment::DoubleQuoted(fragments) => {
+            for fragment in fragments {
+                v.visit_shell_fragment(nodes, fragment);
+            }
+        }
+        WordFragment::Subst(subst) => v.visit_substitution(nodes, subst),
+        _ => {}
+    }
+}
+
+/// Visit the words of a computed variable name, a substitution reference
+/// or the arguments of a function call.
+pub fn walk_variable<'a, U, V>(v: &mut V, nodes: &'a AmNodes<U>, var: &'a AmVar)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    match var {
+        AmVar::Param(_) | AmVar::Template(_) => {}
+        AmVar::Computed(word) => v.visit_word(nodes, word),
+        AmVar::SubstRef(subst_ref) => {
+            v.visit_word(nodes, &subst_ref.var);
+            v.visit_word(nodes, &subst_ref.pattern);
+            v.visit_word(nodes, &subst_ref.replacement);
+        }
+        AmVar::Function(func) => {
+            for arg in &func.args {
+                visit_words(v, nodes, arg);
+            }
+        }
+    }
+}
+
+/// Visit the commands or the word of a parameter substitution.
+pub fn walk_substitution<'a, U, V>(
+    v: &mut V,
+    nodes: &'a AmNodes<U>,
+    subst: &'a ParameterSubstitution<AmWord>,
+) where
+    V: Visit<'a, U> + ?Sized,
+{
+    let (ids, word) = subst_parts(subst);
+    visit_nodes(v, nodes, ids);
+    if let Some(word) = word {
+        v.visit_word(nodes, word);
+    }
+}
+
+/// A mutating traversal of the statements of a makefile.
+///
+/// While a node is visited, its statement is moved out of the nodes, which
+/// hold an empty substitution at its id until the walk of the node returns.
+/// Other nodes, including the children of the visited node, stay in place
+/// and may be edited through the nodes.
+pub trait VisitMut<U = ()> {
+    /// Visit the statement `id`.
+    fn visit_node_mut(&mut self, nodes: &mut AmNodes<U>, id: NodeId) {
+        walk_node_mut(self, nodes, id)
+    }
+
+    /// Visit a statement.
+    fn visit_line_mut(&mut self, nodes: &mut AmNodes<U>, line: &mut AmLine) {
+        walk_line_mut(self, nodes, line)
+    }
+
+    /// Visit a shell command, either a statement or a line of a recipe.
+    fn visit_shell_command_mut(&mut self, nodes: &mut AmNodes<U>, cmd: &mut ShellCommand<AmWord>) {
+        walk_shell_command_mut(self, nodes, cmd)
+    }
+
+    /// Visit the condition of an `if`, a loop or an and/or command.
+    fn visit_condition_mut(&mut self, nodes: &mut AmNodes<U>, cond: &mut Condition<AmWord>) {
+        walk_condition_mut(self, nodes, cond)
+    }
+
+    /// Visit a test operator.
+    fn visit_operator_mut(&mut self, nodes: &mut AmNodes<U>, op: &mut Operator<AmWord>) {
+        walk_operator_mut(self, nodes, op)
+    }
+
+    /// Visit a redirect.
+    fn visit_redirect_mut(&mut self, nodes: &mut AmNodes<U>, redirect: &mut Redirect<AmWord>) {
+        walk_redirect_mut(self, nodes, redirect)
+    }
+
+    /// Visit a word.
+    fn visit_word_mut(&mut self, nodes: &mut AmNodes<U>, word: &mut AmWord) {
+        walk_word_mut(self, nodes, word)
+    }
+
+    /// Visit a fragment of a word, either shell or make.
+    fn visit_fragment_mut(&mut self, nodes: &mut AmNodes<U>, fragment: &mut AmWordFragment) {
+        walk_fragment_mut(self, nodes, fragment)
+    }
+
+    /// Visit a shell fragment of a word.
+    fn visit_shell_fragment_mut(
+        &mut self,
+        nodes: &mut AmNodes<U>,
+        fragment: &mut WordFragment<AmWord>,
+    ) {
+        walk_shell_fragment_mut(self, nodes, fragment)
+    }
+
+    /// Visit a make variable reference or function call, e.g. `$(CC)`.
+    fn visit_variable_mut(&mut self, nodes: &mut AmNodes<U>, var: &mut AmVar) {
+        walk_variable_mut(self, nodes, var)
+    }
+
+    /// Visit a parameter substitution, e.g. `${foo:-bar}` or `$(cmd)`.
+    fn visit_substitution_mut(
+        &mut self,
+        nodes: &mut AmNodes<U>,
+        subst: &mut ParameterSubstitution<AmWord>,
+    ) {
+        walk_substitution_mut(self, nodes, subst)
+    }
+}
+
+fn visit_nodes_mut<U, V>(v: &mut V, nodes: &mut AmNodes<U>, ids: &[NodeId])
+where
+    V: VisitMut<U> + ?Sized,
+{
+    for id in ids {
+        v.visit_node_mut(nodes, *id);
+    }
+}
+
+fn visit_words_mut<U, V>(v: &mut V, nodes: &mut AmNodes<U>, words: &mut [AmWord])
+where
+    V: VisitMut<U> + ?Sized,
+{
+    for word in words {
+        v.visit_word_mut(nodes, word);
+    }
+}
+
+/// Visit the statement of a node, if the node is still in place.
+pub fn walk_node_mut<U, V>(v: &mut V, nodes: &mut AmNodes<U>, id: NodeId)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    let Some(node) = nodes.get_mut(id) else {
+        return;
+    };
+    let mut line = mem::replace(&mut node.cmd, AmLine::Substitution(Vec::new()));
+    v.visit_line_mut(nodes, &mut line);
+    if let Some(node) = nodes.get_mut(id) {
+        node.cmd = line;
+    }
+}
+
+/// Visit the words and child statements of a statement.
+pub fn walk_line_mut<U, V>(v: &mut V, nodes: &mut AmNodes<U>, line: &mut AmLine)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    match line {
+        AmLine::Rule(rule) => {
+            visit_words_mut(v, nodes, &mut rule.target);
+            visit_words_mut(v, nodes, &mut rule.dependency);
+            visit_nodes_mut(v, nodes, &rule.recipe);
+        }
+        AmLine::Conditional(cond) => {
+            visit_nodes_mut(v, nodes, &cond.then);
+            visit_nodes_mut(v, nodes, &cond.otherwise);
+        }
+        AmLine::Assignment(assignment) => visit_words_mut(v, nodes, &mut assignment.rhs),
+        AmLine::Shell(cmd) => v.visit_shell_command_mut(nodes, cmd),
+        AmLine::MakeConditional(cond) => {
+            match &mut cond.condition {
+                MakeCondition::Eq(lhs, rhs) | MakeCondition::Neq(lhs, rhs) => {
+                    visit_words_mut(v, nodes, lhs);
+                    visit_words_mut(v, nodes, rhs);
+                }
+                MakeCondition::Defined(word) | MakeCondition::NotDefined(word) => {
+                    v.visit_word_mut(nodes, word)
+                }
+            }
+            visit_nodes_mut(v, nodes, &cond.then);
+            visit_nodes_mut(v, nodes, &cond.otherwise);
+        }
+        AmLine::Define(_) => {}
+        AmLine::Export(export) => {
+            visit_words_mut(v, nodes, &mut export.vars);
+            if let Some(id) = export.assignment {
+                v.visit_node_mut(nodes, id);
+            }
+        }
+        AmLine::Override(id) => v.visit_node_mut(nodes, *id),
+        AmLine::Include(include) => {
+            visit_words_mut(v, nodes, &mut include.paths);
+            if let Some(included) = &include.included {
+                visit_nodes_mut(v, nodes, &included.body);
+            }
+        }
+        AmLine::Vpath(vpath) => {
+            if let Some(pattern) = &mut vpath.pattern {
+                v.visit_word_mut(nodes, pattern);
+            }
+            visit_words_mut(v, nodes, &mut vpath.dirs);
+        }
+        AmLine::Substitution(words) => visit_words_mut(v, nodes, words),
+    }
+}
+
+/// Visit the words, conditions, redirects and child nodes of a shell command.
+pub fn walk_shell_command_mut<U, V>(
+    v: &mut V,
+    nodes: &mut AmNodes<U>,
+    cmd: &mut ShellCommand<AmWord>,
+) where
+    V: VisitMut<U> + ?Sized,
+{
+    use ShellCommand::*;
+    match cmd {
+        Assignment(_, word) => v.visit_word_mut(nodes, word),
+        Cmd(words) => visit_words_mut(v, nodes, words),
+        Brace(ids) | Subshell(ids) | Pipe(_, ids) => visit_nodes_mut(v, nodes, ids),
+        While(pair) | Until(pair) => {
+            v.visit_condition_mut(nodes, &mut pair.condition);
+            visit_nodes_mut(v, nodes, &pair.body);
+        }
+        If {
+            conditionals,
+            else_branch,
+        } => {
+            for pair in conditionals {
+                v.visit_condition_mut(nodes, &mut pair.condition);
+                visit_nodes_mut(v, nodes, &pair.body);
+            }
+            visit_nodes_mut(v, nodes, else_branch);
+        }
+        For { words, body, .. } => {
+            visit_words_mut(v, nodes, words);
+            visit_nodes_mut(v, nodes, body);
+        }
+        Case { word, arms } => {
+            v.visit_word_mut(nodes, word);
+            for arm in arms {
+                visit_words_mut(v, nodes, &mut arm.patterns);
+                visit_nodes_mut(v, nodes, &arm.body);
+            }
+        }
+        And(cond, id) | Or(cond, id) => {
+            v.visit_condition_mut(nodes, cond);
+            v.visit_node_mut(nodes, *id);
+        }
+        Redirect(id, redirects) => {
+            v.visit_node_mut(nodes, *id);
+            for redirect in redirects {
+                v.visit_redirect_mut(nodes, redirect);
+            }
+        }
+        Background(id) | FunctionDef { body: id, .. } => v.visit_node_mut(nodes, *id),
+    }
+}
+
+/// Visit the operators, sub-conditions or commands of a condition.
+pub fn walk_condition_mut<U, V>(v: &mut V, nodes: &mut AmNodes<U>, cond: &mut Condition<AmWord>)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    match cond {
+        Condition::Cond(op) => v.visit_operator_mut(nodes, op),
+        Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+            v.visit_condition_mut(nodes, lhs);
+            v.visit_condition_mut(nodes, rhs);
+        }
+        Condition::Eval(ids) => visit_nodes_mut(v, nodes, ids),
+        Condition::ReturnZero(id) => v.visit_node_mut(nodes, **id),
+    }
+}
+
+/// Visit the operands of a test operator.
+pub fn walk_operator_mut<U, V>(v: &mut V, nodes: &mut AmNodes<U>, op: &mut Operator<AmWord>)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    for word in operands_mut(op).into_iter().flatten() {
+        v.visit_word_mut(nodes, word);
+    }
+}
+
+/// Visit the word of a redirect.
+pub fn walk_redirect_mut<U, V>(v: &mut V, nodes: &mut AmNodes<U>, redirect: &mut Redirect<AmWord>)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    v.visit_word_mut(nodes, redirect_word_mut(redirect))
+}
+
+/// Visit the fragments of a word.
+pub fn walk_word_mut<U, V>(v: &mut V, nodes: &mut AmNodes<U>, word: &mut AmWord)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    for fragment in fragments_mut(&mut word.0) {
+        v.visit_fragment_mut(nodes, fragment);
+    }
+}
+
+/// Visit the shell fragment or the make variable of a word fragment.
+pub fn walk_fragment_mut<U, V>(v: &mut V, nodes: &mut AmNodes<U>, fragment: &mut AmWordFragment)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    match fragment {
+        MayAm::Shell(fragment) => v.visit_shell_fragment_mut(nodes, fragment),
+        MayAm::Automake(var) => v.visit_variable_mut(nodes, var),
+    }
+}
+
+/// Visit the double quoted fragments or the substitution of a shell fragment.
+pub fn walk_shell_fragment_mut<U, V>(
+    v: &mut V,
+    nodes: &mut AmNodes<U>,
+    fragment: &mut WordFragment<AmWord>,
+) where
+    V: VisitMut<U> + ?Sized,
+{
+    match fragment {
+        WordFragment::DoubleQuoted(fragments) => {
+            for fragment in fragments {
+                v.visit_shell_fragment_mut(nodes, fragment);
+            }
+        }
+        WordFragment::Subst(subst) => v.visit_substitution_mut(nodes, subst),
+        _ => {}
+    }
+}
+
+/// Visit the words of a computed variable name, a substitution reference
+/// or the arguments of a function call.
+pub fn walk_variable_mut<U, V>(v: &mut V, nodes: &mut AmNodes<U>, var: &mut AmVar)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    match var {
+        AmVar::Param(_) | AmVar::Template(_) => {}
+        AmVar::Computed(word) => v.visit_word_mut(nodes, word),
+        AmVar::SubstRef(subst_ref) => {
+            v.visit_word_mut(nodes, &mut subst_ref.var);
+            v.visit_word_mut(nodes, &mut subst_ref.pattern);
+            v.visit_word_mut(nodes, &mut subst_ref.replacement);
+        }
+        AmVar::Function(func) => {
+            for arg in &mut func.args {
+                visit_words_mut(v, nodes, arg);
+            }
+        }
+    }
+}
+
+/// Visit the commands or the word of a parameter substitution.
+pub fn walk_substitution_mut<U, V>(
+    v: &mut V,
+    nodes: &mut AmNodes<U>,
+    subst: &mut ParameterSubstitution<AmWord>,
+) where
+    V: VisitMut<U> + ?Sized,
+{
+    let (ids, word) = subst_parts_mut(subst);
+    if let Some(ids) = ids {
+        visit_nodes_mut(v, nodes, ids);
+    }
+    if let Some(Some(word)) = word {
+        v.visit_word_mut(nodes, word);
+    }
+}
@@ -0,0 +1,799 @@
+//! Traversals over the minimal AST, rooted at `MinimalCommand`.
+use super::{fragments, fragments_mut, map_fragments, map_operands, map_redirect, operands};
+use super::{operands_mut, redirect_word, redirect_word_mut, subst_parts, subst_parts_mut};
+use crate::ast::minimal::{
+    AcCommand, AcWord, Command, CompoundCommand, Condition, GuardBodyPair, MinimalCommand,
+    MinimalWordFragment, Operator, WordFragment,
+};
+use crate::ast::{Arithmetic, MayM4, Parameter, ParameterSubstitution, PatternBodyPair, Redirect};
+use crate::m4_macro::{M4Argument, M4Macro};
+use std::mem;
+
+type Compound<L> = CompoundCommand<MinimalCommand<L>, AcWord<L>>;
+type Macro<L> = M4Macro<MinimalCommand<L>, AcWord<L>>;
+type Argument<L> = M4Argument<MinimalCommand<L>, AcWord<L>>;
+type Cond<L> = Condition<MinimalCommand<L>, AcWord<L>>;
+type ShellFragment<L> = WordFragment<L, MinimalCommand<L>, AcWord<L>>;
+type Subst<L> = ParameterSubstitution<Parameter<L>, MinimalCommand<L>, AcWord<L>, Arithmetic<L>>;
+
+/// A read-only traversal of the minimal AST.
+pub trait Visit<'a, L: 'a> {
+    /// Visit a command.
+    fn visit_command(&mut self, cmd: &'a MinimalCommand<L>) {
+        walk_command(self, cmd)
+    }
+
+    /// Visit a compound command.
+    fn visit_compound_command(&mut self, cmd: &'a Compound<L>) {
+        walk_compound_command(self, cmd)
+    }
+
+    /// Visit an m4 macro call, either a command or a word fragment.
+    fn visit_macro(&mut self, m4_macro: &'a Macro<L>) {
+        walk_macro(self, m4_macro)
+    }
+
+    /// Visit an argument of an m4 macro call.
+    fn visit_argument(&mut self, arg: &'a Argument<L>) {
+        walk_argument(self, arg)
+    }
+
+    /// Visit the condition of an `if`, a loop or an and/or command.
+    fn visit_condition(&mut self, cond: &'a Cond<L>) {
+        walk_condition(self, cond)
+    }
+
+    /// Visit a test operator.
+    fn visit_operator(&mut self, op: &'a Operator<AcWord<L>>) {
+        walk_operator(self, op)
+    }
+
+    /// Visit a redirect.
+    fn visit_redirect(&mut self, redirect: &'a Redirect<AcWord<L>>) {
+        walk_redirect(self, redirect)
+    }
+
+    /// Visit a word.
+    fn visit_word(&mut self, word: &'a AcWord<L>) {
+        walk_word(self, word)
+    }
+
+    /// Visit a fragment of a word, either shell or m4.
+    fn visit_fragment(&mut self, fragment: &'a MinimalWordFragment<L>) {
+        walk_fragment(self, fragment)
+    }
+
+    /// Visit a shell fragment of a word.
+    fn visit_shell_fragment(&mut self, fragment: &'a ShellFragment<L>) {
+        walk_shell_fragment(self, fragment)
+    }
+
+    /// Visit a parameter substitution, e.g. `${foo:-bar}` or `$(cmd)`.
+    fn visit_substitution(&mut self, subst: &'a Subst<L>) {
+        walk_substitution(self, subst)
+    }
+}
+
+fn visit_commands<'a, L: 'a, V>(v: &mut V, cmds: &'a [MinimalCommand<L>])
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    for cmd in cmds {
+        v.visit_command(cmd);
+    }
+}
+
+fn visit_words<'a, L: 'a, V>(v: &mut V, words: &'a [AcWord<L>])
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    for word in words {
+        v.visit_word(word);
+    }
+}
+
+fn visit_pair<'a, L: 'a, V>(v: &mut V, pair: &'a GuardBodyPair<MinimalCommand<L>, AcWord<L>>)
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    v.visit_condition(&pair.condition);
+    visit_commands(v, &pair.body);
+}
+
+/// Visit the words, compound command or m4 macro call of a command.
+pub fn walk_command<'a, L: 'a, V>(v: &mut V, cmd: &'a MinimalCommand<L>)
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    match &*cmd.cmd {
+        Command::Assignment(_, word) => v.visit_word(word),
+        Command::Compound(MayM4::Shell(cmd)) => v.visit_compound_command(cmd),
+        Command::Compound(MayM4::Macro(m4_macro)) => v.visit_macro(m4_macro),
+        Command::Cmd(words) => visit_words(v, words),
+    }
+}
+
+/// Visit the words, conditions, redirects and inner commands of a compound command.
+pub fn walk_compound_command<'a, L: 'a, V>(v: &mut V, cmd: &'a Compound<L>)
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    use CompoundCommand::*;
+    match cmd {
+        Brace(cmds) | Subshell(cmds) | Pipe(_, cmds) => visit_commands(v, cmds),
+        While(pair) | Until(pair) => visit_pair(v, pair),
+        If {
+            conditionals,
+            else_branch,
+        } => {
+            for pair in conditionals {
+                visit_pair(v, pair);
+            }
+            visit_commands(v, else_branch);
+        }
+        For { words, body, .. } => {
+            visit_words(v, words);
+            visit_commands(v, body);
+        }
+        Case { word, arms } => {
+            v.visit_word(word);
+            for arm in arms {
+                visit_words(v, &arm.patterns);
+                visit_commands(v, &arm.body);
+            }
+        }
+        And(cond, cmd) | Or(cond, cmd) => {
+            v.visit_condition(cond);
+            v.visit_command(cmd);
+        }
+        Redirect(cmd, redirects) => {
+            v.visit_command(cmd);
+            for redirect in redirects {
+                v.visit_redirect(redirect);
+            }
+        }
+        Background(cmd) | FunctionDef { body: cmd, .. } => v.visit_command(cmd),
+    }
+}
+
+/// Visit the arguments of an m4 macro call.
+pub fn walk_macro<'a, L: 'a, V>(v: &mut V, m4_macro: &'a Macro<L>)
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    for arg in &m4_macro.args {
+        v.visit_argument(arg);
+    }
+}
+
+/// Visit the words or commands of an m4 macro argument.
+pub fn walk_argument<'a, L: 'a, V>(v: &mut V, arg: &'a Argument<L>)
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    match arg {
+        M4Argument::Word(word) => v.visit_word(word),
+        M4Argument::Array(words) => visit_words(v, words),
+        M4Argument::Commands(cmds) => visit_commands(v, cmds),
+        M4Argument::Literal(_) | M4Argument::Program(_) | M4Argument::Unknown(_) => {}
+    }
+}
+
+/// Visit the operators, sub-conditions or commands of a condition.
+pub fn walk_condition<'a, L: 'a, V>(v: &mut V, cond: &'a Cond<L>)
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    match cond {
+        Condition::Cond(op) => v.visit_operator(op),
+        Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+            v.visit_condition(lhs);
+            v.visit_condition(rhs);
+        }
+        Condition::Eval(cmds) => visit_commands(v, cmds),
+        Condition::ReturnZero(cmd) => v.visit_command(cmd),
+    }
+}
+
+/// Visit the operands of a test operator.
+pub fn walk_operator<'a, L: 'a, V>(v: &mut V, op: &'a Operator<AcWord<L>>)
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    for word in operands(op).into_iter().flatten() {
+        v.visit_word(word);
+    }
+}
+
+/// Visit the word of a redirect.
+pub fn walk_redirect<'a, L: 'a, V>(v: &mut V, redirect: &'a Redirect<AcWord<L>>)
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    v.visit_word(redirect_word(redirect))
+}
+
+/// Visit the fragments of a word.
+pub fn walk_word<'a, L: 'a, V>(v: &mut V, word: &'a AcWord<L>)
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    for fragment in fragments(&word.0) {
+        v.visit_fragment(fragment);
+    }
+}
+
+/// Visit the shell fragment or the m4 macro call of a word fragment.
+pub fn walk_fragment<'a, L: 'a, V>(v: &mut V, fragment: &'a MinimalWordFragment<L>)
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    match fragment {
+        MayM4::Shell(fragment) => v.visit_shell_fragment(fragment),
+        MayM4::Macro(m4_macro) => v.visit_macro(m4_macro),
+    }
+}
+
+/// Visit the double quoted fragments or the substitution of a shell fragment.
+pub fn walk_shell_fragment<'a, L: 'a, V>(v: &mut V, fragment: &'a ShellFragment<L>)
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    match fragment {
+        WordFragment::DoubleQuoted(fragments) => {
+            for fragment in fragments {
+                v.visit_shell_fragment(fragment);
+            }
+        }
+        WordFragment::Subst(subst) => v.visit_substitution(subst),
+        _ => {}
+    }
+}
+
+/// Visit the commands or the word of a parameter substitution.
+pub fn walk_substitution<'a, L: 'a, V>(v: &mut V, subst: &'a Subst<L>)
+where
+    V: Visit<'a, L> + ?Sized,
+{
+    let (cmds, word) = subst_parts(subst);
+    visit_commands(v, cmds);
+    if let Some(word) = word {
+        v.visit_word(word);
+    }
+}
+
+/// A mutating traversal of the minimal AST.
+pub trait VisitMut<L> {
+    /// Visit a command.
+    fn visit_command_mut(&mut self, cmd: &mut MinimalCommand<L>) {
+        walk_command_mut(self, cmd)
+    }
+
+    /// Visit a compound command.
+    fn visit_compound_command_mut(&mut self, cmd: &mut Compound<L>) {
+        walk_compound_command_mut(self, cmd)
+    }
+
+    /// Visit an m4 macro call, either a command or a word fragment.
+    fn visit_macro_mut(&mut self, m4_macro: &mut Macro<L>) {
+        walk_macro_mut(self, m4_macro)
+    }
+
+    /// Visit an argument of an m4 macro call.
+    fn visit_argument_mut(&mut self, arg: &mut Argument<L>) {
+        walk_argument_mut(self, arg)
+    }
+
+    /// Visit the condition of an `if`, a loop or an and/or command.
+    fn visit_condition_mut(&mut self, cond: &mut Cond<L>) {
+        walk_condition_mut(self, cond)
+    }
+
+    /// Visit a test operator.
+    fn visit_operator_mut(&mut self, op: &mut Operator<AcWord<L>>) {
+        walk_operator_mut(self, op)
+    }
+
+    /// Visit a redirect.
+    fn visit_redirect_mut(&mut self, redirect: &mut Redirect<AcWord<L>>) {
+        walk_redirect_mut(self, redirect)
+    }
+
+    /// Visit a word.
+    fn visit_word_mut(&mut self, word: &mut AcWord<L>) {
+        walk_word_mut(self, word)
+    }
+
+    /// Visit a fragment of a word, either shell or m4.
+    fn visit_fragment_mut(&mut self, fragment: &mut MinimalWordFragment<L>) {
+        walk_fragment_mut(self, fragment)
+    }
+
+    /// Visit a shell fragment of a word.
+    fn visit_shell_fragment_mut(&mut self, fragment: &mut ShellFragment<L>) {
+        walk_shell_fragment_mut(self, fragment)
+    }
+
+    /// Visit a parameter substitution, e.g. `${foo:-bar}` or `$(cmd)`.
+    fn visit_substitution_mut(&mut self, subst: &mut Subst<L>) {
+        walk_substitution_mut(self, subst)
+    }
+}
+
+fn visit_commands_mut<L, V>(v: &mut V, cmds: &mut [MinimalCommand<L>])
+where
+    V: VisitMut<L> + ?Sized,
+{
+    for cmd in cmds {
+        v.visit_command_mut(cmd);
+    }
+}
+
+fn visit_words_mut<L, V>(v: &mut V, words: &mut [AcWord<L>])
+where
+    V: VisitMut<L> + ?Sized,
+{
+    for word in words {
+        v.visit_word_mut(word);
+    }
+}
+
+fn visit_pair_mut<L, V>(v: &mut V, pair: &mut GuardBodyPair<MinimalCommand<L>, AcWord<L>>)
+where
+    V: VisitMut<L> + ?Sized,
+{
+    v.visit_condition_mut(&mut pair.condition);
+    visit_commands_mut(v, &mut pair.body);
+}
+
+/// Visit the words, compound command or m4 macro call of a command.
+pub fn walk_command_mut<L, V>(v: &mut V, cmd: &mut MinimalCommand<L>)
+where
+    V: VisitMut<L> + ?Sized,
+{
+    match &mut *cmd.cmd {
+        Command::Assignment(_, word) => v.visit_word_mut(word),
+        Command::Compound(MayM4::Shell(cmd)) => v.visit_compound_command_mut(cmd),
+        Command::Compound(MayM4::Macro(m4_macro)) => v.visit_macro_mut(m4_macro),
+        Command::Cmd(words) => visit_words_mut(v, words),
+    }
+}
+
+/// Visit the words, conditions, redirects and inner commands of a compound command.
+pub fn walk_compound_command_mut<L, V>(v: &mut V, cmd: &mut Compound<L>)
+where
+    V: VisitMut<L> + ?Sized,
+{
+    use CompoundCommand::*;
+    match cmd {
+        Brace(cmds) | Subshell(cmds) | Pipe(_, cmds) => visit_commands_mut(v, cmds),
+        While(pair) | Until(pair) => visit_pair_mut(v, pair),
+        If {
+            conditionals,
+            else_branch,
+        } => {
+            for pair in conditionals {
+                visit_pair_mut(v, pair);
+            }
+            visit_commands_mut(v, else_branch);
+        }
+        For { words, body, .. } => {
+            visit_words_mut(v, words);
+            visit_commands_mut(v, body);
+        }
+        Case { word, arms } => {
+            v.visit_word_mut(word);
+            for arm in arms {
+                visit_words_mut(v, &mut arm.patterns);
+                visit_commands_mut(v, &mut arm.body);
+            }
+        }
+        And(cond, cmd) | Or(cond, cmd) => {
+            v.visit_condition_mut(cond);
+            v.visit_command_mut(cmd);
+        }
+        Redirect(cmd, redirects) => {
+            v.visit_command_mut(cmd);
+            for redirect in redirects {
+                v.visit_redirect_mut(redirect);
+            }
+        }
+        Background(cmd) | FunctionDef { body: cmd, .. } => v.visit_command_mut(cmd),
+    }
+}
+
+/// Visit the arguments of an m4 macro call.
+pub fn walk_macro_mut<L, V>(v: &mut V, m4_macro: &mut Macro<L>)
+where
+    V: VisitMut<L> + ?Sized,
+{
+    for arg in &mut m4_macro.args {
+        v.visit_argument_mut(arg);
+    }
+}
+
+/// Visit the words or commands of an m4 macro argument.
+pub fn walk_argument_mut<L, V>(v: &mut V, arg: &mut Argument<L>)
+where
+    V: VisitMut<L> + ?Sized,
+{
+    match arg {
+        M4Argument::Word(word) => v.visit_word_mut(word),
+        M4Argument::Array(words) => visit_words_mut(v, words),
+        M4Argument::Commands(cmds) => visit_commands_mut(v, cmds),
+        M4Argument::Literal(_) | M4Argument::Program(_) | M4Argument::Unknown(_) => {}
+    }
+}
+
+/// Visit the operators, sub-conditions or commands of a condition.
+pub fn walk_condition_mut<L, V>(v: &mut V, cond: &mut Cond<L>)
+where
+    V: VisitMut<L> + ?Sized,
+{
+    match cond {
+        Condition::Cond(op) => v.visit_operator_mut(op),
+        Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+            v.visit_condition_mut(lhs);
+            v.visit_condition_mut(rhs);
+        }
+        Condition::Eval(cmds) => visit_commands_mut(v, cmds),
+        Condition::ReturnZero(cmd) => v.visit_command_mut(cmd),
+    }
+}
+
+/// Visit the operands of a test operator.
+pub fn walk_operator_mut<L, V>(v: &mut V, op: &mut Operator<AcWord<L>>)
+where
+    V: VisitMut<L> + ?Sized,
+{
+    for word in operands_mut(op).into_iter().flatten() {
+        v.visit_word_mut(word);
+    }
+}
+
+/// Visit the word of a redirect.
+pub fn walk_redirect_mut<L, V>(v: &mut V, redirect: &mut Redirect<AcWord<L>>)
+where
+    V: VisitMut<L> + ?Sized,
+{
+    v.visit_word_mut(redirect_word_mut(redirect))
+}
+
+/// Visit the fragments of a word.
+pub fn walk_word_mut<L, V>(v: &mut V, word: &mut AcWord<L>)
+where
+    V: VisitMut<L> + ?Sized,
+{
+    for fragment in fragments_mut(&mut word.0) {
+        v.visit_fragment_mut(fragment);
+    }
+}
+
+/// Visit the shell fragment or the m4 macro call of a word fragment.
+pub fn walk_fragment_mut<L, V>(v: &mut V, fragment: &mut MinimalWordFragment<L>)
+where
+    V: VisitMut<L> + ?Sized,
+{
+    match fragment {
+        MayM4::Shell(fragment) => v.visit_shell_fragment_mut(fragment),
+        MayM4::Macro(m4_macro) => v.visit_macro_mut(m4_macro),
+    }
+}
+
+/// Visit the double quoted fragments or the substitution of a shell fragment.
+pub fn walk_shell_fragment_mut<L, V>(v: &mut V, fragment: &mut ShellFragment<L>)
+where
+    V: VisitMut<L> + ?Sized,
+{
+    match fragment {
+        WordFragment::DoubleQuoted(fragments) => {
+            for fragment in fragments {
+                v.visit_shell_fragment_mut(fragment);
+            }
+        }
+        WordFragment::Subst(subst) => v.visit_substitution_mut(subst),
+        _ => {}
+    }
+}
+
+/// Visit the commands or the word of a parameter substitution.
+pub fn walk_substitution_mut<L, V>(v: &mut V, subst: &mut Subst<L>)
+where
+    V: VisitMut<L> + ?Sized,
+{
+    let (cmds, word) = subst_parts_mut(subst);
+    if let Some(cmds) = cmds {
+        visit_commands_mut(v, cmds);
+    }
+    if let Some(Some(word)) = word {
+        v.visit_word_mut(word);
+    }
+}
+
+/// A traversal of the minimal AST which consumes and rebuilds it.
+pub trait Fold<L> {
+    /// Fold a command.
+    fn fold_command(&mut self, cmd: MinimalCommand<L>) -> MinimalCommand<L> {
+        fold_command(self, cmd)
+    }
+
+    /// Fold a compound command.
+    fn fold_compound_command(&mut self, cmd: Compound<L>) -> Compound<L> {
+        fold_compound_command(self, cmd)
+    }
+
+    /// Fold an m4 macro call, either a command or a word fragment.
+    fn fold_macro(&mut self, m4_macro: Macro<L>) -> Macro<L> {
+        fold_macro(self, m4_macro)
+    }
+
+    /// Fold an argument of an m4 macro call.
+    fn fold_argument(&mut self, arg: Argument<L>) -> Argument<L> {
+        fold_argument(self, arg)
+    }
+
+    /// Fold the condition of an `if`, a loop or an and/or command.
+    fn fold_condition(&mut self, cond: Cond<L>) -> Cond<L> {
+        fold_condition(self, cond)
+    }
+
+    /// Fold a test operator.
+    fn fold_operator(&mut self, op: Operator<AcWord<L>>) -> Operator<AcWord<L>> {
+        fold_operator(self, op)
+    }
+
+    /// Fold a redirect.
+    fn fold_redirect(&mut self, redirect: Redirect<AcWord<L>>) -> Redirect<AcWord<L>> {
+        fold_redirect(self, redirect)
+    }
+
+    /// Fold a word.
+    fn fold_word(&mut self, word: AcWord<L>) -> AcWord<L> {
+        fold_word(self, word)
+    }
+
+    /// Fold a fragment of a word, either shell or m4.
+    fn fold_fragment(&mut self, fragment: MinimalWordFragment<L>) -> MinimalWordFragment<L> {
+        fold_fragment(self, fragment)
+    }
+
+    /// Fold a shell fragment of a word.
+    fn fold_shell_fragment(&mut self, fragment: ShellFragment<L>) -> ShellFragment<L> {
+        fold_shell_fragment(self, fragment)
+    }
+
+    /// Fold a parameter substitution, e.g. `${foo:-bar}` or `$(cmd)`.
+    fn fold_substitution(&mut self, subst: Subst<L>) -> Subst<L> {
+        fold_substitution(self, subst)
+    }
+}
+
+fn fold_commands<L, F>(f: &mut F, cmds: Vec<MinimalCommand<L>>) -> Vec<MinimalCommand<L>>
+where
+    F: Fold<L> + ?Sized,
+{
+    cmds.into_iter().map(|cmd| f.fold_command(cmd)).collect()
+}
+
+fn fold_boxed<L, F>(f: &mut F, cmd: MinimalCommand<L>) -> Box<MinimalCommand<L>>
+where
+    F: Fold<L> + ?Sized,
+{
+    Box::new(f.fold_command(cmd))
+}
+
+fn fold_words<L, F>(f: &mut F, words: Vec<AcWord<L>>) -> Vec<AcWord<L>>
+where
+    F: Fold<L> + ?Sized,
+{
+    words.into_iter().map(|word| f.fold_word(word)).collect()
+}
+
+fn fold_pair<L, F>(
+    f: &mut F,
+    pair: GuardBodyPair<MinimalCommand<L>, AcWord<L>>,
+) -> GuardBodyPair<MinimalCommand<L>, AcWord<L>>
+where
+    F: Fold<L> + ?Sized,
+{
+    GuardBodyPair {
+        condition: f.fold_condition(pair.condition),
+        body: fold_commands(f, pair.body),
+    }
+}
+
+/// Fold the words, compound command or m4 macro call of a command.
+pub fn fold_command<L, F>(f: &mut F, cmd: MinimalCommand<L>) -> MinimalCommand<L>
+where
+    F: Fold<L> + ?Sized,
+{
+    let AcCommand {
+        comment,
+        range,
+        cmd,
+    } = cmd;
+    let cmd = match *cmd {
+        Command::Assignment(name, word) => Command::Assignment(name, f.fold_word(word)),
+        Command::Compound(MayM4::Shell(cmd)) => {
+            Command::Compound(MayM4::Shell(f.fold_compound_command(cmd)))
+        }
+        Command::Compound(MayM4::Macro(m4_macro)) => {
+            Command::Compound(MayM4::Macro(f.fold_macro(m4_macro)))
+        }
+        Command::Cmd(words) => Command::Cmd(fold_words(f, words)),
+    };
+    AcCommand {
+        comment,
+        range,
+        cmd: Box::new(cmd),
+    }
+}
+
+/// Fold the words, conditions, redirects and inner commands of a compound command.
+pub fn fold_compound_command<L, F>(f: &mut F, cmd: Compound<L>) -> Compound<L>
+where
+    F: Fold<L> + ?Sized,
+{
+    use CompoundCommand::*;
+    match cmd {
+        Brace(cmds) => Brace(fold_commands(f, cmds)),
+        Subshell(cmds) => Subshell(fold_commands(f, cmds)),
+        While(pair) => While(fold_pair(f, pair)),
+        Until(pair) => Until(fold_pair(f, pair)),
+        If {
+            conditionals,
+            else_branch,
+        } => If {
+            conditionals: conditionals
+                .into_iter()
+                .map(|pair| fold_pair(f, pair))
+                .collect(),
+            else_branch: fold_commands(f, else_branch),
+        },
+        For { var, words, body } => For {
+            var,
+            words: fold_words(f, words),
+            body: fold_commands(f, body),
+        },
+        Case { word, arms } => Case {
+            word: f.fold_word(word),
+            arms: arms
+                .into_iter()
+                .map(|arm| PatternBodyPair {
+                    patterns: fold_words(f, arm.patterns),
+                    body: fold_commands(f, arm.body),
+                })
+                .collect(),
+        },
+        And(cond, cmd) => And(f.fold_condition(cond), fold_boxed(f, *cmd)),
+        Or(cond, cmd) => Or(f.fold_condition(cond), fold_boxed(f, *cmd)),
+        Pipe(bang, cmds) => Pipe(bang, fold_commands(f, cmds)),
+        Redirect(cmd, redirects) => Redirect(
+            fold_boxed(f, *cmd),
+            redirects
+                .into_iter()
+                .map(|redirect| f.fold_redirect(redirect))
+                .collect(),
+        ),
+        Background(cmd) => Background(fold_boxed(f, *cmd)),
+        FunctionDef { name, body } => FunctionDef {
+            name,
+            body: fold_boxed(f, *body),
+        },
+    }
+}
+
+/// Fold the arguments of an m4 macro call.
+pub fn fold_macro<L, F>(f: &mut F, m4_macro: Macro<L>) -> Macro<L>
+where
+    F: Fold<L> + ?Sized,
+{
+    M4Macro {
+        args: m4_macro
+            .args
+            .into_iter()
+            .map(|arg| f.fold_argument(arg))
+            .collect(),
+        ..m4_macro
+    }
+}
+
+/// Fold the words or commands of an m4 macro argument.
+pub fn fold_argument<L, F>(f: &mut F, arg: Argument<L>) -> Argument<L>
+where
+    F: Fold<L> + ?Sized,
+{
+    match arg {
+        M4Argument::Word(word) => M4Argument::Word(f.fold_word(word)),
+        M4Argument::Array(words) => M4Argument::Array(fold_words(f, words)),
+        M4Argument::Commands(cmds) => M4Argument::Commands(fold_commands(f, cmds)),
+        arg => arg,
+    }
+}
+
+/// Fold the operators, sub-conditions or commands of a condition.
+pub fn fold_condition<L, F>(f: &mut F, cond: Cond<L>) -> Cond<L>
+where
+    F: Fold<L> + ?Sized,
+{
+    match cond {
+        Condition::Cond(op) => Condition::Cond(f.fold_operator(op)),
+        Condition::And(lhs, rhs) => {
+            let lhs = Box::new(f.fold_condition(*lhs));
+            Condition::And(lhs, Box::new(f.fold_condition(*rhs)))
+        }
+        Condition::Or(lhs, rhs) => {
+            let lhs = Box::new(f.fold_condition(*lhs));
+            Condition::Or(lhs, Box::new(f.fold_condition(*rhs)))
+        }
+        Condition::Eval(cmds) => Condition::Eval(fold_commands(f, cmds)),
+        Condition::ReturnZero(cmd) => Condition::ReturnZero(fold_boxed(f, *cmd)),
+    }
+}
+
+/// Fold the operands of a test operator.
+pub fn fold_operator<L, F>(f: &mut F, op: Operator<AcWord<L>>) -> Operator<AcWord<L>>
+where
+    F: Fold<L> + ?Sized,
+{
+    map_operands(op, |word| f.fold_word(word))
+}
+
+/// Fold the word of a redirect.
+pub fn fold_redirect<L, F>(f: &mut F, redirect: Redirect<AcWord<L>>) -> Redirect<AcWord<L>>
+where
+    F: Fold<L> + ?Sized,
+{
+    map_redirect(redirect, |word| f.fold_word(word))
+}
+
+/// Fold the fragments of a word.
+pub fn fold_word<L, F>(f: &mut F, word: AcWord<L>) -> AcWord<L>
+where
+    F: Fold<L> + ?Sized,
+{
+    AcWord(map_fragments(word.0, |fragment| f.fold_fragment(fragment)))
+}
+
+/// Fold the shell fragment or the m4 macro call of a word fragment.
+pub fn fold_fragment<L, F>(f: &mut F, fragment: MinimalWordFragment<L>) -> MinimalWordFragment<L>
+where
+    F: Fold<L> + ?Sized,
+{
+    match fragment {
+        MayM4::Shell(fragment) => MayM4::Shell(f.fold_shell_fragment(fragment)),
+        MayM4::Macro(m4_macro) => MayM4::Macro(f.fold_macro(m4_macro)),
+    }
+}
+
+/// Fold the double quoted fragments or the substitution of a shell fragment.
+pub fn fold_shell_fragment<L, F>(f: &mut F, fragment: ShellFragment<L>) -> ShellFragment<L>
+where
+    F: Fold<L> + ?Sized,
+{
+    match fragment {
+        WordFragment::DoubleQuoted(fragments) => WordFragment::DoubleQuoted(
+            fragments
+                .into_iter()
+                .map(|fragment| f.fold_shell_fragment(fragment))
+                .collect(),
+        ),
+        WordFragment::Subst(subst) => WordFragment::Subst(Box::new(f.fold_substitution(*subst))),
+        fragment => fragment,
+    }
+}
+
+/// Fold the commands or the word of a parameter substitution.
+pub fn fold_substitution<L, F>(f: &mut F, mut subst: Subst<L>) -> Subst<L>
+where
+    F: Fold<L> + ?Sized,
+{
+    let (cmds, word) = subst_parts_mut(&mut subst);
+    if let Some(cmds) = cmds {
+        *cmds = fold_commands(f, mem::take(cmds));
+    }
+    if let Some(word) = word {
+        *word = word.take().map(|word| f.fold_word(word));
+    }
+    subst
+}
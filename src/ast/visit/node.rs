@@ -0,0 +1,554 @@
+//! Traversals over the nodes of an `AutoconfPool`.
+//!
+//! Nodes refer to their children by `NodeId`, so every method also receives
+//! the pool through which the walk resolves them.
+use super::{fragments, fragments_mut, operands, operands_mut, redirect_word, redirect_word_mut};
+use super::{subst_parts, subst_parts_mut};
+use crate::ast::node::{
+    AcCommand, AcWord, AcWordFragment, AutoconfPool, Condition, M4Argument, M4Macro, NodeId,
+    Operator, ParameterSubstitution, Redirect, ShellCommand, WordFragment,
+};
+use crate::ast::MayM4;
+use std::mem;
+
+/// A read-only traversal of the nodes of an `AutoconfPool`.
+///
+/// Start the walk with `visit_node` on each top-level node.
+pub trait Visit<'a, U: 'a = ()> {
+    /// Visit the node `id` of the pool.
+    fn visit_node(&mut self, pool: &'a AutoconfPool<U>, id: NodeId) {
+        walk_node(self, pool, id)
+    }
+
+    /// Visit a shell command.
+    fn visit_shell_command(&mut self, pool: &'a AutoconfPool<U>, cmd: &'a ShellCommand<AcWord>) {
+        walk_shell_command(self, pool, cmd)
+    }
+
+    /// Visit an m4 macro call, either a command or a word fragment.
+    fn visit_macro(&mut self, pool: &'a AutoconfPool<U>, m4_macro: &'a M4Macro) {
+        walk_macro(self, pool, m4_macro)
+    }
+
+    /// Visit an argument of an m4 macro call.
+    fn visit_argument(&mut self, pool: &'a AutoconfPool<U>, arg: &'a M4Argument) {
+        walk_argument(self, pool, arg)
+    }
+
+    /// Visit the condition of an `if`, a loop or an and/or command.
+    fn visit_condition(&mut self, pool: &'a AutoconfPool<U>, cond: &'a Condition<AcWord>) {
+        walk_condition(self, pool, cond)
+    }
+
+    /// Visit a test operator.
+    fn visit_operator(&mut self, pool: &'a AutoconfPool<U>, op: &'a Operator<AcWord>) {
+        walk_operator(self, pool, op)
+    }
+
+    /// Visit a redirect.
+    fn visit_redirect(&mut self, pool: &'a AutoconfPool<U>, redirect: &'a Redirect<AcWord>) {
+        walk_redirect(self, pool, redirect)
+    }
+
+    /// Visit a word.
+    fn visit_word(&mut self, pool: &'a AutoconfPool<U>, word: &'a AcWord) {
+        walk_word(self, pool, word)
+    }
+
+    /// Visit a fragment of a word, either shell or m4.
+    fn visit_fragment(&mut self, pool: &'a AutoconfPool<U>, fragment: &'a AcWordFragment) {
+        walk_fragment(self, pool, fragment)
+    }
+
+    /// Visit a shell fragment of a word.
+    fn visit_shell_fragment(
+        &mut self,
+        pool: &'a AutoconfPool<U>,
+        fragment: &'a WordFragment<AcWord>,
+    ) {
+        walk_shell_fragment(self, pool, fragment)
+    }
+
+    /// Visit a parameter substitution, e.g. `${foo:-bar}` or `$(cmd)`.
+    fn visit_substitution(
+        &mut self,
+        pool: &'a AutoconfPool<U>,
+        subst: &'a ParameterSubstitution<AcWord>,
+    ) {
+        walk_substitution(self, pool, subst)
+    }
+}
+
+fn visit_nodes<'a, U, V>(v: &mut V, pool: &'a AutoconfPool<U>, ids: &[NodeId])
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    for id in ids {
+        v.visit_node(pool, *id);
+    }
+}
+
+fn visit_words<'a, U, V>(v: &mut V, pool: &'a AutoconfPool<U>, words: &'a [AcWord])
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    for word in words {
+        v.visit_word(pool, word);
+    }
+}
+
+/// Visit the command of a node.
+pub fn walk_node<'a, U, V>(v: &mut V, pool: &'a AutoconfPool<U>, id: NodeId)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    match &pool.nodes[id].cmd.0 {
+        MayM4::Shell(cmd) => v.visit_shell_command(pool, cmd),
+        MayM4::Macro(m4_macro) => v.visit_macro(pool, m4_macro),
+    }
+}
+
+/// Visit the words, conditions, redirects and child nodes of a shell command.
+pub fn walk_shell_command<'a, U, V>(
+    v: &mut V,
+    pool: &'a AutoconfPool<U>,
+    cmd: &'a ShellCommand<AcWord>,
+) where
+    V: Visit<'a, U> + ?Sized,
+{
+    use ShellCommand::*;
+    match cmd {
+        Assignment(_, word) => v.visit_word(pool, word),
+        Cmd(words) => visit_words(v, pool, words),
+        Brace(ids) | Subshell(ids) | Pipe(_, ids) => visit_nodes(v, pool, ids),
+        While(pair) | Until(pair) => {
+            v.visit_condition(pool, &pair.condition);
+            visit_nodes(v, pool, &pair.body);
+        }
+        If {
+            conditionals,
+            else_branch,
+        } => {
+            for pair in conditionals {
+                v.visit_condition(pool, &pair.condition);
+                visit_nodes(v, pool, &pair.body);
+            }
+            visit_nodes(v, pool, else_branch);
+        }
+        For { words, body, .. } => {
+            visit_words(v, pool, words);
+            visit_nodes(v, pool, body);
+        }
+        Case { word, arms } => {
+            v.visit_word(pool, word);
+            for arm in arms {
+                visit_words(v, pool, &arm.patterns);
+                visit_nodes(v, pool, &arm.body);
+            }
+        }
+        And(cond, id) | Or(cond, id) => {
+            v.visit_condition(pool, cond);
+            v.visit_node(pool, *id);
+        }
+        Redirect(id, redirects) => {
+            v.visit_node(pool, *id);
+            for redirect in redirects {
+                v.visit_redirect(pool, redirect);
+            }
+        }
+        Background(id) | FunctionDef { body: id, .. } => v.visit_node(pool, *id),
+    }
+}
+
+/// Visit the arguments of an m4 macro call.
+pub fn walk_macro<'a, U, V>(v: &mut V, pool: &'a AutoconfPool<U>, m4_macro: &'a M4Macro)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    for arg in &m4_macro.args {
+        v.visit_argument(pool, arg);
+    }
+}
+
+/// Visit the words or commands of an m4 macro argument.
+pub fn walk_argument<'a, U, V>(v: &mut V, pool: &'a AutoconfPool<U>, arg: &'a M4Argument)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    match arg {
+        M4Argument::Word(word) => v.visit_word(pool, word),
+        M4Argument::Array(words) => visit_words(v, pool, words),
+        M4Argument::Commands(ids) => visit_nodes(v, pool, ids),
+        M4Argument::Literal(_) | M4Argument::Program(_) | M4Argument::Unknown(_) => {}
+    }
+}
+
+/// Visit the operators, sub-conditions or commands of a condition.
+pub fn walk_condition<'a, U, V>(v: &mut V, pool: &'a AutoconfPool<U>, cond: &'a Condition<AcWord>)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    match cond {
+        Condition::Cond(op) => v.visit_operator(pool, op),
+        Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+            v.visit_condition(pool, lhs);
+            v.visit_condition(pool, rhs);
+        }
+        Condition::Eval(ids) => visit_nodes(v, pool, ids),
+        Condition::ReturnZero(id) => v.visit_node(pool, **id),
+    }
+}
+
+/// Visit the operands of a test operator.
+pub fn walk_operator<'a, U, V>(v: &mut V, pool: &'a AutoconfPool<U>, op: &'a Operator<AcWord>)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    for word in operands(op).into_iter().flatten() {
+        v.visit_word(pool, word);
+    }
+}
+
+/// Visit the word of a redirect.
+pub fn walk_redirect<'a, U, V>(v: &mut V, pool: &'a AutoconfPool<U>, redirect: &'a Redirect<AcWord>)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    v.visit_word(pool, redirect_word(redirect))
+}
+
+/// Visit the fragments of a word.
+pub fn walk_word<'a, U, V>(v: &mut V, pool: &'a AutoconfPool<U>, word: &'a AcWord)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    for fragment in fragments(&word.0) {
+        v.visit_fragment(pool, fragment);
+    }
+}
+
+/// Visit the shell fragment or the m4 macro call of a word fragment.
+pub fn walk_fragment<'a, U, V>(v: &mut V, pool: &'a AutoconfPool<U>, fragment: &'a AcWordFragment)
+where
+    V: Visit<'a, U> + ?Sized,
+{
+    match fragment {
+        MayM4::Shell(fragment) => v.visit_shell_fragment(pool, fragment),
+        MayM4::Macro(m4_macro) => v.visit_macro(pool, m4_macro),
+    }
+}
+
+/// Visit the double quoted fragments or the substitution of a shell fragment.
+pub fn walk_shell_fragment<'a, U, V>(
+    v: &mut V,
+    pool: &'a AutoconfPool<U>,
+    fragment: &'a WordFragment<AcWord>,
+) where
+    V: Visit<'a, U> + ?Sized,
+{
+    match fragment {
+        WordFragment::DoubleQuoted(fragments) => {
+            for fragment in fragments {
+                v.visit_shell_fragment(pool, fragment);
+            }
+        }
+        WordFragment::Subst(subst) => v.visit_substitution(pool, subst),
+        _ => {}
+    }
+}
+
+/// Visit the commands or the word of a parameter substitution.
+pub fn walk_substitution<'a, U, V>(
+    v: &mut V,
+    pool: &'a AutoconfPool<U>,
+    subst: &'a ParameterSubstitution<AcWord>,
+) where
+    V: Visit<'a, U> + ?Sized,
+{
+    let (ids, word) = subst_parts(subst);
+    visit_nodes(v, pool, ids);
+    if let Some(word) = word {
+        v.visit_word(pool, word);
+    }
+}
+
+/// A mutating traversal of the nodes of an `AutoconfPool`.
+///
+/// While a node is visited, its command is moved out of the pool, which
+/// holds an empty simple command at its id until the walk of the node
+/// returns. Other nodes, including the children of the visited node, stay
+/// in place and may be edited through the pool.
+pub trait VisitMut<U = ()> {
+    /// Visit the node `id` of the pool.
+    fn visit_node_mut(&mut self, pool: &mut AutoconfPool<U>, id: NodeId) {
+        walk_node_mut(self, pool, id)
+    }
+
+    /// Visit a shell command.
+    fn visit_shell_command_mut(
+        &mut self,
+        pool: &mut AutoconfPool<U>,
+        cmd: &mut ShellCommand<AcWord>,
+    ) {
+        walk_shell_command_mut(self, pool, cmd)
+    }
+
+    /// Visit an m4 macro call, either a command or a word fragment.
+    fn visit_macro_mut(&mut self, pool: &mut AutoconfPool<U>, m4_macro: &mut M4Macro) {
+        walk_macro_mut(self, pool, m4_macro)
+    }
+
+    /// Visit an argument of an m4 macro call.
+    fn visit_argument_mut(&mut self, pool: &mut AutoconfPool<U>, arg: &mut M4Argument) {
+        walk_argument_mut(self, pool, arg)
+    }
+
+    /// Visit the condition of an `if`, a loop or an and/or command.
+    fn visit_condition_mut(&mut self, pool: &mut AutoconfPool<U>, cond: &mut Condition<AcWord>) {
+        walk_condition_mut(self, pool, cond)
+    }
+
+    /// Visit a test operator.
+    fn visit_operator_mut(&mut self, pool: &mut AutoconfPool<U>, op: &mut Operator<AcWord>) {
+        walk_operator_mut(self, pool, op)
+    }
+
+    /// Visit a redirect.
+    fn visit_redirect_mut(&mut self, pool: &mut AutoconfPool<U>, redirect: &mut Redirect<AcWord>) {
+        walk_redirect_mut(self, pool, redirect)
+    }
+
+    /// Visit a word.
+    fn visit_word_mut(&mut self, pool: &mut AutoconfPool<U>, word: &mut AcWord) {
+        walk_word_mut(self, pool, word)
+    }
+
+    /// Visit a fragment of a word, either shell or m4.
+    fn visit_fragment_mut(&mut self, pool: &mut AutoconfPool<U>, fragment: &mut AcWordFragment) {
+        walk_fragment_mut(self, pool, fragment)
+    }
+
+    /// Visit a shell fragment of a word.
+    fn visit_shell_fragment_mut(
+        &mut self,
+        pool: &mut AutoconfPool<U>,
+        fragment: &mut WordFragment<AcWord>,
+    ) {
+        walk_shell_fragment_mut(self, pool, fragment)
+    }
+
+    /// Visit a parameter substitution, e.g. `${foo:-bar}` or `$(cmd)`.
+    fn visit_substitution_mut(
+        &mut self,
+        pool: &mut AutoconfPool<U>,
+        subst: &mut ParameterSubstitution<AcWord>,
+    ) {
+        walk_substitution_mut(self, pool, subst)
+    }
+}
+
+fn visit_nodes_mut<U, V>(v: &mut V, pool: &mut AutoconfPool<U>, ids: &[NodeId])
+where
+    V: VisitMut<U> + ?Sized,
+{
+    for id in ids {
+        v.visit_node_mut(pool, *id);
+    }
+}
+
+fn visit_words_mut<U, V>(v: &mut V, pool: &mut AutoconfPool<U>, words: &mut [AcWord])
+where
+    V: VisitMut<U> + ?Sized,
+{
+    for word in words {
+        v.visit_word_mut(pool, word);
+    }
+}
+
+/// Visit the command of a node, if the node is still in the pool.
+pub fn walk_node_mut<U, V>(v: &mut V, pool: &mut AutoconfPool<U>, id: NodeId)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    let Some(node) = pool.nodes.get_mut(id) else {
+        return;
+    };
+    let placeholder = AcCommand(MayM4::Shell(ShellCommand::Cmd(Vec::new())));
+    let mut cmd = mem::replace(&mut node.cmd, placeholder);
+    match &mut cmd.0 {
+        MayM4::Shell(cmd) => v.visit_shell_command_mut(pool, cmd),
+        MayM4::Macro(m4_macro) => v.visit_macro_mut(pool, m4_macro),
+    }
+    if let Some(node) = pool.nodes.get_mut(id) {
+        node.cmd = cmd;
+    }
+}
+
+/// Visit the words, conditions, redirects and child nodes of a shell command.
+pub fn walk_shell_command_mut<U, V>(
+    v: &mut V,
+    pool: &mut AutoconfPool<U>,
+    cmd: &mut ShellCommand<AcWord>,
+) where
+    V: VisitMut<U> + ?Sized,
+{
+    use ShellCommand::*;
+    match cmd {
+        Assignment(_, word) => v.visit_word_mut(pool, word),
+        Cmd(words) => visit_words_mut(v, pool, words),
+        Brace(ids) | Subshell(ids) | Pipe(_, ids) => visit_nodes_mut(v, pool, ids),
+        While(pair) | Until(pair) => {
+            v.visit_condition_mut(pool, &mut pair.condition);
+            visit_nodes_mut(v, pool, &pair.body);
+        }
+        If {
+            conditionals,
+            else_branch,
+        } => {
+            for pair in conditionals {
+                v.visit_condition_mut(pool, &mut pair.condition);
+                visit_nodes_mut(v, pool, &pair.body);
+            }
+            visit_nodes_mut(v, pool, else_branch);
+        }
+        For { words, body, .. } => {
+            visit_words_mut(v, pool, words);
+            visit_nodes_mut(v, pool, body);
+        }
+        Case { word, arms } => {
+            v.visit_word_mut(pool, word);
+            for arm in arms {
+                visit_words_mut(v, pool, &mut arm.patterns);
+                visit_nodes_mut(v, pool, &arm.body);
+            }
+        }
+        And(cond, id) | Or(cond, id) => {
+            v.visit_condition_mut(pool, cond);
+            v.visit_node_mut(pool, *id);
+        }
+        Redirect(id, redirects) => {
+            v.visit_node_mut(pool, *id);
+            for redirect in redirects {
+                v.visit_redirect_mut(pool, redirect);
+            }
+        }
+        Background(id) | FunctionDef { body: id, .. } => v.visit_node_mut(pool, *id),
+    }
+}
+
+/// Visit the arguments of an m4 macro call.
+pub fn walk_macro_mut<U, V>(v: &mut V, pool: &mut AutoconfPool<U>, m4_macro: &mut M4Macro)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    for arg in &mut m4_macro.args {
+        v.visit_argument_mut(pool, arg);
+    }
+}
+
+/// Visit the words or commands of an m4 macro argument.
+pub fn walk_argument_mut<U, V>(v: &mut V, pool: &mut AutoconfPool<U>, arg: &mut M4Argument)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    match arg {
+        M4Argument::Word(word) => v.visit_word_mut(pool, word),
+        M4Argument::Array(words) => visit_words_mut(v, pool, words),
+        M4Argument::Commands(ids) => visit_nodes_mut(v, pool, ids),
+        M4Argument::Literal(_) | M4Argument::Program(_) | M4Argument::Unknown(_) => {}
+    }
+}
+
+/// Visit the operators, sub-conditions or commands of a condition.
+pub fn walk_condition_mut<U, V>(v: &mut V, pool: &mut AutoconfPool<U>, cond: &mut Condition<AcWord>)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    match cond {
+        Condition::Cond(op) => v.visit_operator_mut(pool, op),
+        Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+            v.visit_condition_mut(pool, lhs);
+            v.visit_condition_mut(pool, rhs);
+        }
+        Condition::Eval(ids) => visit_nodes_mut(v, pool, ids),
+        Condition::ReturnZero(id) => v.visit_node_mut(pool, **id),
+    }
+}
+
+/// Visit the operands of a test operator.
+pub fn walk_operator_mut<U, V>(v: &mut V, pool: &mut AutoconfPool<U>, op: &mut Operator<AcWord>)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    for word in operands_mut(op).into_iter().flatten() {
+        v.visit_word_mut(pool, word);
+    }
+}
+
+/// Visit the word of a redirect.
+pub fn walk_redirect_mut<U, V>(
+    v: &mut V,
+    pool: &mut AutoconfPool<U>,
+    redirect: &mut Redirect<AcWord>,
+) where
+    V: VisitMut<U> + ?Sized,
+{
+    v.visit_word_mut(pool, redirect_word_mut(redirect))
+}
+
+/// Visit the fragments of a word.
+pub fn walk_word_mut<U, V>(v: &mut V, pool: &mut AutoconfPool<U>, word: &mut AcWord)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    for fragment in fragments_mut(&mut word.0) {
+        v.visit_fragment_mut(pool, fragment);
+    }
+}
+
+/// Visit the shell fragment or the m4 macro call of a word fragment.
+pub fn walk_fragment_mut<U, V>(v: &mut V, pool: &mut AutoconfPool<U>, fragment: &mut AcWordFragment)
+where
+    V: VisitMut<U> + ?Sized,
+{
+    match fragment {
+        MayM4::Shell(fragment) => v.visit_shell_fragment_mut(pool, fragment),
+        MayM4::Macro(m4_macro) => v.visit_macro_mut(pool, m4_macro),
+    }
+}
+
+/// Visit the double quoted fragments or the substitution of a shell fragment.
+pub fn walk_shell_fragment_mut<U, V>(
+    v: &mut V,
+    pool: &mut AutoconfPool<U>,
+    fragment: &mut WordFragment<AcWord>,
+) where
+    V: VisitMut<U> + ?Sized,
+{
+    match fragment {
+        WordFragment::DoubleQuoted(fragments) => {
+            for fragment in fragments {
+                v.visit_shell_fragment_mut(pool, fragment);
+            }
+        }
+        WordFragment::Subst(subst) => v.visit_substitution_mut(pool, subst),
+        _ => {}
+    }
+}
+
+/// Visit the commands or the word of a parameter substitution.
+pub fn walk_substitution_mut<U, V>(
+    v: &mut V,
+    pool: &mut AutoconfPool<U>,
+    subst: &mut ParameterSubstitution<AcWord>,
+) where
+    V: VisitMut<U> + ?Sized,
+{
+    let (ids, word) = subst_parts_mut(subst);
+    if let Some(ids) = ids {
+        visit_nodes_mut(v, pool, ids);
+    }
+    if let Some(Some(word)) = word {
+        v.visit_word_mut(pool, word);
+    }
+}
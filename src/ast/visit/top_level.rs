@@ -0,0 +1,850 @@
+//! Traversals over the default AST, rooted at `TopLevelCommand`.
+use super::{map_redirect, redirect_word, redirect_word_mut, subst_parts, subst_parts_mut};
+use crate::ast::{
+    AndOr, AndOrList, Arithmetic, Command, ComplexWord, CompoundCommand, CompoundCommandKind,
+    GuardBodyPair, ListableCommand, MayM4, Parameter, ParameterSubstitution, PatternBodyPair,
+    PipeableCommand, Redirect, RedirectOrCmdWord, RedirectOrEnvVar, ShellCompoundCommand,
+    ShellPipeableCommand, SimpleCommand, SimpleWord, TopLevelCommand, TopLevelWord, Word,
+};
+use crate::m4_macro::{M4Argument, M4Macro};
+use std::iter;
+use std::mem;
+use std::rc::Rc;
+
+type Pipeable<T> = ShellPipeableCommand<T, TopLevelCommand<T>, TopLevelWord<T>>;
+type Listable<T> = ListableCommand<Pipeable<T>>;
+type Simple<T> = SimpleCommand<T, TopLevelWord<T>, Redirect<TopLevelWord<T>>>;
+type Compound<T> = ShellCompoundCommand<T, TopLevelCommand<T>, TopLevelWord<T>>;
+type Macro<T> = M4Macro<TopLevelCommand<T>, TopLevelWord<T>>;
+type Argument<T> = M4Argument<TopLevelCommand<T>, TopLevelWord<T>>;
+type Subst<T> =
+    ParameterSubstitution<Parameter<T>, TopLevelCommand<T>, TopLevelWord<T>, Arithmetic<T>>;
+type Fragment<T> = MayM4<SimpleWord<T, Parameter<T>, Box<Subst<T>>>, Macro<T>>;
+type QuotedWord<T> = Word<T, Fragment<T>>;
+
+/// A read-only traversal of the default AST.
+pub trait Visit<'a, T: 'a> {
+    /// Visit a top-level command.
+    fn visit_command(&mut self, cmd: &'a TopLevelCommand<T>) {
+        walk_command(self, cmd)
+    }
+
+    /// Visit a command of a pipeline, or a command out of any pipeline.
+    fn visit_pipeable_command(&mut self, cmd: &'a Pipeable<T>) {
+        walk_pipeable_command(self, cmd)
+    }
+
+    /// Visit a simple command.
+    fn visit_simple_command(&mut self, cmd: &'a Simple<T>) {
+        walk_simple_command(self, cmd)
+    }
+
+    /// Visit a compound command, including an m4 macro call as a command.
+    fn visit_compound_command(&mut self, cmd: &'a Compound<T>) {
+        walk_compound_command(self, cmd)
+    }
+
+    /// Visit an m4 macro call, either a command or a word fragment.
+    fn visit_macro(&mut self, m4_macro: &'a Macro<T>) {
+        walk_macro(self, m4_macro)
+    }
+
+    /// Visit an argument of an m4 macro call.
+    fn visit_argument(&mut self, arg: &'a Argument<T>) {
+        walk_argument(self, arg)
+    }
+
+    /// Visit a redirect.
+    fn visit_redirect(&mut self, redirect: &'a Redirect<TopLevelWord<T>>) {
+        walk_redirect(self, redirect)
+    }
+
+    /// Visit a word.
+    fn visit_word(&mut self, word: &'a TopLevelWord<T>) {
+        walk_word(self, word)
+    }
+
+    /// Visit a fragment of a word, either shell or m4.
+    fn visit_fragment(&mut self, fragment: &'a Fragment<T>) {
+        walk_fragment(self, fragment)
+    }
+
+    /// Visit a parameter substitution, e.g. `${foo:-bar}` or `$(cmd)`.
+    fn visit_substitution(&mut self, subst: &'a Subst<T>) {
+        walk_substitution(self, subst)
+    }
+}
+
+fn visit_commands<'a, T: 'a, V>(v: &mut V, cmds: &'a [TopLevelCommand<T>])
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    for cmd in cmds {
+        v.visit_command(cmd);
+    }
+}
+
+fn visit_words<'a, T: 'a, V>(v: &mut V, words: &'a [TopLevelWord<T>])
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    for word in words {
+        v.visit_word(word);
+    }
+}
+
+fn visit_pair<'a, T: 'a, V>(v: &mut V, pair: &'a GuardBodyPair<TopLevelCommand<T>>)
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    visit_commands(v, &pair.guard);
+    visit_commands(v, &pair.body);
+}
+
+/// Visit the pipeable commands of the and/or list of a command.
+pub fn walk_command<'a, T: 'a, V>(v: &mut V, cmd: &'a TopLevelCommand<T>)
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    let (Command::Job(list) | Command::List(list)) = &cmd.0;
+    let rest = list.rest.iter().map(|and_or| match and_or {
+        AndOr::And(cmd) | AndOr::Or(cmd) => cmd,
+    });
+    for listable in iter::once(&list.first).chain(rest) {
+        match listable {
+            ListableCommand::Single(cmd) => v.visit_pipeable_command(cmd),
+            ListableCommand::Pipe(_, cmds) => {
+                for cmd in cmds {
+                    v.visit_pipeable_command(cmd);
+                }
+            }
+        }
+    }
+}
+
+/// Visit the simple command, compound command or function body of a pipeable command.
+pub fn walk_pipeable_command<'a, T: 'a, V>(v: &mut V, cmd: &'a Pipeable<T>)
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    match cmd {
+        PipeableCommand::Simple(cmd) => v.visit_simple_command(cmd),
+        PipeableCommand::Compound(cmd) => v.visit_compound_command(cmd),
+        PipeableCommand::FunctionDef(_, body) => v.visit_compound_command(body),
+    }
+}
+
+/// Visit the redirects, environment variables and words of a simple command.
+pub fn walk_simple_command<'a, T: 'a, V>(v: &mut V, cmd: &'a Simple<T>)
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    for redirect_or_env_var in &cmd.redirects_or_env_vars {
+        match redirect_or_env_var {
+            RedirectOrEnvVar::Redirect(redirect) => v.visit_redirect(redirect),
+            RedirectOrEnvVar::EnvVar(_, Some(word)) => v.visit_word(word),
+            RedirectOrEnvVar::EnvVar(_, None) => {}
+        }
+    }
+    for redirect_or_word in &cmd.redirects_or_cmd_words {
+        match redirect_or_word {
+            RedirectOrCmdWord::Redirect(redirect) => v.visit_redirect(redirect),
+            RedirectOrCmdWord::CmdWord(word) => v.visit_word(word),
+        }
+    }
+}
+
+/// Visit the words, inner commands and redirects of a compound command.
+pub fn walk_compound_command<'a, T: 'a, V>(v: &mut V, cmd: &'a Compound<T>)
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    use CompoundCommandKind::*;
+    match &cmd.kind {
+        Brace(cmds) | Subshell(cmds) => visit_commands(v, cmds),
+        While(pair) | Until(pair) => visit_pair(v, pair),
+        If {
+            conditionals,
+            else_branch,
+        } => {
+            for pair in conditionals {
+                visit_pair(v, pair);
+            }
+            if let Some(cmds) = else_branch {
+                visit_commands(v, cmds);
+            }
+        }
+        For { words, body, .. } => {
+            if let Some(words) = words {
+                visit_words(v, words);
+            }
+            visit_commands(v, body);
+        }
+        Case { word, arms } => {
+            v.visit_word(word);
+            for arm in arms {
+                visit_words(v, &arm.patterns);
+                visit_commands(v, &arm.body);
+            }
+        }
+        Macro(m4_macro) => v.visit_macro(m4_macro),
+    }
+    for redirect in &cmd.io {
+        v.visit_redirect(redirect);
+    }
+}
+
+/// Visit the arguments of an m4 macro call.
+pub fn walk_macro<'a, T: 'a, V>(v: &mut V, m4_macro: &'a Macro<T>)
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    for arg in &m4_macro.args {
+        v.visit_argument(arg);
+    }
+}
+
+/// Visit the words or commands of an m4 macro argument.
+pub fn walk_argument<'a, T: 'a, V>(v: &mut V, arg: &'a Argument<T>)
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    match arg {
+        M4Argument::Word(word) => v.visit_word(word),
+        M4Argument::Array(words) => visit_words(v, words),
+        M4Argument::Commands(cmds) => visit_commands(v, cmds),
+        M4Argument::Literal(_) | M4Argument::Program(_) | M4Argument::Unknown(_) => {}
+    }
+}
+
+/// Visit the word of a redirect.
+pub fn walk_redirect<'a, T: 'a, V>(v: &mut V, redirect: &'a Redirect<TopLevelWord<T>>)
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    v.visit_word(redirect_word(redirect))
+}
+
+/// Visit the fragments of a word, quoted or not.
+pub fn walk_word<'a, T: 'a, V>(v: &mut V, word: &'a TopLevelWord<T>)
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    let words = match &word.0 {
+        ComplexWord::Concat(words) => words.as_slice(),
+        ComplexWord::Single(word) => std::slice::from_ref(word),
+    };
+    for word in words {
+        match word {
+            Word::Simple(fragment) => v.visit_fragment(fragment),
+            Word::DoubleQuoted(fragments) => {
+                for fragment in fragments {
+                    v.visit_fragment(fragment);
+                }
+            }
+            Word::SingleQuoted(_) => {}
+        }
+    }
+}
+
+/// Visit the substitution or the m4 macro call of a word fragment.
+pub fn walk_fragment<'a, T: 'a, V>(v: &mut V, fragment: &'a Fragment<T>)
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    match fragment {
+        MayM4::Shell(SimpleWord::Subst(subst)) => v.visit_substitution(subst),
+        MayM4::Shell(_) => {}
+        MayM4::Macro(m4_macro) => v.visit_macro(m4_macro),
+    }
+}
+
+/// Visit the commands or the word of a parameter substitution.
+pub fn walk_substitution<'a, T: 'a, V>(v: &mut V, subst: &'a Subst<T>)
+where
+    V: Visit<'a, T> + ?Sized,
+{
+    let (cmds, word) = subst_parts(subst);
+    visit_commands(v, cmds);
+    if let Some(word) = word {
+        v.visit_word(word);
+    }
+}
+
+/// A mutating traversal of the default AST.
+///
+/// Function bodies are shared through `Rc`, so visiting one clones it
+/// first if it is shared with another command.
+pub trait VisitMut<T: Clone> {
+    /// Visit a top-level command.
+    fn visit_command_mut(&mut self, cmd: &mut TopLevelCommand<T>) {
+        walk_command_mut(self, cmd)
+    }
+
+    /// Visit a command of a pipeline, or a command out of any pipeline.
+    fn visit_pipeable_command_mut(&mut self, cmd: &mut Pipeable<T>) {
+        walk_pipeable_command_mut(self, cmd)
+    }
+
+    /// Visit a simple command.
+    fn visit_simple_command_mut(&mut self, cmd: &mut Simple<T>) {
+        walk_simple_command_mut(self, cmd)
+    }
+
+    /// Visit a compound command, including an m4 macro call as a command.
+    fn visit_compound_command_mut(&mut self, cmd: &mut Compound<T>) {
+        walk_compound_command_mut(self, cmd)
+    }
+
+    /// Visit an m4 macro call, either a command or a word fragment.
+    fn visit_macro_mut(&mut self, m4_macro: &mut Macro<T>) {
+        walk_macro_mut(self, m4_macro)
+    }
+
+    /// Visit an argument of an m4 macro call.
+    fn visit_argument_mut(&mut self, arg: &mut Argument<T>) {
+        walk_argument_mut(self, arg)
+    }
+
+    /// Visit a redirect.
+    fn visit_redirect_mut(&mut self, redirect: &mut Redirect<TopLevelWord<T>>) {
+        walk_redirect_mut(self, redirect)
+    }
+
+    /// Visit a word.
+    fn visit_word_mut(&mut self, word: &mut TopLevelWord<T>) {
+        walk_word_mut(self, word)
+    }
+
+    /// Visit a fragment of a word, either shell or m4.
+    fn visit_fragment_mut(&mut self, fragment: &mut Fragment<T>) {
+        walk_fragment_mut(self, fragment)
+    }
+
+    /// Visit a parameter substitution, e.g. `${foo:-bar}` or `$(cmd)`.
+    fn visit_substitution_mut(&mut self, subst: &mut Subst<T>) {
+        walk_substitution_mut(self, subst)
+    }
+}
+
+fn visit_commands_mut<T: Clone, V>(v: &mut V, cmds: &mut [TopLevelCommand<T>])
+where
+    V: VisitMut<T> + ?Sized,
+{
+    for cmd in cmds {
+        v.visit_command_mut(cmd);
+    }
+}
+
+fn visit_words_mut<T: Clone, V>(v: &mut V, words: &mut [TopLevelWord<T>])
+where
+    V: VisitMut<T> + ?Sized,
+{
+    for word in words {
+        v.visit_word_mut(word);
+    }
+}
+
+fn visit_pair_mut<T: Clone, V>(v: &mut V, pair: &mut GuardBodyPair<TopLevelCommand<T>>)
+where
+    V: VisitMut<T> + ?Sized,
+{
+    visit_commands_mut(v, &mut pair.guard);
+    visit_commands_mut(v, &mut pair.body);
+}
+
+/// Visit the pipeable commands of the and/or list of a command.
+pub fn walk_command_mut<T: Clone, V>(v: &mut V, cmd: &mut TopLevelCommand<T>)
+where
+    V: VisitMut<T> + ?Sized,
+{
+    let (Command::Job(list) | Command::List(list)) = &mut cmd.0;
+    let rest = list.rest.iter_mut().map(|and_or| match and_or {
+        AndOr::And(cmd) | AndOr::Or(cmd) => cmd,
+    });
+    for listable in iter::once(&mut list.first).chain(rest) {
+        match listable {
+            ListableCommand::Single(cmd) => v.visit_pipeable_command_mut(cmd),
+            ListableCommand::Pipe(_, cmds) => {
+                for cmd in cmds {
+                    v.visit_pipeable_command_mut(cmd);
+                }
+            }
+        }
+    }
+}
+
+/// Visit the simple command, compound command or function body of a pipeable command.
+pub fn walk_pipeable_command_mut<T: Clone, V>(v: &mut V, cmd: &mut Pipeable<T>)
+where
+    V: VisitMut<T> + ?Sized,
+{
+    match cmd {
+        PipeableCommand::Simple(cmd) => v.visit_simple_command_mut(cmd),
+        PipeableCommand::Compound(cmd) => v.visit_compound_command_mut(cmd),
+        PipeableCommand::FunctionDef(_, body) => v.visit_compound_command_mut(Rc::make_mut(body)),
+    }
+}
+
+/// Visit the redirects, environment variables and words of a simple command.
+pub fn walk_simple_command_mut<T: Clone, V>(v: &mut V, cmd: &mut Simple<T>)
+where
+    V: VisitMut<T> + ?Sized,
+{
+    for redirect_or_env_var in &mut cmd.redirects_or_env_vars {
+        match redirect_or_env_var {
+            RedirectOrEnvVar::Redirect(redirect) => v.visit_redirect_mut(redirect),
+            RedirectOrEnvVar::EnvVar(_, Some(word)) => v.visit_word_mut(word),
+            RedirectOrEnvVar::EnvVar(_, None) => {}
+        }
+    }
+    for redirect_or_word in &mut cmd.redirects_or_cmd_words {
+        match redirect_or_word {
+            RedirectOrCmdWord::Redirect(redirect) => v.visit_redirect_mut(redirect),
+            RedirectOrCmdWord::CmdWord(word) => v.visit_word_mut(word),
+        }
+    }
+}
+
+/// Visit the words, inner commands and redirects of a compound command.
+pub fn walk_compound_command_mut<T: Clone, V>(v: &mut V, cmd: &mut Compound<T>)
+where
+    V: VisitMut<T> + ?Sized,
+{
+    use CompoundCommandKind::*;
+    match &mut cmd.kind {
+        Brace(cmds) | Subshell(cmds) => visit_commands_mut(v, cmds),
+        While(pair) | Until(pair) => visit_pair_mut(v, pair),
+        If {
+            conditionals,
+            else_branch,
+        } => {
+            for pair in conditionals {
+                visit_pair_mut(v, pair);
+            }
+            if let Some(cmds) = else_branch {
+                visit_commands_mut(v, cmds);
+            }
+        }
+        For { words, body, .. } => {
+            if let Some(words) = words {
+                visit_words_mut(v, words);
+            }
+            visit_commands_mut(v, body);
+        }
+        Case { word, arms } => {
+            v.visit_word_mut(word);
+            for arm in arms {
+                visit_words_mut(v, &mut arm.patterns);
+                visit_commands_mut(v, &mut arm.body);
+            }
+        }
+        Macro(m4_macro) => v.visit_macro_mut(m4_macro),
+    }
+    for redirect in &mut cmd.io {
+        v.visit_redirect_mut(redirect);
+    }
+}
+
+/// Visit the arguments of an m4 macro call.
+pub fn walk_macro_mut<T: Clone, V>(v: &mut V, m4_macro: &mut Macro<T>)
+where
+    V: VisitMut<T> + ?Sized,
+{
+    for arg in &mut m4_macro.args {
+        v.visit_argument_mut(arg);
+    }
+}
+
+/// Visit the words or commands of an m4 macro argument.
+pub fn walk_argument_mut<T: Clone, V>(v: &mut V, arg: &mut Argument<T>)
+where
+    V: VisitMut<T> + ?Sized,
+{
+    match arg {
+        M4Argument::Word(word) => v.visit_word_mut(word),
+        M4Argument::Array(words) => visit_words_mut(v, words),
+        M4Argument::Commands(cmds) => visit_commands_mut(v, cmds),
+        M4Argument::Literal(_) | M4Argument::Program(_) | M4Argument::Unknown(_) => {}
+    }
+}
+
+/// Visit the word of a redirect.
+pub fn walk_redirect_mut<T: Clone, V>(v: &mut V, redirect: &mut Redirect<TopLevelWord<T>>)
+where
+    V: VisitMut<T> + ?Sized,
+{
+    v.visit_word_mut(redirect_word_mut(redirect))
+}
+
+/// Visit the fragments of a word, quoted or not.
+pub fn walk_word_mut<T: Clone, V>(v: &mut V, word: &mut TopLevelWord<T>)
+where
+    V: VisitMut<T> + ?Sized,
+{
+    let words = match &mut word.0 {
+        ComplexWord::Concat(words) => words.as_mut_slice(),
+        ComplexWord::Single(word) => std::slice::from_mut(word),
+    };
+    for word in words {
+        match word {
+            Word::Simple(fragment) => v.visit_fragment_mut(fragment),
+            Word::DoubleQuoted(fragments) => {
+                for fragment in fragments {
+                    v.visit_fragment_mut(fragment);
+                }
+            }
+            Word::SingleQuoted(_) => {}
+        }
+    }
+}
+
+/// Visit the substitution or the m4 macro call of a word fragment.
+pub fn walk_fragment_mut<T: Clone, V>(v: &mut V, fragment: &mut Fragment<T>)
+where
+    V: VisitMut<T> + ?Sized,
+{
+    match fragment {
+        MayM4::Shell(SimpleWord::Subst(subst)) => v.visit_substitution_mut(subst),
+        MayM4::Shell(_) => {}
+        MayM4::Macro(m4_macro) => v.visit_macro_mut(m4_macro),
+    }
+}
+
+/// Visit the commands or the word of a parameter substitution.
+pub fn walk_substitution_mut<T: Clone, V>(v: &mut V, subst: &mut Subst<T>)
+where
+    V: VisitMut<T> + ?Sized,
+{
+    let (cmds, word) = subst_parts_mut(subst);
+    if let Some(cmds) = cmds {
+        visit_commands_mut(v, cmds);
+    }
+    if let Some(Some(word)) = word {
+        v.visit_word_mut(word);
+    }
+}
+
+/// A traversal of the default AST which consumes and rebuilds it.
+///
+/// Function bodies are shared through `Rc`, so folding one clones it
+/// first if it is shared with another command.
+pub trait Fold<T: Clone> {
+    /// Fold a top-level command.
+    fn fold_command(&mut self, cmd: TopLevelCommand<T>) -> TopLevelCommand<T> {
+        fold_command(self, cmd)
+    }
+
+    /// Fold a command of a pipeline, or a command out of any pipeline.
+    fn fold_pipeable_command(&mut self, cmd: Pipeable<T>) -> Pipeable<T> {
+        fold_pipeable_command(self, cmd)
+    }
+
+    /// Fold a simple command.
+    fn fold_simple_command(&mut self, cmd: Simple<T>) -> Simple<T> {
+        fold_simple_command(self, cmd)
+    }
+
+    /// Fold a compound command, including an m4 macro call as a command.
+    fn fold_compound_command(&mut self, cmd: Compound<T>) -> Compound<T> {
+        fold_compound_command(self, cmd)
+    }
+
+    /// Fold an m4 macro call, either a command or a word fragment.
+    fn fold_macro(&mut self, m4_macro: Macro<T>) -> Macro<T> {
+        fold_macro(self, m4_macro)
+    }
+
+    /// Fold an argument of an m4 macro call.
+    fn fold_argument(&mut self, arg: Argument<T>) -> Argument<T> {
+        fold_argument(self, arg)
+    }
+
+    /// Fold a redirect.
+    fn fold_redirect(&mut self, redirect: Redirect<TopLevelWord<T>>) -> Redirect<TopLevelWord<T>> {
+        fold_redirect(self, redirect)
+    }
+
+    /// Fold a word.
+    fn fold_word(&mut self, word: TopLevelWord<T>) -> TopLevelWord<T> {
+        fold_word(self, word)
+    }
+
+    /// Fold a fragment of a word, either shell or m4.
+    fn fold_fragment(&mut self, fragment: Fragment<T>) -> Fragment<T> {
+        fold_fragment(self, fragment)
+    }
+
+    /// Fold a parameter substitution, e.g. `${foo:-bar}` or `$(cmd)`.
+    fn fold_substitution(&mut self, subst: Subst<T>) -> Subst<T> {
+        fold_substitution(self, subst)
+    }
+}
+
+fn fold_commands<T: Clone, F>(f: &mut F, cmds: Vec<TopLevelCommand<T>>) -> Vec<TopLevelCommand<T>>
+where
+    F: Fold<T> + ?Sized,
+{
+    cmds.into_iter().map(|cmd| f.fold_command(cmd)).collect()
+}
+
+fn fold_words<T: Clone, F>(f: &mut F, words: Vec<TopLevelWord<T>>) -> Vec<TopLevelWord<T>>
+where
+    F: Fold<T> + ?Sized,
+{
+    words.into_iter().map(|word| f.fold_word(word)).collect()
+}
+
+fn fold_pair<T: Clone, F>(
+    f: &mut F,
+    pair: GuardBodyPair<TopLevelCommand<T>>,
+) -> GuardBodyPair<TopLevelCommand<T>>
+where
+    F: Fold<T> + ?Sized,
+{
+    GuardBodyPair {
+        guard: fold_commands(f, pair.guard),
+        body: fold_commands(f, pair.body),
+    }
+}
+
+fn fold_listable<T: Clone, F>(f: &mut F, cmd: Listable<T>) -> Listable<T>
+where
+    F: Fold<T> + ?Sized,
+{
+    match cmd {
+        ListableCommand::Single(cmd) => ListableCommand::Single(f.fold_pipeable_command(cmd)),
+        ListableCommand::Pipe(bang, cmds) => ListableCommand::Pipe(
+            bang,
+            cmds.into_iter()
+                .map(|cmd| f.fold_pipeable_command(cmd))
+                .collect(),
+        ),
+    }
+}
+
+fn fold_and_or_list<T: Clone, F>(f: &mut F, list: AndOrList<Listable<T>>) -> AndOrList<Listable<T>>
+where
+    F: Fold<T> + ?Sized,
+{
+    AndOrList {
+        first: fold_listable(f, list.first),
+        rest: list
+            .rest
+            .into_iter()
+            .map(|and_or| match and_or {
+                AndOr::And(cmd) => AndOr::And(fold_listable(f, cmd)),
+                AndOr::Or(cmd) => AndOr::Or(fold_listable(f, cmd)),
+            })
+            .collect(),
+    }
+}
+
+fn fold_quoted_word<T: Clone, F>(f: &mut F, word: QuotedWord<T>) -> QuotedWord<T>
+where
+    F: Fold<T> + ?Sized,
+{
+    match word {
+        Word::Simple(fragment) => Word::Simple(f.fold_fragment(fragment)),
+        Word::DoubleQuoted(fragments) => Word::DoubleQuoted(
+            fragments
+                .into_iter()
+                .map(|fragment| f.fold_fragment(fragment))
+                .collect(),
+        ),
+        Word::SingleQuoted(literal) => Word::SingleQuoted(literal),
+    }
+}
+
+/// Fold the pipeable commands of the and/or list of a command.
+pub fn fold_command<T: Clone, F>(f: &mut F, cmd: TopLevelCommand<T>) -> TopLevelCommand<T>
+where
+    F: Fold<T> + ?Sized,
+{
+    TopLevelCommand(match cmd.0 {
+        Command::Job(list) => Command::Job(fold_and_or_list(f, list)),
+        Command::List(list) => Command::List(fold_and_or_list(f, list)),
+    })
+}
+
+/// Fold the simple command, compound command or function body of a pipeable command.
+pub fn fold_pipeable_command<T: Clone, F>(f: &mut F, cmd: Pipeable<T>) -> Pipeable<T>
+where
+    F: Fold<T> + ?Sized,
+{
+    match cmd {
+        PipeableCommand::Simple(cmd) => {
+            PipeableCommand::Simple(Box::new(f.fold_simple_command(*cmd)))
+        }
+        PipeableCommand::Compound(cmd) => {
+            PipeableCommand::Compound(Box::new(f.fold_compound_command(*cmd)))
+        }
+        PipeableCommand::FunctionDef(name, body) => {
+            let body = Rc::try_unwrap(body).unwrap_or_else(|body| (*body).clone());
+            PipeableCommand::FunctionDef(name, Rc::new(f.fold_compound_command(body)))
+        }
+    }
+}
+
+/// Fold the redirects, environment variables and words of a simple command.
+pub fn fold_simple_command<T: Clone, F>(f: &mut F, cmd: Simple<T>) -> Simple<T>
+where
+    F: Fold<T> + ?Sized,
+{
+    SimpleCommand {
+        redirects_or_env_vars: cmd
+            .redirects_or_env_vars
+            .into_iter()
+            .map(|redirect_or_env_var| match redirect_or_env_var {
+                RedirectOrEnvVar::Redirect(redirect) => {
+                    RedirectOrEnvVar::Redirect(f.fold_redirect(redirect))
+                }
+                RedirectOrEnvVar::EnvVar(name, word) => {
+                    RedirectOrEnvVar::EnvVar(name, word.map(|word| f.fold_word(word)))
+                }
+            })
+            .collect(),
+        redirects_or_cmd_words: cmd
+            .redirects_or_cmd_words
+            .into_iter()
+            .map(|redirect_or_word| match redirect_or_word {
+                RedirectOrCmdWord::Redirect(redirect) => {
+                    RedirectOrCmdWord::Redirect(f.fold_redirect(redirect))
+                }
+                RedirectOrCmdWord::CmdWord(word) => RedirectOrCmdWord::CmdWord(f.fold_word(word)),
+            })
+            .collect(),
+    }
+}
+
+/// Fold the words, inner commands and redirects of a compound command.
+pub fn fold_compound_command<T: Clone, F>(f: &mut F, cmd: Compound<T>) -> Compound<T>
+where
+    F: Fold<T> + ?Sized,
+{
+    use CompoundCommandKind::*;
+    let kind = match cmd.kind {
+        Brace(cmds) => Brace(fold_commands(f, cmds)),
+        Subshell(cmds) => Subshell(fold_commands(f, cmds)),
+        While(pair) => While(fold_pair(f, pair)),
+        Until(pair) => Until(fold_pair(f, pair)),
+        If {
+            conditionals,
+            else_branch,
+        } => If {
+            conditionals: conditionals
+                .into_iter()
+                .map(|pair| fold_pair(f, pair))
+                .collect(),
+            else_branch: else_branch.map(|cmds| fold_commands(f, cmds)),
+        },
+        For { var, words, body } => For {
+            var,
+            words: words.map(|words| fold_words(f, words)),
+            body: fold_commands(f, body),
+        },
+        Case { word, arms } => Case {
+            word: f.fold_word(word),
+            arms: arms
+                .into_iter()
+                .map(|arm| PatternBodyPair {
+                    patterns: fold_words(f, arm.patterns),
+                    body: fold_commands(f, arm.body),
+                })
+                .collect(),
+        },
+        Macro(m4_macro) => Macro(f.fold_macro(m4_macro)),
+    };
+    CompoundCommand {
+        kind,
+        io: cmd
+            .io
+            .into_iter()
+            .map(|redirect| f.fold_redirect(redirect))
+            .collect(),
+    }
+}
+
+/// Fold the arguments of an m4 macro call.
+pub fn fold_macro<T: Clone, F>(f: &mut F, m4_macro: Macro<T>) -> Macro<T>
+where
+    F: Fold<T> + ?Sized,
+{
+    M4Macro {
+        args: m4_macro
+            .args
+            .into_iter()
+            .map(|arg| f.fold_argument(arg))
+            .collect(),
+        ..m4_macro
+    }
+}
+
+/// Fold the words or commands of an m4 macro argument.
+pub fn fold_argument<T: Clone, F>(f: &mut F, arg: Argument<T>) -> Argument<T>
+where
+    F: Fold<T> + ?Sized,
+{
+    match arg {
+        M4Argument::Word(word) => M4Argument::Word(f.fold_word(word)),
+        M4Argument::Array(words) => M4Argument::Array(fold_words(f, words)),
+        M4Argument::Commands(cmds) => M4Argument::Commands(fold_commands(f, cmds)),
+        arg => arg,
+    }
+}
+
+/// Fold the word of a redirect.
+pub fn fold_redirect<T: Clone, F>(
+    f: &mut F,
+    redirect: Redirect<TopLevelWord<T>>,
+) -> Redirect<TopLevelWord<T>>
+where
+    F: Fold<T> + ?Sized,
+{
+    map_redirect(redirect, |word| f.fold_word(word))
+}
+
+/// Fold the fragments of a word, quoted or not.
+pub fn fold_word<T: Clone, F>(f: &mut F, word: TopLevelWord<T>) -> TopLevelWord<T>
+where
+    F: Fold<T> + ?Sized,
+{
+    TopLevelWord(match word.0 {
+        ComplexWord::Concat(words) => ComplexWord::Concat(
+            words
+                .into_iter()
+                .map(|word| fold_quoted_word(f, word))
+                .collect(),
+        ),
+        ComplexWord::Single(word) => ComplexWord::Single(fold_quoted_word(f, word)),
+    })
+}
+
+/// Fold the substitution or the m4 macro call of a word fragment.
+pub fn fold_fragment<T: Clone, F>(f: &mut F, fragment: Fragment<T>) -> Fragment<T>
+where
+    F: Fold<T> + ?Sized,
+{
+    match fragment {
+        MayM4::Shell(SimpleWord::Subst(subst)) => {
+            MayM4::Shell(SimpleWord::Subst(Box::new(f.fold_substitution(*subst))))
+        }
+        MayM4::Macro(m4_macro) => MayM4::Macro(f.fold_macro(m4_macro)),
+        fragment => fragment,
+    }
+}
+
+/// Fold the commands or the word of a parameter substitution.
+pub fn fold_substitution<T: Clone, F>(f: &mut F, mut subst: Subst<T>) -> Subst<T>
+where
+    F: Fold<T> + ?Sized,
+{
+    let (cmds, word) = subst_parts_mut(&mut subst);
+    if let Some(cmds) = cmds {
+        *cmds = fold_commands(f, mem::take(cmds));
+    }
+    if let Some(word) = word {
+        *word = word.take().map(|word| f.fold_word(word));
+    }
+    subst
+}
@@ -0,0 +1,320 @@
+//! Rewriting of obsolete macro calls of autoconf scripts, after the
+//! `autoupdate` program of autoconf.
+//!
+//! The obsolete calls are those the parser resolved to their replacement,
+//! i.e. whose `M4Macro::original_name` is set, and a few obsolete macros
+//! which are still known under their own name, such as `AC_TRY_COMPILE`.
+//! Each call is located in the source with the lines of its node and the
+//! macro names found by `highlight::classify`, and the edits are kept
+//! minimal:
+//!
+//! * a macro taking the same arguments as its replacement only has its name
+//!   replaced, e.g. `AC_STDC_HEADERS` becomes `AC_HEADER_STDC`;
+//! * the calls of the macros whose arguments changed are rewritten as a
+//!   whole, their arguments being copied verbatim, e.g.
+//!   `AC_TRY_COMPILE(inc, body, yes, no)` becomes
+//!   `AC_COMPILE_IFELSE([AC_LANG_PROGRAM([inc], [body])], yes, no)`.
+//!
+//! The rest of the source, comments and layout included, is left untouched.
+//!
+//! ```
+//! use autotools_parser::ast::node::AutoconfPool;
+//! use autotools_parser::autoupdate::autoupdate;
+//! use autotools_parser::lexer::Lexer;
+//! use autotools_parser::parse::autoconf::NodeParser;
+//!
+//! let src = "AC_STDC_HEADERS\nAC_TRY_COMPILE([#include <x.h>], [f();], [ok=yes])\n";
+//! let (nodes, top_ids) = NodeParser::<_, ()>::new(Lexer::new(src.chars()))
+//!     .parse_all();
+//! let pool = AutoconfPool::new(nodes, None);
+//! let update = autoupdate(&pool, &top_ids, src);
+//! assert_eq!(
+//!     update.source,
+//!     "AC_HEADER_STDC\n\
+//!      AC_COMPILE_IFELSE([AC_LANG_PROGRAM([[#include <x.h>]], [[f();]])], [ok=yes])\n"
+//! );
+//! assert_eq!(update.changes[1].line, 2);
+//! assert_eq!(update.changes[1].from, "AC_TRY_COMPILE");
+//! assert_eq!(update.changes[1].to, "AC_COMPILE_IFELSE");
+//! ```
+use std::fmt;
+use std::ops::Range;
+
+use crate::ast::node::{AutoconfPool, M4Macro, NodeId};
+use crate::ast::visit::node::{walk_macro, walk_node, Visit};
+use crate::highlight::{self, TokenKind};
+
+/// Builds the call replacing an obsolete one from the name of the
+/// replacement and the text of the original arguments.
+type Rewrite = fn(&str, &[String]) -> String;
+
+/// Obsolete macros whose arguments differ from those of their replacement.
+const REWRITES: &[(&str, &str, Rewrite)] = &[
+    ("AC_TRY_COMPILE", "AC_COMPILE_IFELSE", lang_program),
+    ("AC_TRY_LINK", "AC_LINK_IFELSE", lang_program),
+    ("AC_TRY_RUN", "AC_RUN_IFELSE", lang_source),
+    ("AC_TRY_CPP", "AC_PREPROC_IFELSE", lang_source),
+    ("AC_HAVE_LIBRARY", "AC_CHECK_LIB", have_library),
+    ("AC_OUTPUT_COMMANDS", "AC_CONFIG_COMMANDS", output_commands),
+    ("AC_LIBTOOL_DLOPEN", "LT_INIT", |name, _| {
+        format!("{}([dlopen])", name)
+    }),
+    ("AC_LIBTOOL_WIN32_DLL", "LT_INIT", |name, _| {
+        format!("{}([win32-dll])", name)
+    }),
+];
+
+/// A rewritten call of an obsolete macro.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The node of the call, either the command or the one holding it.
+    pub node: NodeId,
+    /// The line of the call in the original source, starting at 1.
+    pub line: usize,
+    /// The name of the obsolete macro.
+    pub from: String,
+    /// The name of the macro replacing it.
+    pub to: String,
+    /// The bytes of the original source which are replaced: the macro name
+    /// for a plain rename, the whole call otherwise.
+    ///
+    /// The span of a call nested in the arguments of a rewritten call lies
+    /// within the span of the latter, whose `text` includes its change.
+    pub span: Range<usize>,
+    /// The text replacing `span`.
+    pub text: String,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.from, self.to)
+    }
+}
+
+/// The result of `autoupdate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Update {
+    /// The updated source.
+    pub source: String,
+    /// The changes in the order of the source.
+    pub changes: Vec<Change>,
+}
+
+/// Replace the calls of obsolete macros of the script parsed from
+/// `source` into `pool` with their modern forms.
+///
+/// The calls which cannot be found in the source, e.g. because they come
+/// from the expansion of another macro, are left alone.
+pub fn autoupdate<U>(pool: &AutoconfPool<U>, top_ids: &[NodeId], source: &str) -> Update {
+    let mut calls = Calls {
+        node: 0,
+        calls: Vec::new(),
+    };
+    for id in top_ids {
+        calls.visit_node(pool, *id);
+    }
+
+    let tokens: Vec<Range<usize>> = highlight::classify(source)
+        .into_iter()
+        .filter(|token| matches!(token.kind, TokenKind::Macro | TokenKind::UserMacro))
+        .map(|token| token.span)
+        .collect();
+    let mut used = vec![false; tokens.len()];
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let line_of = |pos: usize| line_starts.partition_point(|start| *start <= pos);
+
+    let mut pending = Vec::new();
+    for (node, m4_macro) in calls.calls {
+        let from = m4_macro.original_name.as_deref().unwrap_or(&m4_macro.name);
+        let rewrite = REWRITES.iter().find(|(name, _, _)| *name == from);
+        let to = match (rewrite, &m4_macro.original_name) {
+            (Some((_, to, _)), _) => to.to_string(),
+            (None, Some(_)) => m4_macro.name.clone(),
+            (None, None) => continue,
+        };
+        let lines = {
+            let node = &pool.nodes[node];
+            node.range_start().zip(node.range_end())
+        };
+        let Some(i) = (0..tokens.len()).find(|i| {
+            !used[*i]
+                && &source[tokens[*i].clone()] == from
+                && lines
+                    .is_none_or(|(start, end)| (start..=end).contains(&line_of(tokens[*i].start)))
+        }) else {
+            continue;
+        };
+        used[i] = true;
+        let name = tokens[i].clone();
+
+        let Some((_, _, rewrite)) = rewrite else {
+            pending.push(Pending {
+                change: change(node, line_of(name.start), from, to, name),
+                args: Vec::new(),
+                rewrite: None,
+            });
+            continue;
+        };
+        let (span, args) = if source[name.end..].starts_with('(') {
+            let args = highlight::split_args(source, name.end + 1, source.len());
+            let end = args.last().map_or(source.len(), |arg| arg.end);
+            if !source[end..].starts_with(')') {
+                // The call is not closed.
+                continue;
+            }
+            (name.start..end + 1, args)
+        } else {
+            (name.clone(), Vec::new())
+        };
+        pending.push(Pending {
+            change: change(node, line_of(name.start), from, to, span),
+            args,
+            rewrite: Some(*rewrite),
+        });
+    }
+
+    // The calls nested in the arguments of a rewritten call are resolved
+    // first, so that their text is carried over by the outer one.
+    pending.sort_by_key(|p| p.change.span.start);
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    let mut changes = Vec::new();
+    for mut p in pending.into_iter().rev() {
+        if let Some(rewrite) = p.rewrite {
+            let args: Vec<String> = p
+                .args
+                .iter()
+                .map(|arg| {
+                    let arg = arg.start..arg.start + source[arg.clone()].trim_end().len();
+                    let mut text = String::new();
+                    let mut pos = arg.start;
+                    edits.retain(|(span, edit)| {
+                        if span.start < arg.start || span.end > arg.end {
+                            return true;
+                        }
+                        text.push_str(&source[pos..span.start]);
+                        text.push_str(edit);
+                        pos = span.end;
+                        false
+                    });
+                    text.push_str(&source[pos..arg.end]);
+                    text
+                })
+                .collect();
+            p.change.text = rewrite(&p.change.to, &args);
+        }
+        // Later edits come first.
+        edits.insert(0, (p.change.span.clone(), p.change.text.clone()));
+        changes.push(p.change);
+    }
+    changes.reverse();
+
+    let mut updated = String::with_capacity(source.len());
+    let mut pos = 0;
+    for (span, text) in edits {
+        updated.push_str(&source[pos..span.start]);
+        updated.push_str(&text);
+        pos = span.end;
+    }
+    updated.push_str(&source[pos..]);
+    Update {
+        source: updated,
+        changes,
+    }
+}
+
+/// An obsolete call located in the source.
+struct Pending {
+    change: Change,
+    /// The arguments of the call, when it is rewritten as a whole.
+    args: Vec<Range<usize>>,
+    rewrite: Option<Rewrite>,
+}
+
+fn change(node: NodeId, line: usize, from: &str, to: String, span: Range<usize>) -> Change {
+    Change {
+        node,
+        line,
+        from: from.to_string(),
+        text: to.clone(),
+        to,
+        span,
+    }
+}
+
+/// Collects the macro calls with the node holding them.
+struct Calls<'a> {
+    node: NodeId,
+    calls: Vec<(NodeId, &'a M4Macro)>,
+}
+
+impl<'a, U: 'a> Visit<'a, U> for Calls<'a> {
+    fn visit_node(&mut self, pool: &'a AutoconfPool<U>, id: NodeId) {
+        let outer = self.node;
+        self.node = id;
+        walk_node(self, pool, id);
+        self.node = outer;
+    }
+
+    fn visit_macro(&mut self, pool: &'a AutoconfPool<U>, m4_macro: &'a M4Macro) {
+        self.calls.push((self.node, m4_macro));
+        walk_macro(self, pool, m4_macro);
+    }
+}
+
+/// Format a call of `name` with `args`.
+fn call<'a>(name: &str, args: impl IntoIterator<Item = &'a str>) -> String {
+    format!(
+        "{}({})",
+        name,
+        args.into_iter().collect::<Vec<_>>().join(", ")
+    )
+}
+
+fn arg(args: &[String], i: usize) -> &str {
+    args.get(i).map_or("", String::as_str)
+}
+
+/// `AC_TRY_COMPILE(includes, body, ...)` to
+/// `AC_COMPILE_IFELSE([AC_LANG_PROGRAM([includes], [body])], ...)`.
+fn lang_program(name: &str, args: &[String]) -> String {
+    let program = format!("[AC_LANG_PROGRAM([{}], [{}])]", arg(args, 0), arg(args, 1));
+    let rest = args.iter().skip(2).map(String::as_str);
+    call(name, std::iter::once(program.as_str()).chain(rest))
+}
+
+/// `AC_TRY_RUN(program, ...)` to
+/// `AC_RUN_IFELSE([AC_LANG_SOURCE([program])], ...)`.
+fn lang_source(name: &str, args: &[String]) -> String {
+    let program = format!("[AC_LANG_SOURCE([{}])]", arg(args, 0));
+    let rest = args.iter().skip(1).map(String::as_str);
+    call(name, std::iter::once(program.as_str()).chain(rest))
+}
+
+/// `AC_HAVE_LIBRARY(library, ...)` to `AC_CHECK_LIB(library, [main], ...)`,
+/// where `-lfoo` and `libfoo.a` are reduced to `foo`.
+fn have_library(name: &str, args: &[String]) -> String {
+    let library = arg(args, 0);
+    let unquoted = library
+        .strip_prefix('[')
+        .and_then(|lib| lib.strip_suffix(']'))
+        .unwrap_or(library);
+    let short = unquoted.strip_prefix("-l").or_else(|| {
+        unquoted
+            .strip_prefix("lib")
+            .and_then(|lib| lib.strip_suffix(".a"))
+    });
+    let library = short.map_or(library.to_string(), |lib| format!("[{}]", lib));
+    let rest = args.iter().skip(1).map(String::as_str);
+    call(name, [library.as_str(), "[main]"].into_iter().chain(rest))
+}
+
+/// `AC_OUTPUT_COMMANDS(commands, init)` to
+/// `AC_CONFIG_COMMANDS([default], commands, init)`.
+fn output_commands(name: &str, args: &[String]) -> String {
+    call(
+        name,
+        std::iter::once("[default]").chain(args.iter().map(String::as_str)),
+    )
+}
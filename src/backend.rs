@@ -0,0 +1,779 @@
+//! Translation of automake projects to other build systems.
+//!
+//! A `BuildModel` fixes one configuration of a project: the automake
+//! conditionals get the values chosen for them, and variables the values
+//! `configure` would substitute. Programs and libraries are built with the
+//! commands of `analysis::flags`, libtool libraries becoming shared libraries,
+//! and the custom rules of the makefiles keep their recipes as shell commands.
+//! The backends write the model in the format of their build system.
+//! Whatever can not be translated is reported as a `Diagnostic`.
+//!
+//! `cmake` translates the checks of `configure.ac` instead, and `meson` both
+//! the checks and the targets, keeping the conditionals. `build_script` runs
+//! the checks again from the `build.rs` of a `-sys` crate.
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::analysis::compdb::makefile_outputs;
+use crate::analysis::flags::{BuildFlags, ConfigureOutputs, Language};
+use crate::analysis::install::{InstallDirs, InstallLayout};
+use crate::analysis::rules::RuleGraph;
+use crate::analysis::targets::{collect_targets, Primary, Target};
+use crate::analysis::variables::{CondValues, Evaluator};
+use crate::analysis::{is_make_test, CondSet};
+use crate::ast::am::{AmLine, AmVar, AmWord, AmWordFragment, MakeDF, MakeParameter, MayAm};
+use crate::ast::minimal::{Word, WordFragment};
+use crate::ast::node::{DisplayNode, NodeId, NodePool};
+use crate::project::{AutomakeFile, FileId, Project};
+use crate::util::{normalize, references, REFERENCE};
+
+pub mod build_script;
+pub mod cmake;
+pub mod configure;
+pub mod meson;
+pub mod ninja;
+
+/// Libtool link flags which have no equivalent when linking directly.
+const LIBTOOL_FLAGS: &[(&str, bool)] = &[
+    ("-version-info", true),
+    ("-version-number", true),
+    ("-release", true),
+    ("-export-symbols", true),
+    ("-export-symbols-regex", true),
+    ("-rpath", true),
+    ("-module", false),
+    ("-avoid-version", false),
+    ("-shared", false),
+    ("-static", false),
+];
+
+/// The configuration of the project to translate.
+#[derive(Debug, Default, Clone)]
+pub struct BackendOptions {
+    /// Values of the automake conditionals.
+    pub conds: BTreeMap<String, bool>,
+    /// Values substituted by `configure`, e.g. `CC` or `CFLAGS`.
+    pub outputs: ConfigureOutputs,
+    /// The installation directories.
+    pub install_dirs: InstallDirs,
+    /// The source tree as seen from the build directory, relative or
+    /// absolute. Defaults to the absolute path of the project root.
+    pub srcdir: Option<PathBuf>,
+}
+
+/// A construct which could not be translated.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    /// The `Makefile.am` or `configure.ac` the construct comes from.
+    pub makefile: PathBuf,
+    /// What could not be translated.
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.makefile.display(), self.message)
+    }
+}
+
+/// Kinds of built targets.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TargetKind {
+    /// A program.
+    Program,
+    /// A static library from `LIBRARIES`.
+    StaticLibrary,
+    /// A shared library, from `LTLIBRARIES`.
+    SharedLibrary,
+}
+
+/// An object file of a target.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ObjectFile {
+    /// The source relative to the directory of the makefile.
+    pub source: String,
+    /// Whether the source is built rather than found in the source tree.
+    pub built: bool,
+    /// The object relative to the directory of the makefile.
+    pub object: String,
+    /// The language of the source.
+    pub language: Language,
+    /// The compile command, run in the directory of the makefile.
+    pub arguments: Vec<String>,
+}
+
+/// A program or library.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BuildTarget {
+    /// The `Makefile.am` declaring the target.
+    pub makefile: PathBuf,
+    /// The directory of the makefile, relative to the project root.
+    pub dir: PathBuf,
+    /// The name the target is declared with, e.g. `libfoo.la`.
+    pub name: String,
+    /// The kind of the target.
+    pub kind: TargetKind,
+    /// The built file relative to `dir`, e.g. `libfoo.so`.
+    pub output: String,
+    /// The object files, in the order of the sources.
+    pub objects: Vec<ObjectFile>,
+    /// The link or archive command, run in `dir`.
+    pub link: Vec<String>,
+    /// Indices of the targets of the project this target links with.
+    pub links: Vec<usize>,
+    /// Whether `make install` installs the target.
+    pub installed: bool,
+}
+
+impl BuildTarget {
+    /// The built file relative to the project root.
+    pub fn path(&self) -> PathBuf {
+        normalize(&self.dir.join(&self.output))
+    }
+}
+
+/// A custom rule of a makefile.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CustomCommand {
+    /// The `Makefile.am` of the rule.
+    pub makefile: PathBuf,
+    /// The directory of the makefile, relative to the project root.
+    pub dir: PathBuf,
+    /// The target of the rule, relative to `dir`.
+    pub output: String,
+    /// The prerequisites of the rule, relative to `dir`.
+    pub inputs: Vec<String>,
+    /// The recipe as a single shell command, run in `dir`.
+    pub command: String,
+    /// Whether the target is not a file, e.g. a `.PHONY` target.
+    pub phony: bool,
+    /// The standard target running the rule, e.g. `install-data` for
+    /// `install-data-local`.
+    pub extends: Option<&'static str>,
+}
+
+/// A file installed by `make install`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InstallFile {
+    /// The installed file relative to the project root.
+    pub source: PathBuf,
+    /// Whether the file is built rather than found in the source tree.
+    pub built: bool,
+    /// The installation path, without `$(DESTDIR)`.
+    pub dest: PathBuf,
+    /// The permission bits the file is installed with.
+    pub mode: u32,
+}
+
+/// A project in one configuration, ready to be written by a backend.
+#[derive(Debug, Clone)]
+pub struct BuildModel {
+    /// The source tree as seen from the build directory.
+    pub srcdir: PathBuf,
+    /// The programs and libraries, in the order of the makefiles.
+    pub targets: Vec<BuildTarget>,
+    /// The custom rules with a recipe.
+    pub commands: Vec<CustomCommand>,
+    /// The installed files.
+    pub installs: Vec<InstallFile>,
+    /// Constructs which were left out or translated partially.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl BuildModel {
+    /// Translate a project in the configuration of `options`.
+    pub fn new(project: &Project, options: &BackendOptions) -> Self {
+        let srcdir = options.srcdir.clone().unwrap_or_else(|| {
+            std::path::absolute(project.root()).unwrap_or_else(|_| project.root().into())
+        });
+        let mut model = Self {
+            srcdir,
+            targets: Vec::new(),
+            commands: Vec::new(),
+            installs: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        for id in project.automake_files() {
+            model.add_makefile(project, id, options);
+        }
+        model.resolve_links();
+        model.add_installs(project, options);
+        model
+    }
+
+    /// Returns the target building `path`, relative to the project root.
+    pub fn target_of(&self, path: &Path) -> Option<usize> {
+        self.targets.iter().position(|t| t.path() == path)
+    }
+
+    fn report(&mut self, makefile: &Path, message: String) {
+        let diagnostic = Diagnostic {
+            makefile: makefile.to_path_buf(),
+            message,
+        };
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Returns whether the conditionals hold in the chosen configuration,
+    /// reporting those without a chosen value.
+    fn holds(&mut self, makefile: &Path, cond: &CondSet, options: &BackendOptions) -> bool {
+        match cond_holds(cond, &options.conds) {
+            Ok(holds) => holds,
+            Err(name) => {
+                self.report(makefile, unchosen_message(&name));
+                false
+            }
+        }
+    }
+
+    fn report_unknown(&mut self, makefile: &Path, arguments: &[String]) {
+        for argument in arguments {
+            for name in references(argument) {
+                self.report(makefile, format!("unknown value of `{}`", name));
+            }
+        }
+    }
+
+    fn add_makefile(&mut self, project: &Project, id: FileId, options: &BackendOptions) {
+        let file = project.file(id);
+        let am = file.as_automake().unwrap();
+        let makefile = file.path.as_path();
+        let in_tree = self.srcdir == Path::new(".");
+        let mut outputs = makefile_outputs(project, id, (!in_tree).then_some(&self.srcdir));
+        outputs.extend(options.outputs.clone());
+
+        let srcdir = outputs["srcdir"].clone();
+        let flags = BuildFlags::new(am, &outputs);
+        for target in collect_targets(am) {
+            let declared = target
+                .conds
+                .iter()
+                .any(|cond| self.holds(makefile, cond, options));
+            if !declared || target.dir == "EXTRA" {
+                continue;
+            }
+            let dir = file.dir();
+            self.add_target(makefile, dir, &srcdir, &flags, &target, options);
+        }
+
+        // Recipes may refer to the directories and the package name, and
+        // install into `$(DESTDIR)` which is left to the environment.
+        let dirs = match project
+            .package_of(id)
+            .and_then(|ac| project.file(ac).as_autoconf())
+        {
+            Some(ac) => options.install_dirs.for_package(ac),
+            None => options.install_dirs.clone(),
+        };
+        let mut eval = Evaluator::new(am);
+        for name in dirs.names() {
+            eval.substitute(name, &dirs.resolve(name).unwrap());
+        }
+        eval.substitute("DESTDIR", "$DESTDIR");
+        for (name, value) in &outputs {
+            eval.substitute(name, value);
+        }
+        let graph = RuleGraph::new(am);
+        for rule in graph.inference_rules() {
+            if !rule.recipe.is_empty() {
+                let message = format!("inference rule `{}` is not translated", rule.target);
+                self.report(makefile, message);
+            }
+        }
+        for rule in graph.targets() {
+            if rule.recipe.is_empty()
+                || !rule
+                    .conds
+                    .iter()
+                    .any(|cond| self.holds(makefile, cond, options))
+            {
+                continue;
+            }
+            if let Some(extension) = rule.extension {
+                if extension.base != "all" && !extension.base.starts_with("install-") {
+                    let message = format!("`{}` is not translated", rule.name);
+                    self.report(makefile, message);
+                    continue;
+                }
+            }
+            let recipe = Recipe {
+                am,
+                eval: &eval,
+                conds: &options.conds,
+                target: &rule.name,
+                prerequisites: &rule.prerequisites,
+                errors: RefCell::new(Vec::new()),
+            };
+            let command = recipe.render(&rule.recipe);
+            for error in recipe.errors.into_inner() {
+                self.report(makefile, format!("recipe of `{}`: {}", rule.name, error));
+            }
+            let Some(command) = command else {
+                continue;
+            };
+            // `@VAR@` is often meant literally in recipes, e.g. by `sed`.
+            for capture in REFERENCE.captures_iter(&command) {
+                if let Some(name) = capture.get(1) {
+                    self.report(makefile, format!("unknown value of `{}`", name.as_str()));
+                }
+            }
+            self.commands.push(CustomCommand {
+                makefile: makefile.to_path_buf(),
+                dir: file.dir().to_path_buf(),
+                output: rule.name.clone(),
+                inputs: rule.prerequisites.clone(),
+                command,
+                phony: graph.is_phony(&rule.name) || rule.extension.is_some(),
+                extends: rule.extension.map(|e| e.base),
+            });
+        }
+    }
+
+    fn add_target(
+        &mut self,
+        makefile: &Path,
+        dir: &Path,
+        srcdir: &str,
+        flags: &BuildFlags<'_>,
+        target: &Target,
+        options: &BackendOptions,
+    ) {
+        let kind = match target.primary {
+            Primary::Programs => TargetKind::Program,
+            Primary::Libraries => TargetKind::StaticLibrary,
+            Primary::LtLibraries => TargetKind::SharedLibrary,
+        };
+        let output = match kind {
+            TargetKind::SharedLibrary => shared_library_name(&target.name),
+            _ => target.name.clone(),
+        };
+
+        let mut objects = Vec::new();
+        for command in flags.compile_commands(target) {
+            if !self.holds(makefile, &command.cond, options) {
+                continue;
+            }
+            let mut arguments = command.arguments;
+            if kind == TargetKind::SharedLibrary {
+                if let Some(i) = arguments.iter().position(|a| a == "--mode=compile") {
+                    arguments.drain(..=i);
+                }
+                arguments.insert(1.min(arguments.len()), "-fPIC".to_string());
+            }
+            if srcdir != "." && !command.built {
+                if let Some(last) = arguments.last_mut() {
+                    *last = format!("{}/{}", srcdir, command.source);
+                }
+            }
+            self.report_unknown(makefile, &arguments);
+            objects.push(ObjectFile {
+                source: command.source,
+                built: command.built,
+                object: command.object,
+                language: command.language,
+                arguments,
+            });
+        }
+
+        let mut link = Vec::new();
+        for command in flags.link_commands(target) {
+            if self.holds(makefile, &command.cond, options) {
+                link = command.arguments;
+                break;
+            }
+        }
+        if kind == TargetKind::SharedLibrary {
+            link = self.strip_libtool(makefile, link, &target.name, &output);
+        }
+        self.report_unknown(makefile, &link);
+
+        self.targets.push(BuildTarget {
+            makefile: makefile.to_path_buf(),
+            dir: dir.to_path_buf(),
+            name: target.name.clone(),
+            kind,
+            output,
+            objects,
+            link,
+            links: Vec::new(),
+            installed: !matches!(target.dir.as_str(), "noinst" | "check" | "EXTRA"),
+        });
+    }
+
+    /// Turn a libtool link command into one linking a shared library.
+    fn strip_libtool(
+        &mut self,
+        makefile: &Path,
+        mut link: Vec<String>,
+        name: &str,
+        output: &str,
+    ) -> Vec<String> {
+        if let Some(i) = link.iter().position(|a| a == "--mode=link") {
+            link.drain(..=i);
+        }
+        let mut ret = Vec::new();
+        let mut args = link.into_iter();
+        while let Some(arg) = args.next() {
+            if let Some((_, takes_value)) = LIBTOOL_FLAGS.iter().find(|(flag, _)| *flag == arg) {
+                if *takes_value {
+                    args.next();
+                }
+                if arg != "-rpath" {
+                    let message = format!("libtool flag `{}` of `{}` is dropped", arg, name);
+                    self.report(makefile, message);
+                }
+            } else if arg == "-no-undefined" {
+                ret.push("-Wl,--no-undefined".to_string());
+            } else if arg == name {
+                ret.push(output.to_string());
+            } else {
+                ret.push(arg);
+            }
+        }
+        ret.insert(1.min(ret.len()), "-shared".to_string());
+        ret
+    }
+
+    /// Point the libraries of the project given to the linker at the
+    /// libraries the model builds.
+    fn resolve_links(&mut self) {
+        let declared = self
+            .targets
+            .iter()
+            .map(|t| normalize(&t.dir.join(&t.name)))
+            .collect::<Vec<_>>();
+        for i in 0..self.targets.len() {
+            let dir = self.targets[i].dir.clone();
+            let mut links = Vec::new();
+            let mut link = std::mem::take(&mut self.targets[i].link);
+            for arg in link.iter_mut() {
+                if arg.starts_with('-') || !(arg.ends_with(".la") || arg.ends_with(".a")) {
+                    continue;
+                }
+                let path = normalize(&dir.join(&*arg));
+                if let Some(j) = declared.iter().position(|d| *d == path) {
+                    *arg = relative_path(&dir, &self.targets[j].path())
+                        .display()
+                        .to_string();
+                    links.push(j);
+                }
+            }
+            self.targets[i].link = link;
+            self.targets[i].links = links;
+        }
+    }
+
+    fn add_installs(&mut self, project: &Project, options: &BackendOptions) {
+        let layout = InstallLayout::new(project, &options.install_dirs);
+        for unresolved in &layout.unresolved {
+            let message = format!("installation directory of `{}` is unknown", unresolved.word);
+            self.report(&unresolved.makefile, message);
+        }
+        for entry in &layout.entries {
+            let makefile = project
+                .automake_files()
+                .map(|id| project.file(id))
+                .find(|file| entry.source.starts_with(file.dir()))
+                .map_or(PathBuf::new(), |file| file.path.clone());
+            if !self.holds(&makefile, &entry.cond, options) {
+                continue;
+            }
+            let declared = self
+                .targets
+                .iter()
+                .find(|t| normalize(&t.dir.join(&t.name)) == entry.source);
+            let (source, dest) = match declared {
+                Some(t) => (t.path(), entry.dest.with_file_name(&t.output)),
+                None => (entry.source.clone(), entry.dest.clone()),
+            };
+            let built = declared.is_some()
+                || self
+                    .commands
+                    .iter()
+                    .any(|c| !c.phony && normalize(&c.dir.join(&c.output)) == source);
+            self.installs.push(InstallFile {
+                source,
+                built,
+                dest,
+                mode: entry.mode,
+            });
+        }
+    }
+}
+
+/// `libfoo.la` is built as `libfoo.so`.
+fn shared_library_name(name: &str) -> String {
+    format!("{}.so", name.strip_suffix(".la").unwrap_or(name))
+}
+
+/// Returns whether `cond` holds with the chosen values, or the name of a
+/// conditional it depends on without a chosen value.
+fn cond_holds(cond: &CondSet, chosen: &BTreeMap<String, bool>) -> Result<bool, String> {
+    let mut unknown = None;
+    for (name, value) in cond.iter() {
+        match chosen.get(name) {
+            Some(v) if *v != value => return Ok(false),
+            Some(_) => {}
+            None => unknown = unknown.or(Some(name)),
+        }
+    }
+    match unknown {
+        Some(name) => Err(name.to_string()),
+        None => Ok(true),
+    }
+}
+
+/// The path of `to` as seen from `from`, both relative to the same directory.
+pub(crate) fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from = from.components().collect::<Vec<_>>();
+    let to = to.components().collect::<Vec<_>>();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut ret = PathBuf::new();
+    for _ in common..from.len() {
+        ret.push("..");
+    }
+    for component in &to[common..] {
+        ret.push(component);
+    }
+    ret
+}
+
+/// Renders the recipe of a rule as shell commands, expanding the make
+/// variables and the automatic variables of the rule.
+struct Recipe<'a> {
+    am: &'a AutomakeFile,
+    eval: &'a Evaluator<'a>,
+    conds: &'a BTreeMap<String, bool>,
+    target: &'a str,
+    prerequisites: &'a [String],
+    errors: RefCell<Vec<String>>,
+}
+
+impl Recipe<'_> {
+    /// Render the recipe as a single shell command, or `None` if it has no
+    /// command. Like make, every line runs in its own shell and a failing
+    /// line stops the recipe unless it starts with `-`.
+    fn render(&self, recipe: &[NodeId]) -> Option<String> {
+        let mut lines = Vec::new();
+        for id in recipe {
+            // Statements following a rule are parsed into its recipe.
+            if !matches!(self.am.nodes[*id].cmd, AmLine::Shell(_)) {
+                continue;
+            }
+            let text = one_line(&self.display_node(*id, 0));
+            let text = text.trim_start_matches('@');
+            lines.push(match text.strip_prefix('-') {
+                Some(text) => format!("{} || true", text.trim_start_matches('@')),
+                None => text.to_string(),
+            });
+        }
+        match lines.len() {
+            0 => None,
+            1 => lines.pop(),
+            _ => Some(
+                lines
+                    .iter()
+                    .map(|line| format!("({})", line))
+                    .collect::<Vec<_>>()
+                    .join(" && "),
+            ),
+        }
+    }
+
+    fn fragment(&self, frag: &AmWordFragment) -> String {
+        use MakeParameter::*;
+        let param = match frag {
+            MayAm::Shell(WordFragment::Literal(text)) => return self.literal(text),
+            MayAm::Shell(frag) => return self.shell_word_to_string(frag),
+            MayAm::Automake(AmVar::Param(param)) => param,
+            MayAm::Automake(_) => return self.expand(frag),
+        };
+        let (words, df) = match param {
+            Var(_) => return self.expand(frag),
+            Target(df) => (vec![self.target], df),
+            FirstDependency(df) => (
+                self.prerequisites
+                    .iter()
+                    .take(1)
+                    .map(String::as_str)
+                    .collect(),
+                df,
+            ),
+            AllDependency(df) | AllDependencyAllowingDuplicate(df) | NewerDependency(df) => {
+                (self.prerequisites.iter().map(String::as_str).collect(), df)
+            }
+            Match(_) => {
+                let error = format!("`{}` is only meaningful in inference rules", param);
+                self.errors.borrow_mut().push(error);
+                return String::new();
+            }
+        };
+        words
+            .iter()
+            .map(|word| {
+                let path = Path::new(word);
+                match df {
+                    None => word.to_string(),
+                    Some(MakeDF::Dir) => match path.parent() {
+                        Some(dir) if !dir.as_os_str().is_empty() => dir.display().to_string(),
+                        _ => ".".to_string(),
+                    },
+                    Some(MakeDF::File) => path
+                        .file_name()
+                        .map_or(String::new(), |f| f.to_string_lossy().into_owned()),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Make expands the references in quoted strings too, which are
+    /// literals once parsed.
+    fn literal(&self, text: &str) -> String {
+        let expanded = REFERENCE.replace_all(text, |captures: &regex::Captures<'_>| match captures
+            .get(1)
+        {
+            Some(name) => self.expand(&AmWordFragment::Automake(AmVar::Param(MakeParameter::Var(
+                name.as_str().to_string(),
+            )))),
+            None => captures[0].to_string(),
+        });
+        if expanded.contains(char::is_whitespace) {
+            format!("'{}'", expanded.replace('\'', "'\\''"))
+        } else {
+            expanded.into_owned()
+        }
+    }
+
+    fn expand(&self, frag: &AmWordFragment) -> String {
+        let word = AmWord(Word::Single(frag.clone()));
+        let values = self.eval.eval_words(std::slice::from_ref(&word));
+        match choose(&values, self.conds) {
+            Ok(words) => words.join(" "),
+            Err(name) => {
+                self.errors.borrow_mut().push(unchosen_message(&name));
+                String::new()
+            }
+        }
+    }
+}
+
+impl DisplayNode for Recipe<'_> {
+    type Word = AmWord;
+
+    fn display_node(&self, node_id: NodeId, indent_level: usize) -> String {
+        match self.am.nodes.get(node_id).map(|node| &node.cmd) {
+            Some(AmLine::Shell(cmd)) => self.command_to_string(cmd, None, indent_level),
+            _ => String::new(),
+        }
+    }
+
+    fn display_word(&self, word: &AmWord, should_quote: bool) -> String {
+        let frags = match &word.0 {
+            Word::Single(frag) => std::slice::from_ref(frag),
+            Word::Concat(frags) => frags.as_slice(),
+            Word::Empty => return "\"\"".to_string(),
+        };
+        let text = frags.iter().map(|f| self.fragment(f)).collect::<String>();
+        let quoted = matches!(
+            &word.0,
+            Word::Single(MayAm::Shell(WordFragment::DoubleQuoted(_)))
+        );
+        if should_quote && !quoted {
+            format!("\"{}\"", text)
+        } else {
+            text
+        }
+    }
+}
+
+impl NodePool<AmWord> for Recipe<'_> {}
+
+/// The diagnostic of a conditional without a chosen value. The tests of GNU
+/// make conditionals are not translated, so their branches are left out.
+fn unchosen_message(name: &str) -> String {
+    if is_make_test(name) {
+        format!("GNU make conditional `{}` is not translated", name)
+    } else {
+        format!("no value chosen for conditional `{}`", name)
+    }
+}
+
+/// Returns the words of the alternative holding with the chosen values, or
+/// the name of a conditional the value depends on without a chosen value.
+fn choose<'a>(
+    values: &'a CondValues,
+    chosen: &BTreeMap<String, bool>,
+) -> Result<&'a [String], String> {
+    let mut unknown = None;
+    for (cond, words) in values {
+        match cond_holds(cond, chosen) {
+            Ok(true) => return Ok(words),
+            Ok(false) => {}
+            Err(name) => unknown = unknown.or(Some(name)),
+        }
+    }
+    match unknown {
+        Some(name) => Err(name),
+        None => Ok(&[]),
+    }
+}
+
+/// Join the lines of a rendered shell script into a single line.
+fn one_line(script: &str) -> String {
+    const OPENING: &[&str] = &["then", "do", "else", "{", "(", ";;"];
+    let mut ret = String::new();
+    // Whether the next line is a pattern of a `case` command.
+    let mut pattern = false;
+    let mut after_pattern = false;
+    for line in script.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let opened = OPENING
+            .iter()
+            .any(|k| ret == *k || ret.ends_with(&format!(" {}", k)));
+        if !ret.is_empty() {
+            let joined = opened || pattern || after_pattern || line == ";;";
+            ret.push_str(if joined { " " } else { "; " });
+        }
+        after_pattern = pattern && line != "esac";
+        pattern = line == ";;" || (line.starts_with("case ") && line.ends_with(" in"));
+        ret.push_str(line);
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_line() {
+        assert_eq!(
+            one_line("if test -f x; then\n  echo a\n  echo b\nelse\n  exit 1\nfi"),
+            "if test -f x; then echo a; echo b; else exit 1; fi"
+        );
+        assert_eq!(
+            one_line("for f in a b; do\n  cp $f d\ndone"),
+            "for f in a b; do cp $f d; done"
+        );
+        assert_eq!(
+            one_line("case $x in\n  a|b)\n    echo ab\n    ;;\n  *)\n    echo c\n    ;;\nesac"),
+            "case $x in a|b) echo ab ;; *) echo c ;; esac"
+        );
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(Path::new("src"), Path::new("lib/libfoo.so")),
+            PathBuf::from("../lib/libfoo.so")
+        );
+        assert_eq!(
+            relative_path(Path::new(""), Path::new("libfoo.a")),
+            PathBuf::from("libfoo.a")
+        );
+    }
+}
@@ -0,0 +1,593 @@
+//! Cargo build scripts running the checks of `configure.ac`.
+//!
+//! A `-sys` crate vendoring a C library needs the config header the library
+//! was configured with. The generated `build.rs` compiles the test programs
+//! of the checks with the compiler of the `cc` crate, preceded by the
+//! results of the previous checks as `configure` does with `confdefs.h`:
+//! headers, types and sizes are compiled, functions and libraries are
+//! linked, and `AC_COMPILE_IFELSE` and its kin build the programs of
+//! `AC_LANG_PROGRAM`. The results and `AC_DEFINE` are written to the config
+//! header in `OUT_DIR`, and every `HAVE_` symbol is set as a cfg of the same
+//! name in lowercase. Macros and shell code which can not be translated are
+//! kept as `TODO` comments.
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::ast::node::{AcCommand, M4Argument, M4Macro, NodeId};
+use crate::ast::MayM4;
+use crate::project::{AutoconfFile, ProjectFile};
+
+use super::configure::{self, Names, AUTOTOOLS_ONLY};
+use super::Diagnostic;
+
+/// Macros with nothing to translate, as they only matter to the makefiles.
+const MAKEFILE_ONLY: &[&str] = &[
+    "AC_CONFIG_FILES",
+    "AC_CONFIG_HEADERS",
+    "AC_CONFIG_SUBDIRS",
+    "AC_INIT",
+    "AC_SUBST",
+    "AM_CONDITIONAL",
+    "AM_CONFIG_HEADER",
+];
+
+/// The helpers of the generated scripts.
+const PRELUDE: &str = r##"use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// The headers of `AC_INCLUDES_DEFAULT`.
+const DEFAULT_INCLUDES: &str = "\
+#include <stddef.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#ifdef HAVE_SYS_TYPES_H
+# include <sys/types.h>
+#endif
+#ifdef HAVE_SYS_STAT_H
+# include <sys/stat.h>
+#endif
+#ifdef HAVE_STRINGS_H
+# include <strings.h>
+#endif
+#ifdef HAVE_INTTYPES_H
+# include <inttypes.h>
+#endif
+#ifdef HAVE_STDINT_H
+# include <stdint.h>
+#endif
+#ifdef HAVE_UNISTD_H
+# include <unistd.h>
+#endif
+";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    Compile,
+    Link,
+    Run,
+}
+
+struct Config {
+    out_dir: PathBuf,
+    compiler: cc::Tool,
+    /// The results so far, as the lines of the config header.
+    defines: String,
+    /// The libraries found by the checks, in the order of `LIBS`.
+    libs: Vec<String>,
+    count: usize,
+}
+
+impl Config {
+    fn new() -> Self {
+        Config {
+            out_dir: PathBuf::from(env::var_os("OUT_DIR").unwrap()),
+            compiler: cc::Build::new().cargo_metadata(false).get_compiler(),
+            defines: String::new(),
+            libs: Vec::new(),
+            count: 0,
+        }
+    }
+
+    fn define(&mut self, name: &str, value: &str) {
+        self.defines.push_str(&format!("#define {} {}\n", name, value));
+    }
+
+    /// Define `name` to 1 and set its cfg when `found`.
+    fn have(&mut self, name: &str, found: bool) {
+        let cfg = name.to_lowercase();
+        println!("cargo:rustc-check-cfg=cfg({})", cfg);
+        if found {
+            self.define(name, "1");
+            println!("cargo:rustc-cfg={}", cfg);
+        } else {
+            self.defines.push_str(&format!("/* #undef {} */\n", name));
+        }
+    }
+
+    /// Build a test program after the results so far with the command line
+    /// of GCC and Clang, returning whether every step up to `stage` succeeds.
+    fn check(&mut self, source: &str, stage: Stage, libs: &[&str]) -> bool {
+        self.count += 1;
+        let dir = self.out_dir.join("conftest");
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join(format!("conftest{}.c", self.count));
+        let output = dir.join(format!("conftest{}{}", self.count, env::consts::EXE_SUFFIX));
+        fs::write(&input, format!("{}{}", self.defines, source)).unwrap();
+        let mut command = self.compiler.to_command();
+        if stage == Stage::Compile {
+            command.arg("-c");
+        }
+        command.arg(&input).arg("-o").arg(&output);
+        if stage != Stage::Compile {
+            for lib in libs.iter().copied().chain(self.libs.iter().map(String::as_str)) {
+                command.arg(format!("-l{}", lib));
+            }
+        }
+        let ok = |command: &mut Command| {
+            command
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success())
+        };
+        if !ok(&mut command) {
+            return false;
+        }
+        if stage != Stage::Run {
+            return true;
+        }
+        if env::var("HOST") != env::var("TARGET") {
+            println!("cargo:warning=test programs can not run when cross compiling");
+            return false;
+        }
+        ok(&mut Command::new(&output))
+    }
+
+    /// The size of a type, found with assertions on it as `configure` does
+    /// when cross compiling, or 0 if the type does not exist.
+    fn sizeof(&mut self, ty: &str, includes: &str) -> usize {
+        let assertion = |relation: &str, size: usize| {
+            format!(
+                "{}int\nmain (void)\n{{\nstatic int test_array [1 - 2 * !((long int) (sizeof ({})) {} {})];\ntest_array [0] = 0;\nreturn test_array [0];\n}}\n",
+                includes, ty, relation, size
+            )
+        };
+        if !self.check(&assertion(">", 0), Stage::Compile, &[]) {
+            return 0;
+        }
+        let (mut low, mut high) = (1, 1);
+        while !self.check(&assertion("<=", high), Stage::Compile, &[]) {
+            low = high + 1;
+            high *= 2;
+        }
+        while low < high {
+            let middle = (low + high) / 2;
+            if self.check(&assertion("<=", middle), Stage::Compile, &[]) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        low
+    }
+
+    /// Write the config header to `OUT_DIR` and link with the libraries
+    /// found.
+    fn write(self, header: &str) {
+        let path = self.out_dir.join(header);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &self.defines).unwrap();
+        for lib in &self.libs {
+            println!("cargo:rustc-link-lib={}", lib);
+        }
+        println!("cargo:include={}", path.parent().unwrap().display());
+    }
+}
+"##;
+
+/// A `build.rs` translated from a `configure.ac`.
+#[derive(Debug, Clone)]
+pub struct BuildScript {
+    /// The text of `build.rs`.
+    pub text: String,
+    /// The macros and shell code left as `TODO` comments.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl BuildScript {
+    /// Translate a `configure.ac`, or returns `None` for other files.
+    pub fn new(file: &ProjectFile) -> Option<Self> {
+        let ac = file.as_autoconf()?;
+        let mut translator = Translator {
+            file,
+            ac,
+            body: String::new(),
+            diagnostics: Vec::new(),
+        };
+        for id in &ac.top_ids {
+            translator.command(*id);
+        }
+        Some(translator.finish())
+    }
+
+    /// Write the script, usually to `build.rs`.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, &self.text)
+    }
+}
+
+/// The symbols an action defines when a check succeeds, or `Err` if the
+/// action does something else.
+type Found = Result<Option<Vec<(String, String)>>, ()>;
+
+/// The error an action raises when a check fails, or `Err` if the action
+/// does something else.
+type NotFound = Result<Option<String>, ()>;
+
+/// How the checks of a macro build their test programs.
+enum Check {
+    /// Whether a header compiles.
+    Header,
+    /// Whether a function links.
+    Function,
+    /// Whether a type exists.
+    Type,
+    /// The size of a type.
+    Sizeof,
+}
+
+struct Translator<'a> {
+    file: &'a ProjectFile,
+    ac: &'a AutoconfFile,
+    /// The statements of `main`.
+    body: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Translator<'_> {
+    fn command(&mut self, id: NodeId) {
+        let node = &self.ac.pool.nodes[id];
+        match &node.cmd {
+            AcCommand(MayM4::Macro(m4_macro)) => self.macro_call(id, m4_macro),
+            AcCommand(MayM4::Shell(_)) => self.todo(id, "shell code is not translated"),
+        }
+    }
+
+    fn macro_call(&mut self, id: NodeId, m4_macro: &M4Macro) {
+        let name = m4_macro.name.as_str();
+        match name {
+            "AC_DEFINE" | "AC_DEFINE_UNQUOTED" => match define(self.ac, m4_macro) {
+                Some((var, value)) => {
+                    self.quote(id);
+                    self.define(&var, &value, "true");
+                }
+                None => self.todo(id, "the value depends on the shell"),
+            },
+            "AC_COMPILE_IFELSE" | "AC_LINK_IFELSE" | "AC_RUN_IFELSE" => self.program(id, m4_macro),
+            "AC_ARG_ENABLE" | "AC_ARG_WITH" => self.todo(id, "options are not translated"),
+            _ if AUTOTOOLS_ONLY.contains(&name) || MAKEFILE_ONLY.contains(&name) => {}
+            _ => {
+                let probes = configure::probes(self.ac, m4_macro);
+                if probes.is_empty() {
+                    return self.todo(id, &format!("`{}` is not translated", name));
+                }
+                self.probes(id, m4_macro, &probes);
+            }
+        }
+    }
+
+    /// Checks of headers, functions, types and libraries, told apart by the
+    /// cache variables the macro sets.
+    fn probes(&mut self, id: NodeId, m4_macro: &M4Macro, probes: &[configure::Probe]) {
+        if probes.iter().any(|probe| probe.names == Names::Libraries) {
+            return self.library(id, m4_macro, probes);
+        }
+        let mut checks = Vec::new();
+        for probe in probes {
+            let cache_var = probe.cache_var.as_deref().unwrap_or_default();
+            let check = if cache_var.starts_with("ac_cv_header_") {
+                Check::Header
+            } else if cache_var.starts_with("ac_cv_func_") {
+                Check::Function
+            } else if cache_var.starts_with("ac_cv_type_") {
+                Check::Type
+            } else if cache_var.starts_with("ac_cv_sizeof_") {
+                Check::Sizeof
+            } else {
+                return self.todo(id, &format!("`{}` is not translated", m4_macro.name));
+            };
+            checks.push((check, probe));
+        }
+
+        self.quote(id);
+        let includes = configure::includes(m4_macro);
+        let source = |body: &str| match &includes {
+            Some(includes) => string(&format!("{}{}", includes, body)),
+            None => format!("&[DEFAULT_INCLUDES, {}].concat()", string(body)),
+        };
+        let (found, not_found) = self.actions(m4_macro, 1, 2);
+        for (check, probe) in checks {
+            let symbol = probe.symbol.as_deref();
+            let (source, stage) = match check {
+                Check::Header => (
+                    source(&format!("#include <{}>\n", probe.word)),
+                    "Compile",
+                ),
+                Check::Function => (string(&configure::function_call(&probe.word)), "Link"),
+                Check::Type => (
+                    source(&format!(
+                        "int\nmain (void)\n{{\nif (sizeof ({}))\n  return 0;\n  ;\n  return 0;\n}}\n",
+                        probe.word
+                    )),
+                    "Compile",
+                ),
+                Check::Sizeof => {
+                    let includes = includes
+                        .as_deref()
+                        .map_or("DEFAULT_INCLUDES".to_string(), string);
+                    self.line(&format!(
+                        "let size = config.sizeof({}, {});",
+                        string(&probe.word),
+                        includes
+                    ));
+                    if let Some(symbol) = symbol {
+                        self.line(&format!(
+                            "config.define({}, &size.to_string());",
+                            string(symbol)
+                        ));
+                    }
+                    continue;
+                }
+            };
+            let used = symbol.is_some() || uses_result(&found, &not_found);
+            self.line(&format!(
+                "{}config.check({}, Stage::{}, &[]);",
+                if used { "let found = " } else { "" },
+                source,
+                stage
+            ));
+            if let Some(symbol) = symbol {
+                self.line(&format!("config.have({}, found);", string(symbol)));
+            }
+            self.results(&found, &not_found);
+        }
+        if found.is_err() || not_found.is_err() {
+            self.todo(id, "the actions of the check are not translated");
+        }
+    }
+
+    /// `AC_CHECK_LIB([m], [cos])` links a call of `cos` with `-lm`, which
+    /// is added to the libraries unless the call has an action.
+    fn library(&mut self, id: NodeId, m4_macro: &M4Macro, probes: &[configure::Probe]) {
+        let Some(library) = probes.iter().find(|p| p.names == Names::Libraries) else {
+            return;
+        };
+        // `AC_HAVE_LIBRARY` is `AC_CHECK_LIB` without the function.
+        let (function, index, other_libraries) = if m4_macro.name == "AC_CHECK_LIB" {
+            let function = m4_macro.args.get(1).and_then(|arg| self.ac.render_arg(arg));
+            (function, 2, m4_macro.args.get(4))
+        } else {
+            (None, 1, m4_macro.args.get(3))
+        };
+        let function = function
+            .filter(|f| !f.is_empty())
+            .unwrap_or_else(|| "main".to_string());
+        let mut libs = vec![string(&library.word)];
+        if let Some(other) = other_libraries.and_then(|arg| self.ac.render_arg(arg)) {
+            libs.extend(
+                other
+                    .split_whitespace()
+                    .filter_map(|l| l.strip_prefix("-l"))
+                    .map(string),
+            );
+        }
+
+        self.quote(id);
+        self.line(&format!(
+            "let found = config.check({}, Stage::Link, &[{}]);",
+            string(&configure::function_call(&function)),
+            libs.join(", ")
+        ));
+        let (found, not_found) = self.actions(m4_macro, index, index + 1);
+        match &found {
+            Ok(None) => {
+                if let Some(symbol) = &library.symbol {
+                    self.line(&format!("config.have({}, found);", string(symbol)));
+                }
+                self.line(&format!(
+                    "if found {{\n    config.libs.insert(0, {}.to_string());\n}}",
+                    string(&library.word)
+                ));
+            }
+            _ => self.results(&found, &Ok(None)),
+        }
+        self.results(&Ok(None), &not_found);
+        if found.is_err() || not_found.is_err() {
+            self.todo(id, "the actions of the check are not translated");
+        }
+    }
+
+    /// `AC_COMPILE_IFELSE([AC_LANG_PROGRAM(...)], [AC_DEFINE(...)])` builds
+    /// the program, defining the symbols of the action when it succeeds.
+    fn program(&mut self, id: NodeId, m4_macro: &M4Macro) {
+        let Some(M4Argument::Program(text)) = m4_macro.args.first() else {
+            return self.todo(id, "the arguments are not understood");
+        };
+        let stage = match m4_macro.name.as_str() {
+            "AC_COMPILE_IFELSE" => "Compile",
+            "AC_LINK_IFELSE" => "Link",
+            _ => "Run",
+        };
+        let (found, not_found) = self.actions(m4_macro, 1, 2);
+        let used = uses_result(&found, &not_found);
+        self.quote(id);
+        self.line(&format!(
+            "{}config.check({}, Stage::{}, &[]);",
+            if used { "let found = " } else { "" },
+            string(&configure::program_source(text)),
+            stage
+        ));
+        self.results(&found, &not_found);
+        if found.is_err() || not_found.is_err() {
+            self.todo(id, "the actions of the check are not translated");
+        }
+    }
+
+    /// The actions of a check, given the indexes of their arguments.
+    fn actions(&self, m4_macro: &M4Macro, found: usize, not_found: usize) -> (Found, NotFound) {
+        let commands = |index: usize| match m4_macro.args.get(index) {
+            Some(M4Argument::Commands(cmds)) => Ok(cmds
+                .iter()
+                .map(|id| match &self.ac.pool.nodes[*id].cmd {
+                    AcCommand(MayM4::Macro(m4_macro)) => Some(m4_macro),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or(())?),
+            _ if configure::has_arg(self.ac, m4_macro, index) => Err(()),
+            _ => Ok(Vec::new()),
+        };
+        let found = commands(found).and_then(|macros| {
+            if macros.is_empty() {
+                return Ok(None);
+            }
+            macros
+                .into_iter()
+                .map(|m| match m.name.as_str() {
+                    "AC_DEFINE" | "AC_DEFINE_UNQUOTED" => define(self.ac, m).ok_or(()),
+                    _ => Err(()),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Some)
+        });
+        let not_found = commands(not_found).and_then(|macros| match macros.as_slice() {
+            [] => Ok(None),
+            [m] if m.name == "AC_MSG_ERROR" || m.name == "AC_MSG_FAILURE" => Ok(Some(
+                m.args
+                    .first()
+                    .and_then(|arg| self.ac.render_arg(arg))
+                    .unwrap_or_default(),
+            )),
+            _ => Err(()),
+        });
+        (found, not_found)
+    }
+
+    /// Define the symbols of the action if found, and raise the error of
+    /// the action if not found.
+    fn results(&mut self, found: &Found, not_found: &NotFound) {
+        if let Ok(Some(defines)) = found {
+            for (var, value) in defines {
+                self.define(var, value, "found");
+            }
+        }
+        if let Ok(Some(message)) = not_found {
+            self.line(&format!(
+                "if !found {{\n    panic!(\"{{}}\", {});\n}}",
+                string(message)
+            ));
+        }
+    }
+
+    /// Define a symbol when `cond` holds, `HAVE_` symbols being set as
+    /// cfgs too.
+    fn define(&mut self, var: &str, value: &str, cond: &str) {
+        if var.starts_with("HAVE_") && value == "1" {
+            self.line(&format!("config.have({}, {});", string(var), cond));
+        } else if cond == "true" {
+            self.line(&format!(
+                "config.define({}, {});",
+                string(var),
+                string(value)
+            ));
+        } else {
+            self.line(&format!(
+                "if {} {{\n    config.define({}, {});\n}}",
+                cond,
+                string(var),
+                string(value)
+            ));
+        }
+    }
+
+    /// Separate a translated command by a comment quoting its lines.
+    fn quote(&mut self, id: NodeId) {
+        let node = &self.ac.pool.nodes[id];
+        self.body.push('\n');
+        if let (Some(start), Some(end)) = (node.range_start(), node.range_end()) {
+            for line in self.file.source.lines().skip(start - 1).take(end - start) {
+                self.line(format!("// {}", line).trim_end());
+            }
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        for line in line.lines() {
+            self.body.push_str("    ");
+            self.body.push_str(line);
+            self.body.push('\n');
+        }
+    }
+
+    /// Quote the lines of a command as a `TODO` comment.
+    fn todo(&mut self, id: NodeId, message: &str) {
+        let (comment, diagnostic) =
+            configure::todo(self.file, &self.ac.pool.nodes[id], "//", message);
+        self.body.push('\n');
+        self.line(&comment);
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn finish(self) -> BuildScript {
+        let mut text = format!("// Generated from {}.\n", self.file.path.display());
+        text.push_str(PRELUDE);
+        text.push_str("\nfn main() {\n    let mut config = Config::new();\n");
+        for (var, value) in configure::package_values(self.ac) {
+            text.push_str(&format!(
+                "    config.define({}, {});\n",
+                string(var),
+                string(&configure::c_string(&value))
+            ));
+        }
+        text.push_str(&self.body);
+        let header = configure::config_header(self.ac)
+            .map_or("config.h".to_string(), |h| h.display().to_string());
+        text.push_str(&format!("\n    config.write({});\n}}\n", string(&header)));
+        BuildScript {
+            text,
+            diagnostics: self.diagnostics,
+        }
+    }
+}
+
+/// The symbol and value of `AC_DEFINE`, unless the value depends on the
+/// shell.
+fn define(ac: &AutoconfFile, m4_macro: &M4Macro) -> Option<(String, String)> {
+    let arg = |i: usize| {
+        m4_macro
+            .args
+            .get(i)
+            .and_then(|arg| ac.render_arg(arg))
+            .filter(|arg| !arg.is_empty())
+    };
+    let var = arg(0)?;
+    let value = arg(1).unwrap_or_else(|| "1".to_string());
+    if m4_macro.name == "AC_DEFINE_UNQUOTED" && value.contains(['$', '`']) {
+        return None;
+    }
+    Some((var, value))
+}
+
+/// Whether the translated actions of a check need its result.
+fn uses_result(found: &Found, not_found: &NotFound) -> bool {
+    matches!(found, Ok(Some(_))) || matches!(not_found, Ok(Some(_)))
+}
+
+/// A Rust string literal on one line.
+fn string(value: &str) -> String {
+    format!("\"{}\"", value.escape_default())
+}
@@ -0,0 +1,461 @@
+//! `CMakeLists.txt` skeletons from `configure.ac`.
+//!
+//! The checks of `configure.ac` have counterparts in the modules shipped
+//! with CMake: headers, functions and libraries are checked with
+//! `check_include_file`, `check_symbol_exists` and `check_library_exists`,
+//! `AC_ARG_ENABLE` and `AC_ARG_WITH` become options and `PKG_CHECK_MODULES`
+//! uses `FindPkgConfig`. The results and `AC_DEFINE` go to a template of the
+//! config header for `configure_file`, and automake conditionals become
+//! variables. Macros and shell code which can not be translated are kept as
+//! `TODO` comments quoting the lines of `configure.ac`.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::ast::node::{AcCommand, M4Macro, NodeId};
+use crate::ast::MayM4;
+use crate::project::{AutoconfFile, ProjectFile};
+
+use super::configure::{self, Names, AUTOTOOLS_ONLY};
+use super::Diagnostic;
+
+/// The headers of `AC_INCLUDES_DEFAULT`.
+const DEFAULT_INCLUDES: &[&str] = &[
+    "stdio.h",
+    "stdlib.h",
+    "string.h",
+    "inttypes.h",
+    "stdint.h",
+    "strings.h",
+    "sys/stat.h",
+    "sys/types.h",
+    "unistd.h",
+];
+
+/// A `CMakeLists.txt` translated from a `configure.ac`.
+#[derive(Debug, Clone)]
+pub struct CMakeLists {
+    /// The text of `CMakeLists.txt`.
+    pub text: String,
+    /// The config header of `AC_CONFIG_HEADERS` and the text of its template
+    /// for `configure_file`, named after the header with a `.cmake` suffix.
+    pub config_header: Option<(PathBuf, String)>,
+    /// The macros and shell code left as `TODO` comments.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CMakeLists {
+    /// Translate a `configure.ac`, or returns `None` for other files.
+    pub fn new(file: &ProjectFile) -> Option<Self> {
+        let ac = file.as_autoconf()?;
+        let mut translator = Translator {
+            file,
+            ac,
+            modules: Vec::new(),
+            body: String::new(),
+            subdirs: Vec::new(),
+            headers: Vec::new(),
+            defines: Vec::new(),
+            options: BTreeMap::new(),
+            diagnostics: Vec::new(),
+        };
+        for id in &ac.top_ids {
+            translator.command(*id);
+        }
+        Some(translator.finish())
+    }
+
+    /// Write `CMakeLists.txt` and the template of the config header to a
+    /// directory.
+    pub fn write<P: AsRef<Path>>(&self, dir: P) -> io::Result<()> {
+        let dir = dir.as_ref();
+        fs::write(dir.join("CMakeLists.txt"), &self.text)?;
+        if let Some((header, template)) = &self.config_header {
+            fs::write(dir.join(template_name(header)), template)?;
+        }
+        Ok(())
+    }
+}
+
+/// `config.h` is configured from `config.h.cmake`.
+fn template_name(header: &Path) -> PathBuf {
+    let mut name = header.as_os_str().to_owned();
+    name.push(".cmake");
+    PathBuf::from(name)
+}
+
+struct Translator<'a> {
+    file: &'a ProjectFile,
+    ac: &'a AutoconfFile,
+    /// The CMake modules to load, in the order of first use.
+    modules: Vec<&'static str>,
+    body: String,
+    /// The directories of `AC_CONFIG_FILES` and `AC_CONFIG_SUBDIRS`, added
+    /// once the config header is set up for them.
+    subdirs: Vec<String>,
+    /// The headers checked so far, which declare the functions checked
+    /// after them.
+    headers: Vec<String>,
+    /// Lines of the config header template.
+    defines: Vec<String>,
+    /// Shell variables set by `AC_ARG_ENABLE` and `AC_ARG_WITH`, with the
+    /// names of their options.
+    options: BTreeMap<String, String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Translator<'_> {
+    fn command(&mut self, id: NodeId) {
+        let node = &self.ac.pool.nodes[id];
+        match &node.cmd {
+            AcCommand(MayM4::Macro(m4_macro)) => self.macro_call(id, m4_macro),
+            AcCommand(MayM4::Shell(_)) => self.todo(id, "shell code is not translated"),
+        }
+    }
+
+    fn macro_call(&mut self, id: NodeId, m4_macro: &M4Macro) {
+        let arg = |i: usize| {
+            m4_macro
+                .args
+                .get(i)
+                .and_then(|arg| self.ac.render_arg(arg))
+                .filter(|arg| !arg.is_empty())
+        };
+        let has_action = |i: usize| configure::has_arg(self.ac, m4_macro, i);
+        let name = m4_macro.name.as_str();
+        match name {
+            "AC_CHECK_HEADERS" | "AC_CHECK_HEADER" => {
+                self.module("include(CheckIncludeFile)");
+                for probe in configure::probes(self.ac, m4_macro) {
+                    let var = have(&probe.word);
+                    self.line(&format!("check_include_file({} {})", probe.word, var));
+                    if let Some(symbol) = probe.symbol {
+                        self.defines.push(format!("#cmakedefine {} 1", symbol));
+                    }
+                    self.header(&probe.word);
+                }
+                if has_action(1) || has_action(2) {
+                    self.todo(id, "the actions of the check are not translated");
+                }
+            }
+            "AC_CHECK_FUNCS" | "AC_CHECK_FUNC" => {
+                // Without a known header, link against the function as
+                // autoconf does.
+                let headers = self.headers.join(";");
+                if headers.is_empty() {
+                    self.module("include(CheckFunctionExists)");
+                } else {
+                    self.module("include(CheckSymbolExists)");
+                }
+                for probe in configure::probes(self.ac, m4_macro) {
+                    let var = have(&probe.word);
+                    if headers.is_empty() {
+                        self.line(&format!("check_function_exists({} {})", probe.word, var));
+                    } else {
+                        self.line(&format!(
+                            "check_symbol_exists({} \"{}\" {})",
+                            probe.word, headers, var
+                        ));
+                    }
+                    if let Some(symbol) = probe.symbol {
+                        self.defines.push(format!("#cmakedefine {} 1", symbol));
+                    }
+                }
+                if has_action(1) || has_action(2) {
+                    self.todo(id, "the actions of the check are not translated");
+                }
+            }
+            "AC_CHECK_LIB" => {
+                let library = configure::probes(self.ac, m4_macro)
+                    .into_iter()
+                    .find(|probe| probe.names == Names::Libraries);
+                let (Some(library), Some(function)) = (library, arg(1)) else {
+                    return self.todo(id, "the arguments are not understood");
+                };
+                self.module("include(CheckLibraryExists)");
+                let var = library
+                    .symbol
+                    .unwrap_or_else(|| have(&format!("lib{}", library.word)));
+                let library = library.word;
+                self.line(&format!(
+                    "check_library_exists({} {} \"\" {})",
+                    library, function, var
+                ));
+                if has_action(2) {
+                    self.todo(id, "the action if found is not translated");
+                } else {
+                    self.line(&format!(
+                        "if({})\n  list(APPEND LIBS {})\nendif()",
+                        var, library
+                    ));
+                    self.defines.push(format!("#cmakedefine {} 1", var));
+                }
+                if has_action(3) {
+                    self.todo(id, "the action if not found is not translated");
+                }
+            }
+            "AC_ARG_ENABLE" | "AC_ARG_WITH" => self.option(id, m4_macro),
+            "PKG_CHECK_MODULES" => {
+                let (Some(prefix), Some(modules)) = (arg(0), arg(1)) else {
+                    return self.todo(id, "the arguments are not understood");
+                };
+                self.module("find_package(PkgConfig REQUIRED)");
+                let required = if has_action(3) { "" } else { " REQUIRED" };
+                self.line(&format!(
+                    "pkg_check_modules({}{} {})",
+                    prefix,
+                    required,
+                    configure::pkg_modules(&modules)
+                        .into_iter()
+                        .map(|(module, constraint)| module + &constraint)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                ));
+                if has_action(2) || has_action(3) {
+                    self.todo(id, "the actions of the check are not translated");
+                }
+            }
+            "AC_DEFINE" | "AC_DEFINE_UNQUOTED" => {
+                let Some(var) = arg(0) else {
+                    return self.todo(id, "the arguments are not understood");
+                };
+                let value = arg(1).unwrap_or_else(|| "1".to_string());
+                if name == "AC_DEFINE_UNQUOTED" && value.contains(['$', '`']) {
+                    return self.todo(id, "the value depends on the shell");
+                }
+                self.line(&format!("set({} {})", var, quote(&value)));
+                if let Some(description) = arg(2) {
+                    self.defines.push(format!("/* {} */", description));
+                }
+                self.defines.push(format!("#cmakedefine {} @{}@", var, var));
+            }
+            "AC_SUBST" => match (arg(0), arg(1)) {
+                (Some(var), Some(value)) if !value.contains(['$', '`']) => {
+                    self.line(&format!("set({} {})", var, quote(&value)))
+                }
+                (Some(_), Some(_)) => self.todo(id, "the value depends on the shell"),
+                _ => {}
+            },
+            "AM_CONDITIONAL" => self.conditional(id, m4_macro),
+            "AC_CONFIG_FILES" => {
+                for output in arg(0).unwrap_or_default().split_whitespace() {
+                    let (output, input) = match output.split_once(':') {
+                        Some((output, input)) => (output.to_string(), input.to_string()),
+                        None => (output.to_string(), format!("{}.in", output)),
+                    };
+                    match output.strip_suffix("Makefile") {
+                        Some("") => {}
+                        Some(dir) => self.subdirs.push(dir.trim_end_matches('/').to_string()),
+                        None => {
+                            self.line(&format!("configure_file({} {} @ONLY)", input, output));
+                        }
+                    }
+                }
+            }
+            "AC_CONFIG_SUBDIRS" => {
+                let dirs = arg(0).unwrap_or_default();
+                self.subdirs
+                    .extend(dirs.split_whitespace().map(String::from));
+            }
+            "AC_CHECK_INCLUDES_DEFAULT" | "AC_HEADER_STDC" => {
+                for header in DEFAULT_INCLUDES {
+                    self.header(header);
+                }
+            }
+            "AC_INIT" | "AC_CONFIG_HEADERS" | "AM_CONFIG_HEADER" => {}
+            _ if AUTOTOOLS_ONLY.contains(&name) => {}
+            _ => self.todo(id, &format!("`{}` is not translated", name)),
+        }
+    }
+
+    /// `AC_ARG_ENABLE([foo], [help])` is the option `ENABLE_FOO`, set in the
+    /// shell variable `enable_foo`.
+    fn option(&mut self, id: NodeId, m4_macro: &M4Macro) {
+        let Some(option) = configure::configure_option(self.ac, m4_macro) else {
+            return self.todo(id, "the arguments are not understood");
+        };
+        let name = option.shell_var.to_uppercase();
+        self.line(&format!(
+            "option({} {} {})",
+            name,
+            quote(&option.help),
+            if option.default { "ON" } else { "OFF" }
+        ));
+        if !option.plain {
+            self.todo(id, "the actions of the option are not translated");
+        }
+        self.options.insert(option.shell_var, name);
+    }
+
+    /// `AM_CONDITIONAL([NAME], [test "x$enable_foo" = xyes])` is the
+    /// variable `NAME`, following the option the shell variable belongs to.
+    fn conditional(&mut self, id: NodeId, m4_macro: &M4Macro) {
+        let Some(name) = m4_macro.args.first().and_then(|a| self.ac.render_arg(a)) else {
+            return self.todo(id, "the arguments are not understood");
+        };
+        let test = configure::commands(self.ac, m4_macro.args.get(1)).unwrap_or_default();
+        let cond = configure::shell_test(&test).and_then(|(var, holds)| {
+            let option = self.options.get(&var)?;
+            Some(if holds {
+                option.clone()
+            } else {
+                format!("NOT {}", option)
+            })
+        });
+        match cond {
+            // The option is the variable already.
+            Some(cond) if cond == name => {}
+            Some(cond) => self.line(&format!(
+                "if({})\n  set({} ON)\nelse()\n  set({} OFF)\nendif()",
+                cond, name, name
+            )),
+            None => {
+                self.line(&format!("set({} OFF)", name));
+                self.todo(id, "the condition is not translated");
+            }
+        }
+    }
+
+    fn module(&mut self, module: &'static str) {
+        if !self.modules.contains(&module) {
+            self.modules.push(module);
+        }
+    }
+
+    fn header(&mut self, header: &str) {
+        if !self.headers.iter().any(|h| h == header) {
+            self.headers.push(header.to_string());
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        self.body.push_str(line);
+        self.body.push('\n');
+    }
+
+    /// Quote the lines of a command as a `TODO` comment.
+    fn todo(&mut self, id: NodeId, message: &str) {
+        let (comment, diagnostic) =
+            configure::todo(self.file, &self.ac.pool.nodes[id], "#", message);
+        self.body.push_str(&comment);
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn finish(self) -> CMakeLists {
+        let ac = self.ac;
+        let name = ac
+            .macro_arg("AC_INIT", 0)
+            .unwrap_or_else(|| "project".to_string());
+        let version = ac.macro_arg("AC_INIT", 1).unwrap_or_default();
+        let mut languages = Vec::new();
+        if ac.calls_macro("AC_PROG_CC") || !ac.calls_macro("AC_PROG_CXX") {
+            languages.push("C");
+        }
+        if ac.calls_macro("AC_PROG_CXX") {
+            languages.push("CXX");
+        }
+
+        let mut text = "cmake_minimum_required(VERSION 3.10)\n".to_string();
+        let numeric = !version.is_empty()
+            && version.split('.').all(|n| n.parse::<u32>().is_ok())
+            && version.split('.').count() <= 4;
+        text.push_str(&format!(
+            "project({}{} LANGUAGES {})\n\n",
+            quote(&name),
+            if numeric {
+                format!(" VERSION {}", version)
+            } else {
+                String::new()
+            },
+            languages.join(" ")
+        ));
+        for module in &self.modules {
+            text.push_str(module);
+            text.push('\n');
+        }
+        if !self.modules.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&self.body);
+
+        let header = configure::config_header(ac);
+        let config_header = header.map(|header| {
+            let template = template_name(&header);
+            text.push_str(&format!(
+                "\nconfigure_file({} {})\n",
+                template.display(),
+                header.display()
+            ));
+            text.push_str("include_directories(${CMAKE_CURRENT_BINARY_DIR})\n");
+            text.push_str("add_compile_definitions(HAVE_CONFIG_H)\n");
+
+            let mut lines = Vec::new();
+            for (var, value) in configure::package_values(ac) {
+                lines.push(format!("#define {} {}", var, configure::c_string(&value)));
+            }
+            lines.extend(self.defines.iter().cloned());
+            (header, lines.join("\n") + "\n")
+        });
+        if !self.subdirs.is_empty() {
+            text.push('\n');
+        }
+        // Only the top directory is translated, so the subdirectories are
+        // left for the user to add.
+        let mut diagnostics = self.diagnostics;
+        for dir in &self.subdirs {
+            let message = format!("`{}` has no CMakeLists.txt", dir);
+            text.push_str(&format!(
+                "# TODO {}: {}\n# add_subdirectory({})\n",
+                self.file.path.display(),
+                message,
+                dir
+            ));
+            diagnostics.push(Diagnostic {
+                makefile: self.file.path.clone(),
+                message,
+            });
+        }
+        CMakeLists {
+            text,
+            config_header,
+            diagnostics,
+        }
+    }
+}
+
+/// The variable autoconf defines when `name` is found, e.g. `HAVE_SYS_TYPES_H`.
+fn have(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("HAVE_{}", name)
+}
+
+/// Quote a CMake argument if needed.
+fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || "\"\\$;#()".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        let mut ret = String::from("\"");
+        for c in value.chars() {
+            if matches!(c, '"' | '\\' | '$') {
+                ret.push('\\');
+            }
+            ret.push(c);
+        }
+        ret.push('"');
+        ret
+    }
+}
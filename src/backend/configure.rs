@@ -0,0 +1,465 @@
+//! Reading `configure.ac` for the backends translating it and the tools
+//! listing what it checks.
+//!
+//! What a macro call checks or declares is read from the side effects its
+//! `M4MacroSignature` gives to each argument, e.g. the `HAVE_` symbol and
+//! the `ac_cv_header_` cache variable of a header.
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::ast::minimal::Word;
+use crate::ast::node::{DisplayNode, M4Argument, M4Macro, Node};
+use crate::ast::MayM4;
+use std::path::PathBuf;
+
+use crate::analysis::install::tarname_of;
+use crate::m4_macro::{self, M4ExportType, M4Type, VarKind};
+use crate::project::{AutoconfFile, ProjectFile};
+
+use super::Diagnostic;
+
+lazy_static! {
+    /// `test "x$enable_foo" = xyes` and its variants.
+    static ref SHELL_TEST: Regex = Regex::new(
+        r#"^test +"?x?\$\{?([A-Za-z_][A-Za-z0-9_]*)\}?"? *(=|!=) *"?x?(yes|no)"?$"#
+    )
+    .unwrap();
+}
+
+/// Macros with nothing to translate, as the other build systems do the same
+/// by themselves or the macro only matters to the autotools.
+pub(crate) const AUTOTOOLS_ONLY: &[&str] = &[
+    "AC_CANONICAL_BUILD",
+    "AC_CANONICAL_HOST",
+    "AC_CANONICAL_TARGET",
+    "AC_CONFIG_AUX_DIR",
+    "AC_CONFIG_MACRO_DIR",
+    "AC_CONFIG_MACRO_DIRS",
+    "AC_CONFIG_SRCDIR",
+    "AC_HEADER_STDC",
+    "AC_LANG",
+    "AC_OUTPUT",
+    "AC_PREREQ",
+    "AC_PROG_CC",
+    "AC_PROG_CC_C99",
+    "AC_PROG_CPP",
+    "AC_PROG_CXX",
+    "AC_PROG_INSTALL",
+    "AC_PROG_LIBTOOL",
+    "AC_PROG_LN_S",
+    "AC_PROG_MAKE_SET",
+    "AC_PROG_RANLIB",
+    "AM_INIT_AUTOMAKE",
+    "AM_MAINTAINER_MODE",
+    "AM_PROG_AR",
+    "AM_PROG_CC_C_O",
+    "AM_SILENT_RULES",
+    "LT_INIT",
+    "PKG_PROG_PKG_CONFIG",
+];
+
+/// What the words of an argument name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Names {
+    /// Files, e.g. headers.
+    Paths,
+    /// C types.
+    Types,
+    /// Shell variables, or the part of their names given by the argument.
+    Variables,
+    /// Libraries to link with.
+    Libraries,
+    /// C functions or declarations.
+    Symbols,
+}
+
+/// A value a macro call exports for one word of an argument.
+#[derive(Debug, Clone)]
+pub(crate) struct Export {
+    /// The index of the argument.
+    pub arg: usize,
+    /// What the argument names.
+    pub names: Names,
+    /// The word the value is derived from, e.g. `stdio.h`.
+    pub word: String,
+    /// What the value is.
+    pub kind: M4ExportType,
+    /// The value, e.g. `HAVE_STDIO_H`.
+    pub name: String,
+}
+
+/// The values exported by the arguments of a macro call, in the order of
+/// the arguments and their words.
+pub(crate) fn exports(ac: &AutoconfFile, m4_macro: &M4Macro) -> Vec<Export> {
+    let Some((_, signature, _)) = m4_macro::get_macro(&m4_macro.name) else {
+        return Vec::new();
+    };
+    let mut ret = Vec::new();
+    for (i, arg_type) in signature.arg_types.iter().enumerate() {
+        use M4Type::*;
+        let (names, func) = match arg_type {
+            Path(Some(f)) | Paths(_, Some(f)) => (Names::Paths, f),
+            Type(Some(f)) | Types(_, Some(f)) => (Names::Types, f),
+            VarName(_, Some(f)) => (Names::Variables, f),
+            Library(Some(f)) => (Names::Libraries, f),
+            Symbol(Some(f)) | Symbols(_, Some(f)) => (Names::Symbols, f),
+            _ => continue,
+        };
+        let Some(words) = m4_macro.args.get(i).and_then(|arg| ac.render_arg(arg)) else {
+            continue;
+        };
+        for word in words.split_whitespace() {
+            for (kind, name) in func(word) {
+                ret.push(Export {
+                    arg: i,
+                    names,
+                    word: word.to_string(),
+                    kind,
+                    name,
+                });
+            }
+        }
+    }
+    ret
+}
+
+/// Something a macro call checks for, such as a header.
+#[derive(Debug, Clone)]
+pub struct Probe {
+    /// What is checked for.
+    pub names: Names,
+    /// The name of what is checked for, e.g. `stdio.h`.
+    pub word: String,
+    /// The cache variable of the result, e.g. `ac_cv_header_stdio_h`.
+    pub cache_var: Option<String>,
+    /// The preprocessor symbol defined when it is found, e.g. `HAVE_STDIO_H`.
+    pub symbol: Option<String>,
+}
+
+/// The headers, functions, libraries and types a macro call checks for,
+/// with the symbols the call defines for them.
+pub fn probes(ac: &AutoconfFile, m4_macro: &M4Macro) -> Vec<Probe> {
+    let mut ret: Vec<(usize, Probe)> = Vec::new();
+    for export in exports(ac, m4_macro) {
+        if export.names == Names::Variables {
+            continue;
+        }
+        let i = match ret
+            .iter()
+            .position(|(arg, probe)| *arg == export.arg && probe.word == export.word)
+        {
+            Some(i) => i,
+            None => {
+                ret.push((
+                    export.arg,
+                    Probe {
+                        names: export.names,
+                        word: export.word,
+                        cache_var: None,
+                        symbol: None,
+                    },
+                ));
+                ret.len() - 1
+            }
+        };
+        match export.kind {
+            M4ExportType::ExCPP => ret[i].1.symbol = Some(export.name),
+            M4ExportType::ExVar(_) => ret[i].1.cache_var = Some(export.name),
+            _ => {}
+        }
+    }
+    ret.into_iter().map(|(_, probe)| probe).collect()
+}
+
+/// Returns whether an argument is given and not empty.
+pub(crate) fn has_arg(ac: &AutoconfFile, m4_macro: &M4Macro, index: usize) -> bool {
+    match m4_macro.args.get(index) {
+        Some(M4Argument::Commands(cmds)) => !cmds.is_empty(),
+        Some(arg) => ac.render_arg(arg).is_some_and(|a| !a.is_empty()),
+        None => false,
+    }
+}
+
+/// Render an argument holding shell commands, one command per line.
+pub(crate) fn commands(ac: &AutoconfFile, arg: Option<&M4Argument>) -> Option<String> {
+    match arg? {
+        M4Argument::Commands(cmds) if cmds.is_empty() => None,
+        M4Argument::Commands(cmds) => Some(
+            cmds.iter()
+                .map(|c| ac.pool.display_node(*c, 0))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        arg => ac.render_arg(arg).filter(|a| !a.is_empty()),
+    }
+}
+
+/// An option of `configure` declared with `AC_ARG_ENABLE` or `AC_ARG_WITH`.
+#[derive(Debug, Clone)]
+pub struct ConfigureOption {
+    /// The feature or package, e.g. `foo` for `--enable-foo`.
+    pub name: String,
+    /// The shell variable holding the answer, e.g. `enable_foo`.
+    pub shell_var: String,
+    /// The description of the help string.
+    pub help: String,
+    /// Whether the option is enabled unless it is given.
+    pub default: bool,
+    /// Whether the actions only copy the answer and give the default, which
+    /// is all the translation keeps.
+    pub plain: bool,
+}
+
+/// Read a macro call declaring an option, recognized by the input variable
+/// its first argument exports.
+pub fn configure_option(ac: &AutoconfFile, m4_macro: &M4Macro) -> Option<ConfigureOption> {
+    let export = exports(ac, m4_macro).into_iter().find(|export| {
+        export.arg == 0
+            && matches!(export.kind, M4ExportType::ExVar(attrs) if attrs.kind == VarKind::Input)
+    })?;
+    // The answer is given to the actions in e.g. `enableval`.
+    let (_, signature, _) = m4_macro::get_macro(&m4_macro.name)?;
+    let answer = signature
+        .shell_vars
+        .as_ref()
+        .and_then(|vars| vars.iter().find(|var| var.name.ends_with("val")))
+        .map_or(String::new(), |var| var.name.clone());
+
+    let help = match m4_macro.args.get(1) {
+        Some(M4Argument::Word(word)) => match &word.0 {
+            Word::Single(MayM4::Macro(help)) if help.name == "AS_HELP_STRING" => {
+                help.args.get(1).and_then(|a| ac.render_arg(a))
+            }
+            _ => Some(ac.pool.display_word(word, false)),
+        },
+        Some(arg) => ac.render_arg(arg),
+        None => None,
+    }
+    .unwrap_or_default();
+
+    let shell_var = export.name;
+    let value = |cmd: &str| {
+        cmd.strip_prefix(&format!("{}=", shell_var))
+            .map(|value| value.trim_matches('"').replace(['{', '}'], ""))
+    };
+    let given = commands(ac, m4_macro.args.get(2));
+    let not_given = commands(ac, m4_macro.args.get(3));
+    let default = not_given.as_deref().and_then(value);
+    let plain = given
+        .as_deref()
+        .is_none_or(|cmd| value(cmd) == Some(format!("${}", answer)))
+        && (not_given.is_none() || matches!(default.as_deref(), Some("yes" | "no")));
+    Some(ConfigureOption {
+        name: export.word,
+        shell_var,
+        help,
+        default: default.as_deref() == Some("yes"),
+        plain,
+    })
+}
+
+/// The modules of `PKG_CHECK_MODULES` with their version constraints, e.g.
+/// `glib-2.0 >= 2.40` is `("glib-2.0", ">=2.40")`.
+pub fn pkg_modules(modules: &str) -> Vec<(String, String)> {
+    let mut ret: Vec<(String, String)> = Vec::new();
+    let mut attach = false;
+    for word in modules.split_whitespace() {
+        let operator = word.chars().all(|c| matches!(c, '<' | '>' | '=' | '!'));
+        match ret.last_mut() {
+            Some((_, constraint)) if operator || attach => constraint.push_str(word),
+            _ => ret.push((word.to_string(), String::new())),
+        }
+        attach = operator;
+    }
+    ret
+}
+
+/// Read a shell test such as `test "x$enable_foo" = xyes`, returning the
+/// variable and whether the test holds when it is `yes`.
+pub(crate) fn shell_test(test: &str) -> Option<(String, bool)> {
+    let captures = SHELL_TEST.captures(test.trim())?;
+    let holds = (&captures[2] == "=") == (&captures[3] == "yes");
+    Some((captures[1].to_string(), holds))
+}
+
+/// A comment on a command which is not translated, quoting its lines after
+/// the comment marker `comment`, and the diagnostic reporting it.
+pub(crate) fn todo<C, U>(
+    file: &ProjectFile,
+    node: &Node<C, U>,
+    comment: &str,
+    message: &str,
+) -> (String, Diagnostic) {
+    let path = file.path.display();
+    let mut text = match node.range_start() {
+        Some(line) => format!("{} TODO {}:{}: {}\n", comment, path, line, message),
+        None => format!("{} TODO {}: {}\n", comment, path, message),
+    };
+    if let (Some(start), Some(end)) = (node.range_start(), node.range_end()) {
+        for line in file.source.lines().skip(start - 1).take(end - start) {
+            text.push_str(format!("{}   {}", comment, line).trim_end());
+            text.push('\n');
+        }
+    }
+    let message = match node.range_start() {
+        Some(line) => format!("line {}: {}", line, message),
+        None => message.to_string(),
+    };
+    let diagnostic = Diagnostic {
+        makefile: file.path.clone(),
+        message,
+    };
+    (text, diagnostic)
+}
+
+/// The values of the `PACKAGE_` symbols defined from `AC_INIT`, along with
+/// `PACKAGE` and `VERSION` when automake is used.
+pub(crate) fn package_values(ac: &AutoconfFile) -> Vec<(&'static str, String)> {
+    let name = ac
+        .macro_arg("AC_INIT", 0)
+        .unwrap_or_else(|| "project".to_string());
+    let version = ac.macro_arg("AC_INIT", 1).unwrap_or_default();
+    let tarname = ac
+        .macro_arg("AC_INIT", 3)
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| tarname_of(&name));
+    let mut ret = vec![
+        ("PACKAGE_NAME", name.clone()),
+        ("PACKAGE_TARNAME", tarname.clone()),
+        ("PACKAGE_VERSION", version.clone()),
+        ("PACKAGE_STRING", format!("{} {}", name, version)),
+        (
+            "PACKAGE_BUGREPORT",
+            ac.macro_arg("AC_INIT", 2).unwrap_or_default(),
+        ),
+    ];
+    if ac.calls_macro("AM_INIT_AUTOMAKE") {
+        ret.push(("PACKAGE", tarname));
+        ret.push(("VERSION", version));
+    }
+    ret
+}
+
+/// The first header of `AC_CONFIG_HEADERS`.
+pub(crate) fn config_header(ac: &AutoconfFile) -> Option<PathBuf> {
+    ac.macro_array_args("AC_CONFIG_HEADERS")
+        .into_iter()
+        .chain(ac.macro_array_args("AM_CONFIG_HEADER"))
+        .next()
+        .map(|header| PathBuf::from(header.split(':').next().unwrap()))
+}
+
+/// The argument of a macro call holding the includes of its test program,
+/// unless it is not given or empty.
+pub(crate) fn includes(m4_macro: &M4Macro) -> Option<String> {
+    let (_, signature, _) = m4_macro::get_macro(&m4_macro.name)?;
+    let index = signature
+        .arg_types
+        .iter()
+        .position(|t| matches!(t, M4Type::Prog))?;
+    match m4_macro.args.get(index) {
+        Some(M4Argument::Program(text)) => {
+            let text = unquote(text.trim());
+            (!text.is_empty() && text != "AC_INCLUDES_DEFAULT").then(|| program_source(&text))
+        }
+        _ => None,
+    }
+}
+
+/// The C source of a test program, expanding `AC_LANG_PROGRAM`,
+/// `AC_LANG_CALL` and `AC_LANG_SOURCE` as autoconf does.
+pub(crate) fn program_source(text: &str) -> String {
+    let text = unquote(text.trim());
+    let call = |name: &str| {
+        text.strip_prefix(name)
+            .and_then(|rest| rest.trim_start().strip_prefix('('))
+            .and_then(|rest| rest.trim_end().strip_suffix(')'))
+            .map(split_args)
+    };
+    let source = if let Some(args) = call("AC_LANG_PROGRAM") {
+        let prologue = args.first().map_or("", String::as_str);
+        let body = args.get(1).map_or("", String::as_str);
+        format!(
+            "{}\nint\nmain (void)\n{{\n{}\n  ;\n  return 0;\n}}\n",
+            prologue, body
+        )
+    } else if let Some(args) = call("AC_LANG_CALL") {
+        let prologue = args.first().map_or("", String::as_str);
+        let function = args.get(1).map_or("main", String::as_str);
+        format!("{}\n{}", prologue, function_call(function))
+    } else if let Some(args) = call("AC_LANG_SOURCE") {
+        format!("{}\n", args.first().map_or("", String::as_str))
+    } else {
+        format!("{}\n", text)
+    };
+    // Quadrigraphs stand for the characters m4 would take for quotes.
+    source
+        .replace("@<:@", "[")
+        .replace("@:>@", "]")
+        .replace("@S|@", "$")
+        .replace("@%:@", "#")
+        .replace("@&t@", "")
+}
+
+/// The program calling a function, declared with a dummy prototype, which
+/// only links when the function exists.
+pub(crate) fn function_call(function: &str) -> String {
+    format!(
+        "#ifdef __cplusplus\nextern \"C\"\n#endif\nchar {f} (void);\nint\nmain (void)\n{{\nreturn {f} ();\n  ;\n  return 0;\n}}\n",
+        f = function
+    )
+}
+
+/// Remove the m4 quotes around a text, e.g. `[[int x;]]` is `int x;`.
+fn unquote(text: &str) -> String {
+    let mut text = text;
+    while text.starts_with('[') && matching_bracket(text) == Some(text.len() - 1) {
+        text = &text[1..text.len() - 1];
+    }
+    text.to_string()
+}
+
+/// The index of the bracket closing the one text starts with.
+fn matching_bracket(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split the arguments of a macro call at the commas outside of quotes and
+/// parentheses, removing their quotes.
+fn split_args(text: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let (mut quotes, mut parens, mut start) = (0, 0, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '[' => quotes += 1,
+            ']' => quotes -= 1,
+            '(' if quotes == 0 => parens += 1,
+            ')' if quotes == 0 => parens -= 1,
+            ',' if quotes == 0 && parens == 0 => {
+                args.push(unquote(text[start..i].trim_start()));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(unquote(text[start..].trim()));
+    args
+}
+
+/// A C string literal.
+pub(crate) fn c_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,1167 @@
+//! Meson build files from `configure.ac` and the `Makefile.am` files.
+//!
+//! The checks of `configure.ac` become calls of the compiler object:
+//! headers, functions and types are checked with `cc.has_header`,
+//! `cc.has_function` and `cc.has_type`, libraries are found with
+//! `cc.find_library` and `PKG_CHECK_MODULES` uses `dependency()`. What a
+//! macro checks for and the symbol it defines are read from the side
+//! effects of its signature. The symbols and `AC_DEFINE` are set in a
+//! `configuration_data` written to the config header. `AC_ARG_ENABLE` and
+//! `AC_ARG_WITH` become boolean options of `meson_options.txt`, and
+//! automake conditionals become variables which the `meson.build` of every
+//! makefile tests in `if` blocks. Programs and libraries become
+//! `executable`, `library` and `static_library` targets. Whatever can not
+//! be translated is kept as `TODO` comments.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::analysis::targets::{collect_targets, Primary, Target};
+use crate::analysis::{conditional_words, is_make_test, CondSet, CondWord};
+use crate::ast::am::AmLine;
+use crate::ast::node::{AcCommand, M4Macro, NodeId};
+use crate::ast::MayM4;
+use crate::project::{AutoconfFile, AutomakeFile, FileId, Project, ProjectFile};
+use crate::util::{normalize, references, top_of};
+
+use super::configure::{self, Names, AUTOTOOLS_ONLY};
+use super::{Diagnostic, LIBTOOL_FLAGS};
+
+lazy_static! {
+    /// A word which is only a reference to a variable, e.g. `$(AM_CFLAGS)`.
+    static ref VARIABLE: Regex =
+        Regex::new(r"^(?:\$[({]([A-Za-z_][A-Za-z0-9_]*)[)}]|@([A-Za-z_][A-Za-z0-9_]*)@)$")
+            .unwrap();
+}
+
+/// The keyword arguments of targets given from flags, in the order written.
+const FLAG_KEYWORDS: &[&str] = &[
+    "c_args",
+    "cpp_args",
+    "include_directories",
+    "dependencies",
+    "link_with",
+    "link_args",
+];
+
+/// The install directories meson knows, by their automake prefix. `None`
+/// stands for the default directory of the kind of target.
+const INSTALL_DIRS: &[(&str, Option<&str>)] = &[
+    ("bin", None),
+    ("lib", None),
+    ("sbin", Some("get_option('sbindir')")),
+    ("libexec", Some("get_option('libexecdir')")),
+    (
+        "pkglib",
+        Some("get_option('libdir') / meson.project_name()"),
+    ),
+    (
+        "pkglibexec",
+        Some("get_option('libexecdir') / meson.project_name()"),
+    ),
+];
+
+/// The `meson.build` files translated from a project.
+#[derive(Debug, Clone)]
+pub struct MesonBuild {
+    /// The directories of the `meson.build` files, relative to the project
+    /// root, and their texts. The top-level file comes first.
+    pub files: Vec<(PathBuf, String)>,
+    /// The text of `meson_options.txt`, empty if there are no options.
+    pub options: String,
+    /// The macros, shell code and makefile constructs left as `TODO`
+    /// comments or left out.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl MesonBuild {
+    /// Translate the top-level package of a project. Nested packages of
+    /// `AC_CONFIG_SUBDIRS` are not translated.
+    pub fn new(project: &Project) -> Self {
+        let configure = project.configure();
+        let file = project.file(configure);
+        let ac = file.as_autoconf().unwrap();
+        let mut translator = Translator {
+            file,
+            ac,
+            body: String::new(),
+            options: String::new(),
+            shell_options: BTreeMap::new(),
+            deps: BTreeMap::new(),
+            uses_libs: false,
+            uses_subst: false,
+            defines: false,
+            diagnostics: Vec::new(),
+        };
+        for id in &ac.top_ids {
+            translator.command(*id);
+        }
+        let (root, mut scope, mut diagnostics) = translator.finish();
+
+        let makefiles = project
+            .automake_files()
+            .filter(|&id| project.package_of(id) == Some(configure))
+            .collect::<Vec<_>>();
+        for &id in &makefiles {
+            let file = project.file(id);
+            for target in collect_targets(file.as_automake().unwrap()) {
+                if target.primary != Primary::Programs {
+                    let path = normalize(&file.dir().join(&target.name));
+                    scope.libraries.insert(path, variable_of(&target));
+                }
+            }
+        }
+
+        let mut files = Vec::new();
+        for &id in &makefiles {
+            let file = project.file(id);
+            let mut makefile = Makefile {
+                project,
+                id,
+                file,
+                am: file.as_automake().unwrap(),
+                top: top_of(
+                    file.dir()
+                        .strip_prefix(project.file(configure).dir())
+                        .unwrap(),
+                ),
+                scope: &scope,
+                out: String::new(),
+                diagnostics: &mut diagnostics,
+            };
+            makefile.translate();
+            let text = makefile.out;
+            if file.dir() == project.file(configure).dir() {
+                let text = if text.is_empty() {
+                    text
+                } else {
+                    format!("\n{}", text)
+                };
+                files.push((file.dir().to_path_buf(), root.clone() + &text));
+            } else {
+                let header = format!("# Generated from {}.\n\n", file.path.display());
+                files.push((file.dir().to_path_buf(), header + &text));
+            }
+        }
+        if files.is_empty() {
+            files.push((file.dir().to_path_buf(), root));
+        }
+        // The top-level file comes first, as the others are read from it.
+        files.sort_by_key(|(dir, _)| dir != file.dir());
+        MesonBuild {
+            files,
+            options: scope.options,
+            diagnostics,
+        }
+    }
+
+    /// Write the `meson.build` files and `meson_options.txt` under the
+    /// project root.
+    pub fn write<P: AsRef<Path>>(&self, root: P) -> io::Result<()> {
+        let root = root.as_ref();
+        for (dir, text) in &self.files {
+            fs::create_dir_all(root.join(dir))?;
+            fs::write(root.join(dir).join("meson.build"), text)?;
+        }
+        if !self.options.is_empty() {
+            let dir = self.files.first().map_or(Path::new(""), |(dir, _)| dir);
+            fs::write(root.join(dir).join("meson_options.txt"), &self.options)?;
+        }
+        Ok(())
+    }
+}
+
+/// What `configure.ac` gives to the `meson.build` of the makefiles.
+struct Scope {
+    /// The text of `meson_options.txt`.
+    options: String,
+    /// The dependencies of `PKG_CHECK_MODULES` by the variables holding
+    /// their flags, e.g. `GLIB_CFLAGS`.
+    deps: BTreeMap<String, String>,
+    /// Whether the libraries of `AC_CHECK_LIB` are collected in `libs`.
+    uses_libs: bool,
+    /// Whether `config_inc` holds the directory of the config header.
+    config_header: bool,
+    /// The languages of the project, as named by meson.
+    languages: Vec<&'static str>,
+    /// The variables of the libraries by their paths, relative to the
+    /// project root.
+    libraries: BTreeMap<PathBuf, String>,
+}
+
+struct Translator<'a> {
+    file: &'a ProjectFile,
+    ac: &'a AutoconfFile,
+    body: String,
+    /// The text of `meson_options.txt`.
+    options: String,
+    /// Shell variables set by `AC_ARG_ENABLE` and `AC_ARG_WITH`, with the
+    /// names of their options.
+    shell_options: BTreeMap<String, String>,
+    deps: BTreeMap<String, String>,
+    uses_libs: bool,
+    uses_subst: bool,
+    /// Whether symbols are set in `conf`, which only a config header uses.
+    defines: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Translator<'_> {
+    fn command(&mut self, id: NodeId) {
+        let node = &self.ac.pool.nodes[id];
+        match &node.cmd {
+            AcCommand(MayM4::Macro(m4_macro)) => self.macro_call(id, m4_macro),
+            AcCommand(MayM4::Shell(_)) => self.todo(id, "shell code is not translated"),
+        }
+    }
+
+    fn macro_call(&mut self, id: NodeId, m4_macro: &M4Macro) {
+        let arg = |i: usize| {
+            m4_macro
+                .args
+                .get(i)
+                .and_then(|arg| self.ac.render_arg(arg))
+                .filter(|arg| !arg.is_empty())
+        };
+        let name = m4_macro.name.as_str();
+        match name {
+            "AC_ARG_ENABLE" | "AC_ARG_WITH" => self.option(id, m4_macro),
+            "PKG_CHECK_MODULES" => self.dependency(id, m4_macro),
+            "AC_DEFINE" | "AC_DEFINE_UNQUOTED" => {
+                let Some(var) = arg(0) else {
+                    return self.todo(id, "the arguments are not understood");
+                };
+                let value = arg(1).unwrap_or_else(|| "1".to_string());
+                if name == "AC_DEFINE_UNQUOTED" && value.contains(['$', '`']) {
+                    return self.todo(id, "the value depends on the shell");
+                }
+                self.defines = true;
+                let value = match value.parse::<i64>() {
+                    Ok(n) => n.to_string(),
+                    Err(_) => string(&value),
+                };
+                match arg(2) {
+                    Some(description) => self.line(&format!(
+                        "conf.set({}, {}, description : {})",
+                        string(&var),
+                        value,
+                        string(&description)
+                    )),
+                    None => self.line(&format!("conf.set({}, {})", string(&var), value)),
+                }
+            }
+            "AC_SUBST" => match (arg(0), arg(1)) {
+                (Some(var), Some(value)) if !value.contains(['$', '`']) => {
+                    self.uses_subst = true;
+                    self.line(&format!("subst.set({}, {})", string(&var), string(&value)));
+                }
+                (Some(_), Some(_)) => self.todo(id, "the value depends on the shell"),
+                _ => {}
+            },
+            "AM_CONDITIONAL" => self.conditional(id, m4_macro),
+            "AC_CONFIG_FILES" => {
+                for output in arg(0).unwrap_or_default().split_whitespace() {
+                    let (output, input) = match output.split_once(':') {
+                        Some((output, input)) => (output.to_string(), input.to_string()),
+                        None => (output.to_string(), format!("{}.in", output)),
+                    };
+                    if Path::new(&output)
+                        .file_name()
+                        .is_some_and(|n| n == "Makefile")
+                    {
+                        continue;
+                    }
+                    if output.contains('/') || input.contains([':', '/']) {
+                        self.todo(id, &format!("`{}` is not in the top directory", output));
+                        continue;
+                    }
+                    self.uses_subst = true;
+                    self.line(&format!(
+                        "configure_file(input : {}, output : {}, configuration : subst)",
+                        string(&input),
+                        string(&output)
+                    ));
+                }
+            }
+            "AC_CONFIG_SUBDIRS" => {
+                self.todo(id, "nested packages are not translated");
+            }
+            "AC_INIT" | "AC_CONFIG_HEADERS" | "AM_CONFIG_HEADER" => {}
+            _ if AUTOTOOLS_ONLY.contains(&name) => {}
+            _ => {
+                let probes = configure::probes(self.ac, m4_macro);
+                if probes.is_empty() {
+                    return self.todo(id, &format!("`{}` is not translated", name));
+                }
+                self.probes(id, m4_macro, &probes);
+            }
+        }
+    }
+
+    /// Checks of headers, functions, types and libraries, told apart by the
+    /// cache variables the macro sets.
+    fn probes(&mut self, id: NodeId, m4_macro: &M4Macro, probes: &[configure::Probe]) {
+        let has_action = |i: usize| configure::has_arg(self.ac, m4_macro, i);
+        if probes.iter().any(|probe| probe.names == Names::Libraries) {
+            return self.library(id, m4_macro, probes);
+        }
+        for probe in probes {
+            let cache_var = probe.cache_var.as_deref().unwrap_or_default();
+            let method = if cache_var.starts_with("ac_cv_header_") {
+                "has_header"
+            } else if cache_var.starts_with("ac_cv_func_") {
+                "has_function"
+            } else if cache_var.starts_with("ac_cv_type_") {
+                "has_type"
+            } else {
+                return self.todo(id, &format!("`{}` is not translated", m4_macro.name));
+            };
+            let check = format!("cc.{}({})", method, string(&probe.word));
+            match &probe.symbol {
+                Some(symbol) => {
+                    self.defines = true;
+                    self.line(&format!(
+                        "if {}\n  conf.set({}, 1)\nendif",
+                        check,
+                        string(symbol)
+                    ));
+                }
+                None => self.line(&format!("{} = {}", cache_var, check)),
+            }
+        }
+        if has_action(1) || has_action(2) {
+            self.todo(id, "the actions of the check are not translated");
+        }
+    }
+
+    /// `AC_CHECK_LIB([m], [cos])` finds the library `m`, which is linked
+    /// with every program and shared library unless the call has an action.
+    fn library(&mut self, id: NodeId, m4_macro: &M4Macro, probes: &[configure::Probe]) {
+        let has_action = |i: usize| configure::has_arg(self.ac, m4_macro, i);
+        for probe in probes.iter().filter(|p| p.names == Names::Libraries) {
+            let var = format!("lib{}_dep", identifier(&probe.word));
+            self.line(&format!(
+                "{} = cc.find_library({}, required : false)",
+                var,
+                string(&probe.word)
+            ));
+            if has_action(2) {
+                self.todo(id, "the action if found is not translated");
+                continue;
+            }
+            if let Some(symbol) = &probe.symbol {
+                self.defines = true;
+                self.line(&format!(
+                    "if {}.found()\n  conf.set({}, 1)\nendif",
+                    var,
+                    string(symbol)
+                ));
+            }
+            self.line(&format!("libs += {}", var));
+            self.uses_libs = true;
+        }
+        if has_action(3) {
+            self.todo(id, "the action if not found is not translated");
+        }
+    }
+
+    /// `AC_ARG_ENABLE([foo], [help])` is the boolean option `foo`.
+    fn option(&mut self, id: NodeId, m4_macro: &M4Macro) {
+        let Some(option) = configure::configure_option(self.ac, m4_macro) else {
+            return self.todo(id, "the arguments are not understood");
+        };
+        self.options.push_str(&format!(
+            "option({}, type : 'boolean', value : {}, description : {})\n",
+            string(&option.name),
+            option.default,
+            string(&option.help)
+        ));
+        if !option.plain {
+            self.todo(id, "the actions of the option are not translated");
+        }
+        self.shell_options.insert(option.shell_var, option.name);
+    }
+
+    /// `PKG_CHECK_MODULES([GLIB], [glib-2.0])` is the dependency
+    /// `glib_dep`, standing for the variables `GLIB_CFLAGS` and `GLIB_LIBS`
+    /// in the makefiles.
+    fn dependency(&mut self, id: NodeId, m4_macro: &M4Macro) {
+        let has_action = |i: usize| configure::has_arg(self.ac, m4_macro, i);
+        let exports = configure::exports(self.ac, m4_macro);
+        let prefix = exports
+            .iter()
+            .find(|export| export.names == Names::Variables)
+            .map(|export| export.word.clone());
+        let modules = m4_macro
+            .args
+            .get(1)
+            .and_then(|arg| self.ac.render_arg(arg))
+            .map(|modules| configure::pkg_modules(&modules))
+            .unwrap_or_default();
+        let Some(prefix) = prefix.filter(|_| !modules.is_empty()) else {
+            return self.todo(id, "the arguments are not understood");
+        };
+        let required = if has_action(3) {
+            ", required : false"
+        } else {
+            ""
+        };
+        let deps = modules
+            .iter()
+            .map(|(module, constraint)| {
+                let version = if constraint.is_empty() {
+                    String::new()
+                } else {
+                    format!(", version : {}", string(constraint))
+                };
+                format!("dependency({}{}{})", string(module), version, required)
+            })
+            .collect::<Vec<_>>();
+        let var = if let [dep] = deps.as_slice() {
+            let var = format!("{}_dep", identifier(&prefix));
+            self.line(&format!("{} = {}", var, dep));
+            var
+        } else {
+            let var = format!("{}_deps", identifier(&prefix));
+            self.line(&format!("{} = [\n  {},\n]", var, deps.join(",\n  ")));
+            var
+        };
+        for export in exports.iter().filter(|e| e.names == Names::Variables) {
+            self.deps.insert(export.name.clone(), var.clone());
+        }
+        if has_action(2) || has_action(3) {
+            self.todo(id, "the actions of the check are not translated");
+        }
+    }
+
+    /// `AM_CONDITIONAL([NAME], [test "x$enable_foo" = xyes])` is the
+    /// variable `name`, following the option the shell variable belongs to.
+    fn conditional(&mut self, id: NodeId, m4_macro: &M4Macro) {
+        let Some(name) = m4_macro.args.first().and_then(|a| self.ac.render_arg(a)) else {
+            return self.todo(id, "the arguments are not understood");
+        };
+        let test = configure::commands(self.ac, m4_macro.args.get(1)).unwrap_or_default();
+        let cond = configure::shell_test(&test).and_then(|(var, holds)| {
+            let option = self.shell_options.get(&var)?;
+            let value = format!("get_option({})", string(option));
+            Some(if holds {
+                value
+            } else {
+                format!("not {}", value)
+            })
+        });
+        match cond {
+            Some(cond) => self.line(&format!("{} = {}", identifier(&name), cond)),
+            None => {
+                self.line(&format!("{} = false", identifier(&name)));
+                self.todo(id, "the condition is not translated");
+            }
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        self.body.push_str(line);
+        self.body.push('\n');
+    }
+
+    /// Quote the lines of a command as a `TODO` comment.
+    fn todo(&mut self, id: NodeId, message: &str) {
+        let (comment, diagnostic) =
+            configure::todo(self.file, &self.ac.pool.nodes[id], "#", message);
+        self.body.push_str(&comment);
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// The top of the top-level `meson.build`, the scope of the makefiles,
+    /// and the diagnostics so far.
+    fn finish(self) -> (String, Scope, Vec<Diagnostic>) {
+        let ac = self.ac;
+        let package = configure::package_values(ac);
+        let mut languages = Vec::new();
+        if ac.calls_macro("AC_PROG_CC") || !ac.calls_macro("AC_PROG_CXX") {
+            languages.push("c");
+        }
+        if ac.calls_macro("AC_PROG_CXX") {
+            languages.push("cpp");
+        }
+
+        let mut text = format!(
+            "project({}, {}, version : {}, meson_version : '>= 0.50')\n\n",
+            string(&package[0].1),
+            languages
+                .iter()
+                .map(|l| string(l))
+                .collect::<Vec<_>>()
+                .join(", "),
+            string(&package[2].1)
+        );
+        text.push_str(&format!(
+            "cc = meson.get_compiler({})\n",
+            string(languages[0])
+        ));
+        text.push_str("conf = configuration_data()\n");
+        for (var, value) in &package {
+            text.push_str(&format!(
+                "conf.set_quoted({}, {})\n",
+                string(var),
+                string(value)
+            ));
+        }
+        if self.uses_subst {
+            text.push_str("subst = configuration_data()\n");
+            for (var, value) in &package {
+                text.push_str(&format!("subst.set({}, {})\n", string(var), string(value)));
+            }
+            text.push_str(
+                "subst.set('prefix', get_option('prefix'))\n\
+                 subst.set('exec_prefix', '${prefix}')\n\
+                 subst.set('libdir', '${exec_prefix}' / get_option('libdir'))\n\
+                 subst.set('includedir', '${prefix}' / get_option('includedir'))\n",
+            );
+        }
+        if self.uses_libs {
+            text.push_str("libs = []\n");
+        }
+        if !self.body.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&self.body);
+
+        let mut diagnostics = self.diagnostics;
+        let header = configure::config_header(ac);
+        match &header {
+            Some(header) => {
+                let name = header.file_name().unwrap().to_string_lossy();
+                text.push('\n');
+                // The output of `configure_file` can not name a directory.
+                if header
+                    .parent()
+                    .is_some_and(|dir| !dir.as_os_str().is_empty())
+                {
+                    let message = format!(
+                        "`{}` is generated as `{}` in the top build directory",
+                        header.display(),
+                        name
+                    );
+                    text.push_str(&format!(
+                        "# TODO {}: {}\n",
+                        self.file.path.display(),
+                        message
+                    ));
+                    diagnostics.push(Diagnostic {
+                        makefile: self.file.path.clone(),
+                        message,
+                    });
+                }
+                text.push_str(&format!(
+                    "configure_file(output : {}, configuration : conf)\n",
+                    string(&name)
+                ));
+                text.push_str("config_inc = include_directories('.')\n");
+                text.push_str(&format!(
+                    "add_project_arguments('-DHAVE_CONFIG_H', language : [{}])\n",
+                    languages
+                        .iter()
+                        .map(|l| string(l))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            None if self.defines => {
+                let message = "the symbols are not defined without a config header";
+                text.push_str(&format!(
+                    "\n# TODO {}: {}\n",
+                    self.file.path.display(),
+                    message
+                ));
+                diagnostics.push(Diagnostic {
+                    makefile: self.file.path.clone(),
+                    message: message.to_string(),
+                });
+            }
+            None => {}
+        }
+        let scope = Scope {
+            options: self.options,
+            deps: self.deps,
+            uses_libs: self.uses_libs,
+            config_header: header.is_some(),
+            languages,
+            libraries: BTreeMap::new(),
+        };
+        (text, scope, diagnostics)
+    }
+}
+
+/// Translates a `Makefile.am` into the `meson.build` of its directory.
+struct Makefile<'a> {
+    project: &'a Project,
+    id: FileId,
+    file: &'a ProjectFile,
+    am: &'a AutomakeFile,
+    /// The top directory of the package as seen from the makefile.
+    top: String,
+    scope: &'a Scope,
+    out: String,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl Makefile<'_> {
+    fn translate(&mut self) {
+        // The directories before `.` are built first, which is all of them
+        // when `.` is not listed.
+        let subdirs = self.expand(conditional_words(self.am, "SUBDIRS").unwrap_or_default());
+        let here = subdirs.iter().position(|(_, dir)| dir == ".");
+        let (before, after) = subdirs.split_at(here.unwrap_or(subdirs.len()));
+        self.subdirs(before);
+
+        let targets = collect_targets(self.am);
+        let tests = self
+            .expand(conditional_words(self.am, "TESTS").unwrap_or_default())
+            .into_iter()
+            .map(|(_, test)| test)
+            .collect::<Vec<_>>();
+        for target in targets.iter().filter(|t| t.dir != "EXTRA") {
+            self.target(target, &tests);
+        }
+        self.headers();
+        self.rules();
+        self.subdirs(after);
+    }
+
+    fn subdirs(&mut self, dirs: &[(CondSet, String)]) {
+        if dirs.iter().any(|(_, dir)| dir != ".") {
+            self.section();
+        }
+        for (cond, dir) in dirs {
+            if dir == "." {
+                continue;
+            }
+            let path = normalize(&self.file.dir().join(dir));
+            let id = self.project.find(path.join("Makefile.am"));
+            if id.is_none_or(|id| self.project.package_of(id) != self.project.package_of(self.id)) {
+                let message = format!("`{}` is not a directory of the package", dir);
+                self.todo(&message);
+                continue;
+            }
+            self.conditional(cond, &format!("subdir({})", string(dir)));
+        }
+    }
+
+    fn target(&mut self, target: &Target, tests: &[String]) {
+        self.section();
+        let var = variable_of(target);
+        let prefix = &target.canonical;
+
+        let mut sources = Vec::new();
+        for (cond, source) in self.expand(target.sources.clone()) {
+            match self.local_path(&source) {
+                Some(path) => sources.push((cond, string(&path))),
+                None => self.report(format!("unknown source `{}` of `{}`", source, target.name)),
+            }
+        }
+        if !target.nodist_sources.is_empty() {
+            self.report(format!(
+                "the built sources of `{}` are not translated",
+                target.name
+            ));
+        }
+        let source_list = self.list(&format!("{}_sources", prefix), &sources, true);
+
+        let cxx = sources.iter().any(|(_, s)| {
+            [".cc'", ".cpp'", ".cxx'", ".C'", ".c++'"]
+                .iter()
+                .any(|ext| s.ends_with(ext))
+        });
+        let c = sources.iter().any(|(_, s)| s.ends_with(".c'")) || !cxx;
+        let mut compile_langs = Vec::new();
+        if c && self.scope.languages.contains(&"c") {
+            compile_langs.push("c_args");
+        }
+        if cxx && self.scope.languages.contains(&"cpp") {
+            compile_langs.push("cpp_args");
+        }
+
+        let mut flags: Vec<(&'static str, CondSet, String)> = Vec::new();
+        if self.scope.config_header {
+            flags.push((
+                "include_directories",
+                CondSet::always(),
+                "config_inc".into(),
+            ));
+        }
+        let cppflags = self.flags(target.cppflags.as_ref(), "AM_CPPFLAGS");
+        for (cond, flag) in cppflags {
+            for key in &compile_langs {
+                self.compile_flag(&mut flags, key, &cond, &flag);
+            }
+        }
+        if compile_langs.contains(&"c_args") {
+            for (cond, flag) in self.flags(target.cflags.as_ref(), "AM_CFLAGS") {
+                self.compile_flag(&mut flags, "c_args", &cond, &flag);
+            }
+        }
+        if compile_langs.contains(&"cpp_args") {
+            for (cond, flag) in self.flags(target.cxxflags.as_ref(), "AM_CXXFLAGS") {
+                self.compile_flag(&mut flags, "cpp_args", &cond, &flag);
+            }
+        }
+
+        let libadd = match target.primary {
+            Primary::Programs => "LDADD",
+            _ => "",
+        };
+        for (cond, flag) in self.flags(target.ldadd.as_ref(), libadd) {
+            self.link_flag(&mut flags, &cond, &flag);
+        }
+        let mut version = None;
+        let mut module = false;
+        let ldflags = self.flags(target.ldflags.as_ref(), "AM_LDFLAGS");
+        let mut ldflags = ldflags.into_iter();
+        while let Some((cond, flag)) = ldflags.next() {
+            match LIBTOOL_FLAGS.iter().find(|(f, _)| *f == flag) {
+                Some((_, has_value)) => {
+                    let value = has_value.then(|| ldflags.next()).flatten();
+                    match (flag.as_str(), value) {
+                        ("-version-info", Some((_, value))) => {
+                            version = libtool_version(&value);
+                        }
+                        ("-module", _) => module = true,
+                        _ => {}
+                    }
+                }
+                None if flag == "-no-undefined" => {}
+                None => self.link_flag(&mut flags, &cond, &flag),
+            }
+        }
+
+        let installed = !matches!(target.dir.as_str(), "noinst" | "check");
+        let (function, name) = match target.primary {
+            Primary::Programs => ("executable", target.name.clone()),
+            Primary::LtLibraries if installed && module => {
+                ("shared_module", library_name(&target.name))
+            }
+            Primary::LtLibraries if installed => ("library", library_name(&target.name)),
+            _ => ("static_library", library_name(&target.name)),
+        };
+        if function != "static_library" && self.scope.uses_libs {
+            flags.push(("dependencies", CondSet::always(), "libs".into()));
+        }
+
+        let mut arguments = vec![string(&name), source_list];
+        if !target.name.starts_with("lib") && target.primary != Primary::Programs {
+            arguments.push("name_prefix : ''".into());
+        }
+        for key in FLAG_KEYWORDS {
+            let items = flags
+                .iter()
+                .filter(|(k, _, _)| k == key)
+                .map(|(_, cond, item)| (cond.clone(), item.clone()))
+                .collect::<Vec<_>>();
+            if !items.is_empty() {
+                let list = self.list(&format!("{}_{}", prefix, key), &items, false);
+                arguments.push(format!("{} : {}", key, list));
+            }
+        }
+        if function == "library" || function == "shared_module" {
+            if let Some((version, soversion)) = &version {
+                arguments.push(format!("version : {}", string(version)));
+                arguments.push(format!("soversion : {}", string(soversion)));
+            }
+        }
+        if target.dir == "check" {
+            arguments.push("build_by_default : false".into());
+        }
+        if installed {
+            match INSTALL_DIRS.iter().find(|(dir, _)| *dir == target.dir) {
+                Some((_, dir)) => {
+                    arguments.push("install : true".into());
+                    if let Some(dir) = dir {
+                        arguments.push(format!("install_dir : {}", dir));
+                    }
+                }
+                None => self.report(format!(
+                    "the install directory of `{}` is not translated",
+                    target.name
+                )),
+            }
+        }
+
+        let mut call = format!("{} = {}(\n", var, function);
+        for argument in arguments {
+            call.push_str(&format!("  {},\n", argument));
+        }
+        call.push(')');
+        if tests.contains(&target.name) {
+            call.push_str(&format!("\ntest({}, {})", string(&target.name), var));
+        }
+        let conds = target
+            .conds
+            .iter()
+            .map(|cond| self.cond_expr(cond))
+            .collect::<Vec<_>>();
+        if conds.iter().any(|c| c.is_empty()) {
+            self.line(&call);
+        } else if let [cond] = conds.as_slice() {
+            self.block(cond, &call);
+        } else {
+            let cond = conds
+                .iter()
+                .map(|c| format!("({})", c))
+                .collect::<Vec<_>>()
+                .join(" or ");
+            self.block(&cond, &call);
+        }
+    }
+
+    /// The flags of a target, or those given to every target by `default`.
+    fn flags(&mut self, flags: Option<&Vec<CondWord>>, default: &str) -> Vec<(CondSet, String)> {
+        let words = match flags {
+            Some(flags) => flags.clone(),
+            None if default.is_empty() => Vec::new(),
+            None => conditional_words(self.am, default).unwrap_or_default(),
+        };
+        self.expand(words)
+    }
+
+    fn compile_flag(
+        &mut self,
+        flags: &mut Vec<(&'static str, CondSet, String)>,
+        key: &'static str,
+        cond: &CondSet,
+        flag: &str,
+    ) {
+        if let Some(dep) = self.dependency(flag) {
+            if !flags.iter().any(|(_, _, d)| *d == dep) {
+                flags.push(("dependencies", cond.clone(), dep));
+            }
+        } else if let Some(dir) = flag.strip_prefix("-I") {
+            match self.local_path(dir) {
+                Some(dir) => {
+                    let dir = string(&dir);
+                    if !flags.iter().any(|(_, _, d)| *d == dir) {
+                        flags.push(("include_directories", cond.clone(), dir));
+                    }
+                }
+                None => self.report(format!("unknown include directory `{}`", dir)),
+            }
+        } else if flag.contains(['$', '@']) {
+            self.report_unknown(flag);
+        } else {
+            flags.push((key, cond.clone(), string(flag)));
+        }
+    }
+
+    fn link_flag(
+        &mut self,
+        flags: &mut Vec<(&'static str, CondSet, String)>,
+        cond: &CondSet,
+        flag: &str,
+    ) {
+        if let Some(dep) = self.dependency(flag) {
+            if !flags.iter().any(|(_, _, d)| *d == dep) {
+                flags.push(("dependencies", cond.clone(), dep));
+            }
+        } else if flag.ends_with(".la") || flag.ends_with(".a") {
+            let library = self
+                .local_path(flag)
+                .map(|path| normalize(&self.file.dir().join(path)))
+                .and_then(|path| self.scope.libraries.get(&path));
+            match library {
+                Some(var) => flags.push(("link_with", cond.clone(), var.clone())),
+                None => self.report(format!("unknown library `{}`", flag)),
+            }
+        } else if let Some(library) = flag.strip_prefix("-l") {
+            let dep = format!("cc.find_library({})", string(library));
+            flags.push(("dependencies", cond.clone(), dep));
+        } else if flag.contains(['$', '@']) {
+            self.report_unknown(flag);
+        } else {
+            flags.push(("link_args", cond.clone(), string(flag)));
+        }
+    }
+
+    /// The dependency of `PKG_CHECK_MODULES` a flag such as `$(GLIB_LIBS)`
+    /// stands for.
+    fn dependency(&self, flag: &str) -> Option<String> {
+        let captures = VARIABLE.captures(flag)?;
+        let name = captures.get(1).or(captures.get(2))?.as_str();
+        self.scope.deps.get(name).cloned()
+    }
+
+    fn headers(&mut self) {
+        for (var, subdir) in [("include_HEADERS", false), ("pkginclude_HEADERS", true)] {
+            let Some(words) = conditional_words(self.am, var) else {
+                continue;
+            };
+            self.section();
+            let mut headers = Vec::new();
+            for (cond, header) in self.expand(words) {
+                match self.local_path(&header) {
+                    Some(path) => headers.push((cond, string(&path))),
+                    None => self.report(format!("unknown header `{}`", header)),
+                }
+            }
+            let list = self.list(var, &headers, true);
+            if subdir {
+                self.line(&format!(
+                    "install_headers({}, subdir : meson.project_name())",
+                    list
+                ));
+            } else {
+                self.line(&format!("install_headers({})", list));
+            }
+        }
+    }
+
+    /// Rules with recipes are quoted as `TODO` comments.
+    fn rules(&mut self) {
+        let mut rules = self
+            .am
+            .nodes
+            .iter()
+            .filter(|(_, node)| matches!(&node.cmd, AmLine::Rule(rule) if !rule.recipe.is_empty()))
+            .map(|(_, node)| node)
+            .collect::<Vec<_>>();
+        rules.sort_by_key(|node| node.range_start());
+        if !rules.is_empty() {
+            self.section();
+        }
+        for node in rules {
+            let AmLine::Rule(rule) = &node.cmd else {
+                continue;
+            };
+            let names = rule
+                .target
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let message = format!("the rule of `{}` is not translated", names);
+            let (comment, diagnostic) = configure::todo(self.file, node, "#", &message);
+            self.out.push_str(&comment);
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Expand the words which are references to variables of the makefile,
+    /// keeping the conditionals they depend on.
+    fn expand(&self, words: Vec<CondWord>) -> Vec<(CondSet, String)> {
+        let mut ret = Vec::new();
+        let mut stack = words
+            .into_iter()
+            .rev()
+            .map(|w| (0, w.cond, w.word.to_string()))
+            .collect::<Vec<_>>();
+        while let Some((depth, cond, word)) = stack.pop() {
+            let value = VARIABLE
+                .captures(&word)
+                .and_then(|captures| captures.get(1))
+                .filter(|_| depth < 16)
+                .and_then(|name| conditional_words(self.am, name.as_str()));
+            match value {
+                Some(value) => {
+                    for w in value.into_iter().rev() {
+                        if let Some(cond) = cond.and(&w.cond) {
+                            stack.push((depth + 1, cond, w.word.to_string()));
+                        }
+                    }
+                }
+                None => ret.push((cond, word)),
+            }
+        }
+        ret
+    }
+
+    /// A path of the makefile as seen from its directory, with the
+    /// directory variables replaced.
+    fn local_path(&self, path: &str) -> Option<String> {
+        let mut path = path.to_string();
+        for (var, value) in [
+            ("top_srcdir", self.top.as_str()),
+            ("top_builddir", self.top.as_str()),
+            ("srcdir", "."),
+            ("builddir", "."),
+        ] {
+            for reference in [
+                format!("$({})", var),
+                format!("${{{}}}", var),
+                format!("@{}@", var),
+            ] {
+                path = path.replace(&reference, value);
+            }
+        }
+        if path.contains(['$', '@']) {
+            return None;
+        }
+        let path = normalize(Path::new(&path));
+        if path.as_os_str().is_empty() {
+            Some(".".to_string())
+        } else {
+            Some(path.display().to_string())
+        }
+    }
+
+    /// Render a list, declaring the variable `var` when some items depend
+    /// on conditionals. Lists of files are given to `files()`.
+    fn list(&mut self, var: &str, items: &[(CondSet, String)], files: bool) -> String {
+        let render = |items: &[&String]| {
+            let items = items.iter().map(|i| i.as_str()).collect::<Vec<_>>();
+            if files {
+                format!("files({})", items.join(", "))
+            } else {
+                format!("[{}]", items.join(", "))
+            }
+        };
+        if items.iter().all(|(cond, _)| cond.is_always()) {
+            return render(&items.iter().map(|(_, i)| i).collect::<Vec<_>>());
+        }
+        let always = items
+            .iter()
+            .filter(|(cond, _)| cond.is_always())
+            .map(|(_, i)| i)
+            .collect::<Vec<_>>();
+        self.line(&format!("{} = {}", var, render(&always)));
+        let mut groups: Vec<(&CondSet, Vec<&String>)> = Vec::new();
+        for (cond, item) in items.iter().filter(|(cond, _)| !cond.is_always()) {
+            match groups.last_mut() {
+                Some((last, group)) if *last == cond => group.push(item),
+                _ => groups.push((cond, vec![item])),
+            }
+        }
+        for (cond, group) in groups {
+            let cond = self.cond_expr(cond);
+            self.block(&cond, &format!("{} += {}", var, render(&group)));
+        }
+        var.to_string()
+    }
+
+    fn conditional(&mut self, cond: &CondSet, text: &str) {
+        match self.cond_expr(cond) {
+            cond if cond.is_empty() => self.line(text),
+            cond => self.block(&cond, text),
+        }
+    }
+
+    /// The meson expression testing `cond`, reporting the tests of GNU make
+    /// conditionals it depends on, which are not translated.
+    fn cond_expr(&mut self, cond: &CondSet) -> String {
+        for (name, _) in cond.iter().filter(|(name, _)| is_make_test(name)) {
+            self.report(format!("GNU make conditional `{}` is not translated", name));
+        }
+        cond_expr(cond)
+    }
+
+    fn block(&mut self, cond: &str, text: &str) {
+        self.line(&format!("if {}", cond));
+        for line in text.lines() {
+            self.line(&format!("  {}", line));
+        }
+        self.line("endif");
+    }
+
+    /// Separate what follows from the previous lines.
+    fn section(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn todo(&mut self, message: &str) {
+        self.line(&format!("# TODO {}: {}", self.file.path.display(), message));
+        self.report(message.to_string());
+    }
+
+    fn report(&mut self, message: String) {
+        let diagnostic = Diagnostic {
+            makefile: self.file.path.clone(),
+            message,
+        };
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn report_unknown(&mut self, flag: &str) {
+        for name in references(flag) {
+            self.report(format!("unknown value of `{}`", name));
+        }
+    }
+}
+
+/// The variable holding a target, e.g. `libfoo` for `libfoo.la`.
+fn variable_of(target: &Target) -> String {
+    let canonical = target.canonical.as_str();
+    let name = match target.primary {
+        Primary::Programs => canonical,
+        Primary::Libraries => canonical.strip_suffix("_a").unwrap_or(canonical),
+        Primary::LtLibraries => canonical.strip_suffix("_la").unwrap_or(canonical),
+    };
+    identifier(name)
+}
+
+/// The name meson gives to a library, e.g. `foo` for `libfoo.la`.
+fn library_name(name: &str) -> String {
+    let name = name
+        .strip_suffix(".la")
+        .or_else(|| name.strip_suffix(".a"))
+        .unwrap_or(name);
+    name.strip_prefix("lib").unwrap_or(name).to_string()
+}
+
+/// The version and the major version of the shared library libtool builds
+/// for `-version-info current:revision:age`.
+fn libtool_version(info: &str) -> Option<(String, String)> {
+    let mut numbers = info.split(':').map(|n| n.parse::<u32>());
+    let current = numbers.next()?.ok()?;
+    let revision = numbers.next().unwrap_or(Ok(0)).ok()?;
+    let age = numbers.next().unwrap_or(Ok(0)).ok()?;
+    let major = current.checked_sub(age)?;
+    Some((format!("{}.{}.{}", major, age, revision), major.to_string()))
+}
+
+/// The meson expression testing automake conditionals, empty when they
+/// always hold. What depends on the test of a GNU make conditional is left
+/// out with `false`.
+fn cond_expr(cond: &CondSet) -> String {
+    if cond.iter().any(|(name, _)| is_make_test(name)) {
+        return "false".to_string();
+    }
+    cond.iter()
+        .map(|(name, value)| {
+            if value {
+                identifier(name)
+            } else {
+                format!("not {}", identifier(name))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// A meson variable name, e.g. `enable_x` for `ENABLE_X`.
+fn identifier(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// A meson string literal.
+fn string(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
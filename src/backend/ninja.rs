@@ -0,0 +1,259 @@
+//! Ninja build files.
+//!
+//! The build file is written to the root of the build tree, which mirrors the
+//! directories of the makefiles. Every command runs in the directory of its
+//! makefile, like it would under `make`, and sources found in the source
+//! tree are referenced through the `srcdir` variable.
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::util::normalize;
+
+use super::{BuildModel, TargetKind};
+
+const RULES: &str = "\
+rule compile
+  command = cd $dir && $cmd
+  description = CC $out
+
+rule link
+  command = cd $dir && $cmd
+  description = LINK $out
+
+rule archive
+  command = cd $dir && rm -f $target && $cmd
+  description = AR $out
+
+rule custom
+  command = cd $dir && $cmd
+  description = GEN $out
+
+rule install
+  command = install -D -m $mode $in \"$$DESTDIR\"$dest
+  description = INSTALL $dest
+";
+
+/// Render the build file of a model as `build.ninja`.
+pub fn generate(model: &BuildModel) -> String {
+    let mut ninja = Ninja::new(model);
+    ninja
+        .out
+        .push_str("# Generated from the Makefile.am files of the project.\n");
+    ninja.out.push_str("ninja_required_version = 1.3\n");
+    ninja.out.push_str(&format!(
+        "srcdir = {}\n\n",
+        escape(&model.srcdir.display().to_string())
+    ));
+    ninja.out.push_str(RULES);
+    ninja.compile_edges();
+    ninja.link_edges();
+    ninja.custom_edges();
+    ninja.install_edges();
+    ninja.default_edges();
+    ninja.out
+}
+
+/// Write the build file of a model, usually to `build.ninja`.
+pub fn write<P: AsRef<Path>>(model: &BuildModel, path: P) -> io::Result<()> {
+    fs::write(path, generate(model))
+}
+
+struct Ninja<'a> {
+    model: &'a BuildModel,
+    /// The files built by an edge, relative to the build root.
+    built: BTreeSet<PathBuf>,
+    /// The edges `all` depends on.
+    all: Vec<String>,
+    out: String,
+}
+
+impl<'a> Ninja<'a> {
+    fn new(model: &'a BuildModel) -> Self {
+        let mut built = BTreeSet::new();
+        for target in &model.targets {
+            built.insert(target.path());
+            for object in &target.objects {
+                built.insert(normalize(&target.dir.join(&object.object)));
+            }
+        }
+        for command in &model.commands {
+            built.insert(normalize(&command.dir.join(&command.output)));
+        }
+        Self {
+            model,
+            built,
+            all: Vec::new(),
+            out: String::new(),
+        }
+    }
+
+    /// The path of a file relative to the project root, in the build tree
+    /// if an edge builds it and in the source tree otherwise.
+    fn input(&self, path: &Path, built: bool) -> String {
+        let path = normalize(path);
+        if built || self.built.contains(&path) || self.model.srcdir == Path::new(".") {
+            escape_path(&path)
+        } else {
+            format!("$srcdir/{}", escape_path(&path))
+        }
+    }
+
+    fn edge(&mut self, rule: &str, output: &str, inputs: &[String], order_only: &[String]) {
+        self.out.push_str(&format!("\nbuild {}: {}", output, rule));
+        for input in inputs {
+            self.out.push_str(&format!(" {}", input));
+        }
+        if !order_only.is_empty() {
+            self.out.push_str(&format!(" || {}", order_only.join(" ")));
+        }
+        self.out.push('\n');
+    }
+
+    fn variable(&mut self, name: &str, value: &str) {
+        self.out
+            .push_str(&format!("  {} = {}\n", name, value.replace('$', "$$")));
+    }
+
+    fn compile_edges(&mut self) {
+        let mut seen = BTreeSet::new();
+        for target in &self.model.targets {
+            // Built sources may include headers generated by custom rules.
+            let generated = self
+                .model
+                .commands
+                .iter()
+                .filter(|c| c.dir == target.dir && !c.phony)
+                .map(|c| escape_path(&c.dir.join(&c.output)))
+                .collect::<Vec<_>>();
+            for object in &target.objects {
+                let output = normalize(&target.dir.join(&object.object));
+                if !seen.insert(output.clone()) {
+                    continue;
+                }
+                let input = self.input(&target.dir.join(&object.source), object.built);
+                self.edge("compile", &escape_path(&output), &[input], &generated);
+                self.variable("dir", &dir_of(&target.dir));
+                self.variable("cmd", &object.arguments.join(" "));
+            }
+        }
+    }
+
+    fn link_edges(&mut self) {
+        for target in &self.model.targets {
+            let output = escape_path(&target.path());
+            let objects = target
+                .objects
+                .iter()
+                .map(|o| escape_path(&target.dir.join(&o.object)))
+                .collect::<Vec<_>>();
+            let (rule, libraries) = match target.kind {
+                TargetKind::StaticLibrary => ("archive", Vec::new()),
+                _ => (
+                    "link",
+                    target
+                        .links
+                        .iter()
+                        .map(|i| escape_path(&self.model.targets[*i].path()))
+                        .collect::<Vec<_>>(),
+                ),
+            };
+            self.out.push_str(&format!("\nbuild {}: {}", output, rule));
+            for object in &objects {
+                self.out.push_str(&format!(" {}", object));
+            }
+            if !libraries.is_empty() {
+                self.out.push_str(&format!(" | {}", libraries.join(" ")));
+            }
+            self.out.push('\n');
+            self.variable("dir", &dir_of(&target.dir));
+            if rule == "archive" {
+                self.variable("target", &target.output);
+            }
+            self.variable("cmd", &target.link.join(" "));
+            self.all.push(output);
+        }
+    }
+
+    fn custom_edges(&mut self) {
+        for command in &self.model.commands {
+            let output = escape_path(&command.dir.join(&command.output));
+            let inputs = command
+                .inputs
+                .iter()
+                .map(|input| self.input(&command.dir.join(input), false))
+                .collect::<Vec<_>>();
+            // Install hooks run once the files are installed.
+            let after = match command.extends {
+                Some(base) if base.starts_with("install") && command.output.ends_with("-hook") => {
+                    (0..self.model.installs.len())
+                        .map(|i| format!("install_{}", i))
+                        .collect()
+                }
+                _ => Vec::new(),
+            };
+            self.edge("custom", &output, &inputs, &after);
+            self.variable("dir", &dir_of(&command.dir));
+            self.variable("cmd", &command.command);
+            match command.extends {
+                Some("all") => self.all.push(output),
+                Some(_) => {}
+                None if !command.phony => self.all.push(output),
+                None => {}
+            }
+        }
+    }
+
+    fn install_edges(&mut self) {
+        let mut edges = Vec::new();
+        for (i, install) in self.model.installs.iter().enumerate() {
+            let output = format!("install_{}", i);
+            let input = self.input(&install.source, install.built);
+            self.edge("install", &output, &[input], &[]);
+            self.variable("dest", &install.dest.display().to_string());
+            self.variable("mode", &format!("{:o}", install.mode));
+            edges.push(output);
+        }
+        for command in &self.model.commands {
+            if command
+                .extends
+                .is_some_and(|base| base.starts_with("install"))
+            {
+                edges.push(escape_path(&command.dir.join(&command.output)));
+            }
+        }
+        self.out
+            .push_str(&format!("\nbuild install: phony all {}\n", edges.join(" ")));
+    }
+
+    fn default_edges(&mut self) {
+        self.out
+            .push_str(&format!("\nbuild all: phony {}\n", self.all.join(" ")));
+        self.out.push_str("\ndefault all\n");
+    }
+}
+
+/// The directory a command runs in, relative to the build root.
+fn dir_of(dir: &Path) -> String {
+    if dir.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        dir.display().to_string()
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('$', "$$")
+}
+
+fn escape_path(path: &Path) -> String {
+    let mut ret = String::new();
+    for c in path.display().to_string().chars() {
+        if matches!(c, '$' | ' ' | ':') {
+            ret.push('$');
+        }
+        ret.push(c);
+    }
+    ret
+}
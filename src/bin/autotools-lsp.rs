@@ -0,0 +1,12 @@
+//! A language server for `configure.ac` and `Makefile.am`, speaking the
+//! Language Server Protocol over its standard streams.
+use autotools_parser::lsp::{self, LspResult};
+use lsp_server::Connection;
+
+fn main() -> LspResult<()> {
+    let (connection, io_threads) = Connection::stdio();
+    lsp::run(&connection)?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
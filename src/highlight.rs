@@ -0,0 +1,685 @@
+//! Token-level classification of autoconf scripts for syntax highlighting.
+//!
+//! `classify` scans the source of a `configure.ac` and returns the byte
+//! spans of its m4 quotes, macro names, macro arguments, shell keywords,
+//! variables, parameter substitution operators, here document bodies and
+//! comments. It works on the text alone, so that a script which does not
+//! parse, e.g. while it is being edited, is still classified.
+//!
+//! The arguments of a macro call are typed after its `M4MacroSignature`:
+//! `[Cmds]` and `[Body]` arguments are classified as shell code, word-like
+//! arguments have their variables picked out, and the rest is left raw.
+//! The text of an argument which is not covered by a more specific token is
+//! classified as the argument, one token per whitespace-separated run.
+//!
+//! The quotes are always `[` and `]`: `changequote` is not followed.
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::m4_macro::{self, M4Type};
+
+/// Shell reserved words.
+const KEYWORDS: &[&str] = &[
+    "case", "do", "done", "elif", "else", "esac", "fi", "for", "function", "if", "in", "then",
+    "until", "while", "!", "{", "}",
+];
+
+/// Macros defining the macro named by their first argument.
+const DEFINING_MACROS: &[&str] = &[
+    "AC_DEFUN",
+    "AC_DEFUN_ONCE",
+    "AU_DEFUN",
+    "m4_define",
+    "m4_define_default",
+    "m4_pushdef",
+];
+
+/// Operators of parameter substitutions, longest first.
+const PARAM_OPS: &[&str] = &[
+    ":-", ":=", ":?", ":+", "##", "%%", "-", "=", "?", "+", "#", "%",
+];
+
+/// The type of a macro argument, after the `M4Type` of its signature.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum ArgType {
+    /// A raw literal, e.g. the message of `AC_MSG_ERROR`.
+    Lit,
+    /// A shell word.
+    Word,
+    /// Words separated by whitespace.
+    Arr,
+    /// Macro arguments separated by commas.
+    Args,
+    /// A test program to compile.
+    Prog,
+    /// Shell code or macro calls.
+    Cmds,
+    /// The result of a macro definition.
+    Def,
+    /// An argument controlling m4, e.g. of `changequote`.
+    Ctrl,
+    /// The body of a macro definition.
+    Body,
+    /// A path, or paths separated by colons.
+    Path,
+    /// Paths separated by whitespace.
+    Paths,
+    /// A C type, or a struct member such as `struct A.member`.
+    Type,
+    /// C types separated by commas.
+    Types,
+    /// The name of an output shell variable.
+    VarName,
+    /// The name of a library.
+    Library,
+    /// A C preprocessor symbol.
+    CPP,
+    /// A C symbol.
+    Symbol,
+    /// C symbols separated by whitespace.
+    Symbols,
+    /// The name of an automake conditional.
+    AMCond,
+    /// An argument of a user-defined macro, or beyond its signature.
+    Unknown,
+}
+
+impl From<&M4Type> for ArgType {
+    fn from(ty: &M4Type) -> Self {
+        match ty {
+            M4Type::Lit => ArgType::Lit,
+            M4Type::Word => ArgType::Word,
+            M4Type::Arr(_) => ArgType::Arr,
+            M4Type::Args => ArgType::Args,
+            M4Type::Prog => ArgType::Prog,
+            M4Type::Cmds => ArgType::Cmds,
+            M4Type::Def => ArgType::Def,
+            M4Type::Ctrl => ArgType::Ctrl,
+            M4Type::Body => ArgType::Body,
+            M4Type::Path(_) => ArgType::Path,
+            M4Type::Paths(_, _) => ArgType::Paths,
+            M4Type::Type(_) => ArgType::Type,
+            M4Type::Types(_, _) => ArgType::Types,
+            M4Type::VarName(_, _) => ArgType::VarName,
+            M4Type::Library(_) => ArgType::Library,
+            M4Type::CPP => ArgType::CPP,
+            M4Type::Symbol(_) => ArgType::Symbol,
+            M4Type::Symbols(_, _) => ArgType::Symbols,
+            M4Type::AMCond => ArgType::AMCond,
+        }
+    }
+}
+
+impl ArgType {
+    /// How the text of an argument of this type is scanned.
+    fn mode(self) -> Mode {
+        match self {
+            ArgType::Cmds | ArgType::Body => Mode::Shell,
+            ArgType::Word
+            | ArgType::Arr
+            | ArgType::Path
+            | ArgType::Paths
+            | ArgType::Library
+            | ArgType::CPP
+            | ArgType::Symbol
+            | ArgType::Symbols => Mode::Word,
+            _ => Mode::Raw,
+        }
+    }
+}
+
+/// The class of a span of the source.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum TokenKind {
+    /// An m4 quote, `[` or `]`.
+    Quote,
+    /// The name of a macro known by `m4_macro::get_macro`.
+    Macro,
+    /// The name of a macro defined in the script, e.g. with `AC_DEFUN`,
+    /// or which looks like one and is called with arguments.
+    UserMacro,
+    /// Text of a macro argument of the given type.
+    Argument(ArgType),
+    /// A shell reserved word, e.g. `if` or `done`.
+    Keyword,
+    /// A variable reference such as `$foo`, `$1` or the `${foo` and `}` of a
+    /// parameter substitution, or the name of an assignment.
+    Variable,
+    /// The operator of a parameter substitution, e.g. `:-` in `${foo:-bar}`.
+    ParamOp,
+    /// The body of a here document.
+    Heredoc,
+    /// A shell comment, from `#` to the end of the line.
+    Comment,
+    /// An m4 `dnl` comment, to the end of the line.
+    Dnl,
+}
+
+/// A classified span of the source.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SemanticToken {
+    /// The bytes of the token in the source.
+    pub span: Range<usize>,
+    /// The class of the token.
+    pub kind: TokenKind,
+}
+
+/// Classify the tokens of an autoconf script. The tokens are sorted by
+/// their position and do not overlap. Whitespace, punctuation and plain
+/// shell words are not classified.
+pub fn classify(source: &str) -> Vec<SemanticToken> {
+    let mut classifier = Classifier {
+        src: source,
+        pos: 0,
+        tokens: Vec::new(),
+        calls: Vec::new(),
+        defined: HashSet::new(),
+    };
+    classifier.scan(source.len(), Mode::Shell);
+    let mut tokens = classifier.tokens;
+    tokens.sort_by_key(|t| t.span.start);
+    tokens
+}
+
+/// How a region of the source is scanned.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Mode {
+    /// Shell commands.
+    Shell,
+    /// A shell word, whose variables are classified.
+    Word,
+    /// Raw text, where only quotes and macros are classified.
+    Raw,
+}
+
+/// The state of the shell lexer within a region.
+#[derive(Debug, Default)]
+struct ShellState {
+    /// Whether the next word is in command position.
+    cmd_pos: bool,
+    /// Whether a word is being read.
+    in_word: bool,
+    /// Whether the word being read keeps the next one in command position,
+    /// as an assignment or most keywords do.
+    keeps_cmd_pos: bool,
+    /// Whether `in` is expected after `for` or `case`.
+    expect_in: bool,
+    single_quoted: bool,
+    double_quoted: bool,
+    /// The delimiters of the here documents whose bodies follow the line,
+    /// and whether their leading tabs are stripped.
+    heredocs: Vec<(String, bool)>,
+}
+
+impl ShellState {
+    /// Start a word, returning whether it is in command position.
+    fn start_word(&mut self) -> bool {
+        if self.in_word {
+            return false;
+        }
+        self.in_word = true;
+        self.keeps_cmd_pos = false;
+        std::mem::take(&mut self.cmd_pos)
+    }
+
+    fn end_word(&mut self) {
+        if self.in_word {
+            self.in_word = false;
+            self.cmd_pos = self.keeps_cmd_pos;
+        }
+    }
+
+    /// Separate commands, as with `;` or a newline.
+    fn separate(&mut self) {
+        self.in_word = false;
+        self.cmd_pos = true;
+    }
+}
+
+struct Classifier<'a> {
+    src: &'a str,
+    pos: usize,
+    tokens: Vec<SemanticToken>,
+    /// The spans of the macro calls scanned so far.
+    calls: Vec<Range<usize>>,
+    /// The macros defined so far.
+    defined: HashSet<String>,
+}
+
+fn is_name_start(c: u8) -> bool {
+    c == b'_' || c.is_ascii_alphabetic()
+}
+
+fn is_name_char(c: u8) -> bool {
+    c == b'_' || c.is_ascii_alphanumeric()
+}
+
+/// Whether a name looks like that of a macro rather than of a shell command,
+/// e.g. `MY_CHECK`, the heuristic used by the parser for user macros.
+fn looks_like_macro(name: &str) -> bool {
+    name.bytes()
+        .all(|c| c == b'_' || c.is_ascii_uppercase() || c.is_ascii_digit())
+        && !name.as_bytes()[0].is_ascii_digit()
+}
+
+/// Split the arguments of a macro call whose opening parenthesis is right
+/// before `pos`, at the commas and the closing parenthesis outside of quotes
+/// and nested parentheses. The leading whitespace of the arguments is left
+/// out. The last argument ends at `end` if the call is not closed.
+pub(crate) fn split_args(src: &str, mut pos: usize, end: usize) -> Vec<Range<usize>> {
+    let bytes = src.as_bytes();
+    let mut args = Vec::new();
+    let (mut quotes, mut parens) = (0usize, 0usize);
+    let mut arg_start = None;
+    while pos < end {
+        let c = bytes[pos];
+        if arg_start.is_none() {
+            if c.is_ascii_whitespace() {
+                pos += 1;
+                continue;
+            }
+            arg_start = Some(pos);
+        }
+        match c {
+            b'[' => quotes += 1,
+            b']' => quotes = quotes.saturating_sub(1),
+            b'(' if quotes == 0 => parens += 1,
+            b')' if quotes == 0 && parens > 0 => parens -= 1,
+            b',' | b')' if quotes == 0 => {
+                args.push(arg_start.take().unwrap()..pos);
+                if c == b')' {
+                    return args;
+                }
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+    // The call is not closed.
+    args.push(arg_start.unwrap_or(end)..end);
+    args
+}
+
+impl Classifier<'_> {
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.src.as_bytes().get(self.pos + offset).copied()
+    }
+
+    fn push(&mut self, span: Range<usize>, kind: TokenKind) {
+        if !span.is_empty() {
+            self.tokens.push(SemanticToken { span, kind });
+        }
+    }
+
+    /// The end of the line containing `pos`, bounded by `end`.
+    fn line_end(&self, pos: usize, end: usize) -> usize {
+        self.src[pos..end].find('\n').map_or(end, |i| pos + i)
+    }
+
+    fn name_end(&self, start: usize, end: usize) -> usize {
+        self.src.as_bytes()[start..end]
+            .iter()
+            .position(|&c| !is_name_char(c))
+            .map_or(end, |i| start + i)
+    }
+
+    /// Scan the source from the current position up to `end`.
+    fn scan(&mut self, end: usize, mode: Mode) {
+        let bytes = self.src.as_bytes();
+        let mut shell = ShellState {
+            cmd_pos: true,
+            ..Default::default()
+        };
+        while self.pos < end {
+            let c = bytes[self.pos];
+            let start = self.pos;
+            match c {
+                b'[' | b']' => {
+                    self.pos += 1;
+                    self.push(start..self.pos, TokenKind::Quote);
+                }
+                c if is_name_start(c) => {
+                    let name_end = self.name_end(start, end);
+                    let name = &self.src[start..name_end];
+                    if name == "dnl" {
+                        self.pos = self.line_end(start, end);
+                        self.push(start..self.pos, TokenKind::Dnl);
+                        continue;
+                    }
+                    let at_cmd = mode == Mode::Shell && shell.start_word();
+                    if self.macro_call(name_end, end) {
+                        continue;
+                    }
+                    self.pos = name_end;
+                    if mode != Mode::Shell || shell.single_quoted || shell.double_quoted {
+                        continue;
+                    }
+                    let next = bytes.get(name_end).copied();
+                    let delimited = next.is_none_or(|c| {
+                        name_end == end || c.is_ascii_whitespace() || b";&|()<>".contains(&c)
+                    });
+                    if (at_cmd || shell.expect_in && name == "in")
+                        && delimited
+                        && KEYWORDS.contains(&name)
+                    {
+                        self.push(start..name_end, TokenKind::Keyword);
+                        shell.expect_in = matches!(name, "for" | "case");
+                        shell.keeps_cmd_pos = !matches!(name, "for" | "case" | "in" | "function");
+                    } else if at_cmd
+                        && (next == Some(b'=') || self.src[name_end..].starts_with("+="))
+                    {
+                        self.push(start..name_end, TokenKind::Variable);
+                        shell.keeps_cmd_pos = true;
+                    }
+                }
+                b'$' if mode != Mode::Raw && !shell.single_quoted => {
+                    shell.start_word();
+                    self.parameter(end, &mut shell);
+                }
+                b'\n' => {
+                    self.pos += 1;
+                    if mode == Mode::Shell {
+                        shell.separate();
+                        shell.expect_in = false;
+                        for (delim, strip_tabs) in std::mem::take(&mut shell.heredocs) {
+                            self.heredoc_body(&delim, strip_tabs, end);
+                        }
+                    }
+                }
+                _ if mode != Mode::Shell => self.pos += 1,
+                b'#' if !shell.in_word && !shell.single_quoted && !shell.double_quoted => {
+                    self.pos = self.line_end(start, end);
+                    self.push(start..self.pos, TokenKind::Comment);
+                }
+                b'\'' if !shell.double_quoted => {
+                    shell.start_word();
+                    shell.single_quoted = !shell.single_quoted;
+                    self.pos += 1;
+                }
+                b'"' if !shell.single_quoted => {
+                    shell.start_word();
+                    shell.double_quoted = !shell.double_quoted;
+                    self.pos += 1;
+                }
+                _ if shell.single_quoted || shell.double_quoted => {
+                    // An escaped quote does not close the double quotes.
+                    let escaped = c == b'\\' && shell.double_quoted;
+                    self.pos += if escaped { self.escaped_len() } else { 1 };
+                }
+                b'\\' => {
+                    shell.start_word();
+                    self.pos += self.escaped_len();
+                }
+                b' ' | b'\t' => {
+                    shell.end_word();
+                    self.pos += 1;
+                }
+                b';' => {
+                    shell.separate();
+                    shell.expect_in = false;
+                    self.pos += 1;
+                }
+                b'&' | b'|' | b'(' | b')' | b'`' => {
+                    shell.separate();
+                    self.pos += 1;
+                }
+                b'<' if self.src[start..end].starts_with("<<") => {
+                    shell.end_word();
+                    self.pos += 2;
+                    let strip_tabs = self.peek(0) == Some(b'-');
+                    if strip_tabs {
+                        self.pos += 1;
+                    }
+                    if let Some(delim) = self.heredoc_delimiter(end) {
+                        shell.heredocs.push((delim, strip_tabs));
+                    }
+                }
+                b'<' | b'>' => {
+                    shell.end_word();
+                    self.pos += 1;
+                }
+                b'!' | b'{' | b'}' if !shell.in_word => {
+                    let at_cmd = shell.start_word();
+                    self.pos += 1;
+                    let delimited = self
+                        .peek(0)
+                        .is_none_or(|c| self.pos == end || c.is_ascii_whitespace() || c == b';');
+                    if at_cmd && delimited {
+                        self.push(start..self.pos, TokenKind::Keyword);
+                        shell.keeps_cmd_pos = true;
+                    }
+                }
+                _ => {
+                    shell.start_word();
+                    self.pos += self.src[start..].chars().next().map_or(1, char::len_utf8);
+                }
+            }
+        }
+        self.pos = self.pos.max(end);
+    }
+
+    /// The length of a backslash and the character it escapes, which is not
+    /// escaped when it is an m4 quote.
+    fn escaped_len(&self) -> usize {
+        match self.src[self.pos + 1..].chars().next() {
+            None | Some('[' | ']') => 1,
+            Some(c) => 1 + c.len_utf8(),
+        }
+    }
+
+    /// Classify a macro call whose name ends at `name_end`, if the name is
+    /// that of a macro.
+    fn macro_call(&mut self, name_end: usize, end: usize) -> bool {
+        let start = self.pos;
+        let name = &self.src[start..name_end];
+        let has_args = name_end < end && self.src.as_bytes()[name_end] == b'(';
+        let known = m4_macro::get_macro(name);
+        let kind = match known {
+            Some((_, signature, _)) if has_args || signature.num_args_required == 0 => {
+                TokenKind::Macro
+            }
+            Some(_) => return false,
+            None if self.defined.contains(name) || has_args && looks_like_macro(name) => {
+                TokenKind::UserMacro
+            }
+            None => return false,
+        };
+        self.push(start..name_end, kind);
+        self.pos = name_end;
+        if has_args {
+            self.pos += 1;
+            let args = split_args(self.src, self.pos, end);
+            let canonical = known.map_or(name, |(canonical, _, _)| canonical.as_str());
+            if DEFINING_MACROS.contains(&canonical) || DEFINING_MACROS.contains(&name) {
+                if let Some(first) = args.first() {
+                    let defined = self.src[first.clone()].replace(['[', ']'], "");
+                    self.defined.insert(defined.trim().to_string());
+                }
+            }
+            let signature = known.map(|(_, signature, _)| signature);
+            let mut idx_arg_type = 0;
+            for (i, arg) in args.iter().enumerate() {
+                let ty = signature
+                    .and_then(|s| s.arg_types.get(idx_arg_type))
+                    .map_or(ArgType::Unknown, ArgType::from);
+                self.argument(arg.clone(), ty);
+                // Types repeat as the parser does, see `AutoconfParser::macro_call`.
+                idx_arg_type += 1;
+                if let Some((start, end)) = signature.and_then(|s| s.repeat) {
+                    let remain_size = args.len() - i - 1;
+                    if idx_arg_type > end && remain_size > end - start {
+                        idx_arg_type = start;
+                    }
+                }
+            }
+            self.pos = match args.last() {
+                // Past the closing parenthesis.
+                Some(last) if last.end < end => last.end + 1,
+                Some(_) => end,
+                None => self.pos,
+            };
+        }
+        self.calls.push(start..self.pos);
+        true
+    }
+
+    /// Classify an argument of a macro call. The text which is not covered
+    /// by other tokens is classified as the argument.
+    fn argument(&mut self, span: Range<usize>, ty: ArgType) {
+        let (first_token, first_call) = (self.tokens.len(), self.calls.len());
+        self.pos = span.start;
+        self.scan(span.end, ty.mode());
+        let mut covered = self.tokens[first_token..]
+            .iter()
+            .map(|t| t.span.clone())
+            .chain(self.calls[first_call..].iter().cloned())
+            .collect::<Vec<_>>();
+        covered.sort_by_key(|r| r.start);
+        let mut pos = span.start;
+        for covered in covered
+            .into_iter()
+            .chain(std::iter::once(span.end..span.end))
+        {
+            if covered.start > pos {
+                self.words(pos..covered.start, ty);
+            }
+            pos = pos.max(covered.end);
+        }
+    }
+
+    /// Classify the whitespace-separated runs of a span as an argument.
+    fn words(&mut self, span: Range<usize>, ty: ArgType) {
+        let mut start = None;
+        for (i, c) in self.src[span.clone()].char_indices() {
+            match (c.is_whitespace(), start) {
+                (true, Some(s)) => {
+                    self.push(s..span.start + i, TokenKind::Argument(ty));
+                    start = None;
+                }
+                (false, None) => start = Some(span.start + i),
+                _ => {}
+            }
+        }
+        if let Some(s) = start {
+            self.push(s..span.end, TokenKind::Argument(ty));
+        }
+    }
+
+    /// Classify a parameter starting with `$` at the current position.
+    fn parameter(&mut self, end: usize, shell: &mut ShellState) {
+        let start = self.pos;
+        let bytes = self.src.as_bytes();
+        self.pos += 1;
+        match self.peek(0) {
+            _ if self.pos >= end => {}
+            Some(b'{') => {
+                self.pos += 1;
+                let Some(close) = self.closing_brace(end) else {
+                    return;
+                };
+                let mut name_start = self.pos;
+                if self.peek(0) == Some(b'#') && self.peek(1).is_some_and(is_name_start) {
+                    self.push(start..self.pos, TokenKind::Variable);
+                    self.push(self.pos..self.pos + 1, TokenKind::ParamOp);
+                    name_start = self.pos + 1;
+                }
+                let name_end = match bytes[name_start] {
+                    c if is_name_char(c) => self.name_end(name_start, close),
+                    _ if name_start == close => name_start,
+                    _ => name_start + 1,
+                };
+                let var_start = if name_start == self.pos {
+                    start
+                } else {
+                    name_start
+                };
+                self.push(var_start..name_end, TokenKind::Variable);
+                self.pos = name_end;
+                if let Some(op) = PARAM_OPS
+                    .iter()
+                    .find(|op| self.src[self.pos..close].starts_with(**op))
+                {
+                    self.push(self.pos..self.pos + op.len(), TokenKind::ParamOp);
+                    self.pos += op.len();
+                }
+                if self.pos < close {
+                    let in_shell = std::mem::take(shell);
+                    self.scan(close, Mode::Word);
+                    *shell = in_shell;
+                }
+                self.push(close..close + 1, TokenKind::Variable);
+                self.pos = close + 1;
+            }
+            Some(b'(') => {
+                // A command substitution, whose commands follow.
+                self.pos += 1;
+                shell.separate();
+            }
+            Some(c) if is_name_start(c) => {
+                self.pos = self.name_end(self.pos, end);
+                self.push(start..self.pos, TokenKind::Variable);
+            }
+            Some(c) if c.is_ascii_digit() || b"@*#?-$!".contains(&c) => {
+                self.pos += 1;
+                self.push(start..self.pos, TokenKind::Variable);
+            }
+            _ => {}
+        }
+    }
+
+    /// The position of the `}` closing a parameter substitution whose
+    /// opening brace precedes the current position.
+    fn closing_brace(&self, end: usize) -> Option<usize> {
+        let mut depth = 0;
+        for (i, c) in self.src.as_bytes()[self.pos..end].iter().enumerate() {
+            match c {
+                b'{' => depth += 1,
+                b'}' if depth == 0 => return Some(self.pos + i),
+                b'}' => depth -= 1,
+                b'\n' => return None,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Read the delimiter of a here document following `<<` or `<<-`,
+    /// without its quotes.
+    fn heredoc_delimiter(&mut self, end: usize) -> Option<String> {
+        let bytes = self.src.as_bytes();
+        while self.pos < end && matches!(bytes[self.pos], b' ' | b'\t') {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < end
+            && !bytes[self.pos].is_ascii_whitespace()
+            && !b";&|()<>".contains(&bytes[self.pos])
+        {
+            self.pos += 1;
+        }
+        let delim = self.src[start..self.pos].replace(['\'', '"', '\\', '[', ']'], "");
+        (!delim.is_empty()).then_some(delim)
+    }
+
+    /// Classify the body of a here document at the current position, which
+    /// is the start of a line, up to its delimiter.
+    fn heredoc_body(&mut self, delim: &str, strip_tabs: bool, end: usize) {
+        let start = self.pos;
+        let mut body_end = start;
+        while self.pos < end {
+            let line_end = self.line_end(self.pos, end);
+            let mut line = &self.src[self.pos..line_end];
+            if strip_tabs {
+                line = line.trim_start_matches('\t');
+            }
+            if line == delim {
+                self.push(start..body_end, TokenKind::Heredoc);
+                self.pos = line_end;
+                return;
+            }
+            body_end = line_end;
+            self.pos = (line_end + 1).min(end);
+        }
+        self.push(start..end, TokenKind::Heredoc);
+    }
+}
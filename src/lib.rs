@@ -27,6 +27,17 @@
 //!  * Common arithmetic operations required by the POSIX standard
 //!  * Variable expansion
 //!  * **Not yet implemented**: Other inner abitrary parameter/substitution expansion
+//!
+//! # Features
+//!
+//! * `serde`: implements `Serialize` and `Deserialize` for the AST, the m4
+//!   macro types and `SourcePos`. `AutoconfPool` follows the JSON schema in
+//!   `schema/autoconf-pool.schema.json`.
+//! * `cli`: builds the `autotools-parser` command, which dumps, formats,
+//!   lints and queries autotools files from the shell, e.g.
+//!   `autotools-parser lint --format json configure.ac Makefile.am`.
+//! * `lsp`: adds the `lsp` module and builds the `autotools-lsp` language
+//!   server, which speaks the Language Server Protocol over stdio.
 
 #![doc(html_root_url = "https://docs.rs/autotools-parser/0.1")]
 #![cfg_attr(not(test), deny(clippy::print_stdout))]
@@ -41,9 +52,18 @@
 #![deny(unused_qualifications)]
 #![forbid(unsafe_code)]
 
+pub mod analysis;
 pub mod ast;
+pub mod autoupdate;
+pub mod backend;
+pub mod highlight;
 pub mod lexer;
+#[cfg(feature = "lsp")]
+pub mod lsp;
 pub mod m4_macro;
 pub mod parse;
 pub mod preprocess;
+pub mod project;
+pub mod query;
 pub mod token;
+mod util;
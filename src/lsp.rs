@@ -0,0 +1,712 @@
+//! A language server for `configure.ac` and `Makefile.am`.
+//!
+//! `run` serves the Language Server Protocol over a `lsp_server::Connection`,
+//! e.g. the standard streams of the `autotools-lsp` command. Documents are
+//! synchronized in full and parsed again on every change as `ProjectFile`s,
+//! which tells autoconf scripts from makefiles by their names. The server
+//! provides:
+//!
+//! * diagnostics from the `ParseError` of a document which does not parse,
+//! * hover on a macro name, showing its `M4MacroSignature`,
+//! * go to definition of shell variables, which may be defined by macros,
+//!   and of the macros defined with `AC_DEFUN`,
+//! * find references of an automake conditional, from its `AM_CONDITIONAL`
+//!   to the `if` of every `Makefile.am` of the workspace,
+//! * document symbols for the `AC_ARG_*` options and the `AC_DEFUN` macros,
+//! * semantic tokens of autoconf scripts from `highlight::classify`.
+//!
+//! Automake nodes carry no source positions, so positions in makefiles are
+//! found in their text.
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    DocumentSymbolRequest, GotoDefinition, HoverRequest, References, Request as RequestTrait,
+    SemanticTokensFullRequest,
+};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DocumentSymbol, GotoDefinitionResponse, Hover, HoverContents,
+    HoverProviderCapability, InitializeParams, Location, MarkupContent, MarkupKind, OneOf,
+    Position, PublishDiagnosticsParams, Range, SemanticToken, SemanticTokenModifier,
+    SemanticTokenType, SemanticTokens, SemanticTokensFullOptions, SemanticTokensLegend,
+    SemanticTokensOptions, SemanticTokensResult, SemanticTokensServerCapabilities,
+    ServerCapabilities, SymbolKind, TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
+};
+
+use crate::ast::node::{AcCommand, M4Argument, M4Macro, ShellCommand};
+use crate::ast::MayM4;
+use crate::backend::configure::configure_option;
+use crate::highlight::{self, ArgType, TokenKind};
+use crate::m4_macro::{self, VarUsage};
+use crate::parse::ParseErrorKind;
+use crate::project::{is_autoconf, AutoconfFile, FileContent, Project, ProjectError, ProjectFile};
+
+/// Macros defining other macros, with the name as their first argument.
+const DEFINING_MACROS: &[&str] = &[
+    "AC_DEFUN",
+    "AC_DEFUN_ONCE",
+    "AU_DEFUN",
+    "m4_define",
+    "define",
+];
+
+/// The semantic token types of the server, indexed by `token_type`.
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::MACRO,
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::OPERATOR,
+    SemanticTokenType::STRING,
+    SemanticTokenType::COMMENT,
+    SemanticTokenType::TYPE,
+];
+
+/// The semantic token modifiers of the server, as bits of `token_modifiers_bitset`.
+const TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[SemanticTokenModifier::DEFAULT_LIBRARY];
+
+/// The result of the handlers of the server.
+pub type LspResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Serve a client until it asks to shut down.
+pub fn run(connection: &Connection) -> LspResult<()> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: SemanticTokensLegend {
+                    token_types: TOKEN_TYPES.to_vec(),
+                    token_modifiers: TOKEN_MODIFIERS.to_vec(),
+                },
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    };
+    let params = connection.initialize(serde_json::to_value(capabilities)?)?;
+    let params: InitializeParams = serde_json::from_value(params)?;
+    let mut server = Server::new(workspace_root(&params));
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                connection.sender.send(server.handle(request).into())?;
+            }
+            Message::Notification(notification) => {
+                if let Some(published) = server.notify(notification)? {
+                    connection.sender.send(published.into())?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+#[allow(deprecated)]
+fn workspace_root(params: &InitializeParams) -> Option<PathBuf> {
+    let uri = match &params.workspace_folders {
+        Some(folders) if !folders.is_empty() => &folders[0].uri,
+        _ => params.root_uri.as_ref()?,
+    };
+    Some(uri_path(uri))
+}
+
+/// An open document.
+#[derive(Debug)]
+struct Document {
+    text: String,
+    file: Result<ProjectFile, ProjectError>,
+}
+
+/// The state of the server: the documents opened by the client.
+#[derive(Debug, Default)]
+pub struct Server {
+    root: Option<PathBuf>,
+    documents: HashMap<Uri, Document>,
+}
+
+impl Server {
+    /// A server for the workspace in `root`, whose makefiles are searched
+    /// for references.
+    pub fn new(root: Option<PathBuf>) -> Self {
+        Self {
+            root,
+            documents: HashMap::new(),
+        }
+    }
+
+    fn handle(&self, request: Request) -> Response {
+        fn params<R: RequestTrait>(request: Request) -> serde_json::Result<R::Params> {
+            serde_json::from_value(request.params)
+        }
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            HoverRequest::METHOD => params::<HoverRequest>(request).map(|p| {
+                let p = p.text_document_position_params;
+                serde_json::to_value(self.hover(&p.text_document.uri, p.position))
+            }),
+            GotoDefinition::METHOD => params::<GotoDefinition>(request).map(|p| {
+                let p = p.text_document_position_params;
+                let location = self.definition(&p.text_document.uri, p.position);
+                serde_json::to_value(location.map(GotoDefinitionResponse::Scalar))
+            }),
+            References::METHOD => params::<References>(request).map(|p| {
+                let position = p.text_document_position;
+                serde_json::to_value(self.references(
+                    &position.text_document.uri,
+                    position.position,
+                    p.context.include_declaration,
+                ))
+            }),
+            DocumentSymbolRequest::METHOD => params::<DocumentSymbolRequest>(request)
+                .map(|p| serde_json::to_value(self.document_symbols(&p.text_document.uri))),
+            SemanticTokensFullRequest::METHOD => {
+                params::<SemanticTokensFullRequest>(request).map(|p| {
+                    let data = self.semantic_tokens(&p.text_document.uri);
+                    serde_json::to_value(SemanticTokensResult::Tokens(SemanticTokens {
+                        result_id: None,
+                        data,
+                    }))
+                })
+            }
+            method => {
+                let message = format!("unknown request {}", method);
+                return Response::new_err(id, ErrorCode::MethodNotFound as i32, message);
+            }
+        };
+        match result.and_then(|value| value) {
+            Ok(value) => Response::new_ok(id, value),
+            Err(e) => Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string()),
+        }
+    }
+
+    /// Handle a notification, returning the diagnostics to publish.
+    fn notify(&mut self, notification: Notification) -> LspResult<Option<Notification>> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: <DidOpenTextDocument as NotificationTrait>::Params =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+                self.open(document.uri.clone(), document.text);
+                document.uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: <DidChangeTextDocument as NotificationTrait>::Params =
+                    serde_json::from_value(notification.params)?;
+                // The document is synchronized in full.
+                let Some(change) = params.content_changes.into_iter().last() else {
+                    return Ok(None);
+                };
+                self.open(params.text_document.uri.clone(), change.text);
+                params.text_document.uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: <DidCloseTextDocument as NotificationTrait>::Params =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                params.text_document.uri
+            }
+            _ => return Ok(None),
+        };
+        let params = PublishDiagnosticsParams {
+            diagnostics: self.diagnostics(&uri),
+            uri,
+            version: None,
+        };
+        Ok(Some(Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            params,
+        )))
+    }
+
+    /// Open a document, or replace its text.
+    pub fn open(&mut self, uri: Uri, text: String) {
+        let file = ProjectFile::parse(uri_path(&uri), text.clone());
+        self.documents.insert(uri, Document { text, file });
+    }
+
+    /// The error of a document which does not parse.
+    pub fn diagnostics(&self, uri: &Uri) -> Vec<Diagnostic> {
+        let Some(Document {
+            text,
+            file: Err(ProjectError::Parse(_, error)),
+        }) = self.documents.get(uri)
+        else {
+            return Vec::new();
+        };
+        let start = match error.pos() {
+            Some(pos) => position(text, pos.line.saturating_sub(1), pos.col.saturating_sub(1)),
+            None if matches!(error.kind, ParseErrorKind::UnexpectedEOF) => end_of(text),
+            None => Position::new(0, 0),
+        };
+        // The backtrace, when one is captured, is not for the user.
+        let message = error.to_string();
+        let message = message.split("\nBacktrace:").next().unwrap_or_default();
+        vec![Diagnostic {
+            range: Range::new(start, start),
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("autotools-parser".to_string()),
+            message: message.to_string(),
+            ..Default::default()
+        }]
+    }
+
+    /// The signature of the macro under the cursor.
+    pub fn hover(&self, uri: &Uri, position: Position) -> Option<Hover> {
+        let document = self.documents.get(uri)?;
+        let (name, range) = word_at(&document.text, position)?;
+        let (name, signature, original) = m4_macro::get_macro(&name)?;
+        let mut text = format!("```m4\n{}(", name);
+        for (i, arg_type) in signature.arg_types.iter().enumerate() {
+            let sep = if i == 0 { "" } else { ", " };
+            if i < signature.num_args_required {
+                let _ = write!(text, "{}{:?}", sep, arg_type);
+            } else {
+                let _ = write!(text, "{}[{:?}]", sep, arg_type);
+            }
+        }
+        if signature.repeat.is_some() {
+            text.push_str(", ...");
+        }
+        text.push_str(")\n```\n");
+        if let Some(original) = original {
+            let _ = writeln!(
+                text,
+                "\n`{}` is obsolete and replaced by `{}`.",
+                original, name
+            );
+        }
+        if let Some(vars) = &signature.shell_vars {
+            text.push_str("\nShell variables:\n");
+            for var in vars {
+                let usage = match var.attrs.usage {
+                    VarUsage::Referenced => "read",
+                    VarUsage::Defined => "set",
+                    VarUsage::Added => "appended to",
+                };
+                let kind = format!("{:?}", var.attrs.kind).to_lowercase();
+                let _ = writeln!(text, "* `{}`: {}, {}", var.name, kind, usage);
+            }
+        }
+        if let Some(symbols) = &signature.cpp_symbols {
+            text.push_str("\nPreprocessor symbols:\n");
+            for symbol in symbols {
+                let _ = writeln!(text, "* `{}`", symbol);
+            }
+        }
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: text,
+            }),
+            range: Some(range),
+        })
+    }
+
+    /// Where the macro or shell variable under the cursor is defined. Macros
+    /// are searched in the other open documents too, e.g. `acinclude.m4`.
+    pub fn definition(&self, uri: &Uri, position: Position) -> Option<Location> {
+        let document = self.documents.get(uri)?;
+        let (name, _) = word_at(&document.text, position)?;
+        let others = self.documents.iter().filter(|(other, _)| *other != uri);
+        for (uri, document) in std::iter::once((uri, document)).chain(others) {
+            let Some(ac) = autoconf(document) else {
+                continue;
+            };
+            if let Some(line) = macro_definition(ac, &name) {
+                let range = find_word(&document.text, line, &name);
+                return Some(Location::new(uri.clone(), range));
+            }
+        }
+        let ac = autoconf(document)?;
+        let line = variable_definition(ac, &name)?;
+        Some(Location::new(
+            uri.clone(),
+            find_word(&document.text, line, &name),
+        ))
+    }
+
+    /// The uses of the automake conditional under the cursor in the
+    /// makefiles of the workspace and the open documents, with its
+    /// `AM_CONDITIONAL` if `include_declaration` is set.
+    pub fn references(
+        &self,
+        uri: &Uri,
+        position: Position,
+        include_declaration: bool,
+    ) -> Vec<Location> {
+        let Some((name, _)) = self
+            .documents
+            .get(uri)
+            .and_then(|document| word_at(&document.text, position))
+        else {
+            return Vec::new();
+        };
+        let project = self.root.as_ref().and_then(|root| Project::load(root).ok());
+        let mut files = self
+            .documents
+            .iter()
+            .filter_map(|(uri, document)| Some((uri.clone(), document.file.as_ref().ok()?)))
+            .collect::<Vec<_>>();
+        if let (Some(root), Some(project)) = (&self.root, &project) {
+            for file in project.files() {
+                let uri = path_uri(&root.join(&file.path));
+                if !self.documents.contains_key(&uri) {
+                    files.push((uri, file));
+                }
+            }
+        }
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut ret = Vec::new();
+        for (uri, file) in files {
+            match &file.content {
+                FileContent::Autoconf(ac) if include_declaration => {
+                    for (line, m4_macro) in macro_calls(ac) {
+                        let declared = m4_macro.name == "AM_CONDITIONAL"
+                            && m4_macro
+                                .args
+                                .first()
+                                .and_then(|a| ac.render_arg(a))
+                                .as_ref()
+                                == Some(&name);
+                        if declared {
+                            let range = find_word(&file.source, line, &name);
+                            ret.push(Location::new(uri.clone(), range));
+                        }
+                    }
+                }
+                FileContent::Autoconf(_) => {}
+                FileContent::Automake(_) => {
+                    for (i, line) in file.source.lines().enumerate() {
+                        let mut words = line.split_whitespace();
+                        let keyword = words.next();
+                        let guard = words.next().map(|w| w.trim_start_matches('!'));
+                        if matches!(keyword, Some("if" | "else" | "endif")) && guard == Some(&name)
+                        {
+                            let range = find_word(&file.source, i + 1, &name);
+                            ret.push(Location::new(uri.clone(), range));
+                        }
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    /// The options declared with `AC_ARG_*` and the macros defined with
+    /// `AC_DEFUN`.
+    #[allow(deprecated)]
+    pub fn document_symbols(&self, uri: &Uri) -> Vec<DocumentSymbol> {
+        let Some(document) = self.documents.get(uri) else {
+            return Vec::new();
+        };
+        let Some(ac) = autoconf(document) else {
+            return Vec::new();
+        };
+        let mut ret = Vec::new();
+        for (id, node) in ac.pool.nodes.iter() {
+            let AcCommand(MayM4::Macro(m4_macro)) = &node.cmd else {
+                continue;
+            };
+            let (Some((start, _)), Some((_, end))) = (node.range.first(), node.range.last()) else {
+                continue;
+            };
+            let first_arg = || m4_macro.args.first().and_then(|a| ac.render_arg(a));
+            let (name, word, kind, detail) = match m4_macro.name.as_str() {
+                "AC_ARG_ENABLE" | "AC_ARG_WITH" => {
+                    let Some(option) = configure_option(ac, m4_macro) else {
+                        continue;
+                    };
+                    let prefix = if m4_macro.name == "AC_ARG_WITH" {
+                        "with"
+                    } else {
+                        "enable"
+                    };
+                    let name = format!("--{}-{}", prefix, option.name);
+                    (name, option.name, SymbolKind::PROPERTY, option.help)
+                }
+                "AC_ARG_VAR" => {
+                    let Some(var) = first_arg() else {
+                        continue;
+                    };
+                    let help = m4_macro.args.get(1).and_then(|a| ac.render_arg(a));
+                    (
+                        var.clone(),
+                        var,
+                        SymbolKind::VARIABLE,
+                        help.unwrap_or_default(),
+                    )
+                }
+                "AC_DEFUN" | "AC_DEFUN_ONCE" | "AU_DEFUN" => {
+                    let Some(defined) = first_arg() else {
+                        continue;
+                    };
+                    (
+                        defined.clone(),
+                        defined,
+                        SymbolKind::FUNCTION,
+                        String::new(),
+                    )
+                }
+                _ => continue,
+            };
+            let range = Range::new(
+                Position::new(*start as u32 - 1, 0),
+                Position::new(*end as u32 - 1, 0),
+            );
+            ret.push((
+                id,
+                DocumentSymbol {
+                    name,
+                    detail: (!detail.is_empty()).then_some(detail),
+                    kind,
+                    tags: None,
+                    deprecated: None,
+                    range,
+                    selection_range: find_word(&document.text, *start, &word),
+                    children: None,
+                },
+            ));
+        }
+        ret.sort_by_key(|(id, symbol)| (symbol.range.start.line, *id));
+        ret.into_iter().map(|(_, symbol)| symbol).collect()
+    }
+
+    /// The semantic tokens of an autoconf script, split at the ends of lines.
+    /// Scripts which do not parse are classified too.
+    pub fn semantic_tokens(&self, uri: &Uri) -> Vec<SemanticToken> {
+        let Some(document) = self.documents.get(uri) else {
+            return Vec::new();
+        };
+        if !is_autoconf(&uri_path(uri)) {
+            return Vec::new();
+        }
+        let text = &document.text;
+        let mut ret = Vec::new();
+        let (mut prev_line, mut prev_start) = (0, 0);
+        // The line of `scanned`, and the offset of the line.
+        let (mut line, mut line_start, mut scanned) = (0, 0, 0);
+        for token in highlight::classify(text) {
+            let Some((token_type, modifiers)) = semantic_token_type(token.kind) else {
+                continue;
+            };
+            let mut start = token.span.start;
+            for part in text[token.span.clone()].split('\n') {
+                for (i, _) in text[scanned..start].match_indices('\n') {
+                    line += 1;
+                    line_start = scanned + i + 1;
+                }
+                scanned = start;
+                if !part.is_empty() {
+                    let col = text[line_start..start].encode_utf16().count() as u32;
+                    ret.push(SemanticToken {
+                        delta_line: line - prev_line,
+                        delta_start: if line == prev_line {
+                            col - prev_start
+                        } else {
+                            col
+                        },
+                        length: part.encode_utf16().count() as u32,
+                        token_type,
+                        token_modifiers_bitset: modifiers,
+                    });
+                    (prev_line, prev_start) = (line, col);
+                }
+                start += part.len() + 1;
+            }
+        }
+        ret
+    }
+}
+
+/// The index of the semantic token type of a token kind in `TOKEN_TYPES`
+/// and its modifiers, or `None` if the kind is not highlighted.
+fn semantic_token_type(kind: TokenKind) -> Option<(u32, u32)> {
+    let token_type = match kind {
+        TokenKind::Macro | TokenKind::UserMacro => SemanticTokenType::MACRO,
+        TokenKind::Keyword => SemanticTokenType::KEYWORD,
+        TokenKind::Variable | TokenKind::Argument(ArgType::VarName) => SemanticTokenType::VARIABLE,
+        TokenKind::Quote | TokenKind::ParamOp => SemanticTokenType::OPERATOR,
+        TokenKind::Comment | TokenKind::Dnl => SemanticTokenType::COMMENT,
+        TokenKind::Argument(ArgType::Type | ArgType::Types) => SemanticTokenType::TYPE,
+        // Shell code is highlighted by its own tokens.
+        TokenKind::Argument(ArgType::Cmds | ArgType::Body) => return None,
+        TokenKind::Argument(_) | TokenKind::Heredoc => SemanticTokenType::STRING,
+    };
+    let index = TOKEN_TYPES.iter().position(|t| *t == token_type)?;
+    // Known macros are from the default library.
+    let modifiers = u32::from(kind == TokenKind::Macro);
+    Some((index as u32, modifiers))
+}
+
+fn autoconf(document: &Document) -> Option<&AutoconfFile> {
+    document.file.as_ref().ok()?.as_autoconf()
+}
+
+/// The macro calls of a script with their lines, in the order of the lines.
+fn macro_calls(ac: &AutoconfFile) -> Vec<(usize, &M4Macro)> {
+    let mut ret = ac
+        .pool
+        .nodes
+        .iter()
+        .filter_map(|(id, node)| match &node.cmd {
+            AcCommand(MayM4::Macro(m4_macro)) => Some((node.range_start()?, id, m4_macro)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    ret.sort_by_key(|(line, id, _)| (*line, *id));
+    ret.into_iter().map(|(line, _, m)| (line, m)).collect()
+}
+
+/// The line of the first definition of a macro.
+fn macro_definition(ac: &AutoconfFile, name: &str) -> Option<usize> {
+    macro_calls(ac).into_iter().find_map(|(line, m4_macro)| {
+        let defines = DEFINING_MACROS.contains(&m4_macro.name.as_str())
+            && match m4_macro.args.first() {
+                Some(arg @ (M4Argument::Literal(_) | M4Argument::Word(_))) => {
+                    ac.render_arg(arg).as_deref() == Some(name)
+                }
+                _ => false,
+            };
+        defines.then_some(line)
+    })
+}
+
+/// The line of the first definition of a shell variable, by an assignment,
+/// a `for` loop or a macro call.
+fn variable_definition(ac: &AutoconfFile, name: &str) -> Option<usize> {
+    let mut ret = ac
+        .pool
+        .nodes
+        .iter()
+        .filter(|(_, node)| match &node.cmd {
+            AcCommand(MayM4::Shell(ShellCommand::Assignment(var, _)))
+            | AcCommand(MayM4::Shell(ShellCommand::For { var, .. })) => var == name,
+            AcCommand(MayM4::Macro(m4_macro)) => m4_macro
+                .effects
+                .as_ref()
+                .and_then(|effects| effects.shell_vars.as_ref())
+                .is_some_and(|vars| {
+                    vars.iter()
+                        .any(|var| var.name == name && var.attrs.usage == VarUsage::Defined)
+                }),
+            _ => false,
+        })
+        .filter_map(|(id, node)| Some((node.range_start()?, id)))
+        .collect::<Vec<_>>();
+    ret.sort();
+    ret.first().map(|(line, _)| *line)
+}
+
+/// The path of a `file:` URI.
+fn uri_path(uri: &Uri) -> PathBuf {
+    PathBuf::from(uri.path().as_estr().decode().into_string_lossy().as_ref())
+}
+
+/// The `file:` URI of an absolute path.
+fn path_uri(path: &Path) -> Uri {
+    let mut text = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                text.push(byte as char)
+            }
+            _ => {
+                let _ = write!(text, "%{:02X}", byte);
+            }
+        }
+    }
+    Uri::from_str(&text).unwrap()
+}
+
+/// Converts a line and a byte offset in it, both from 0, to a position
+/// counting UTF-16 code units as LSP does.
+fn position(text: &str, line: usize, byte: usize) -> Position {
+    let col = text
+        .lines()
+        .nth(line)
+        .map_or(0, |l| l[..byte.min(l.len())].encode_utf16().count());
+    Position::new(line as u32, col as u32)
+}
+
+fn end_of(text: &str) -> Position {
+    match text.rsplit_once('\n') {
+        Some((head, last)) => Position::new(
+            head.matches('\n').count() as u32 + 1,
+            last.encode_utf16().count() as u32,
+        ),
+        None => Position::new(0, text.encode_utf16().count() as u32),
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// The name under the cursor, e.g. of a macro or variable.
+fn word_at(text: &str, position: Position) -> Option<(String, Range)> {
+    let line = text.lines().nth(position.line as usize)?;
+    let mut units = 0;
+    let cursor = line
+        .char_indices()
+        .find(|(_, c)| {
+            units += c.len_utf16();
+            units > position.character as usize
+        })
+        .map_or(line.len(), |(i, _)| i);
+    let start = line[..cursor]
+        .rfind(|c| !is_word_char(c))
+        .map_or(0, |i| i + 1);
+    let end = line[cursor..]
+        .find(|c| !is_word_char(c))
+        .map_or(line.len(), |i| cursor + i);
+    if start == end {
+        return None;
+    }
+    let range = Range::new(
+        position_in(line, position.line, start),
+        position_in(line, position.line, end),
+    );
+    Some((line[start..end].to_string(), range))
+}
+
+fn position_in(line: &str, number: u32, byte: usize) -> Position {
+    Position::new(number, line[..byte].encode_utf16().count() as u32)
+}
+
+/// The range of a word on a line, counted from 1, or of the line if the
+/// word is not on it, e.g. for a variable set by a macro.
+fn find_word(text: &str, line: usize, word: &str) -> Range {
+    let number = line.saturating_sub(1) as u32;
+    let text = text.lines().nth(line.saturating_sub(1)).unwrap_or_default();
+    let found = text.match_indices(word).find(|(i, _)| {
+        let before = text[..*i].chars().next_back();
+        let after = text[i + word.len()..].chars().next();
+        !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+    });
+    let (start, end) = match found {
+        Some((i, _)) => (i, i + word.len()),
+        None => (text.len() - text.trim_start().len(), text.trim_end().len()),
+    };
+    Range::new(
+        position_in(text, number, start),
+        position_in(text, number, end),
+    )
+}
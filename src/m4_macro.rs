@@ -5,7 +5,12 @@ use M4ExportType::*;
 use M4Type::*;
 
 /// Specify types of arguments or expansion of m4 macro calls.
+///
+/// With the `serde` feature, an export function is written as whether the
+/// type has one, as closures can not be serialized. It is read back as
+/// none, so deserialized types export nothing.
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum M4Type {
     /// raw literal treated as is.
     Lit,
@@ -28,23 +33,35 @@ pub enum M4Type {
     /// path string, while some variables based on the value may be generated.
     /// a colon-separated list of paths can be appended to a path string.
     /// e.g. path1:path2:path3
-    Path(Option<M4ExportFunc>),
+    Path(#[cfg_attr(feature = "serde", serde(with = "export_func"))] Option<M4ExportFunc>),
     /// array of path strings separated by whitespace.
-    Paths(ArrayDelim, Option<M4ExportFunc>),
+    Paths(
+        ArrayDelim,
+        #[cfg_attr(feature = "serde", serde(with = "export_func"))] Option<M4ExportFunc>,
+    ),
     /// the type string include struct member strings, e.g. `struct A.member`
-    Type(Option<M4ExportFunc>),
+    Type(#[cfg_attr(feature = "serde", serde(with = "export_func"))] Option<M4ExportFunc>),
     /// array of type strings separated by comma (expecting the array to be enclosed by quotes)
-    Types(ArrayDelim, Option<M4ExportFunc>),
+    Types(
+        ArrayDelim,
+        #[cfg_attr(feature = "serde", serde(with = "export_func"))] Option<M4ExportFunc>,
+    ),
     /// output shell variable name, conversions may be applied to create other variable names.
-    VarName(Option<VarAttrs>, Option<M4ExportFunc>),
+    VarName(
+        Option<VarAttrs>,
+        #[cfg_attr(feature = "serde", serde(with = "export_func"))] Option<M4ExportFunc>,
+    ),
     /// library name , conversions may be applied to become variable names.
-    Library(Option<M4ExportFunc>),
+    Library(#[cfg_attr(feature = "serde", serde(with = "export_func"))] Option<M4ExportFunc>),
     /// C preprocessor symbol name, conversions may be applied to become variable names.
     CPP,
     /// C symbol, conversions may be applied to become variable names.
-    Symbol(Option<M4ExportFunc>),
+    Symbol(#[cfg_attr(feature = "serde", serde(with = "export_func"))] Option<M4ExportFunc>),
     /// array of C symbols, separated by whitespace.
-    Symbols(ArrayDelim, Option<M4ExportFunc>),
+    Symbols(
+        ArrayDelim,
+        #[cfg_attr(feature = "serde", serde(with = "export_func"))] Option<M4ExportFunc>,
+    ),
     /// Automake conditional name.
     AMCond,
 }
@@ -52,8 +69,29 @@ pub enum M4Type {
 /// A lambda function exporging side effects.
 pub type M4ExportFunc = &'static (dyn Fn(&str) -> Vec<(M4ExportType, String)> + Sync);
 
+/// The serde representation of export functions in `M4Type`.
+#[cfg(feature = "serde")]
+mod export_func {
+    use super::M4ExportFunc;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        func: &Option<M4ExportFunc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(func.is_some())
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<M4ExportFunc>, D::Error> {
+        bool::deserialize(deserializer).map(|_| None)
+    }
+}
+
 /// The type of any dynamically exported information
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum M4ExportType {
     /// Export a shell variable
     ExVar(VarAttrs),
@@ -104,6 +142,7 @@ impl std::fmt::Debug for M4Type {
 
 /// Represents an argument of m4 macro call.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum M4Argument<C, W> {
     /// raw literal
     Literal(String),
@@ -127,6 +166,7 @@ pub enum M4Argument<C, W> {
 /// 1. CompoundCommand
 /// 2. SimpleWord
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct M4Macro<C, W> {
     /// m4 macro name
     pub name: String,
@@ -162,6 +202,7 @@ impl<C, W> M4Macro<C, W> {
 
 /// Represent side effects that an operation (e.g. macro call) could produce.
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SideEffect {
     /// exported shell variables.
     pub shell_vars: Option<Vec<Var>>,
@@ -238,6 +279,7 @@ impl SideEffect {
 
 /// Represent the external and internal specs of a m4 macro
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct M4MacroSignature {
     /// the types of macro arguments.
     pub arg_types: Vec<M4Type>,
@@ -271,6 +313,7 @@ impl M4MacroSignature {
 
 /// Represents a static shell variable
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Var {
     /// name of shell variable
     pub name: String,
@@ -385,6 +428,7 @@ impl From<&str> for Var {
 
 /// Represents a type of a shell variable
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VarKind {
     /// internally used in the script
     Internal,
@@ -408,6 +452,7 @@ impl Default for VarKind {
 
 /// Represents an usage of a shell variable
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VarUsage {
     /// variable is only read
     Referenced,
@@ -425,6 +470,7 @@ impl Default for VarUsage {
 
 /// Represents attributes of a shell variable
 #[derive(Debug, Clone, Default, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VarAttrs {
     /// about the data flow of the variable
     pub kind: VarKind,
@@ -476,6 +522,7 @@ impl VarAttrs {
 
 /// Delimiter used in a m4 array argument
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ArrayDelim {
     /// whitespace or newline
     Blank,
@@ -3029,7 +3076,7 @@ fn predefined_macros() -> HashMap<String, M4MacroSignature> {
                                         sanitize_shell_name(s.replace("*", "p").as_ref())
                                     ),
                                 ),
-                                (ExCPP, format!("SIZE_OF_{}", sanitize_c_name(s))),
+                                (ExCPP, format!("SIZEOF_{}", sanitize_c_name(s))),
                             ]
                         })),
                         Lit,  // [unused]
@@ -7558,7 +7605,7 @@ fn predefined_macros() -> HashMap<String, M4MacroSignature> {
                     arg_types: vec![
                         Type(Some(&|s| {
                             // type
-                            vec![(ExCPP, format!("SIZE_OF_{}", sanitize_c_name(s)))]
+                            vec![(ExCPP, format!("SIZEOF_{}", sanitize_c_name(s)))]
                         })),
                         Prog, // headers
                     ],
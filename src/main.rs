@@ -0,0 +1,701 @@
+//! Command-line interface to the parsers and analyses of autotools files.
+//!
+//! Every command takes a list of files, parsed on their own: autoconf scripts
+//! (`configure.ac`, `*.m4`) and makefiles (`Makefile.am`, `Makefile.in`) are
+//! told apart by their names. The results are printed as text, one line per
+//! item prefixed with the file and line, or as a JSON array with one object
+//! per file.
+//!
+//! The exit code is 0 on success, 1 when `lint` reports a problem,
+//! `fmt --check` finds a file which is not formatted or `autoupdate` without
+//! `--write` finds an obsolete macro, and 2 on a usage error or when a file
+//! can not be read, parsed or written.
+//!
+//! The binary is only built with the `cli` feature, e.g.
+//! `cargo run --features cli -- lint configure.ac`.
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use autotools_parser::analysis::rules::RuleGraph;
+use autotools_parser::ast::am::{AmAssignOp, AmLine};
+use autotools_parser::ast::minimal::{Word, WordFragment};
+use autotools_parser::ast::node::{
+    AcCommand, AcWord, DisplayNode, M4Macro, NodeId, ShellCommand, POOL_SCHEMA_VERSION,
+};
+use autotools_parser::ast::MayM4;
+use autotools_parser::autoupdate;
+use autotools_parser::backend::configure::{self, Names};
+use autotools_parser::m4_macro;
+use autotools_parser::project::{AutoconfFile, AutomakeFile, FileContent, ProjectFile};
+use autotools_parser::query::Query;
+use serde_json::{json, Value};
+
+const USAGE: &str = "\
+usage: autotools-parser <command> [options] <files>...
+
+commands:
+  dump            print the parsed nodes
+  fmt             print autoconf scripts formatted
+  autoupdate      report the obsolete macros of autoconf scripts and their replacements
+  lint            report problems
+  probes          list the headers, functions, types and libraries checked for
+  options         list the options of configure
+  deps            list the external packages, or the rule dependencies of makefiles
+  query <name>    list the calls of a macro, or the assignments of a makefile variable;
+                  a trailing `*` matches any suffix
+  select <query>  list the elements of autoconf scripts matching a structural query,
+                  e.g. `macro[name=AC_CHECK_HEADERS] > arg[0] word`
+
+options:
+  --format <text|json>  output format, text by default
+  --check               with fmt, only report the files which are not formatted
+  --write               with autoupdate, also rewrite the files with the replacements
+  -h, --help            print this help
+";
+
+/// Something to fix, found by `lint`, `fmt --check` or `autoupdate`.
+const EXIT_FINDINGS: i32 = 1;
+/// A usage error, or a file which can not be read or parsed.
+const EXIT_ERROR: i32 = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Dump,
+    Fmt,
+    Autoupdate,
+    Lint,
+    Probes,
+    Options,
+    Deps,
+    Query,
+    Select,
+}
+
+struct Args {
+    command: Command,
+    format: Format,
+    check: bool,
+    write: bool,
+    name: String,
+    query: Option<Query>,
+    files: Vec<PathBuf>,
+}
+
+/// What a command gives for one file.
+struct Output {
+    /// The lines printed as text.
+    lines: Vec<String>,
+    /// The object printed as JSON.
+    json: Value,
+    /// How many problems were found.
+    findings: usize,
+}
+
+fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(EXIT_ERROR);
+        }
+    };
+    process::exit(run(&args));
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut iter: I) -> Result<Option<Args>, String> {
+    let command = match iter.next().as_deref() {
+        None | Some("-h" | "--help" | "help") => return Ok(None),
+        Some("dump") => Command::Dump,
+        Some("fmt") => Command::Fmt,
+        Some("autoupdate") => Command::Autoupdate,
+        Some("lint") => Command::Lint,
+        Some("probes") => Command::Probes,
+        Some("options") => Command::Options,
+        Some("deps") => Command::Deps,
+        Some("query") => Command::Query,
+        Some("select") => Command::Select,
+        Some(other) => return Err(format!("unknown command `{}`", other)),
+    };
+    let mut args = Args {
+        command,
+        format: Format::Text,
+        check: false,
+        write: false,
+        name: String::new(),
+        query: None,
+        files: Vec::new(),
+    };
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--format" => {
+                args.format = match iter.next().as_deref() {
+                    Some("text") => Format::Text,
+                    Some("json") => Format::Json,
+                    _ => return Err("--format expects `text` or `json`".to_string()),
+                }
+            }
+            "--check" if command == Command::Fmt => args.check = true,
+            "--write" if command == Command::Autoupdate => args.write = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ if command == Command::Query && args.name.is_empty() => args.name = arg,
+            _ if command == Command::Select && args.query.is_none() => {
+                args.query = Some(Query::parse(&arg).map_err(|e| e.to_string())?)
+            }
+            _ => args.files.push(PathBuf::from(arg)),
+        }
+    }
+    if command == Command::Query && args.name.is_empty() {
+        return Err("query expects a name".to_string());
+    }
+    if command == Command::Select && args.query.is_none() {
+        return Err("select expects a query".to_string());
+    }
+    if args.files.is_empty() {
+        return Err("no input files".to_string());
+    }
+    Ok(Some(args))
+}
+
+fn run(args: &Args) -> i32 {
+    let mut code = 0;
+    let mut lines = Vec::new();
+    let mut objects = Vec::new();
+    for path in &args.files {
+        let file = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))
+            .and_then(|source| ProjectFile::parse(path.clone(), source).map_err(|e| e.to_string()));
+        let output = file.and_then(|file| command(args, &file));
+        match output {
+            Ok(output) => {
+                if output.findings > 0 {
+                    code = code.max(EXIT_FINDINGS);
+                }
+                if args.files.len() > 1 && matches!(args.command, Command::Dump | Command::Fmt) {
+                    if !lines.is_empty() {
+                        lines.push(String::new());
+                    }
+                    lines.push(format!("==> {} <==", path.display()));
+                }
+                lines.extend(output.lines);
+                objects.push(output.json);
+            }
+            Err(message) => {
+                eprintln!("error: {}", message);
+                code = EXIT_ERROR;
+            }
+        }
+    }
+    // A closed pipe, e.g. when piped to `head`, is not an error.
+    let mut stdout = io::stdout().lock();
+    let _ = match args.format {
+        Format::Text => lines
+            .iter()
+            .try_for_each(|line| writeln!(stdout, "{}", line)),
+        Format::Json => serde_json::to_writer_pretty(&mut stdout, &objects)
+            .map_err(io::Error::from)
+            .and_then(|()| writeln!(stdout)),
+    };
+    code
+}
+
+fn command(args: &Args, file: &ProjectFile) -> Result<Output, String> {
+    let path = file.path.display().to_string();
+    let items = match (&file.content, args.command) {
+        (_, Command::Dump) => return Ok(dump(file)),
+        (FileContent::Autoconf(ac), Command::Fmt) => return Ok(fmt(file, ac, args.check)),
+        (FileContent::Automake(_), Command::Fmt) => {
+            return Err(format!("{}: only autoconf scripts can be formatted", path))
+        }
+        (FileContent::Autoconf(ac), Command::Autoupdate) => {
+            return autoupdate(file, ac, args.write)
+        }
+        (FileContent::Automake(_), Command::Autoupdate) => {
+            return Err(format!("{}: only autoconf scripts can be updated", path))
+        }
+        (_, Command::Lint) => lint(file),
+        (FileContent::Autoconf(ac), Command::Probes) => probes(ac),
+        (FileContent::Autoconf(ac), Command::Options) => options(ac),
+        (FileContent::Autoconf(ac), Command::Deps) => packages(ac),
+        (FileContent::Automake(am), Command::Deps) => rules(am),
+        (FileContent::Autoconf(ac), Command::Query) => macro_calls(ac, &args.name),
+        (FileContent::Automake(am), Command::Query) => assignments(am, &args.name),
+        (FileContent::Autoconf(ac), Command::Select) => select(ac, args.query.as_ref().unwrap()),
+        (FileContent::Automake(_), Command::Probes | Command::Options | Command::Select) => {
+            Vec::new()
+        }
+    };
+    let findings = if args.command == Command::Lint {
+        items.len()
+    } else {
+        0
+    };
+    let key = match args.command {
+        Command::Lint => "problems",
+        Command::Probes => "probes",
+        Command::Options => "options",
+        Command::Deps => "deps",
+        _ => "matches",
+    };
+    Ok(Output {
+        lines: items
+            .iter()
+            .map(|item| match item.line {
+                Some(line) => format!("{}:{}: {}", path, line, item.text),
+                None => format!("{}: {}", path, item.text),
+            })
+            .collect(),
+        json: json!({
+            "path": path,
+            key: items.into_iter().map(|item| item.json).collect::<Vec<_>>(),
+        }),
+        findings,
+    })
+}
+
+/// A line of the output of a command.
+struct Item {
+    line: Option<usize>,
+    text: String,
+    json: Value,
+}
+
+impl Item {
+    fn new(line: Option<usize>, text: String, mut json: Value) -> Self {
+        if let Value::Object(object) = &mut json {
+            object.insert("line".to_string(), json!(line));
+        }
+        Item { line, text, json }
+    }
+}
+
+/// The macro calls of a script in the order of their lines, nested ones
+/// included.
+fn macros(ac: &AutoconfFile) -> Vec<(NodeId, Option<usize>, &M4Macro)> {
+    let mut ret = ac
+        .pool
+        .nodes
+        .iter()
+        .filter_map(|(id, node)| match &node.cmd {
+            AcCommand(MayM4::Macro(m4_macro)) => Some((id, node.range_start(), m4_macro)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    ret.sort_by_key(|(id, line, _)| (*line, *id));
+    ret
+}
+
+fn dump(file: &ProjectFile) -> Output {
+    let (kind, top_ids, nodes) = match &file.content {
+        FileContent::Autoconf(ac) => {
+            let pool = serde_json::to_value(&ac.pool).unwrap();
+            ("autoconf", &ac.top_ids, pool["nodes"].clone())
+        }
+        FileContent::Automake(am) => {
+            let nodes = am
+                .nodes
+                .iter()
+                .map(|(id, node)| {
+                    let mut value = serde_json::to_value(node).unwrap();
+                    value["id"] = json!(id);
+                    value
+                })
+                .collect();
+            ("automake", &am.top_ids, Value::Array(nodes))
+        }
+    };
+    let lines = match &file.content {
+        FileContent::Autoconf(ac) => ac
+            .pool
+            .nodes
+            .iter()
+            .map(|(id, _)| {
+                let text = ac.pool.display_node(id, 0);
+                let first = text.lines().next().unwrap_or("");
+                format!("{} {} {}", id, lines(&ac.pool.nodes[id].range), first)
+            })
+            .collect(),
+        FileContent::Automake(am) => am
+            .nodes
+            .iter()
+            .map(|(id, node)| format!("{} {} {:?}", id, lines(&node.range), node.cmd))
+            .collect(),
+    };
+    Output {
+        lines,
+        json: json!({
+            "path": file.path,
+            "kind": kind,
+            "version": POOL_SCHEMA_VERSION,
+            "top_ids": top_ids,
+            "nodes": nodes,
+        }),
+        findings: 0,
+    }
+}
+
+/// The lines of a node, e.g. `3-5`, or `-` if they are not known.
+fn lines(range: &[(usize, usize)]) -> String {
+    match (range.first(), range.last()) {
+        (Some((start, _)), Some((_, end))) if end - 1 > *start => {
+            format!("{}-{}", start, end - 1)
+        }
+        (Some((start, _)), Some(_)) => start.to_string(),
+        _ => "-".to_string(),
+    }
+}
+
+fn fmt(file: &ProjectFile, ac: &AutoconfFile, check: bool) -> Output {
+    let mut formatted = ac
+        .top_ids
+        .iter()
+        .map(|id| ac.pool.display_node(*id, 0))
+        .collect::<Vec<_>>()
+        .join("\n");
+    formatted.push('\n');
+    let changed = formatted != file.source;
+    let lines = if !check {
+        formatted.lines().map(str::to_string).collect()
+    } else if changed {
+        vec![format!("{}: not formatted", file.path.display())]
+    } else {
+        Vec::new()
+    };
+    Output {
+        lines,
+        json: json!({
+            "path": file.path,
+            "formatted": formatted,
+            "changed": changed,
+        }),
+        findings: usize::from(check && changed),
+    }
+}
+
+fn autoupdate(file: &ProjectFile, ac: &AutoconfFile, write: bool) -> Result<Output, String> {
+    let path = file.path.display();
+    let update = autoupdate::autoupdate(&ac.pool, &ac.top_ids, &file.source);
+    if write && !update.changes.is_empty() {
+        fs::write(&file.path, &update.source)
+            .map_err(|e| format!("failed to write {}: {}", path, e))?;
+    }
+    let changes = update
+        .changes
+        .iter()
+        .map(|change| {
+            json!({
+                "line": change.line,
+                "from": change.from,
+                "to": change.to,
+                "text": change.text,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(Output {
+        lines: update
+            .changes
+            .iter()
+            .map(|change| format!("{}:{}: {}", path, change.line, change))
+            .collect(),
+        json: json!({
+            "path": file.path,
+            "changes": changes,
+            "updated": update.source,
+            "written": write && !update.changes.is_empty(),
+        }),
+        findings: if write { 0 } else { update.changes.len() },
+    })
+}
+
+fn lint(file: &ProjectFile) -> Vec<Item> {
+    let problem = |line, code: &str, message: String| {
+        Item::new(
+            line,
+            format!("warning[{}]: {}", code, message),
+            json!({"code": code, "message": message}),
+        )
+    };
+    let mut ret = Vec::new();
+    match &file.content {
+        FileContent::Autoconf(ac) => {
+            let calls = macros(ac);
+            let defined = calls
+                .iter()
+                .filter(|(_, _, m)| {
+                    matches!(
+                        m.name.as_str(),
+                        "AC_DEFUN" | "AC_DEFUN_ONCE" | "AU_DEFUN" | "m4_define" | "define"
+                    )
+                })
+                .filter_map(|(_, _, m)| m.args.first().and_then(|arg| ac.render_arg(arg)))
+                .collect::<Vec<_>>();
+            for (_, line, m4_macro) in &calls {
+                if let Some(original) = &m4_macro.original_name {
+                    let message = format!("`{}` is obsolete, use `{}`", original, m4_macro.name);
+                    ret.push(problem(*line, "obsolete-macro", message));
+                } else if m4_macro::get_macro(&m4_macro.name).is_none()
+                    && !defined.contains(&m4_macro.name)
+                {
+                    let message = format!("`{}` is not a known macro", m4_macro.name);
+                    ret.push(problem(*line, "unknown-macro", message));
+                }
+            }
+            // An unknown macro is not expanded and ends up run as a command.
+            for (_, node) in &ac.pool.nodes {
+                let AcCommand(MayM4::Shell(ShellCommand::Cmd(words))) = &node.cmd else {
+                    continue;
+                };
+                let Some(AcWord(Word::Single(MayM4::Shell(WordFragment::Literal(name))))) =
+                    words.first()
+                else {
+                    continue;
+                };
+                if is_macro_name(name) && !defined.contains(name) {
+                    let message = format!("`{}` is not a known macro", name);
+                    ret.push(problem(node.range_start(), "unknown-macro", message));
+                }
+            }
+            ret.sort_by_key(|item| item.line);
+            let name = file.path.file_name().and_then(|n| n.to_str());
+            if matches!(name, Some("configure.ac" | "configure.in")) {
+                for required in ["AC_INIT", "AC_OUTPUT"] {
+                    if !ac.calls_macro(required) {
+                        let message = format!("`{}` is never called", required);
+                        ret.push(problem(None, "missing-macro", message));
+                    }
+                }
+            }
+        }
+        FileContent::Automake(am) => {
+            for cycle in RuleGraph::new(am).cycles() {
+                let mut path = cycle.clone();
+                path.push(cycle[0].clone());
+                let message = format!("rules depend on themselves: {}", path.join(" -> "));
+                ret.push(problem(None, "rule-cycle", message));
+            }
+        }
+    }
+    ret
+}
+
+/// Whether a name is spelled like a macro, e.g. `AX_CHECK_FOO`.
+fn is_macro_name(name: &str) -> bool {
+    name.contains('_')
+        && name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+fn probes(ac: &AutoconfFile) -> Vec<Item> {
+    let mut ret = Vec::new();
+    for (_, line, m4_macro) in macros(ac) {
+        // The headers of `AC_CONFIG_HEADERS` are written, not checked for.
+        if m4_macro.name.starts_with("AC_CONFIG_") {
+            continue;
+        }
+        for probe in configure::probes(ac, m4_macro) {
+            let cache_var = probe.cache_var.as_deref().unwrap_or("");
+            let kind = [
+                ("ac_cv_header_", "header"),
+                ("ac_cv_func_", "function"),
+                ("ac_cv_type_", "type"),
+                ("ac_cv_member_", "member"),
+                ("ac_cv_sizeof_", "sizeof"),
+                ("ac_cv_have_decl_", "declaration"),
+                ("ac_cv_file_", "file"),
+            ]
+            .iter()
+            .find(|(prefix, _)| cache_var.starts_with(prefix))
+            .map(|(_, kind)| *kind)
+            .unwrap_or(match probe.names {
+                Names::Libraries => "library",
+                Names::Paths => "file",
+                Names::Types => "type",
+                _ => "symbol",
+            });
+            let mut text = format!("{} {} {}", m4_macro.name, kind, probe.word);
+            if let Some(symbol) = &probe.symbol {
+                text.push_str(&format!(" {}", symbol));
+            }
+            let value = json!({
+                "macro": m4_macro.name,
+                "kind": kind,
+                "name": probe.word,
+                "symbol": probe.symbol,
+                "cache_var": probe.cache_var,
+            });
+            ret.push(Item::new(line, text, value));
+        }
+    }
+    ret
+}
+
+fn options(ac: &AutoconfFile) -> Vec<Item> {
+    let mut ret = Vec::new();
+    for (_, line, m4_macro) in macros(ac) {
+        let Some(option) = configure::configure_option(ac, m4_macro) else {
+            continue;
+        };
+        let prefix = if m4_macro.name == "AC_ARG_WITH" {
+            "with"
+        } else {
+            "enable"
+        };
+        let flag = format!("--{}-{}", prefix, option.name);
+        let value = json!({
+            "option": flag,
+            "name": option.name,
+            "shell_var": option.shell_var,
+            "help": option.help,
+            "default": option.default,
+        });
+        let text = format!("{} ({}) {}", flag, option.shell_var, option.help);
+        ret.push(Item::new(line, text, value));
+    }
+    ret
+}
+
+/// The packages found with pkg-config and the libraries linked with.
+fn packages(ac: &AutoconfFile) -> Vec<Item> {
+    let mut ret = Vec::new();
+    for (_, line, m4_macro) in macros(ac) {
+        let index = match m4_macro.name.as_str() {
+            "PKG_CHECK_MODULES" | "PKG_CHECK_MODULES_STATIC" => 1,
+            "PKG_CHECK_EXISTS" => 0,
+            _ => {
+                for probe in configure::probes(ac, m4_macro) {
+                    if probe.names == Names::Libraries {
+                        let text = format!("library {}", probe.word);
+                        let value = json!({"kind": "library", "name": probe.word});
+                        ret.push(Item::new(line, text, value));
+                    }
+                }
+                continue;
+            }
+        };
+        let Some(modules) = m4_macro.args.get(index).and_then(|a| ac.render_arg(a)) else {
+            continue;
+        };
+        for (module, constraint) in configure::pkg_modules(&modules) {
+            let text = format!("pkg-config {}{}", module, constraint);
+            let value = json!({"kind": "pkg-config", "name": module, "version": constraint});
+            ret.push(Item::new(line, text, value));
+        }
+    }
+    ret
+}
+
+/// The prerequisites of the targets of the rules.
+fn rules(am: &AutomakeFile) -> Vec<Item> {
+    let graph = RuleGraph::new(am);
+    graph
+        .targets()
+        .iter()
+        .map(|target| {
+            let text = format!("{}: {}", target.name, target.prerequisites.join(" "));
+            let value = json!({
+                "kind": "rule",
+                "name": target.name,
+                "prerequisites": target.prerequisites,
+            });
+            Item::new(None, text.trim_end().to_string(), value)
+        })
+        .collect()
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+fn macro_calls(ac: &AutoconfFile, pattern: &str) -> Vec<Item> {
+    macros(ac)
+        .into_iter()
+        .filter(|(_, _, m4_macro)| matches(pattern, &m4_macro.name))
+        .map(|(_, line, m4_macro)| {
+            let args = m4_macro
+                .args
+                .iter()
+                .map(|arg| ac.render_arg(arg))
+                .collect::<Vec<_>>();
+            let text = format!(
+                "{}({})",
+                m4_macro.name,
+                args.iter()
+                    .map(|arg| format!("[{}]", arg.as_deref().unwrap_or("...")))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            let value = json!({"macro": m4_macro.name, "args": args});
+            Item::new(line, text, value)
+        })
+        .collect()
+}
+
+fn select(ac: &AutoconfFile, query: &Query) -> Vec<Item> {
+    query
+        .find(&ac.pool, &ac.top_ids)
+        .into_iter()
+        .map(|m| {
+            let kind = m.element.kind();
+            let name = m.element.name(&ac.pool);
+            let text = m.element.text(&ac.pool);
+            let label = match (&name, &text) {
+                (Some(name), Some(text)) if name != text => format!("{} {}: {}", kind, name, text),
+                (Some(label), _) | (None, Some(label)) => format!("{} {}", kind, label),
+                (None, None) => kind.to_string(),
+            };
+            let value = json!({
+                "kind": kind,
+                "node": m.node,
+                "name": name,
+                "text": text,
+                "end": m.span.map(|(_, end)| end),
+            });
+            Item::new(m.span.map(|(start, _)| start), label, value)
+        })
+        .collect()
+}
+
+fn assignments(am: &AutomakeFile, pattern: &str) -> Vec<Item> {
+    am.nodes
+        .iter()
+        .filter_map(|(_, node)| match &node.cmd {
+            AmLine::Assignment(assignment) if matches(pattern, &assignment.lhs) => Some(assignment),
+            _ => None,
+        })
+        .map(|assignment| {
+            let words = assignment
+                .rhs
+                .iter()
+                .map(|word| word.to_string())
+                .collect::<Vec<_>>();
+            let op = match assignment.op {
+                AmAssignOp::Lazy => "=",
+                AmAssignOp::Instant => ":=",
+                AmAssignOp::Append => "+=",
+                AmAssignOp::IfUndefined => "?=",
+                AmAssignOp::Shell => "!=",
+            };
+            let text = format!("{} {} {}", assignment.lhs, op, words.join(" "));
+            let value = json!({"variable": assignment.lhs, "op": op, "words": words});
+            Item::new(None, text.trim_end().to_string(), value)
+        })
+        .collect()
+}
@@ -33,8 +33,15 @@ const WHILE: &str = "while";
 /// A specialized `Result` type for parsing shell commands.
 pub type ParseResult<T, E> = Result<T, ParseError<E>>;
 
+/// The node pool and top-level node ids produced by parsing a whole file.
+pub type ParsedPool<C, U> = (
+    slab::Slab<crate::ast::node::Node<C, U>>,
+    Vec<crate::ast::node::NodeId>,
+);
+
 /// Indicates a character/token position in the original source.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SourcePos {
     /// The byte offset since the start of parsing.
     pub byte: usize,
@@ -93,7 +100,7 @@ pub struct ParseError<T> {
     /// Internal error field.
     pub kind: ParseErrorKind<T>,
     /// Take backtrace when the error happened.
-    backtrace: Backtrace,
+    backtrace: Box<Backtrace>,
 }
 
 impl<T> ParseError<T> {
@@ -101,7 +108,7 @@ impl<T> ParseError<T> {
     pub fn new(kind: ParseErrorKind<T>) -> Self {
         ParseError {
             kind,
-            backtrace: Backtrace::capture(),
+            backtrace: Box::new(Backtrace::capture()),
         }
     }
 
@@ -109,6 +116,20 @@ impl<T> ParseError<T> {
     pub fn backtrace(&self) -> &Backtrace {
         &self.backtrace
     }
+
+    /// Return the position the error was found at, if it is known.
+    pub fn pos(&self) -> Option<SourcePos> {
+        match &self.kind {
+            BadFd(pos, _)
+            | BadIdent(_, pos)
+            | BadSubst(_, pos)
+            | Unmatched(_, pos)
+            | IncompleteCmd(_, _, _, pos)
+            | Unexpected(_, pos)
+            | BadInclude(_, pos) => Some(*pos),
+            UnexpectedEOF | Custom(_) => None,
+        }
+    }
 }
 
 impl<T> From<ParseErrorKind<T>> for ParseError<T> {
@@ -150,6 +171,9 @@ pub enum ParseErrorKind<T> {
     Unexpected(Token, SourcePos),
     /// Encountered the end of input while expecting additional tokens.
     UnexpectedEOF,
+    /// A file included at the specified position could not be read,
+    /// or includes itself.
+    BadInclude(String, SourcePos),
     /// A custom error returned by the AST builder.
     Custom(T),
 }
@@ -210,6 +234,9 @@ impl<T: fmt::Display> fmt::Display for ParseError<T> {
             }
 
             UnexpectedEOF => fmt.write_str("unexpected end of input"),
+            BadInclude(ref path, pos) => {
+                write!(fmt, "cannot include `{}` on line {}", path, pos)
+            }
             Custom(ref e) => write!(fmt, "{}", e),
         }?;
         if self.backtrace().status() == BacktraceStatus::Captured {
@@ -12,13 +12,16 @@ use super::iter::{
     PeekableIterator, PositionIterator, TokenIter, TokenIterWrapper, TokenIterator, UnmatchedError,
 };
 use super::{
-    CommandGroupDelimiters, ParseError, ParseErrorKind, ParseResult, Parser, ParserIterator,
-    SourcePos, CASE, DO, DONE, ELIF, ELSE, ESAC, FI, FOR, FUNCTION, IF, IN, THEN, UNTIL, WHILE,
+    CommandGroupDelimiters, ParseError, ParseErrorKind, ParseResult, ParsedPool, Parser,
+    ParserIterator, SourcePos, CASE, DO, DONE, ELIF, ELSE, ESAC, FI, FOR, FUNCTION, IF, IN, THEN,
+    UNTIL, WHILE,
 };
 use crate::ast::builder::ConcatWordKind::{self, Concat, Single};
 use crate::ast::builder::QuoteWordKind::{DoubleQuoted, Simple, SingleQuoted};
-use crate::ast::builder::{self, AutoconfNodeBuilder, M4Builder, ShellBuilder, WordKind};
-use crate::ast::node::{AcCommand, AcWord, Node, NodeId};
+use crate::ast::builder::{
+    self, AutoconfNodeBuilder, BuilderError, M4Builder, ShellBuilder, WordKind,
+};
+use crate::ast::node::{AcCommand, AcWord};
 use crate::ast::{self, DefaultArithmetic, DefaultParameter};
 use crate::m4_macro::{self, ArrayDelim, M4Argument, M4ExportFunc, SideEffect};
 use crate::token::Token;
@@ -194,24 +197,23 @@ where
 {
     /// Parse all complete commands
     /// (special method for NodeBuilder to easily take its state)
-    pub fn parse_all(mut self) -> (slab::Slab<Node<AcCommand, U>>, Vec<NodeId>) {
-        let mut top_ids = Vec::new();
-        // Parse all complete commands
-        let mut take = || match self.complete_command() {
-            Ok(Some(c)) => Some(Ok(c)),
-            Ok(None) => None,
-            Err(e) => Some(Err(e)),
-        };
-        while let Some(id) = take() {
-            match id {
-                Ok(id) => top_ids.push(id),
-                Err(e) => {
-                    println!("{}", e);
-                    panic!();
-                }
+    pub fn parse_all(self) -> ParsedPool<AcCommand, U> {
+        match self.try_parse_all() {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                println!("{}", e);
+                panic!();
             }
         }
-        (self.builder.nodes, top_ids)
+    }
+
+    /// Parse all complete commands, returning the first parse error instead of panicking.
+    pub fn try_parse_all(mut self) -> ParseResult<ParsedPool<AcCommand, U>, BuilderError> {
+        let mut top_ids = Vec::new();
+        while let Some(id) = self.complete_command()? {
+            top_ids.push(id);
+        }
+        Ok((self.builder.nodes, top_ids))
     }
 }
 
@@ -2809,7 +2811,10 @@ where
             eat!(self, {ParenClose => {}});
             peeked_args.into_iter().map(M4Argument::Unknown).collect()
         };
-        assert!(!self.in_quote());
+        if self.in_quote() {
+            // The quotes of the arguments are not balanced.
+            return Err(self.make_unexpected_err());
+        }
         self.pop_quote_context();
         // A whitespace is not allowed between m4 macro name and the opening parenthesis
         // eat!(self, { ParenOpen => {} });
@@ -2882,6 +2887,7 @@ where
                     }
                 }
             };
+            let found_word = word.is_some();
             if let Some(word) = word {
                 if let Some(f) = func {
                     if let Single(Simple(ref frag)) = word {
@@ -2912,7 +2918,17 @@ where
                     break;
                 } else if matches!(tok, Comma | Newline) && in_quote {
                     self.iter.next();
+                } else if !found_word {
+                    // Tokens which can not start a shell word (e.g. `>=` in
+                    // `PKG_CHECK_MODULES([FOO], [foo >= 1.0])`) are kept as literals.
+                    let tok = self.iter.next().unwrap();
+                    let frag = self
+                        .builder
+                        .word_fragment(WordKind::Literal(tok.to_string()))?;
+                    arr.push(self.builder.word(Single(Simple(frag)))?);
                 }
+            } else {
+                return Err(ParseError::new(UnexpectedEOF));
             }
         }
         self.may_close_quote(None, false);
@@ -1,26 +1,34 @@
 //! The definition of a parser (and related methods) for the automake language.
 use std::fmt::Debug;
+use std::fs;
 use std::iter::empty as empty_iter;
 use std::mem;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use super::iter::{
     Multipeek, PeekableIterator, PositionIterator, TokenIter, TokenIterWrapper, TokenIterator,
-    UnmatchedError,
+    TokenOrPos, UnmatchedError,
 };
 use super::{
-    CommandGroupDelimiters, ParseError, ParseErrorKind, ParseResult, Parser, ParserIterator,
-    SourcePos, CASE, DO, DONE, ELIF, ELSE, ESAC, FI, FOR, FUNCTION, IF, IN, THEN, UNTIL, WHILE,
+    CommandGroupDelimiters, ParseError, ParseErrorKind, ParseResult, ParsedPool, Parser,
+    ParserIterator, SourcePos, CASE, DO, DONE, ELIF, ELSE, ESAC, FI, FOR, FUNCTION, IF, IN, THEN,
+    UNTIL, WHILE,
+};
+use crate::ast::am::{
+    AmAssignOp, AmLine, AmVar, IncludedFile, MakeCondition, MakeDF, MakeFunction, MakeParameter,
 };
-use crate::ast::am::{AmAssignOp, AmLine, AmVar, MakeDF, MakeParameter};
 use crate::ast::builder::ConcatWordKind::{self, Concat, Single};
 use crate::ast::builder::QuoteWordKind::{DoubleQuoted, Simple, SingleQuoted};
 use crate::ast::builder::{
-    self, AutoconfNodeBuilder, AutomakeNodeBuilder, MakeBuilder, ShellBuilder, WordKind,
+    self, AutoconfNodeBuilder, AutomakeNodeBuilder, BuilderError, MakeBuilder, ShellBuilder,
+    WordKind,
 };
-use crate::ast::node::{AcWord, Node, NodeId};
+use crate::ast::node::AcWord;
 use crate::ast::{self, DefaultArithmetic, DefaultParameter};
+use crate::lexer::Lexer;
 use crate::token::Token;
+use crate::util::{canonicalize, normalize};
 
 /// A parser which will use a node-based AST builder implementation.
 pub type AutomakeNodeParser<I> = AutomakeParser<I, AutomakeNodeBuilder<()>>;
@@ -28,6 +36,20 @@ pub type AutomakeNodeParser<I> = AutomakeParser<I, AutomakeNodeBuilder<()>>;
 use ParseErrorKind::*;
 use Token::*;
 
+const ENDIF: &str = "endif";
+const INCLUDE: &str = "include";
+const SINCLUDE: &str = "sinclude";
+const IFEQ: &str = "ifeq";
+const IFNEQ: &str = "ifneq";
+const IFDEF: &str = "ifdef";
+const IFNDEF: &str = "ifndef";
+const DEFINE: &str = "define";
+const ENDEF: &str = "endef";
+const EXPORT: &str = "export";
+const UNEXPORT: &str = "unexport";
+const OVERRIDE: &str = "override";
+const VPATH: &str = "vpath";
+
 /// Used to indicate what kind of compound command could be parsed next.
 #[derive(Debug, PartialEq, Eq, Clone)]
 enum CompoundCmdKeyword {
@@ -67,6 +89,20 @@ where
     }
 }
 
+/// Options of the automake parser.
+#[derive(Debug, Clone, Default)]
+pub struct AutomakeConfig {
+    /// The top source directory, `$(top_srcdir)`. If set, `include` directives
+    /// are resolved and the included fragments are parsed in place.
+    pub top_srcdir: Option<PathBuf>,
+    /// Directory of the makefile relative to `top_srcdir`, i.e. `$(srcdir)`.
+    pub subdir: PathBuf,
+    /// Parse a generated `Makefile.in` or a plain `Makefile`. Lines prefixed
+    /// with `@COND_TRUE@` or `@COND_FALSE@` are read as if they were in the
+    /// branches of `if COND`, so the analyses see the automake conditionals.
+    pub makefile_in: bool,
+}
+
 /// A parser for the shell language. It will parse shell commands from a
 /// stream of shell `Token`s, and pass them to an AST builder.
 #[derive(Debug)]
@@ -74,6 +110,9 @@ pub struct AutomakeParser<I, B> {
     iter: TokenIterWrapper<I>,
     builder: B,
     in_recipe: bool,
+    config: AutomakeConfig,
+    /// Fragments being included, to detect recursive includes.
+    include_stack: Vec<PathBuf>,
 }
 
 impl<I: Iterator<Item = Token>, B: ShellBuilder + MakeBuilder + Default> AutomakeParser<I, B>
@@ -95,6 +134,22 @@ where
     {
         AutomakeParser::with_builder(iter.into_iter(), Default::default())
     }
+
+    /// Creates a new Parser reading the input as described by `config`.
+    pub fn new_with_automake_config<T>(iter: T, config: AutomakeConfig) -> AutomakeParser<I, B>
+    where
+        T: IntoIterator<Item = Token, IntoIter = I>,
+    {
+        let mut parser = AutomakeParser::with_builder(iter.into_iter(), Default::default());
+        if config.makefile_in {
+            let tokens = conditional_prefixes_to_blocks(parser.iter.by_ref());
+            let mut tok_iter = TokenIter::with_position(empty_iter(), SourcePos::new());
+            tok_iter.buffer_tokens_and_positions_to_yield_first(tokens, None);
+            parser.iter = TokenIterWrapper::Buffered(tok_iter);
+        }
+        parser.config = config;
+        parser
+    }
 }
 
 impl<I, U> AutomakeParser<I, AutomakeNodeBuilder<U>>
@@ -108,24 +163,23 @@ where
 {
     /// Parse all complete commands
     /// (special method for NodeBuilder to easily take its state)
-    pub fn parse_all(mut self) -> (slab::Slab<Node<AmLine, U>>, Vec<NodeId>) {
-        let mut top_ids = Vec::new();
-        // Parse all complete commands
-        let mut take = || match self.automake_statement() {
-            Ok(Some(c)) => Some(Ok(c)),
-            Ok(None) => None,
-            Err(e) => Some(Err(e)),
-        };
-        while let Some(id) = take() {
-            match id {
-                Ok(id) => top_ids.push(id),
-                Err(e) => {
-                    println!("{}", e);
-                    panic!();
-                }
+    pub fn parse_all(self) -> ParsedPool<AmLine, U> {
+        match self.try_parse_all() {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                println!("{}", e);
+                panic!();
             }
         }
-        (self.builder.nodes, top_ids)
+    }
+
+    /// Parse all statements, returning the first parse error instead of panicking.
+    pub fn try_parse_all(mut self) -> ParseResult<ParsedPool<AmLine, U>, BuilderError> {
+        let mut top_ids = Vec::new();
+        while let Some(id) = self.automake_statement()? {
+            top_ids.push(id);
+        }
+        Ok((self.builder.nodes, top_ids))
     }
 }
 
@@ -216,6 +270,8 @@ where
             iter: TokenIterWrapper::Regular(TokenIter::new(iter)),
             builder,
             in_recipe: false,
+            config: AutomakeConfig::default(),
+            include_stack: Vec::new(),
         }
     }
 
@@ -226,7 +282,6 @@ where
 
     /// Parses a single automake top-level statement.
     pub fn automake_conditional(&mut self) -> ParseResult<B::Statement, B::Error> {
-        const ENDIF: &str = "endif";
         self.skip_whitespace();
         let guard_var = if let Some(Name(name)) = self.iter.next() {
             name.to_owned()
@@ -238,24 +293,31 @@ where
         let mut then = Vec::new();
         let mut otherwise = Vec::new();
         loop {
-            if let Some(directive) = self.peek_reserved_word(&[ELSE, ENDIF]) {
+            self.linebreak_preserve_line_head_whitespace();
+            let recipe_line = self.is_recipe_line();
+            let directive = match recipe_line {
+                false => self.peek_reserved_word(&[ELSE, ENDIF]),
+                true => None,
+            };
+            if let Some(directive) = directive {
+                // automake allows repeating the condition after `else` and `endif`.
+                self.word()?;
+                self.words_with_delim(&[Newline, Pound])?;
+                self.newline();
                 if directive == ELSE {
                     after_else_keyword = true;
-                    self.newline();
-                } else if directive == ENDIF {
-                    self.word()?;
-                    self.newline();
+                    continue;
+                } else {
                     break;
                 }
             }
-            let stmt = if self.in_recipe {
-                self.automake_recipe()?.unwrap()
-            } else {
-                self.automake_statement()?.unwrap()
+            let stmt = match self.conditional_body_statement(recipe_line)? {
+                Some(stmt) => stmt,
+                None => return Err(self.make_unexpected_err()),
             };
             match after_else_keyword {
-                true => then.push(stmt),
-                false => otherwise.push(stmt),
+                false => then.push(stmt),
+                true => otherwise.push(stmt),
             }
         }
         Ok(self.builder.conditional(guard_var, then, otherwise)?)
@@ -280,7 +342,7 @@ where
         if let Some((name, op)) = self.automake_assign_op()? {
             // assignment statement
             self.skip_whitespace();
-            let words = self.words_with_delim(&[Newline])?;
+            let words = self.make_text_words()?;
             eat!(self, { Newline => {} });
             Ok(Some(self.builder.assignment(name, op, words)?))
         } else {
@@ -306,7 +368,11 @@ where
         match self.iter.peek() {
             Some(Name(s)) => {
                 if s == IF {
+                    self.iter.next();
                     Ok(Some(self.automake_conditional()?))
+                } else if let Some(kw) = self.peek_reserved_word(&[IFEQ, IFNEQ, IFDEF, IFNDEF]) {
+                    self.iter.next();
+                    Ok(Some(self.make_conditional(kw)?))
                 } else {
                     // now we are out of recipe.
                     Ok(None)
@@ -326,7 +392,6 @@ where
 
     /// Parses a single automake top-level statement.
     pub fn automake_statement(&mut self) -> ParseResult<Option<B::Statement>, B::Error> {
-        const INCLUDE: &str = "include";
         let start_pos = self.iter.pos();
         let pre_stmt_comments = self.linebreak_preserve_line_head_whitespace();
 
@@ -336,56 +401,351 @@ where
                     // conditional statement
                     eat!(self, { Name(_) => {} });
                     Ok(Some(self.automake_conditional()?))
-                } else if s == INCLUDE {
+                } else if s == INCLUDE || s == SINCLUDE {
                     // include statement
                     eat!(self, { Name(_) => {} });
-                    let path = self.word()?.unwrap();
-                    Ok(Some(self.builder.include(path)?))
+                    Ok(Some(self.make_include(s == SINCLUDE)?))
+                } else if let Some(assignment) = self.automake_assignment()? {
+                    Ok(Some(assignment))
+                } else if let Some(directive) = self.make_directive()? {
+                    Ok(Some(directive))
                 } else {
-                    if let Some(assignment) = self.automake_assignment()? {
-                        Ok(Some(assignment))
-                    } else {
-                        Ok(self.automake_rule()?)
-                    }
+                    Ok(self.automake_rule()?)
                 }
             }
-            Some(Dollar | Percent | Dot) => Ok(self.automake_rule()?),
+            Some(Dash)
+                if self
+                    .peek_reserved_word_with_prefix(&[INCLUDE], Some(&Dash))
+                    .is_some() =>
+            {
+                // optional include statement
+                eat!(self, { Dash => {} });
+                eat!(self, { Name(_) => {} });
+                Ok(Some(self.make_include(true)?))
+            }
+            Some(At) if self.is_substitution_line() => {
+                let words = self.words_with_delim(&[Newline])?;
+                eat_maybe!(self, { Newline => {} });
+                Ok(Some(self.builder.substitution_line(words)?))
+            }
+            Some(Dollar | Percent | Dot | At) => Ok(self.automake_rule()?),
             Some(Whitespace(s)) => Err(self.make_unexpected_err()),
             None => Ok(None),
             _ => Err(self.make_unexpected_err()),
         }
     }
 
+    /// Parses a statement in the body of a conditional. Lines starting with
+    /// a tab are recipe commands, everything else is a top-level statement.
+    fn conditional_body_statement(
+        &mut self,
+        recipe_line: bool,
+    ) -> ParseResult<Option<B::Statement>, B::Error> {
+        if self.in_recipe || recipe_line {
+            self.automake_recipe()
+        } else {
+            self.automake_statement()
+        }
+    }
+
+    /// Check if the next line is neither a rule nor an assignment, such as
+    /// `@SET_MAKE@`, without consuming tokens.
+    fn is_substitution_line(&mut self) -> bool {
+        let mut peeked = self.iter.multipeek();
+        loop {
+            match peeked.peek_next() {
+                Some(Colon) | Some(Equals) => return false,
+                Some(Newline) | None => return true,
+                _ => {}
+            }
+        }
+    }
+
+    /// Check if the next line starts with a tab without consuming tokens.
+    fn is_recipe_line(&mut self) -> bool {
+        matches!(self.iter.peek(), Some(Whitespace(s)) if s.starts_with('\t'))
+    }
+
+    /// Parses GNU make directives other than `include`, returning `None`
+    /// without consuming tokens if the next line is not one of them.
+    pub fn make_directive(&mut self) -> ParseResult<Option<B::Statement>, B::Error> {
+        let Some(directive) = self.peek_reserved_word(&[
+            IFEQ, IFNEQ, IFDEF, IFNDEF, DEFINE, EXPORT, UNEXPORT, OVERRIDE, VPATH,
+        ]) else {
+            return Ok(None);
+        };
+        eat!(self, { Name(_) => {} });
+        let stmt = match directive {
+            IFEQ | IFNEQ | IFDEF | IFNDEF => self.make_conditional(directive)?,
+            DEFINE => self.make_define()?,
+            EXPORT | UNEXPORT => self.make_export(directive == EXPORT)?,
+            OVERRIDE => {
+                self.skip_whitespace();
+                let stmt = if self.peek_reserved_word(&[DEFINE]).is_some() {
+                    eat!(self, { Name(_) => {} });
+                    self.make_define()?
+                } else if let Some(assignment) = self.automake_assignment()? {
+                    assignment
+                } else {
+                    return Err(self.make_unexpected_err());
+                };
+                self.builder.override_directive(stmt)?
+            }
+            VPATH => {
+                let mut words = self.words_with_delim(&[Newline, Pound])?.into_iter();
+                self.newline();
+                let pattern = words.next();
+                self.builder.vpath(pattern, words.collect())?
+            }
+            _ => unreachable!(),
+        };
+        Ok(Some(stmt))
+    }
+
+    /// Parses the files of an include directive whose keyword is already consumed.
+    fn make_include(&mut self, optional: bool) -> ParseResult<B::Statement, B::Error> {
+        // Keep the text of the path to resolve it after parsing the words.
+        let pos = self.iter.pos();
+        let mut line = Vec::new();
+        while let Some(tok) = self.iter.peek() {
+            if matches!(tok, Newline | Pound) {
+                break;
+            }
+            line.push(self.iter.next().unwrap());
+        }
+        let text = concat_tokens(&line);
+        self.iter.buffer_tokens_to_yield_first(line, pos);
+
+        let paths = self.words_with_delim(&[Newline, Pound])?;
+        self.newline();
+        let included = match self.include_path(text.trim()) {
+            Some(path) => self.include_file(path, optional, pos)?,
+            None => None,
+        };
+        Ok(self.builder.include(paths, optional, included)?)
+    }
+
+    /// Resolve the path of an included file relative to the top source directory.
+    /// Returns `None` if includes are not resolved or the path depends on other variables.
+    fn include_path(&self, text: &str) -> Option<PathBuf> {
+        self.config.top_srcdir.as_ref()?;
+        let (base, rest) = if let Some(rest) = strip_var_prefix(text, "top_srcdir") {
+            (PathBuf::new(), rest)
+        } else if let Some(rest) = strip_var_prefix(text, "srcdir") {
+            (self.config.subdir.clone(), rest)
+        } else {
+            (self.config.subdir.clone(), text)
+        };
+        if rest.is_empty() || rest.contains(['$', '@']) || rest.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(normalize(&base.join(rest)))
+    }
+
+    /// Parse an included fragment in place, substituting `%reldir%` and `%canon_reldir%`
+    /// (or their short forms `%D%` and `%C%`) with the directory of the fragment.
+    fn include_file(
+        &mut self,
+        path: PathBuf,
+        optional: bool,
+        pos: SourcePos,
+    ) -> ParseResult<Option<IncludedFile<B::Statement>>, B::Error> {
+        let bad_include = || ParseError::new(BadInclude(path.display().to_string(), pos));
+        if self.include_stack.contains(&path) {
+            return Err(bad_include());
+        }
+        let top_srcdir = self.config.top_srcdir.clone().unwrap_or_default();
+        let source = match fs::read_to_string(top_srcdir.join(&path)) {
+            Ok(source) => source,
+            Err(_) if optional => return Ok(None),
+            Err(_) => return Err(bad_include()),
+        };
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let reldir = relative_path(dir, &self.config.subdir);
+        let source = substitute_reldir(&source, &reldir);
+        let tokens = Lexer::new(source.chars());
+        let tokens = if self.config.makefile_in {
+            conditional_prefixes_to_blocks(tokens)
+        } else {
+            tokens.map(TokenOrPos::Tok).collect()
+        };
+
+        let mut tok_iter = TokenIter::with_position(empty_iter(), SourcePos::new());
+        tok_iter.buffer_tokens_and_positions_to_yield_first(tokens, None);
+        let mut tok_backup = TokenIterWrapper::Buffered(tok_iter);
+        mem::swap(&mut self.iter, &mut tok_backup);
+        self.include_stack.push(path.clone());
+        let mut body = Vec::new();
+        let result = loop {
+            match self.automake_statement() {
+                Ok(Some(stmt)) => body.push(stmt),
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        self.include_stack.pop();
+        let _ = mem::replace(&mut self.iter, tok_backup);
+        result?;
+        Ok(Some(IncludedFile { path, body }))
+    }
+
+    /// Parses a GNU make conditional whose keyword (`ifeq`, `ifneq`, `ifdef`
+    /// or `ifndef`) is already consumed, up to and including its `endif`.
+    pub fn make_conditional(&mut self, keyword: &str) -> ParseResult<B::Statement, B::Error> {
+        self.skip_whitespace();
+        let condition = match keyword {
+            IFEQ | IFNEQ => {
+                let (lhs, rhs) = if let Some(&ParenOpen) = self.iter.peek() {
+                    // ifeq (a,b)
+                    eat!(self, { ParenOpen => {} });
+                    let lhs = self.words_with_delim(&[Comma])?;
+                    eat!(self, { Comma => {} });
+                    let rhs = self.words_with_delim(&[ParenClose])?;
+                    eat!(self, { ParenClose => {} });
+                    (lhs, rhs)
+                } else {
+                    // ifeq "a" "b", or with single quotes
+                    let lhs = self.word()?.into_iter().collect();
+                    let rhs = self.word()?.into_iter().collect();
+                    (lhs, rhs)
+                };
+                if keyword == IFEQ {
+                    MakeCondition::Eq(lhs, rhs)
+                } else {
+                    MakeCondition::Neq(lhs, rhs)
+                }
+            }
+            _ => {
+                let var = match self.word()? {
+                    Some(var) => var,
+                    None => return Err(self.make_unexpected_err()),
+                };
+                if keyword == IFDEF {
+                    MakeCondition::Defined(var)
+                } else {
+                    MakeCondition::NotDefined(var)
+                }
+            }
+        };
+        if self.newline().is_none() && self.iter.peek().is_some() {
+            return Err(self.make_unexpected_err());
+        }
+        let mut after_else_keyword = false;
+        let mut then = Vec::new();
+        let mut otherwise = Vec::new();
+        loop {
+            self.linebreak_preserve_line_head_whitespace();
+            let recipe_line = self.is_recipe_line();
+            let directive = match recipe_line {
+                false => self.peek_reserved_word(&[ELSE, ENDIF]),
+                true => None,
+            };
+            if let Some(directive) = directive {
+                eat!(self, { Name(_) => {} });
+                if directive == ENDIF {
+                    self.newline();
+                    break;
+                }
+                if let Some(kw) = self.peek_reserved_word(&[IFEQ, IFNEQ, IFDEF, IFNDEF]) {
+                    // `else ifeq ...` shares the `endif` of the whole chain.
+                    eat!(self, { Name(_) => {} });
+                    otherwise.push(self.make_conditional(kw)?);
+                    break;
+                }
+                self.newline();
+                after_else_keyword = true;
+                continue;
+            }
+            let stmt = match self.conditional_body_statement(recipe_line)? {
+                Some(stmt) => stmt,
+                None => return Err(self.make_unexpected_err()),
+            };
+            match after_else_keyword {
+                false => then.push(stmt),
+                true => otherwise.push(stmt),
+            }
+        }
+        Ok(self.builder.make_conditional(condition, then, otherwise)?)
+    }
+
+    /// Parses a `define` directive whose keyword is already consumed, up to
+    /// and including its `endef`. The body is kept verbatim.
+    pub fn make_define(&mut self) -> ParseResult<B::Statement, B::Error> {
+        self.skip_whitespace();
+        let name = match self.iter.next() {
+            Some(Name(name)) | Some(Literal(name)) => name,
+            Some(t) => return Err(ParseError::new(Unexpected(t, self.iter.pos()))),
+            None => return Err(ParseError::new(UnexpectedEOF)),
+        };
+        let op = {
+            let mut peeked = self.iter.multipeek();
+            Self::multipeek_assign_op(&mut peeked)
+        };
+        let op = match op {
+            Some((op, len)) => {
+                self.skip_whitespace();
+                for _ in 0..len {
+                    self.iter.next();
+                }
+                op
+            }
+            None => AmAssignOp::Lazy,
+        };
+        self.newline();
+
+        // definitions may nest, each of them closed by its own `endef`.
+        let mut depth = 1;
+        let mut lines = Vec::new();
+        loop {
+            let mut line = Vec::new();
+            loop {
+                match self.iter.next() {
+                    Some(Newline) => break,
+                    Some(t) => line.push(t),
+                    None if line.is_empty() => return Err(ParseError::new(UnexpectedEOF)),
+                    None => break,
+                }
+            }
+            let first = line.iter().find(|t| !matches!(t, Whitespace(_)));
+            match first {
+                Some(Name(kw)) if kw == ENDEF => depth -= 1,
+                Some(Name(kw)) if kw == DEFINE => depth += 1,
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+            lines.push(concat_tokens(&line));
+        }
+        Ok(self.builder.define(name, op, lines.join("\n"))?)
+    }
+
+    /// Parses an `export` or `unexport` directive whose keyword is already consumed.
+    fn make_export(&mut self, export: bool) -> ParseResult<B::Statement, B::Error> {
+        self.skip_whitespace();
+        if export {
+            if let Some(assignment) = self.automake_assignment()? {
+                return Ok(self.builder.export(export, Vec::new(), Some(assignment))?);
+            }
+        }
+        let vars = self.words_with_delim(&[Newline, Pound])?;
+        self.newline();
+        Ok(self.builder.export(export, vars, None)?)
+    }
+
     /// Parse an automake assignment operator without consuming tokens until confirmed.
     pub fn automake_assign_op(&mut self) -> ParseResult<Option<(String, AmAssignOp)>, B::Error> {
-        use AmAssignOp::*;
         let may_op = {
             let mut peeked = self.iter.multipeek();
             peeked.peek_next(); // eat identifier
-            let first_token_after_whitespaces = Self::multipeek_skip_whitespace(&mut peeked);
-            match first_token_after_whitespaces {
-                Some(Equals) => Some(Lazy),
-                Some(Colon) if peeked.peek_next() == Some(&Equals) => Some(Instant),
-                Some(Plus) if peeked.peek_next() == Some(&Equals) => Some(Append),
-                _ => None,
-            }
+            Self::multipeek_assign_op(&mut peeked)
         };
-        if let Some(op) = may_op {
+        if let Some((op, len)) = may_op {
             if let Some(Name(ref name)) = self.iter.next() {
                 self.skip_whitespace();
-                match &op {
-                    Lazy => {
-                        eat!(self, { Equals => {} });
-                    }
-                    Instant => {
-                        eat!(self, { Colon => {} });
-                        eat!(self, { Equals => {} });
-                    }
-                    Append => {
-                        eat!(self, { Plus => {} });
-                        eat!(self, { Equals => {} });
-                    }
-                };
+                for _ in 0..len {
+                    self.iter.next();
+                }
                 Ok(Some((name.to_owned(), op)))
             } else {
                 Err(self.make_unexpected_err())
@@ -395,6 +755,25 @@ where
         }
     }
 
+    /// Peek an assignment operator after optional whitespace, returning it
+    /// with the number of tokens it consists of.
+    fn multipeek_assign_op(peeked: &mut Multipeek<'_>) -> Option<(AmAssignOp, usize)> {
+        use AmAssignOp::*;
+        match Self::multipeek_skip_whitespace(peeked) {
+            Some(Equals) => Some((Lazy, 1)),
+            Some(Colon) => match peeked.peek_next().cloned() {
+                Some(Equals) => Some((Instant, 2)),
+                // POSIX `::=` is the same as `:=`.
+                Some(Colon) if peeked.peek_next() == Some(&Equals) => Some((Instant, 3)),
+                _ => None,
+            },
+            Some(Plus) if peeked.peek_next() == Some(&Equals) => Some((Append, 2)),
+            Some(Question) if peeked.peek_next() == Some(&Equals) => Some((IfUndefined, 2)),
+            Some(Bang) if peeked.peek_next() == Some(&Equals) => Some((Shell, 2)),
+            _ => None,
+        }
+    }
+
     /// Parses a single complete command.
     ///
     /// For example, `foo && bar; baz` will yield two complete
@@ -1089,6 +1468,28 @@ where
         Ok(ret)
     }
 
+    /// Parses the rest of the line as make text, where shell operators
+    /// such as `;` or `|` are kept as literal words.
+    fn make_text_words(&mut self) -> ParseResult<Vec<B::Word>, B::Error> {
+        let mut ret = Vec::new();
+        loop {
+            ret.extend(self.words_with_delim(&[Newline])?);
+            self.skip_whitespace();
+            match self.iter.peek() {
+                Some(Semi | Amp | Pipe | AndIf | OrIf | DSemi | Less | Great | DLess | DGreat)
+                | Some(GreatAnd | LessAnd | DLessDash | Clobber | LessGreat)
+                | Some(ParenOpen | ParenClose) => {
+                    let tok = self.iter.next().unwrap();
+                    let fragment = self
+                        .builder
+                        .word_fragment(WordKind::Literal(tok.to_string()))?;
+                    ret.push(self.builder.word(Single(Simple(fragment)))?);
+                }
+                _ => return Ok(ret),
+            }
+        }
+    }
+
     /// Identical to `Parser::word_preserve_trailing_whitespace_raw()` but
     /// allows for specifying an arbitrary token as a word delimiter.
     fn word_preserve_trailing_whitespace_raw_with_delim(
@@ -1385,17 +1786,20 @@ where
     pub fn parameter(&mut self) -> ParseResult<B::WordFragment, B::Error> {
         eat!(self, { Dollar => {} });
         let ret = match self.iter.peek() {
-            Some(Dollar) => {
-                // shell parameter
+            Some(Dollar) | Some(ParamPositional(_)) => {
+                // shell parameter, e.g. `$$var` or `$$1`
                 let p = self.shell_parameter()?;
                 self.builder.word_fragment(p)?
             }
             Some(&ParenOpen) => {
                 // automake variable
                 eat!(self, { ParenOpen => {} });
-                let p = self.automake_parameter()?;
-                eat!(self, { ParenClose => {} });
-                self.builder.variable(AmVar::Param(p))?
+                self.automake_reference(ParenClose)?
+            }
+            Some(&CurlyOpen) => {
+                // automake variable in the form of ${var}
+                eat!(self, { CurlyOpen => {} });
+                self.automake_reference(CurlyClose)?
             }
             Some(_) => {
                 let p = self.automake_parameter()?;
@@ -1406,6 +1810,145 @@ where
         Ok(ret)
     }
 
+    /// Parses the inside of `$(...)` or `${...}` up to and including `close`:
+    /// a variable reference, a substitution reference or a function call.
+    fn automake_reference(&mut self, close: Token) -> ParseResult<B::WordFragment, B::Error> {
+        if let Some((name, len, max_args)) = self.peek_function_name() {
+            for _ in 0..len {
+                self.iter.next();
+            }
+            self.skip_whitespace();
+            return self.make_function_call(name, max_args, close);
+        }
+        let ret = if let Some(At | Star | Less | Caret | Plus | Question) = self.iter.peek() {
+            // automatic variables, e.g. $(@D)
+            let p = self.automake_parameter()?;
+            self.builder.variable(AmVar::Param(p))?
+        } else {
+            let delims = [Colon, close.clone()];
+            let name = match self.word_preserve_trailing_whitespace_raw_with_delim(Some(&delims))? {
+                Some(name) => name,
+                None => return Err(self.make_unexpected_err()),
+            };
+            if self.iter.peek() == Some(&Colon) {
+                // substitution reference, e.g. $(foo:.c=.o)
+                self.iter.next();
+                let pattern = self.word_or_empty_with_delim(&[Equals, close.clone()])?;
+                eat!(self, { Equals => {} });
+                let replacement = self.word_or_empty_with_delim(std::slice::from_ref(&close))?;
+                let var = self.builder.word(name)?;
+                self.builder
+                    .substitution_reference(var, pattern, replacement)?
+            } else if let Some(name) = concat_literal(&name) {
+                self.builder
+                    .variable(AmVar::Param(MakeParameter::Var(name)))?
+            } else {
+                // computed variable name, e.g. $($(x)_SOURCES)
+                let name = self.builder.word(name)?;
+                self.builder.computed_variable(name)?
+            }
+        };
+        self.skip_whitespace();
+        match self.iter.peek() {
+            Some(t) if *t == close => {
+                self.iter.next();
+                Ok(ret)
+            }
+            _ => Err(self.make_unexpected_err()),
+        }
+    }
+
+    /// Peek the name of a built-in make function followed by whitespace, returning
+    /// the name, the number of tokens it consists of, and its maximum number of arguments.
+    fn peek_function_name(&mut self) -> Option<(String, usize, usize)> {
+        let mut peeked = self.iter.multipeek();
+        let mut name = String::new();
+        let mut len = 0;
+        loop {
+            match peeked.peek_next() {
+                Some(Name(s)) => name.push_str(s),
+                // e.g. filter-out
+                Some(Dash) if !name.is_empty() => name.push('-'),
+                Some(Whitespace(_)) if !name.is_empty() => break,
+                _ => return None,
+            }
+            len += 1;
+        }
+        MakeFunction::max_args(&name).map(|max_args| (name, len, max_args))
+    }
+
+    /// Parses the comma separated arguments of a make function call up to and including `close`.
+    fn make_function_call(
+        &mut self,
+        name: String,
+        max_args: usize,
+        close: Token,
+    ) -> ParseResult<B::WordFragment, B::Error> {
+        let mut args = Vec::new();
+        loop {
+            let last = args.len() + 1 >= max_args;
+            args.push(self.make_function_arg(&close, last)?);
+            let pos = self.iter.pos();
+            match self.iter.next() {
+                Some(Comma) => {}
+                Some(t) if t == close => break,
+                Some(t) => return Err(ParseError::new(Unexpected(t, pos))),
+                None => return Err(ParseError::new(UnexpectedEOF)),
+            }
+        }
+        Ok(self.builder.function_call(name, args)?)
+    }
+
+    /// Parses words of a make function argument until a comma or `close`,
+    /// without consuming the delimiter. Commas are literals in the last argument,
+    /// and so are delimiters enclosed in parentheses.
+    fn make_function_arg(
+        &mut self,
+        close: &Token,
+        last: bool,
+    ) -> ParseResult<Vec<B::Word>, B::Error> {
+        let delims = if last {
+            vec![close.clone()]
+        } else {
+            vec![Comma, close.clone()]
+        };
+        let mut words = Vec::new();
+        let mut depth = 0usize;
+        loop {
+            words.extend(self.words_with_delim(&delims)?);
+            self.skip_whitespace();
+            let tok = match self.iter.peek() {
+                None | Some(Newline) => return Err(self.make_unexpected_err()),
+                Some(t) if depth == 0 && delims.contains(t) => break,
+                Some(t) => t.clone(),
+            };
+            match tok {
+                ParenOpen => depth += 1,
+                ParenClose => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            // Keep tokens which can not be a part of a word, e.g. `|` in $(shell ...).
+            self.iter.next();
+            let frag = self
+                .builder
+                .word_fragment(WordKind::Literal(tok.as_str().to_owned()))?;
+            words.push(self.builder.word(Single(Simple(frag)))?);
+        }
+        Ok(words)
+    }
+
+    /// Parses a single word until one of `delims`, or an empty word if there is none.
+    fn word_or_empty_with_delim(&mut self, delims: &[Token]) -> ParseResult<B::Word, B::Error> {
+        let word = match self.word_preserve_trailing_whitespace_raw_with_delim(Some(delims))? {
+            Some(word) => word,
+            None => Single(Simple(
+                self.builder
+                    .word_fragment(WordKind::Literal(String::new()))?,
+            )),
+        };
+        Ok(self.builder.word(word)?)
+    }
+
     /// Parses escaped shell parameters e.g. $$var, $$(subst).
     fn shell_parameter(&mut self) -> ParseResult<WordKind<B::Command, B::WordFragment>, B::Error> {
         use crate::ast::Parameter;
@@ -1758,6 +2301,7 @@ where
     fn automake_template(&mut self) -> ParseResult<B::WordFragment, B::Error> {
         let is_template = {
             let mut peeked = self.iter.multipeek();
+            peeked.peek_next(); // skip the opening @
             if !matches!(peeked.peek_next(), Some(Name(_) | Literal(_))) {
                 false
             } else if !matches!(peeked.peek_next(), Some(At)) {
@@ -1768,6 +2312,7 @@ where
         };
 
         if is_template {
+            eat!(self, { At => { }});
             let name = match self.iter.next() {
                 Some(Name(s)) => s.to_owned(),
                 Some(Literal(s)) => s.to_owned(),
@@ -3105,3 +3650,141 @@ fn concat_tokens(tokens: &[Token]) -> String {
     s.extend(tokens.iter().map(Token::as_str));
     s
 }
+
+/// Strip a leading `$(var)/` or `${var}/` from an include path.
+fn strip_var_prefix<'a>(text: &'a str, var: &str) -> Option<&'a str> {
+    [format!("$({})/", var), format!("${{{}}}/", var)]
+        .iter()
+        .find_map(|prefix| text.strip_prefix(prefix.as_str()))
+}
+
+/// Express `path` relative to `base`, both relative to the same directory.
+fn relative_path(path: &Path, base: &Path) -> String {
+    let path = path.components().collect::<Vec<_>>();
+    let base = base.components().collect::<Vec<_>>();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let mut ret = PathBuf::new();
+    for _ in common..base.len() {
+        ret.push("..");
+    }
+    for component in &path[common..] {
+        ret.push(component);
+    }
+    if ret.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        ret.to_string_lossy().into_owned()
+    }
+}
+
+/// Substitute the `%reldir%` family in a fragment as automake does.
+/// In the directory of the makefile, `%reldir%/` and `%canon_reldir%_` vanish.
+fn substitute_reldir(text: &str, reldir: &str) -> String {
+    let canon = canonicalize(reldir);
+    let mut text = text.to_string();
+    if reldir == "." {
+        for placeholder in ["%reldir%/", "%D%/", "%canon_reldir%_", "%C%_"] {
+            text = text.replace(placeholder, "");
+        }
+    }
+    for placeholder in ["%reldir%", "%D%"] {
+        text = text.replace(placeholder, reldir);
+    }
+    for placeholder in ["%canon_reldir%", "%C%"] {
+        text = text.replace(placeholder, &canon);
+    }
+    text
+}
+
+/// Rewrite the `@COND_TRUE@` and `@COND_FALSE@` line prefixes automake puts
+/// in a `Makefile.in` into `if COND`, `else` and `endif` lines, opening and
+/// closing blocks as the prefixes of consecutive lines change. Continuation
+/// lines only lose their prefixes. The position is reset after the added
+/// lines and the dropped prefixes, so that the tokens keep their position in
+/// the original source.
+fn conditional_prefixes_to_blocks<I: Iterator<Item = Token>>(iter: I) -> Vec<TokenOrPos> {
+    use TokenOrPos::{Pos, Tok};
+
+    fn open(ret: &mut Vec<TokenOrPos>, (var, value): &(String, bool)) {
+        ret.extend([Name(IF.to_string()), Whitespace(" ".to_string())].map(Tok));
+        ret.extend([Name(var.clone()), Newline].map(Tok));
+        if !value {
+            ret.extend([Name(ELSE.to_string()), Newline].map(Tok));
+        }
+    }
+    fn close(ret: &mut Vec<TokenOrPos>, open: &mut Vec<(String, bool)>, keep: usize) {
+        while open.len() > keep {
+            open.pop();
+            ret.extend([Name(ENDIF.to_string()), Newline].map(Tok));
+        }
+    }
+
+    let tokens = iter.collect::<Vec<_>>();
+    let mut ret = Vec::with_capacity(tokens.len());
+    let mut opened: Vec<(String, bool)> = Vec::new();
+    let mut continued = false;
+    let mut pos = SourcePos::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        // Read the prefixes at the head of the line.
+        let line_start = i;
+        let mut prefixes = Vec::new();
+        while let [At, Name(name), At, ..] = &tokens[i..] {
+            let cond = match name.rsplit_once('_') {
+                Some((var, "TRUE")) if !var.is_empty() => (var.to_string(), true),
+                Some((var, "FALSE")) if !var.is_empty() => (var.to_string(), false),
+                _ => break,
+            };
+            prefixes.push(cond);
+            i += 3;
+        }
+        let added = ret.len();
+        if !continued {
+            let common = opened
+                .iter()
+                .zip(&prefixes)
+                .take_while(|(a, b)| a == b)
+                .count();
+            close(&mut ret, &mut opened, common);
+            for cond in &prefixes[common..] {
+                open(&mut ret, cond);
+                opened.push(cond.clone());
+            }
+        }
+        tokens[line_start..i].iter().for_each(|t| pos.advance(t));
+        if i > line_start || ret.len() > added {
+            ret.push(Pos(pos));
+        }
+        let end = tokens[i..]
+            .iter()
+            .position(|t| *t == Newline)
+            .map_or(tokens.len(), |n| i + n + 1);
+        let line = &tokens[i..end];
+        continued = matches!(line, [.., Backslash, Newline]);
+        for t in line {
+            pos.advance(t);
+            ret.push(Tok(t.clone()));
+        }
+        i = end;
+    }
+    if !opened.is_empty() && tokens.last().is_some_and(|t| *t != Newline) {
+        ret.push(Tok(Newline));
+    }
+    close(&mut ret, &mut opened, 0);
+    ret
+}
+
+/// Returns the string of a word if it consists only of unquoted literals.
+fn concat_literal<F>(word: &ConcatWordKind<F>) -> Option<String>
+where
+    F: Into<Option<String>> + Clone,
+{
+    let literal = |frag: &builder::QuoteWordKind<F>| match frag {
+        Simple(frag) => frag.clone().into(),
+        _ => None,
+    };
+    match word {
+        Single(frag) => literal(frag),
+        Concat(frags) => frags.iter().map(literal).collect(),
+    }
+}
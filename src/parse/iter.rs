@@ -14,7 +14,7 @@ pub struct UnmatchedError(pub Token, pub SourcePos);
 /// An internal variant that indicates if a token should be yielded
 /// or the current position updated to some value.
 #[derive(Debug)]
-enum TokenOrPos {
+pub(crate) enum TokenOrPos {
     /// A consumed token which should be yielded.
     Tok(Token),
     /// The current position should be updated to the contained value.
@@ -251,7 +251,7 @@ impl<I: Iterator<Item = Token>> TokenIter<I> {
     /// inner iterator is advanced further. The optional `buf_start` (if provided)
     /// indicates what the iterator's position should have been if we were to naturally
     /// yield the provided buffer.
-    fn buffer_tokens_and_positions_to_yield_first(
+    pub(crate) fn buffer_tokens_and_positions_to_yield_first(
         &mut self,
         mut tokens: Vec<TokenOrPos>,
         token_start: Option<SourcePos>,
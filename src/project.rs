@@ -0,0 +1,507 @@
+//! Loads a whole autotools project: `configure.ac` and every `Makefile.am` reachable from it.
+//!
+//! `Project::load` parses the top-level `configure.ac`, follows the outputs of
+//! `AC_CONFIG_FILES` named `Makefile` to their `Makefile.am`, recurses into the
+//! directories listed in `SUBDIRS`/`DIST_SUBDIRS`, and loads nested packages
+//! registered by `AC_CONFIG_SUBDIRS`. Fragments included by a `Makefile.am` are
+//! parsed into its node pool. Every parsed file is kept in a file table indexed
+//! by `FileId`, which is the common entry point of project-wide analyses.
+//!
+//! A file can be edited in place with `ProjectFile::edit`, which reparses
+//! only the commands around the change.
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use slab::Slab;
+
+use crate::ast::am::{AmLine, AmWord, MayAm};
+use crate::ast::builder::BuilderError;
+use crate::ast::minimal::{Word, WordFragment};
+use crate::ast::node::{
+    AcCommand, AutoconfPool, DisplayNode, M4Argument, M4Macro, Node, NodeId, NodePool,
+};
+use crate::ast::MayM4;
+use crate::lexer::Lexer;
+use crate::parse::autoconf::NodeParser;
+use crate::parse::automake::{AutomakeConfig, AutomakeNodeParser};
+use crate::parse::ParseError;
+use crate::util::normalize;
+
+mod incremental;
+
+pub use self::incremental::{Reparse, TextEdit};
+
+/// Index of a file in the file table of a `Project`.
+pub type FileId = usize;
+
+/// Names of the autoconf input file, in the order of preference.
+const CONFIGURE_NAMES: &[&str] = &["configure.ac", "configure.in"];
+
+/// Automake variables listing the subdirectories to recurse into.
+const SUBDIR_VARS: &[&str] = &["SUBDIRS", "DIST_SUBDIRS"];
+
+/// The error type returned while loading a project.
+#[derive(Debug)]
+pub enum ProjectError {
+    /// A file could not be read.
+    Io(PathBuf, io::Error),
+    /// A file could not be parsed.
+    Parse(PathBuf, Box<ParseError<BuilderError>>),
+    /// A file is neither an autoconf script nor a makefile by its name.
+    UnknownKind(PathBuf),
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "failed to read {}: {}", path.display(), e),
+            Self::Parse(path, e) => write!(f, "failed to parse {}: {}", path.display(), e),
+            Self::UnknownKind(path) => write!(
+                f,
+                "{} is neither an autoconf script nor a makefile",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for ProjectError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(_, e) => Some(e),
+            Self::Parse(_, e) => Some(&**e),
+            Self::UnknownKind(_) => None,
+        }
+    }
+}
+
+/// A parsed `configure.ac`.
+#[derive(Debug)]
+pub struct AutoconfFile {
+    /// All nodes of the script.
+    pub pool: AutoconfPool,
+    /// Top-level commands in the order of appearance.
+    pub top_ids: Vec<NodeId>,
+}
+
+impl AutoconfFile {
+    /// Collect the array argument of every call of `name`, rendered as strings.
+    pub fn macro_array_args(&self, name: &str) -> Vec<String> {
+        let mut ret = Vec::new();
+        for m4_macro in self.macro_calls(name) {
+            match m4_macro.args.first() {
+                Some(M4Argument::Array(words)) => {
+                    ret.extend(words.iter().map(|w| self.pool.display_word(w, false)))
+                }
+                Some(M4Argument::Word(word)) => ret.push(self.pool.display_word(word, false)),
+                Some(M4Argument::Literal(lit)) => {
+                    ret.extend(lit.split_whitespace().map(|s| s.to_string()))
+                }
+                _ => {}
+            }
+        }
+        ret
+    }
+
+    /// Returns the argument at `index` of the first call of `name`, rendered
+    /// as a string. The words of an array argument are separated by spaces.
+    pub fn macro_arg(&self, name: &str, index: usize) -> Option<String> {
+        let arg = self.macro_calls(name).next()?.args.get(index)?;
+        self.render_arg(arg)
+    }
+
+    /// Returns the arguments of every call of `name`, rendered as by
+    /// `macro_arg`. Arguments holding shell commands are `None`.
+    pub fn macro_args(&self, name: &str) -> Vec<Vec<Option<String>>> {
+        self.macro_calls(name)
+            .map(|m4_macro| {
+                m4_macro
+                    .args
+                    .iter()
+                    .map(|arg| self.render_arg(arg))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns whether the script calls the macro `name`.
+    pub fn calls_macro(&self, name: &str) -> bool {
+        self.macro_calls(name).next().is_some()
+    }
+
+    /// Renders an argument of a macro call as text, or `None` for commands.
+    pub fn render_arg(&self, arg: &M4Argument) -> Option<String> {
+        match arg {
+            M4Argument::Literal(s) | M4Argument::Program(s) | M4Argument::Unknown(s) => {
+                Some(s.clone())
+            }
+            M4Argument::Word(word) => Some(self.pool.display_word(word, false)),
+            M4Argument::Array(words) => Some(
+                words
+                    .iter()
+                    .map(|w| self.pool.display_word(w, false))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            M4Argument::Commands(_) => None,
+        }
+    }
+
+    fn macro_calls<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a M4Macro> + 'a {
+        self.pool
+            .nodes
+            .iter()
+            .filter_map(move |(_, node)| match &node.cmd {
+                AcCommand(MayM4::Macro(m4_macro)) if m4_macro.name == name => Some(m4_macro),
+                _ => None,
+            })
+    }
+}
+
+/// A parsed `Makefile.am`.
+#[derive(Debug)]
+pub struct AutomakeFile {
+    /// All nodes of the makefile.
+    pub nodes: Slab<Node<AmLine, ()>>,
+    /// Top-level statements in the order of appearance.
+    pub top_ids: Vec<NodeId>,
+}
+
+/// Renders the shell commands of recipes and command substitutions.
+impl DisplayNode for AutomakeFile {
+    type Word = AmWord;
+
+    fn display_node(&self, node_id: NodeId, indent_level: usize) -> String {
+        match self.nodes.get(node_id).map(|node| &node.cmd) {
+            Some(AmLine::Shell(cmd)) => self.command_to_string(cmd, None, indent_level),
+            _ => String::new(),
+        }
+    }
+
+    fn display_word(&self, word: &AmWord, should_quote: bool) -> String {
+        let frags = match &word.0 {
+            Word::Single(frag) => std::slice::from_ref(frag),
+            Word::Concat(frags) => frags.as_slice(),
+            Word::Empty => return "\"\"".to_string(),
+        };
+        let text = frags
+            .iter()
+            .map(|frag| match frag {
+                MayAm::Shell(frag) => self.shell_word_to_string(frag),
+                MayAm::Automake(var) => var.to_string(),
+            })
+            .collect::<String>();
+        let quoted = matches!(
+            &word.0,
+            Word::Single(MayAm::Shell(WordFragment::DoubleQuoted(_)))
+        );
+        if should_quote && !quoted {
+            format!("\"{}\"", text)
+        } else {
+            text
+        }
+    }
+}
+
+impl NodePool<AmWord> for AutomakeFile {}
+
+/// Parsed contents of a project file.
+#[derive(Debug)]
+pub enum FileContent {
+    /// An autoconf script.
+    Autoconf(AutoconfFile),
+    /// An automake makefile.
+    Automake(AutomakeFile),
+}
+
+/// An entry of the file table.
+#[derive(Debug)]
+pub struct ProjectFile {
+    /// Path relative to the project root.
+    pub path: PathBuf,
+    /// The original source text.
+    pub source: String,
+    /// The parsed contents.
+    pub content: FileContent,
+}
+
+impl ProjectFile {
+    /// Parse a file on its own, outside of any project. Autoconf scripts
+    /// (`configure.ac`, `configure.in`, `*.ac`, `*.m4`) are told from
+    /// makefiles by their names. `Makefile.am` and `*.am` are read as automake
+    /// makefiles, and `Makefile.in`, `Makefile` and `*.mk` as generated or
+    /// plain makefiles. Included fragments are not resolved.
+    pub fn parse<P: Into<PathBuf>>(path: P, source: String) -> Result<Self, ProjectError> {
+        let path = path.into();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let parse_error = |e| ProjectError::Parse(path.clone(), Box::new(e));
+        let content = if is_autoconf(&path) {
+            let (nodes, top_ids) = NodeParser::<_, ()>::new(Lexer::new(source.chars()))
+                .try_parse_all()
+                .map_err(parse_error)?;
+            FileContent::Autoconf(AutoconfFile {
+                pool: AutoconfPool::new(nodes, None),
+                top_ids,
+            })
+        } else if extension == "am"
+            || matches!(name, "Makefile.in" | "Makefile" | "GNUmakefile")
+            || extension == "mk"
+        {
+            let config = AutomakeConfig {
+                makefile_in: extension != "am",
+                ..Default::default()
+            };
+            let (nodes, top_ids) =
+                AutomakeNodeParser::new_with_automake_config(Lexer::new(source.chars()), config)
+                    .try_parse_all()
+                    .map_err(parse_error)?;
+            FileContent::Automake(AutomakeFile { nodes, top_ids })
+        } else {
+            return Err(ProjectError::UnknownKind(path));
+        };
+        Ok(Self {
+            path,
+            source,
+            content,
+        })
+    }
+
+    /// Directory containing the file, relative to the project root.
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new(""))
+    }
+
+    /// Returns the parsed autoconf script, if this file is one.
+    pub fn as_autoconf(&self) -> Option<&AutoconfFile> {
+        match &self.content {
+            FileContent::Autoconf(ac) => Some(ac),
+            _ => None,
+        }
+    }
+
+    /// Returns the parsed automake makefile, if this file is one.
+    pub fn as_automake(&self) -> Option<&AutomakeFile> {
+        match &self.content {
+            FileContent::Automake(am) => Some(am),
+            _ => None,
+        }
+    }
+}
+
+/// An autotools project loaded from disk.
+#[derive(Debug)]
+pub struct Project {
+    root: PathBuf,
+    files: Vec<ProjectFile>,
+    index: HashMap<PathBuf, FileId>,
+}
+
+impl Project {
+    /// Load the project whose top-level `configure.ac` lives in `root`.
+    pub fn load<P: AsRef<Path>>(root: P) -> Result<Self, ProjectError> {
+        let mut project = Self {
+            root: root.as_ref().to_path_buf(),
+            files: Vec::new(),
+            index: HashMap::new(),
+        };
+        project.load_package(Path::new(""), true)?;
+        Ok(project)
+    }
+
+    /// The root directory given to `load`.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The file table, in the order files were loaded.
+    pub fn files(&self) -> &[ProjectFile] {
+        &self.files
+    }
+
+    /// Get a file by its id.
+    pub fn file(&self, id: FileId) -> &ProjectFile {
+        &self.files[id]
+    }
+
+    /// Find a file by its path relative to the project root.
+    pub fn find<P: AsRef<Path>>(&self, path: P) -> Option<FileId> {
+        self.index.get(&normalize(path.as_ref())).copied()
+    }
+
+    /// The top-level `configure.ac`.
+    pub fn configure(&self) -> FileId {
+        0
+    }
+
+    /// Ids of all loaded autoconf scripts, including those of nested packages.
+    pub fn autoconf_files(&self) -> impl Iterator<Item = FileId> + '_ {
+        (0..self.files.len()).filter(|&id| self.files[id].as_autoconf().is_some())
+    }
+
+    /// Ids of all loaded automake makefiles.
+    pub fn automake_files(&self) -> impl Iterator<Item = FileId> + '_ {
+        (0..self.files.len()).filter(|&id| self.files[id].as_automake().is_some())
+    }
+
+    /// The autoconf script of the innermost package containing a file.
+    pub fn package_of(&self, id: FileId) -> Option<FileId> {
+        let dir = self.files[id].dir();
+        self.autoconf_files()
+            .filter(|&ac| dir.starts_with(self.files[ac].dir()))
+            .max_by_key(|&ac| self.files[ac].dir().components().count())
+    }
+
+    /// Load the package rooted at `dir`, which must have an autoconf script
+    /// if `required` is set.
+    fn load_package(&mut self, dir: &Path, required: bool) -> Result<(), ProjectError> {
+        let Some(name) = self.configure_name(dir) else {
+            if required {
+                let path = self.root.join(dir).join(CONFIGURE_NAMES[0]);
+                let e = io::Error::from(io::ErrorKind::NotFound);
+                return Err(ProjectError::Io(path, e));
+            }
+            return Ok(());
+        };
+        let path = normalize(&dir.join(name));
+        if self.index.contains_key(&path) {
+            return Ok(());
+        }
+        let source = self.read(&path)?;
+        let (nodes, top_ids) = NodeParser::<_, ()>::new(Lexer::new(source.chars()))
+            .try_parse_all()
+            .map_err(|e| ProjectError::Parse(path.clone(), Box::new(e)))?;
+        let ac = AutoconfFile {
+            pool: AutoconfPool::new(nodes, None),
+            top_ids,
+        };
+        let makefiles = config_makefiles(&ac);
+        let subpackages = config_subdirs(&ac);
+        self.insert(path, source, FileContent::Autoconf(ac));
+
+        for makefile in makefiles {
+            self.load_makefile(&normalize(&dir.join(makefile)), dir)?;
+        }
+        for sub in subpackages {
+            self.load_package(&normalize(&dir.join(sub)), false)?;
+        }
+        Ok(())
+    }
+
+    /// Load a `Makefile.am` and the makefiles of its subdirectories, where
+    /// `package` is the directory of the enclosing package, `$(top_srcdir)`.
+    /// Missing files are skipped, as the makefile may be written by hand.
+    fn load_makefile(&mut self, path: &Path, package: &Path) -> Result<(), ProjectError> {
+        if self.index.contains_key(path) || !self.root.join(path).is_file() {
+            return Ok(());
+        }
+        let source = self.read(path)?;
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let config = AutomakeConfig {
+            top_srcdir: Some(self.root.join(package)),
+            subdir: dir.strip_prefix(package).unwrap_or(&dir).to_path_buf(),
+            ..Default::default()
+        };
+        let (nodes, top_ids) =
+            AutomakeNodeParser::new_with_automake_config(Lexer::new(source.chars()), config)
+                .try_parse_all()
+                .map_err(|e| ProjectError::Parse(path.to_path_buf(), Box::new(e)))?;
+        let am = AutomakeFile { nodes, top_ids };
+        let subdirs = automake_subdirs(&am);
+        self.insert(path.to_path_buf(), source, FileContent::Automake(am));
+
+        for sub in subdirs {
+            let sub = normalize(&dir.join(sub));
+            // A subdirectory with its own script is a nested package.
+            if self.configure_name(&sub).is_some() {
+                self.load_package(&sub, false)?;
+            } else {
+                self.load_makefile(&sub.join("Makefile.am"), package)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Name of the autoconf script of the package rooted at `dir`, if any.
+    fn configure_name(&self, dir: &Path) -> Option<&'static str> {
+        CONFIGURE_NAMES
+            .iter()
+            .copied()
+            .find(|name| self.root.join(dir).join(name).is_file())
+    }
+
+    fn read(&self, path: &Path) -> Result<String, ProjectError> {
+        let full = self.root.join(path);
+        fs::read_to_string(&full).map_err(|e| ProjectError::Io(full, e))
+    }
+
+    fn insert(&mut self, path: PathBuf, source: String, content: FileContent) -> FileId {
+        let id = self.files.len();
+        self.index.insert(path.clone(), id);
+        self.files.push(ProjectFile {
+            path,
+            source,
+            content,
+        });
+        id
+    }
+}
+
+/// Whether a file is read as an autoconf script by `ProjectFile::parse`.
+pub(crate) fn is_autoconf(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    CONFIGURE_NAMES.contains(&name) || matches!(extension, "ac" | "m4")
+}
+
+/// Makefile.am paths of `AC_CONFIG_FILES` outputs named `Makefile`,
+/// relative to the directory of the script.
+fn config_makefiles(ac: &AutoconfFile) -> Vec<PathBuf> {
+    let mut ret = Vec::new();
+    for tag in ac.macro_array_args("AC_CONFIG_FILES") {
+        let mut split = tag.split(':');
+        let output = Path::new(split.next().unwrap());
+        if output.file_name().is_none_or(|name| name != "Makefile") {
+            continue;
+        }
+        // `Makefile:foo/Makefile.in` takes the explicit input instead.
+        let input = match split.next() {
+            Some(input) => input.strip_suffix(".in").unwrap_or(input).to_string(),
+            None => output.to_string_lossy().into_owned(),
+        };
+        ret.push(PathBuf::from(format!("{}.am", input)));
+    }
+    ret
+}
+
+/// Directories of nested packages registered by `AC_CONFIG_SUBDIRS`.
+fn config_subdirs(ac: &AutoconfFile) -> Vec<PathBuf> {
+    ac.macro_array_args("AC_CONFIG_SUBDIRS")
+        .into_iter()
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Literal words assigned to `SUBDIRS` or `DIST_SUBDIRS`, including assignments
+/// under automake conditionals.
+fn automake_subdirs(am: &AutomakeFile) -> Vec<String> {
+    let mut ret = Vec::new();
+    for (_, node) in am.nodes.iter() {
+        let AmLine::Assignment(assignment) = &node.cmd else {
+            continue;
+        };
+        if !SUBDIR_VARS.contains(&assignment.lhs.as_str()) {
+            continue;
+        }
+        for dir in assignment.rhs.iter().filter_map(AmWord::as_literal) {
+            if dir != "." && !ret.contains(&dir) {
+                ret.push(dir);
+            }
+        }
+    }
+    ret
+}
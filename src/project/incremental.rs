@@ -0,0 +1,612 @@
+//! Incremental reparsing of edited autoconf scripts.
+//!
+//! `ProjectFile::edit` applies a `TextEdit` to the source of a file and
+//! reparses the smallest region around it: a run of top-level commands, or
+//! a run of commands in a `[Cmds]` argument of a macro call when the edit
+//! stays inside of them. The nodes of the region are replaced in the pool,
+//! the other nodes keep their ids and have their line ranges shifted by the
+//! number of lines the edit adds or removes.
+//!
+//! A region is reparsed on its own only when its old text parses on its own
+//! into the same commands, so that it starts and ends on command boundaries.
+//! Commands in a macro argument are parsed outside of the argument, which is
+//! sound only when the region holds no quote, parenthesis, comma or here
+//! document. Whenever a region cannot be used, the whole file is reparsed.
+use std::mem;
+use std::ops::Range;
+
+use slab::Slab;
+
+use super::{FileContent, ProjectError, ProjectFile};
+use crate::ast::builder::AutoconfNodeBuilder;
+use crate::ast::minimal::{Word, WordFragment};
+use crate::ast::node::{
+    AcCommand, AcWord, AcWordFragment, Condition, M4Argument, M4Macro, Node, NodeId, Redirect,
+    ShellCommand,
+};
+use crate::ast::MayM4;
+use crate::lexer::Lexer;
+use crate::parse::autoconf::{AutoconfParser, NodeParser};
+
+type Nodes = Slab<Node<AcCommand, ()>>;
+
+/// A change of the source of a file: the bytes in `range` are replaced by `text`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TextEdit {
+    /// The replaced bytes of the source.
+    pub range: Range<usize>,
+    /// The replacement text.
+    pub text: String,
+}
+
+impl TextEdit {
+    /// Creates an edit replacing `range` with `text`.
+    pub fn new<S: Into<String>>(range: Range<usize>, text: S) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+}
+
+/// The part of a file reparsed by `ProjectFile::edit`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Reparse {
+    /// Lines of the edited source which were reparsed, 1-based with an exclusive end.
+    pub lines: (usize, usize),
+    /// The macro call whose `[Cmds]` argument was reparsed,
+    /// or `None` when top-level commands were.
+    pub parent: Option<NodeId>,
+    /// Ids of the removed nodes. They may be reused by the inserted ones.
+    pub removed: Vec<NodeId>,
+    /// The commands replacing the removed ones, in the order of appearance.
+    pub inserted: Vec<NodeId>,
+    /// Whether the whole file was reparsed, in which case no id was kept.
+    pub full: bool,
+}
+
+/// Where a list of sibling commands is stored.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Siblings {
+    /// The top-level commands of the script.
+    Top,
+    /// The commands of the argument at the index of a macro call.
+    Arg(NodeId, usize),
+}
+
+/// A region of the old source to be reparsed.
+#[derive(Debug)]
+struct Region {
+    siblings: Siblings,
+    /// Indices of the replaced commands in their siblings.
+    run: Range<usize>,
+    /// The first line of the region.
+    line: usize,
+    /// The line following the region, or `None` when it runs to the end.
+    end_line: Option<usize>,
+    /// Bytes of the region in the old source.
+    bytes: Range<usize>,
+}
+
+/// The source of a file before and after an edit.
+struct Sources<'a> {
+    old: &'a str,
+    new: &'a str,
+    edit: &'a TextEdit,
+    /// Byte offset of each line of the old source.
+    old_lines: Vec<usize>,
+}
+
+impl Sources<'_> {
+    /// The line of the old source containing `byte`.
+    fn line_of(&self, byte: usize) -> usize {
+        self.old_lines.partition_point(|&start| start <= byte)
+    }
+
+    /// The byte offset of `line` in the old source.
+    fn offset(&self, line: usize) -> usize {
+        self.old_lines
+            .get(line - 1)
+            .copied()
+            .unwrap_or(self.old.len())
+    }
+
+    /// The bytes of an old region in the new source.
+    fn new_bytes(&self, region: &Region) -> Range<usize> {
+        let end = match region.end_line {
+            Some(_) => region.bytes.end + self.edit.text.len() - self.edit.range.len(),
+            None => self.new.len(),
+        };
+        region.bytes.start..end
+    }
+
+    /// The number of lines added by the edit, which is negative when it removes lines.
+    fn line_delta(&self) -> isize {
+        let newlines = |s: &str| s.matches('\n').count() as isize;
+        newlines(&self.edit.text) - newlines(&self.old[self.edit.range.clone()])
+    }
+}
+
+impl ProjectFile {
+    /// Replace a range of the source with a new text, and reparse the part
+    /// of the file affected by the change.
+    ///
+    /// Nodes outside of the reparsed region keep their ids. Makefiles, and
+    /// scripts calling `changequote`, are reparsed as a whole, without
+    /// resolving the fragments included by a makefile. When the edited
+    /// source cannot be parsed, the error is returned and the file is left
+    /// unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range of the edit is out of the bounds of the source,
+    /// or does not lie on `char` boundaries.
+    pub fn edit(&mut self, edit: &TextEdit) -> Result<Reparse, ProjectError> {
+        let mut source = self.source.clone();
+        source.replace_range(edit.range.clone(), &edit.text);
+        let ac = match &self.content {
+            FileContent::Autoconf(ac)
+                if !self.source.contains("changequote") && !source.contains("changequote") =>
+            {
+                ac
+            }
+            _ => return self.reparse(source),
+        };
+        let sources = Sources {
+            old: &self.source,
+            new: &source,
+            edit,
+            old_lines: line_offsets(&self.source),
+        };
+        match innermost_region(&ac.pool.nodes, Siblings::Top, &ac.top_ids, &sources) {
+            Some(region) => {
+                let delta = sources.line_delta();
+                let new_bytes = sources.new_bytes(&region);
+                Ok(self.apply(region, new_bytes, delta, source))
+            }
+            None => self.reparse(source),
+        }
+    }
+
+    /// Reparse the whole file with a new source.
+    fn reparse(&mut self, source: String) -> Result<Reparse, ProjectError> {
+        let file = ProjectFile::parse(self.path.clone(), source)?;
+        let removed = match &self.content {
+            FileContent::Autoconf(ac) => ac.pool.nodes.iter().map(|(id, _)| id).collect(),
+            FileContent::Automake(am) => am.nodes.iter().map(|(id, _)| id).collect(),
+        };
+        *self = file;
+        let inserted = match &self.content {
+            FileContent::Autoconf(ac) => ac.top_ids.clone(),
+            FileContent::Automake(am) => am.top_ids.clone(),
+        };
+        Ok(Reparse {
+            lines: (1, 1 + line_count(&self.source)),
+            parent: None,
+            removed,
+            inserted,
+            full: true,
+        })
+    }
+
+    /// Replace the commands of a region with those parsed from `new_bytes` of
+    /// the new source, which are known to parse.
+    fn apply(
+        &mut self,
+        region: Region,
+        new_bytes: Range<usize>,
+        delta: isize,
+        source: String,
+    ) -> Reparse {
+        let FileContent::Autoconf(ac) = &mut self.content else {
+            unreachable!()
+        };
+        let mut nodes = mem::take(&mut ac.pool.nodes);
+        let replaced = match region.siblings {
+            Siblings::Top => &ac.top_ids[region.run.clone()],
+            Siblings::Arg(id, index) => &commands_arg(&nodes, id, index)[region.run.clone()],
+        }
+        .to_vec();
+        let mut removed = Vec::new();
+        for id in replaced {
+            subtree(&nodes, id, &mut removed);
+        }
+        // The builder may leave commands which were turned into something
+        // else, such as the condition of an `if`, out of the tree.
+        let in_region =
+            |line: usize| line >= region.line && region.end_line.is_none_or(|l| line < l);
+        removed.extend(
+            nodes
+                .iter()
+                .filter_map(|(id, node)| node.range_start().is_some_and(in_region).then_some(id)),
+        );
+        removed.sort_unstable();
+        removed.dedup();
+        for &id in &removed {
+            nodes.remove(id);
+        }
+        let mut kept = vec![false; nodes.capacity()];
+        for (id, node) in nodes.iter_mut() {
+            kept[id] = true;
+            for line in node.range.iter_mut().flat_map(|(start, end)| [start, end]) {
+                if region.end_line.is_some_and(|l| *line >= l) {
+                    *line = line.checked_add_signed(delta).unwrap();
+                }
+            }
+        }
+
+        let text = &source[new_bytes];
+        let builder = AutoconfNodeBuilder::with_nodes(nodes);
+        let (mut nodes, inserted) =
+            AutoconfParser::with_builder(Lexer::new(text.chars()), builder, false)
+                .try_parse_all()
+                .expect("the region was parsed before");
+        for (id, node) in nodes.iter_mut() {
+            if !kept.get(id).copied().unwrap_or(false) {
+                for (start, end) in node.range.iter_mut() {
+                    *start += region.line - 1;
+                    *end += region.line - 1;
+                }
+            }
+        }
+        let parent = match region.siblings {
+            Siblings::Top => {
+                ac.top_ids.splice(region.run, inserted.iter().copied());
+                None
+            }
+            Siblings::Arg(id, index) => {
+                let AcCommand(MayM4::Macro(m4_macro)) = &mut nodes[id].cmd else {
+                    unreachable!()
+                };
+                let M4Argument::Commands(ids) = &mut m4_macro.args[index] else {
+                    unreachable!()
+                };
+                ids.splice(region.run, inserted.iter().copied());
+                Some(id)
+            }
+        };
+        ac.pool.nodes = nodes;
+        let lines = (region.line, region.line + line_count(text));
+        self.source = source;
+        Reparse {
+            lines,
+            parent,
+            removed,
+            inserted,
+            full: false,
+        }
+    }
+}
+
+/// Byte offsets of the lines of `text`.
+fn line_offsets(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+/// The number of lines of `text`, counting a last line without a newline.
+fn line_count(text: &str) -> usize {
+    text.matches('\n').count() + (!text.is_empty() && !text.ends_with('\n')) as usize
+}
+
+/// Parse a region of a script on its own.
+fn parse(text: &str) -> Option<(Nodes, Vec<NodeId>)> {
+    NodeParser::<_, ()>::new(Lexer::new(text.chars()))
+        .try_parse_all()
+        .ok()
+}
+
+/// The commands of the argument at `index` of the macro call `id`.
+fn commands_arg(nodes: &Nodes, id: NodeId, index: usize) -> &[NodeId] {
+    match &nodes[id].cmd {
+        AcCommand(MayM4::Macro(m4_macro)) => match &m4_macro.args[index] {
+            M4Argument::Commands(ids) => ids,
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    }
+}
+
+/// The line ranges of commands, or `None` if one of them has none.
+fn line_ranges(nodes: &Nodes, ids: &[NodeId]) -> Option<Vec<(usize, usize)>> {
+    ids.iter()
+        .map(|&id| Some((nodes[id].range_start()?, nodes[id].range_end()?)))
+        .collect()
+}
+
+/// Indices of the first and last commands on the lines of the edit.
+///
+/// The end of a range is the line following a command, unless the command
+/// is followed by another one on its last line.
+fn around_edit(lines: &[(usize, usize)], sources: &Sources<'_>) -> Option<(usize, usize)> {
+    let edit = &sources.edit.range;
+    let (first, last) = (sources.line_of(edit.start), sources.line_of(edit.end));
+    let i = lines
+        .iter()
+        .position(|&(start, end)| start.max(end.saturating_sub(1)) >= first)
+        .unwrap_or(lines.len().checked_sub(1)?);
+    let j = lines.iter().rposition(|&(start, _)| start <= last)?.max(i);
+    Some((i, j))
+}
+
+/// Find the smallest region containing the edit, looking into the `[Cmds]`
+/// arguments of a macro call when the edit lies within one.
+fn innermost_region(
+    nodes: &Nodes,
+    siblings: Siblings,
+    ids: &[NodeId],
+    sources: &Sources<'_>,
+) -> Option<Region> {
+    let (i, j) = around_edit(&line_ranges(nodes, ids)?, sources)?;
+    if i == j {
+        if let AcCommand(MayM4::Macro(m4_macro)) = &nodes[ids[i]].cmd {
+            let nested = m4_macro
+                .args
+                .iter()
+                .enumerate()
+                .find_map(|(index, arg)| match arg {
+                    M4Argument::Commands(cmds) => {
+                        innermost_region(nodes, Siblings::Arg(ids[i], index), cmds, sources)
+                    }
+                    _ => None,
+                });
+            if nested.is_some() {
+                return nested;
+            }
+        }
+    }
+    find_region(nodes, siblings, ids, sources)
+}
+
+/// Find the smallest run of `ids` containing the edit which can be reparsed on its own.
+fn find_region(
+    nodes: &Nodes,
+    siblings: Siblings,
+    ids: &[NodeId],
+    sources: &Sources<'_>,
+) -> Option<Region> {
+    let nested = siblings != Siblings::Top;
+    let lines = line_ranges(nodes, ids)?;
+    let (mut i, mut j) = around_edit(&lines, sources)?;
+    let edit = &sources.edit.range;
+    loop {
+        if nested && (i == 0 || j + 1 == ids.len()) {
+            // The first and last commands share a line with the quotes of the argument.
+            return None;
+        }
+        if i == 0 && j + 1 == ids.len() {
+            return None;
+        }
+        let line = if i == 0 { 1 } else { lines[i].0 };
+        let end_line = lines.get(j + 1).map(|&(start, _)| start);
+        let bytes = sources.offset(line)..end_line.map_or(sources.old.len(), |l| sources.offset(l));
+        let region = Region {
+            siblings,
+            run: i..j + 1,
+            line,
+            end_line,
+            bytes,
+        };
+        let inside =
+            region.bytes.start <= edit.start && (edit.end < region.bytes.end || end_line.is_none());
+        if !inside || !is_same(nodes, &ids[region.run.clone()], &region, sources) {
+            i = i.saturating_sub(1);
+            j = (j + 1).min(ids.len() - 1);
+            continue;
+        }
+        let text = &sources.new[sources.new_bytes(&region)];
+        if nested && !is_plain(text) {
+            return None;
+        }
+        if end_line.is_none() {
+            return parse(text).is_some().then_some(region);
+        }
+        if !is_closed(text) {
+            // The edit may run over any command which follows.
+            j = ids.len() - 1;
+            continue;
+        }
+        match parse(text) {
+            // Trailing blank lines and comments belong to the next command.
+            Some((parsed, top_ids)) => {
+                let newlines = text.matches('\n').count();
+                match top_ids.last() {
+                    Some(&id) if parsed[id].range_end() == Some(newlines + 1) => {
+                        return Some(region)
+                    }
+                    _ => j += 1,
+                }
+            }
+            None if nested => return None,
+            None => j = ids.len() - 1,
+        }
+    }
+}
+
+/// Returns whether the text of a region ending before other commands
+/// cannot run over them.
+fn is_closed(text: &str) -> bool {
+    !text.ends_with("\\\n")
+        && !text.contains("<<")
+        && text.matches('[').count() == text.matches(']').count()
+}
+
+/// Returns whether a region parses the same inside an argument of a macro
+/// call as at the top level.
+fn is_plain(text: &str) -> bool {
+    !text.contains(['[', ']', '(', ')', ',']) && !text.contains("<<") && !text.ends_with("\\\n")
+}
+
+/// Returns whether the old text of a region parses on its own into the
+/// commands `ids` found there.
+fn is_same(nodes: &Nodes, ids: &[NodeId], region: &Region, sources: &Sources<'_>) -> bool {
+    let text = &sources.old[region.bytes.clone()];
+    if region.siblings != Siblings::Top && !is_plain(text) {
+        return false;
+    }
+    let Some((parsed, top_ids)) = parse(text) else {
+        return false;
+    };
+    top_ids.len() == ids.len()
+        && top_ids.iter().zip(ids).all(|(&new, &old)| {
+            let shifted = parsed[new]
+                .range
+                .iter()
+                .map(|(start, end)| (start + region.line - 1, end + region.line - 1));
+            shifted.eq(nodes[old].range.iter().copied())
+        })
+}
+
+/// Push `id` and the ids of all nodes below it to `ids`.
+fn subtree(nodes: &Nodes, id: NodeId, ids: &mut Vec<NodeId>) {
+    ids.push(id);
+    let mut children = Vec::new();
+    match &nodes[id].cmd.0 {
+        MayM4::Shell(cmd) => command_children(cmd, &mut children),
+        MayM4::Macro(m4_macro) => macro_children(m4_macro, &mut children),
+    }
+    for child in children {
+        subtree(nodes, child, ids);
+    }
+}
+
+fn command_children(cmd: &ShellCommand<AcWord>, ids: &mut Vec<NodeId>) {
+    use ShellCommand::*;
+    match cmd {
+        Assignment(_, word) => word_children(word, ids),
+        Cmd(words) => words.iter().for_each(|w| word_children(w, ids)),
+        Brace(cmds) | Subshell(cmds) => ids.extend(cmds),
+        While(pair) | Until(pair) => {
+            condition_children(&pair.condition, ids);
+            ids.extend(&pair.body);
+        }
+        If {
+            conditionals,
+            else_branch,
+        } => {
+            for pair in conditionals {
+                condition_children(&pair.condition, ids);
+                ids.extend(&pair.body);
+            }
+            ids.extend(else_branch);
+        }
+        For { words, body, .. } => {
+            words.iter().for_each(|w| word_children(w, ids));
+            ids.extend(body);
+        }
+        Case { word, arms } => {
+            word_children(word, ids);
+            for arm in arms {
+                arm.patterns.iter().for_each(|w| word_children(w, ids));
+                ids.extend(&arm.body);
+            }
+        }
+        And(cond, cmd) | Or(cond, cmd) => {
+            condition_children(cond, ids);
+            ids.push(*cmd);
+        }
+        Pipe(_, cmds) => ids.extend(cmds),
+        Redirect(cmd, redirects) => {
+            ids.push(*cmd);
+            for redirect in redirects {
+                word_children(redirect_word(redirect), ids);
+            }
+        }
+        Background(cmd) => ids.push(*cmd),
+        FunctionDef { body, .. } => ids.push(*body),
+    }
+}
+
+fn redirect_word(redirect: &Redirect<AcWord>) -> &AcWord {
+    use crate::ast::Redirect::*;
+    match redirect {
+        Read(_, word)
+        | Write(_, word)
+        | ReadWrite(_, word)
+        | Append(_, word)
+        | Clobber(_, word)
+        | Heredoc(_, word)
+        | DupRead(_, word)
+        | DupWrite(_, word) => word,
+    }
+}
+
+fn condition_children(cond: &Condition<AcWord>, ids: &mut Vec<NodeId>) {
+    use crate::ast::minimal::Condition::*;
+    use crate::ast::minimal::Operator::*;
+    match cond {
+        Cond(op) => match op {
+            Eq(lhs, rhs)
+            | Neq(lhs, rhs)
+            | Ge(lhs, rhs)
+            | Gt(lhs, rhs)
+            | Le(lhs, rhs)
+            | Lt(lhs, rhs) => {
+                word_children(lhs, ids);
+                word_children(rhs, ids);
+            }
+            Empty(word) | NonEmpty(word) | Dir(word) | File(word) | NoExists(word) => {
+                word_children(word, ids)
+            }
+        },
+        And(lhs, rhs) | Or(lhs, rhs) => {
+            condition_children(lhs, ids);
+            condition_children(rhs, ids);
+        }
+        Eval(cmds) => ids.extend(cmds),
+        ReturnZero(cmd) => ids.push(**cmd),
+    }
+}
+
+fn macro_children(m4_macro: &M4Macro, ids: &mut Vec<NodeId>) {
+    for arg in &m4_macro.args {
+        match arg {
+            M4Argument::Word(word) => word_children(word, ids),
+            M4Argument::Array(words) => words.iter().for_each(|w| word_children(w, ids)),
+            M4Argument::Commands(cmds) => ids.extend(cmds),
+            M4Argument::Literal(_) | M4Argument::Program(_) | M4Argument::Unknown(_) => {}
+        }
+    }
+}
+
+fn word_children(word: &AcWord, ids: &mut Vec<NodeId>) {
+    match &word.0 {
+        Word::Concat(frags) => frags.iter().for_each(|f| fragment_children(f, ids)),
+        Word::Single(frag) => fragment_children(frag, ids),
+        Word::Empty => {}
+    }
+}
+
+fn fragment_children(frag: &AcWordFragment, ids: &mut Vec<NodeId>) {
+    match frag {
+        MayM4::Shell(frag) => shell_fragment_children(frag, ids),
+        MayM4::Macro(m4_macro) => macro_children(m4_macro, ids),
+    }
+}
+
+fn shell_fragment_children(frag: &WordFragment<String, NodeId, AcWord>, ids: &mut Vec<NodeId>) {
+    use crate::ast::ParameterSubstitution::*;
+    match frag {
+        WordFragment::DoubleQuoted(frags) => {
+            frags.iter().for_each(|f| shell_fragment_children(f, ids))
+        }
+        WordFragment::Subst(subst) => match subst.as_ref() {
+            Command(cmds) => ids.extend(cmds),
+            Default(_, _, word)
+            | Assign(_, _, word)
+            | Error(_, _, word)
+            | Alternative(_, _, word)
+            | RemoveSmallestSuffix(_, word)
+            | RemoveLargestSuffix(_, word)
+            | RemoveSmallestPrefix(_, word)
+            | RemoveLargestPrefix(_, word) => {
+                if let Some(word) = word {
+                    word_children(word, ids);
+                }
+            }
+            Len(_) | Arith(_) => {}
+        },
+        _ => {}
+    }
+}
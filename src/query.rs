@@ -0,0 +1,840 @@
+//! Structural queries over the nodes of an `AutoconfPool`.
+//!
+//! A query is a list of selectors in the spirit of CSS selectors, matched
+//! against a tree of elements built from the pool:
+//!
+//! ```text
+//! macro[name=AC_CHECK_HEADERS] > arg[0] word
+//! if > cond:test(-z) var[name=CC]
+//! ```
+//!
+//! Each shell command and each m4 macro call is an element, as are the
+//! arguments of a macro call, the leaf conditions of an `if`, a loop or an
+//! and/or command, the words, the parameter substitutions and the variables
+//! read. The children of an element are the elements directly nested in it,
+//! in the order of the source; the `&&` and `||` of conditions do not make
+//! elements of their own.
+//!
+//! # Syntax
+//!
+//! * `kind` matches the elements of a kind, `*` any element. The kinds are
+//!   `macro`, `arg`, `cond`, `word`, `var`, `subst` and the commands `cmd`,
+//!   `assign`, `if`, `while`, `until`, `for`, `case`, `brace`, `subshell`,
+//!   `and`, `or`, `pipe`, `redirect`, `background` and `function`.
+//! * `a b` matches `b` nested anywhere in `a`, `a > b` only as a child.
+//! * `[N]` matches the `N`th child of its kind within its parent, e.g.
+//!   `arg[0]` is the first argument of a macro call.
+//! * `[attr]` matches the elements having the attribute, and `[attr=value]`
+//!   those whose attribute is `value`. Besides `=`, the operators `!=`,
+//!   `^=` (prefix), `$=` (suffix), `*=` (substring) and `~=` (regular
+//!   expression) are supported. A value may be quoted with `"` or `'`.
+//! * `:test(op)` matches the conditions testing with `op`, e.g. `-z`, `-n`,
+//!   `-d`, `-f`, `!-e`, `=`, `!=`, `-ge`, `-gt`, `-le` or `-lt`.
+//! * `:not(compound)` matches the elements not matching `compound`, and
+//!   `:has(selector)` those containing an element matching `selector`,
+//!   which may start with `>` to only look at the children.
+//! * `a, b` matches the elements matching either selector.
+//!
+//! The attributes are `name`, the name of a macro, a variable, the
+//! parameter of a substitution, an assigned variable, the variable of a
+//! `for`, a function or the first word of a simple command; `text`, the
+//! printed form of an argument, a word, a simple command, an assigned
+//! value or the word of a `case`; and `op`, the operator of a condition or
+//! a substitution, e.g. `:-`.
+//!
+//! A match gives the node holding the element and the line range of that
+//! node, not the byte span of the element itself.
+//!
+//! ```
+//! use autotools_parser::ast::node::AutoconfPool;
+//! use autotools_parser::lexer::Lexer;
+//! use autotools_parser::parse::autoconf::NodeParser;
+//! use autotools_parser::query::Query;
+//!
+//! let source = "if test -z \"$CC\"; then\n  CC=cc\nfi\n";
+//! let (nodes, top_ids) = NodeParser::<_, ()>::new(Lexer::new(source.chars())).parse_all();
+//! let pool = AutoconfPool::new(nodes, None);
+//!
+//! let query: Query = "if > cond:test(-z) var[name=CC]".parse().unwrap();
+//! let matches = query.find(&pool, &top_ids);
+//! assert_eq!(matches.len(), 1);
+//! assert_eq!(matches[0].node, top_ids[0]);
+//! ```
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::ast::minimal::Operator;
+use crate::ast::node::{
+    AcWord, AutoconfPool, Condition, DisplayNode, M4Argument, M4Macro, NodeId,
+    ParameterSubstitution, ShellCommand, WordFragment,
+};
+use crate::ast::visit::node::{
+    walk_argument, walk_condition, walk_shell_command, walk_shell_fragment, walk_substitution,
+    walk_word, Visit,
+};
+use crate::ast::{MayM4, Parameter};
+
+/// The kinds of elements, as written in queries.
+const KINDS: &[&str] = &[
+    "macro",
+    "arg",
+    "cond",
+    "word",
+    "var",
+    "subst",
+    "cmd",
+    "assign",
+    "if",
+    "while",
+    "until",
+    "for",
+    "case",
+    "brace",
+    "subshell",
+    "and",
+    "or",
+    "pipe",
+    "redirect",
+    "background",
+    "function",
+];
+
+/// The attributes of elements, as written in queries.
+const ATTRIBUTES: &[&str] = &["name", "text", "op"];
+
+/// The error type returned when a query can not be parsed.
+#[derive(Debug)]
+pub enum QueryError {
+    /// The query ended where more was expected.
+    UnexpectedEnd,
+    /// A character was not expected at the given byte offset.
+    UnexpectedChar(usize, char),
+    /// An element kind is unknown.
+    UnknownKind(String),
+    /// An attribute is unknown.
+    UnknownAttribute(String),
+    /// A pseudo-class, the name after `:`, is unknown.
+    UnknownPseudoClass(String),
+    /// The value of a `~=` filter is not a valid regular expression.
+    Regex(String, regex::Error),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of query"),
+            Self::UnexpectedChar(offset, c) => {
+                write!(f, "unexpected `{}` at offset {} of query", c, offset)
+            }
+            Self::UnknownKind(kind) => write!(f, "unknown element kind `{}`", kind),
+            Self::UnknownAttribute(attr) => write!(f, "unknown attribute `{}`", attr),
+            Self::UnknownPseudoClass(name) => write!(f, "unknown pseudo-class `:{}`", name),
+            Self::Regex(pattern, e) => write!(f, "invalid regular expression `{}`: {}", pattern, e),
+        }
+    }
+}
+
+impl Error for QueryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Regex(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// An element of a pool, as seen by queries.
+#[derive(Debug, Clone, Copy)]
+pub enum Element<'a> {
+    /// A shell command.
+    Command(&'a ShellCommand<AcWord>),
+    /// An m4 macro call, either a command or a word fragment.
+    Macro(&'a M4Macro),
+    /// The argument of a macro call at the given position.
+    Argument(usize, &'a M4Argument),
+    /// A leaf condition: a test, or commands whose status is tested.
+    Condition(&'a Condition<AcWord>),
+    /// A word.
+    Word(&'a AcWord),
+    /// A parameter read, e.g. `$CC`, or the parameter of a substitution.
+    Var(&'a Parameter<String>),
+    /// A parameter substitution, e.g. `${CC:-cc}` or `$(cmd)`.
+    Substitution(&'a ParameterSubstitution<AcWord>),
+}
+
+impl<'a> Element<'a> {
+    /// The kind of the element, as written in queries.
+    pub fn kind(&self) -> &'static str {
+        use ShellCommand::*;
+        match self {
+            Element::Command(cmd) => match cmd {
+                Assignment(..) => "assign",
+                Cmd(_) => "cmd",
+                Brace(_) => "brace",
+                Subshell(_) => "subshell",
+                While(_) => "while",
+                Until(_) => "until",
+                If { .. } => "if",
+                For { .. } => "for",
+                Case { .. } => "case",
+                And(..) => "and",
+                Or(..) => "or",
+                Pipe(..) => "pipe",
+                Redirect(..) => "redirect",
+                Background(_) => "background",
+                FunctionDef { .. } => "function",
+            },
+            Element::Macro(_) => "macro",
+            Element::Argument(..) => "arg",
+            Element::Condition(_) => "cond",
+            Element::Word(_) => "word",
+            Element::Var(_) => "var",
+            Element::Substitution(_) => "subst",
+        }
+    }
+
+    /// The `name` attribute of the element.
+    pub fn name<U>(&self, pool: &AutoconfPool<U>) -> Option<String> {
+        match self {
+            Element::Command(ShellCommand::Assignment(name, _))
+            | Element::Command(ShellCommand::For { var: name, .. })
+            | Element::Command(ShellCommand::FunctionDef { name, .. }) => Some(name.clone()),
+            Element::Command(ShellCommand::Cmd(words)) => {
+                words.first().map(|word| pool.display_word(word, false))
+            }
+            Element::Command(_) | Element::Argument(..) | Element::Condition(_) => None,
+            Element::Word(_) => None,
+            Element::Macro(m4_macro) => Some(m4_macro.name.clone()),
+            Element::Var(param) => Some(param_name(param)),
+            Element::Substitution(subst) => subst_param(subst).map(param_name),
+        }
+    }
+
+    /// The `text` attribute of the element.
+    pub fn text<U>(&self, pool: &AutoconfPool<U>) -> Option<String> {
+        match self {
+            Element::Command(ShellCommand::Cmd(words)) => Some(
+                words
+                    .iter()
+                    .map(|word| pool.display_word(word, false))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            Element::Command(ShellCommand::Assignment(_, word))
+            | Element::Command(ShellCommand::Case { word, .. }) => {
+                Some(pool.display_word(word, false))
+            }
+            Element::Word(word) => Some(pool.display_word(word, false)),
+            Element::Argument(_, arg) => match arg {
+                M4Argument::Literal(s) | M4Argument::Program(s) | M4Argument::Unknown(s) => {
+                    Some(s.clone())
+                }
+                M4Argument::Word(word) => Some(pool.display_word(word, false)),
+                M4Argument::Array(words) => Some(
+                    words
+                        .iter()
+                        .map(|word| pool.display_word(word, false))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+                M4Argument::Commands(_) => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The `op` attribute of the element: the operator of a test, without
+    /// whitespace, or of a parameter substitution.
+    pub fn op(&self) -> Option<&'static str> {
+        match self {
+            Element::Condition(Condition::Cond(op)) => Some(match op {
+                Operator::Eq(..) => "=",
+                Operator::Neq(..) => "!=",
+                Operator::Ge(..) => "-ge",
+                Operator::Gt(..) => "-gt",
+                Operator::Le(..) => "-le",
+                Operator::Lt(..) => "-lt",
+                Operator::Empty(_) => "-z",
+                Operator::NonEmpty(_) => "-n",
+                Operator::Dir(_) => "-d",
+                Operator::File(_) => "-f",
+                Operator::NoExists(_) => "!-e",
+            }),
+            Element::Substitution(subst) => {
+                use crate::ast::ParameterSubstitution::*;
+                match subst {
+                    Command(_) | Len(_) | Arith(_) => None,
+                    Default(colon, ..) => Some(if *colon { ":-" } else { "-" }),
+                    Assign(colon, ..) => Some(if *colon { ":=" } else { "=" }),
+                    Error(colon, ..) => Some(if *colon { ":?" } else { "?" }),
+                    Alternative(colon, ..) => Some(if *colon { ":+" } else { "+" }),
+                    RemoveSmallestSuffix(..) => Some("%"),
+                    RemoveLargestSuffix(..) => Some("%%"),
+                    RemoveSmallestPrefix(..) => Some("#"),
+                    RemoveLargestPrefix(..) => Some("##"),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn attribute<U>(&self, pool: &AutoconfPool<U>, attr: &str) -> Option<String> {
+        match attr {
+            "name" => self.name(pool),
+            "text" => self.text(pool),
+            "op" => self.op().map(str::to_string),
+            _ => None,
+        }
+    }
+}
+
+/// The name of a parameter without its `$`, e.g. `CC` or `1`.
+fn param_name(param: &Parameter<String>) -> String {
+    match param {
+        Parameter::At => "@".to_string(),
+        Parameter::Star => "*".to_string(),
+        Parameter::Pound => "#".to_string(),
+        Parameter::Question => "?".to_string(),
+        Parameter::Dash => "-".to_string(),
+        Parameter::Dollar => "$".to_string(),
+        Parameter::Bang => "!".to_string(),
+        Parameter::Positional(n) => n.to_string(),
+        Parameter::Var(name) => name.clone(),
+    }
+}
+
+/// The parameter of a substitution, if it has one.
+fn subst_param(subst: &ParameterSubstitution<AcWord>) -> Option<&Parameter<String>> {
+    use crate::ast::ParameterSubstitution::*;
+    match subst {
+        Command(_) | Arith(_) => None,
+        Len(param)
+        | Default(_, param, _)
+        | Assign(_, param, _)
+        | Error(_, param, _)
+        | Alternative(_, param, _)
+        | RemoveSmallestSuffix(param, _)
+        | RemoveLargestSuffix(param, _)
+        | RemoveSmallestPrefix(param, _)
+        | RemoveLargestPrefix(param, _) => Some(param),
+    }
+}
+
+/// An element matched by a query.
+#[derive(Debug, Clone, Copy)]
+pub struct Match<'a> {
+    /// The node holding the element: the node of a command or of a macro
+    /// call, or the node whose command holds the word, argument or
+    /// condition.
+    pub node: NodeId,
+    /// The first and last lines of `node`, if known. This is the line range
+    /// of the whole node, not of the element: the pool keeps no byte
+    /// offsets, so a word or an argument is located only by the lines of
+    /// the command or macro call holding it.
+    pub span: Option<(usize, usize)>,
+    /// The matched element.
+    pub element: Element<'a>,
+}
+
+/// A parsed query, see the module documentation for its syntax.
+#[derive(Debug, Clone)]
+pub struct Query {
+    selectors: Vec<Selector>,
+}
+
+impl Query {
+    /// Parse a query.
+    pub fn parse(query: &str) -> Result<Self, QueryError> {
+        let mut parser = QueryParser {
+            chars: query.char_indices().collect(),
+            pos: 0,
+        };
+        let mut selectors = Vec::new();
+        loop {
+            selectors.push(parser.selector(false)?);
+            parser.skip_whitespace();
+            match parser.next() {
+                Some((_, ',')) => {}
+                Some((offset, c)) => return Err(QueryError::UnexpectedChar(offset, c)),
+                None => break,
+            }
+        }
+        Ok(Query { selectors })
+    }
+
+    /// Find the elements matching the query under the given top-level
+    /// nodes, in the order of the source.
+    pub fn find<'a, U>(&self, pool: &'a AutoconfPool<U>, top_ids: &[NodeId]) -> Vec<Match<'a>> {
+        let tree = Tree::build(pool, top_ids);
+        let matcher = Matcher { pool, tree: &tree };
+        (0..tree.entries.len())
+            .filter(|&i| {
+                self.selectors
+                    .iter()
+                    .any(|selector| matcher.selector(selector, i, None))
+            })
+            .map(|i| {
+                let entry = &tree.entries[i];
+                let node = &pool.nodes[entry.node];
+                Match {
+                    node: entry.node,
+                    span: node.range_start().zip(node.range_end()),
+                    element: entry.element,
+                }
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Query::parse(s)
+    }
+}
+
+/// Compound selectors joined by combinators.
+#[derive(Debug, Clone)]
+struct Selector {
+    /// Within `:has`, whether the first compound must be a child.
+    leading_child: bool,
+    compounds: Vec<Compound>,
+    /// Whether each compound after the first is a child of the previous
+    /// one, rather than a descendant.
+    child: Vec<bool>,
+}
+
+/// A kind with its filters, e.g. `arg[0]:has(var)`.
+#[derive(Debug, Clone)]
+struct Compound {
+    /// `None` for `*`.
+    kind: Option<&'static str>,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone)]
+enum Filter {
+    Index(usize),
+    Attribute(&'static str, Option<(AttrOp, String)>),
+    Regex(&'static str, Regex),
+    Test(String),
+    Not(Box<Compound>),
+    Has(Box<Selector>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AttrOp {
+    Eq,
+    Neq,
+    Prefix,
+    Suffix,
+    Contains,
+}
+
+struct QueryParser {
+    chars: Vec<(usize, char)>,
+    pos: usize,
+}
+
+impl QueryParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).map(|(_, c)| *c)
+    }
+
+    fn next(&mut self) -> Option<(usize, char)> {
+        let next = self.chars.get(self.pos).copied();
+        self.pos += 1;
+        next
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), QueryError> {
+        match self.next() {
+            Some((_, next)) if next == c => Ok(()),
+            Some((offset, next)) => Err(QueryError::UnexpectedChar(offset, next)),
+            None => Err(QueryError::UnexpectedEnd),
+        }
+    }
+
+    fn unexpected(&self) -> QueryError {
+        match self.chars.get(self.pos) {
+            Some((offset, c)) => QueryError::UnexpectedChar(*offset, *c),
+            None => QueryError::UnexpectedEnd,
+        }
+    }
+
+    /// Skip whitespace, returning whether there was any.
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> String {
+        let mut s = String::new();
+        while let Some(c) = self.peek().filter(|c| f(*c)) {
+            s.push(c);
+            self.pos += 1;
+        }
+        s
+    }
+
+    fn ident(&mut self) -> String {
+        self.take_while(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+
+    fn selector(&mut self, relative: bool) -> Result<Selector, QueryError> {
+        self.skip_whitespace();
+        let leading_child = relative && self.eat('>');
+        self.skip_whitespace();
+        let mut selector = Selector {
+            leading_child,
+            compounds: vec![self.compound()?],
+            child: Vec::new(),
+        };
+        loop {
+            let spaced = self.skip_whitespace();
+            let child = self.eat('>');
+            if child {
+                self.skip_whitespace();
+            } else if !spaced || !matches!(self.peek(), Some(c) if starts_compound(c)) {
+                return Ok(selector);
+            }
+            selector.child.push(child);
+            selector.compounds.push(self.compound()?);
+        }
+    }
+
+    fn compound(&mut self) -> Result<Compound, QueryError> {
+        let kind = if self.eat('*') {
+            None
+        } else if self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            let kind = self.ident();
+            match KINDS.iter().find(|k| **k == kind) {
+                Some(kind) => Some(*kind),
+                None => return Err(QueryError::UnknownKind(kind)),
+            }
+        } else if matches!(self.peek(), Some('[' | ':')) {
+            None
+        } else {
+            return Err(self.unexpected());
+        };
+        let mut filters = Vec::new();
+        loop {
+            if self.eat('[') {
+                filters.push(self.bracket()?);
+            } else if self.eat(':') {
+                filters.push(self.pseudo_class()?);
+            } else {
+                return Ok(Compound { kind, filters });
+            }
+        }
+    }
+
+    /// Parse `[N]` or `[attr op value]` after its `[`.
+    fn bracket(&mut self) -> Result<Filter, QueryError> {
+        self.skip_whitespace();
+        if self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            let index = self.take_while(|c| c.is_ascii_digit());
+            self.skip_whitespace();
+            self.expect(']')?;
+            return index
+                .parse()
+                .map(Filter::Index)
+                .map_err(|_| self.unexpected());
+        }
+        let name = self.ident();
+        let attr = match ATTRIBUTES.iter().find(|a| **a == name) {
+            Some(attr) => *attr,
+            None if name.is_empty() => return Err(self.unexpected()),
+            None => return Err(QueryError::UnknownAttribute(name)),
+        };
+        self.skip_whitespace();
+        if self.eat(']') {
+            return Ok(Filter::Attribute(attr, None));
+        }
+        let op = match self.next() {
+            Some((_, '=')) => None,
+            Some((_, c @ ('!' | '^' | '$' | '*' | '~'))) => {
+                self.expect('=')?;
+                Some(c)
+            }
+            Some((offset, c)) => return Err(QueryError::UnexpectedChar(offset, c)),
+            None => return Err(QueryError::UnexpectedEnd),
+        };
+        self.skip_whitespace();
+        let value = match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.pos += 1;
+                let value = self.take_while(|c| c != quote);
+                self.expect(quote)?;
+                value
+            }
+            _ => self.take_while(|c| c != ']').trim_end().to_string(),
+        };
+        self.skip_whitespace();
+        self.expect(']')?;
+        let op = match op {
+            None => AttrOp::Eq,
+            Some('!') => AttrOp::Neq,
+            Some('^') => AttrOp::Prefix,
+            Some('$') => AttrOp::Suffix,
+            Some('*') => AttrOp::Contains,
+            _ => {
+                return Regex::new(&value)
+                    .map(|re| Filter::Regex(attr, re))
+                    .map_err(|e| QueryError::Regex(value, e))
+            }
+        };
+        Ok(Filter::Attribute(attr, Some((op, value))))
+    }
+
+    /// Parse `test(op)`, `not(compound)` or `has(selector)` after its `:`.
+    fn pseudo_class(&mut self) -> Result<Filter, QueryError> {
+        let name = self.ident();
+        if !matches!(name.as_str(), "test" | "not" | "has") {
+            return Err(QueryError::UnknownPseudoClass(name));
+        }
+        self.expect('(')?;
+        let filter = match name.as_str() {
+            "test" => {
+                let op = self.take_while(|c| c != ')');
+                Filter::Test(op.split_whitespace().collect())
+            }
+            "not" => {
+                self.skip_whitespace();
+                Filter::Not(Box::new(self.compound()?))
+            }
+            _ => Filter::Has(Box::new(self.selector(true)?)),
+        };
+        self.skip_whitespace();
+        self.expect(')')?;
+        Ok(filter)
+    }
+}
+
+fn starts_compound(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, '*' | '[' | ':')
+}
+
+/// An element in the preorder of the tree.
+#[derive(Debug)]
+struct Entry<'a> {
+    element: Element<'a>,
+    node: NodeId,
+    parent: Option<usize>,
+    /// The position of the element among the children of its parent of the
+    /// same kind.
+    index: usize,
+    /// One past the last element of the subtree.
+    end: usize,
+}
+
+/// The elements of a pool in preorder.
+#[derive(Debug)]
+struct Tree<'a> {
+    entries: Vec<Entry<'a>>,
+    /// The elements being built, with the number of their children of each
+    /// kind so far. The first frame stands for the root.
+    stack: Vec<(Option<usize>, [usize; KINDS.len()])>,
+    node: NodeId,
+}
+
+impl<'a> Tree<'a> {
+    fn build<U>(pool: &'a AutoconfPool<U>, top_ids: &[NodeId]) -> Self {
+        let mut tree = Tree {
+            entries: Vec::new(),
+            stack: vec![(None, [0; KINDS.len()])],
+            node: 0,
+        };
+        for id in top_ids {
+            tree.visit_node(pool, *id);
+        }
+        tree
+    }
+
+    fn open(&mut self, element: Element<'a>) {
+        let kind = KINDS.iter().position(|k| *k == element.kind()).unwrap();
+        let (parent, counts) = self.stack.last_mut().unwrap();
+        let index = counts[kind];
+        counts[kind] += 1;
+        self.entries.push(Entry {
+            element,
+            node: self.node,
+            parent: *parent,
+            index,
+            end: 0,
+        });
+        self.stack
+            .push((Some(self.entries.len() - 1), [0; KINDS.len()]));
+    }
+
+    fn close(&mut self) {
+        if let Some((Some(i), _)) = self.stack.pop() {
+            self.entries[i].end = self.entries.len();
+        }
+    }
+}
+
+impl<'a, U: 'a> Visit<'a, U> for Tree<'a> {
+    fn visit_node(&mut self, pool: &'a AutoconfPool<U>, id: NodeId) {
+        let outer = self.node;
+        self.node = id;
+        match &pool.nodes[id].cmd.0 {
+            MayM4::Shell(cmd) => self.visit_shell_command(pool, cmd),
+            MayM4::Macro(m4_macro) => self.visit_macro(pool, m4_macro),
+        }
+        self.node = outer;
+    }
+
+    fn visit_shell_command(&mut self, pool: &'a AutoconfPool<U>, cmd: &'a ShellCommand<AcWord>) {
+        self.open(Element::Command(cmd));
+        walk_shell_command(self, pool, cmd);
+        self.close();
+    }
+
+    fn visit_macro(&mut self, pool: &'a AutoconfPool<U>, m4_macro: &'a M4Macro) {
+        self.open(Element::Macro(m4_macro));
+        for (i, arg) in m4_macro.args.iter().enumerate() {
+            self.open(Element::Argument(i, arg));
+            walk_argument(self, pool, arg);
+            self.close();
+        }
+        self.close();
+    }
+
+    fn visit_condition(&mut self, pool: &'a AutoconfPool<U>, cond: &'a Condition<AcWord>) {
+        match cond {
+            Condition::And(..) | Condition::Or(..) => walk_condition(self, pool, cond),
+            _ => {
+                self.open(Element::Condition(cond));
+                walk_condition(self, pool, cond);
+                self.close();
+            }
+        }
+    }
+
+    fn visit_word(&mut self, pool: &'a AutoconfPool<U>, word: &'a AcWord) {
+        self.open(Element::Word(word));
+        walk_word(self, pool, word);
+        self.close();
+    }
+
+    fn visit_shell_fragment(
+        &mut self,
+        pool: &'a AutoconfPool<U>,
+        fragment: &'a WordFragment<AcWord>,
+    ) {
+        if let WordFragment::Param(param) = fragment {
+            self.open(Element::Var(param));
+            self.close();
+        }
+        walk_shell_fragment(self, pool, fragment);
+    }
+
+    fn visit_substitution(
+        &mut self,
+        pool: &'a AutoconfPool<U>,
+        subst: &'a ParameterSubstitution<AcWord>,
+    ) {
+        self.open(Element::Substitution(subst));
+        if let Some(param) = subst_param(subst) {
+            self.open(Element::Var(param));
+            self.close();
+        }
+        walk_substitution(self, pool, subst);
+        self.close();
+    }
+}
+
+struct Matcher<'t, 'a, U> {
+    pool: &'a AutoconfPool<U>,
+    tree: &'t Tree<'a>,
+}
+
+impl<U> Matcher<'_, '_, U> {
+    /// Whether the element `i` matches the selector, with the elements
+    /// matching its other compounds within the subtree of `scope`, if any.
+    fn selector(&self, selector: &Selector, i: usize, scope: Option<usize>) -> bool {
+        self.compounds(selector, selector.compounds.len() - 1, i, scope)
+    }
+
+    /// Whether the element `i` matches the compound `k` of the selector and
+    /// its ancestors match the compounds before it.
+    fn compounds(&self, selector: &Selector, k: usize, i: usize, scope: Option<usize>) -> bool {
+        if !self.compound(&selector.compounds[k], i) {
+            return false;
+        }
+        let parent = self.tree.entries[i].parent;
+        if k == 0 {
+            return !selector.leading_child || parent == scope;
+        }
+        // Preorder puts the ancestors inside the scope after it.
+        let in_scope = |a: usize| scope.is_none_or(|s| a > s);
+        if selector.child[k - 1] {
+            parent.is_some_and(|p| in_scope(p) && self.compounds(selector, k - 1, p, scope))
+        } else {
+            let mut ancestor = parent;
+            while let Some(a) = ancestor.filter(|a| in_scope(*a)) {
+                if self.compounds(selector, k - 1, a, scope) {
+                    return true;
+                }
+                ancestor = self.tree.entries[a].parent;
+            }
+            false
+        }
+    }
+
+    fn compound(&self, compound: &Compound, i: usize) -> bool {
+        let entry = &self.tree.entries[i];
+        if compound
+            .kind
+            .is_some_and(|kind| kind != entry.element.kind())
+        {
+            return false;
+        }
+        compound.filters.iter().all(|filter| match filter {
+            Filter::Index(index) => entry.index == *index,
+            Filter::Attribute(attr, test) => {
+                let value = entry.element.attribute(self.pool, attr);
+                match (value, test) {
+                    (value, None) => value.is_some(),
+                    (None, Some(_)) => false,
+                    (Some(value), Some((op, expected))) => match op {
+                        AttrOp::Eq => value == *expected,
+                        AttrOp::Neq => value != *expected,
+                        AttrOp::Prefix => value.starts_with(expected.as_str()),
+                        AttrOp::Suffix => value.ends_with(expected.as_str()),
+                        AttrOp::Contains => value.contains(expected.as_str()),
+                    },
+                }
+            }
+            Filter::Regex(attr, re) => entry
+                .element
+                .attribute(self.pool, attr)
+                .is_some_and(|value| re.is_match(&value)),
+            Filter::Test(op) => {
+                matches!(entry.element, Element::Condition(_))
+                    && entry.element.op() == Some(op.as_str())
+            }
+            Filter::Not(compound) => !self.compound(compound, i),
+            Filter::Has(selector) => {
+                (i + 1..entry.end).any(|j| self.selector(selector, j, Some(i)))
+            }
+        })
+    }
+}
@@ -0,0 +1,78 @@
+//! Helpers on names and paths shared by the parsers and the analyses.
+use std::path::{Component, Path, PathBuf};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// A reference to a make variable or a configure substitution, e.g.
+    /// `$(CC)` or `@CC@`.
+    pub(crate) static ref REFERENCE: Regex =
+        Regex::new(r"\$[({]([A-Za-z_][A-Za-z0-9_]*)[)}]|@([A-Za-z_][A-Za-z0-9_]*)@").unwrap();
+}
+
+/// Canonicalize a target name as automake does: every character other than
+/// letters, digits, `_` and `@` is replaced by `_`.
+pub fn canonicalize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '@' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// The names of the variables and substitutions referenced by a text.
+pub(crate) fn references(text: &str) -> impl Iterator<Item = &str> {
+    REFERENCE
+        .captures_iter(text)
+        .map(|capture| capture.get(1).or(capture.get(2)).unwrap().as_str())
+}
+
+/// The relative path of the top directory from a directory below it, e.g.
+/// `../..` from `a/b` and `.` from the top directory itself.
+pub(crate) fn top_of(dir: &Path) -> String {
+    match dir.components().count() {
+        0 => ".".to_string(),
+        n => vec![".."; n].join("/"),
+    }
+}
+
+/// Lexically normalize a path, resolving `.` and `..` components. The
+/// leading `..` of a relative path are kept, and `..` of the root is the root.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut ret = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match ret.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    ret.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => ret.push(".."),
+            },
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let normalized = |path: &str| normalize(Path::new(path));
+        assert_eq!(normalized("a/./b/../c"), PathBuf::from("a/c"));
+        assert_eq!(normalized("../../x"), PathBuf::from("../../x"));
+        assert_eq!(normalized("a/../../b"), PathBuf::from("../b"));
+        assert_eq!(normalized("a/../.."), PathBuf::from(".."));
+        assert_eq!(normalized("/usr/../lib"), PathBuf::from("/lib"));
+        assert_eq!(normalized("/../x"), PathBuf::from("/x"));
+    }
+}
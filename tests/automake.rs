@@ -0,0 +1,416 @@
+#![deny(rust_2018_idioms)]
+use std::fs;
+use std::path::PathBuf;
+
+use autotools_parser::analysis::variables::Evaluator;
+use autotools_parser::analysis::CondSet;
+use autotools_parser::ast::am::{AmAssignOp, AmLine, AmVar, AmWord, MakeCondition, MayAm};
+use autotools_parser::ast::minimal::Word;
+use autotools_parser::lexer::Lexer;
+use autotools_parser::parse::automake::{AutomakeConfig, AutomakeNodeParser};
+use autotools_parser::project::AutomakeFile;
+
+mod project_support;
+use project_support::*;
+
+fn top(am: &AutomakeFile) -> Vec<&AmLine> {
+    am.top_ids.iter().map(|id| &am.nodes[*id].cmd).collect()
+}
+
+fn literals(words: &[AmWord]) -> Vec<String> {
+    words.iter().map(|w| w.as_literal().unwrap()).collect()
+}
+
+#[test]
+fn test_make_assign_ops() {
+    let am = parse_automake("A ?= a\nB != echo b\nC ::= c\n");
+    let ops = top(&am)
+        .into_iter()
+        .map(|line| match line {
+            AmLine::Assignment(assignment) => (assignment.lhs.as_str(), assignment.op),
+            other => panic!("unexpected line: {:?}", other),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ops,
+        vec![
+            ("A", AmAssignOp::IfUndefined),
+            ("B", AmAssignOp::Shell),
+            ("C", AmAssignOp::Instant),
+        ]
+    );
+}
+
+#[test]
+fn test_make_conditionals() {
+    let am = parse_automake(
+        "ifeq ($(CC),gcc)
+A = 1
+else ifdef CLANG
+A = 2
+else
+A = 3
+endif
+ifneq \"x\" \"y\"
+B = 1
+endif
+",
+    );
+    let lines = top(&am);
+    assert_eq!(lines.len(), 2);
+    let AmLine::MakeConditional(first) = lines[0] else {
+        panic!("unexpected line: {:?}", lines[0]);
+    };
+    match &first.condition {
+        MakeCondition::Eq(lhs, rhs) => {
+            assert_eq!(lhs.len(), 1);
+            assert_eq!(literals(rhs), vec!["gcc"]);
+        }
+        other => panic!("unexpected condition: {:?}", other),
+    }
+    assert_eq!(first.then.len(), 1);
+    assert_eq!(first.otherwise.len(), 1);
+    let AmLine::MakeConditional(nested) = &am.nodes[first.otherwise[0]].cmd else {
+        panic!("else ifdef is not nested");
+    };
+    assert!(
+        matches!(&nested.condition, MakeCondition::Defined(w) if w.as_literal().unwrap() == "CLANG")
+    );
+    assert_eq!((nested.then.len(), nested.otherwise.len()), (1, 1));
+
+    let AmLine::MakeConditional(second) = lines[1] else {
+        panic!("unexpected line: {:?}", lines[1]);
+    };
+    assert!(matches!(&second.condition, MakeCondition::Neq(..)));
+    assert!(second.otherwise.is_empty());
+}
+
+#[test]
+fn test_make_conditional_in_recipe() {
+    let am = parse_automake(
+        "all:
+ifdef VERBOSE
+\techo verbose
+endif
+\techo done
+",
+    );
+    let lines = top(&am);
+    assert_eq!(lines.len(), 1);
+    let AmLine::Rule(rule) = lines[0] else {
+        panic!("unexpected line: {:?}", lines[0]);
+    };
+    assert_eq!(rule.recipe.len(), 2);
+    let AmLine::MakeConditional(cond) = &am.nodes[rule.recipe[0]].cmd else {
+        panic!("unexpected recipe: {:?}", am.nodes[rule.recipe[0]].cmd);
+    };
+    assert!(matches!(am.nodes[cond.then[0]].cmd, AmLine::Shell(_)));
+}
+
+#[test]
+fn test_make_directives() {
+    let am = parse_automake(
+        "define RECIPE =
+\t@echo $@
+\t@touch $@
+endef
+export CFLAGS = -O2
+unexport LANG LC_ALL
+export
+override LIBS += -lm
+-include $(DEPS)
+sinclude local.mk
+vpath %.c src
+",
+    );
+    let lines = top(&am);
+    assert_eq!(lines.len(), 8);
+    match lines[0] {
+        AmLine::Define(define) => {
+            assert_eq!(define.name, "RECIPE");
+            assert_eq!(define.op, AmAssignOp::Lazy);
+            assert_eq!(define.body, "\t@echo $@\n\t@touch $@");
+        }
+        other => panic!("unexpected line: {:?}", other),
+    }
+    match lines[1] {
+        AmLine::Export(export) => {
+            assert!(export.export);
+            let assignment = &am.nodes[export.assignment.unwrap()].cmd;
+            assert!(matches!(assignment, AmLine::Assignment(a) if a.lhs == "CFLAGS"));
+        }
+        other => panic!("unexpected line: {:?}", other),
+    }
+    match lines[2] {
+        AmLine::Export(export) => {
+            assert!(!export.export);
+            assert_eq!(literals(&export.vars), vec!["LANG", "LC_ALL"]);
+        }
+        other => panic!("unexpected line: {:?}", other),
+    }
+    assert!(matches!(lines[3], AmLine::Export(e) if e.export && e.vars.is_empty()));
+    match lines[4] {
+        AmLine::Override(id) => {
+            assert!(
+                matches!(&am.nodes[*id].cmd, AmLine::Assignment(a) if a.op == AmAssignOp::Append)
+            )
+        }
+        other => panic!("unexpected line: {:?}", other),
+    }
+    assert!(matches!(lines[5], AmLine::Include(i) if i.optional && i.paths.len() == 1));
+    match lines[6] {
+        AmLine::Include(include) => {
+            assert!(include.optional);
+            assert_eq!(literals(&include.paths), vec!["local.mk"]);
+        }
+        other => panic!("unexpected line: {:?}", other),
+    }
+    match lines[7] {
+        AmLine::Vpath(vpath) => {
+            assert!(vpath.pattern.is_some());
+            assert_eq!(literals(&vpath.dirs), vec!["src"]);
+        }
+        other => panic!("unexpected line: {:?}", other),
+    }
+}
+
+fn rhs(am: &AutomakeFile, index: usize) -> &[AmWord] {
+    match &am.nodes[am.top_ids[index]].cmd {
+        AmLine::Assignment(assignment) => &assignment.rhs,
+        other => panic!("unexpected line: {:?}", other),
+    }
+}
+
+fn var(word: &AmWord) -> &AmVar {
+    match &word.0 {
+        Word::Single(MayAm::Automake(var)) => var,
+        other => panic!("not a variable: {:?}", other),
+    }
+}
+
+#[test]
+fn test_make_function_calls() {
+    let am = parse_automake(
+        "A = $(patsubst %.c,%.o,$(SRCS) main.c)
+B = $(filter-out $(EXCLUDE), a b)
+C = $(shell echo a, b | tr a b)
+",
+    );
+    match var(&rhs(&am, 0)[0]) {
+        AmVar::Function(func) => {
+            assert_eq!(func.name, "patsubst");
+            assert_eq!(func.args.len(), 3);
+            assert_eq!(literals(&func.args[0]), vec!["%.c"]);
+            assert_eq!(func.args[2].len(), 2);
+            assert!(matches!(var(&func.args[2][0]), AmVar::Param(_)));
+        }
+        other => panic!("unexpected variable: {:?}", other),
+    }
+    match var(&rhs(&am, 1)[0]) {
+        AmVar::Function(func) => {
+            assert_eq!(func.name, "filter-out");
+            assert_eq!(literals(&func.args[1]), vec!["a", "b"]);
+        }
+        other => panic!("unexpected variable: {:?}", other),
+    }
+    match var(&rhs(&am, 2)[0]) {
+        AmVar::Function(func) => {
+            assert_eq!(func.args.len(), 1);
+            assert_eq!(rhs(&am, 2)[0].to_string(), "$(shell echo a, b | tr a b)");
+        }
+        other => panic!("unexpected variable: {:?}", other),
+    }
+}
+
+#[test]
+fn test_make_references() {
+    let am = parse_automake("A = $(SRCS:.c=.o) ${SRCS:%.c=obj/%.o} $($(x)_SOURCES) $(@D)\n");
+    let words = rhs(&am, 0);
+    match var(&words[0]) {
+        AmVar::SubstRef(r) => {
+            assert_eq!(r.var.as_literal().unwrap(), "SRCS");
+            assert_eq!(r.pattern.as_literal().unwrap(), ".c");
+            assert_eq!(r.replacement.as_literal().unwrap(), ".o");
+        }
+        other => panic!("unexpected variable: {:?}", other),
+    }
+    assert!(matches!(var(&words[1]), AmVar::SubstRef(_)));
+    match var(&words[2]) {
+        AmVar::Computed(name) => assert_eq!(name.to_string(), "$(x)_SOURCES"),
+        other => panic!("unexpected variable: {:?}", other),
+    }
+    assert_eq!(words[3].to_string(), "$(@D)");
+}
+
+fn parse_with_includes(src: &str, subdir: &str) -> Result<AutomakeFile, String> {
+    let config = AutomakeConfig {
+        top_srcdir: Some(fixture("fragments")),
+        subdir: PathBuf::from(subdir),
+        ..Default::default()
+    };
+    let (nodes, top_ids) =
+        AutomakeNodeParser::new_with_automake_config(Lexer::new(src.chars()), config)
+            .try_parse_all()
+            .map_err(|e| e.to_string())?;
+    Ok(AutomakeFile { nodes, top_ids })
+}
+
+#[test]
+fn test_include_resolution() {
+    let src = fs::read_to_string(fixture("fragments").join("src/Makefile.am")).unwrap();
+    let am = parse_with_includes(&src, "src").unwrap();
+    let included = top(&am)
+        .into_iter()
+        .map(|line| match line {
+            AmLine::Include(include) => include.included.as_ref().map(|i| i.path.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        included,
+        vec![
+            None,
+            Some(PathBuf::from("src/tools/local.mk")),
+            Some(PathBuf::from("common.am")),
+            None,
+        ]
+    );
+
+    let eval = Evaluator::new(&am);
+    let value = |name: &str| eval.eval(name).unwrap()[&CondSet::always()].join(" ");
+    assert_eq!(value("bin_PROGRAMS"), "tools/tool");
+    assert_eq!(value("tools_tool_SOURCES"), "tools/tool.c");
+    assert_eq!(value("EXTRA_DIST"), "tools/README");
+    assert_eq!(value("AM_CFLAGS"), "-Wall");
+    assert_eq!(value("___DIR"), "..");
+}
+
+#[test]
+fn test_include_errors() {
+    let err = parse_with_includes("include missing.am\n", "").unwrap_err();
+    assert!(err.contains("cannot include `missing.am`"), "{}", err);
+    let err = parse_with_includes("include loop.am\n", "").unwrap_err();
+    assert!(err.contains("cannot include `loop.am`"), "{}", err);
+
+    // Without a top source directory includes are kept unresolved.
+    let am = parse_automake("include missing.am\n");
+    assert!(matches!(top(&am)[0], AmLine::Include(i) if i.included.is_none()));
+}
+
+fn parse_makefile_in(src: &str) -> AutomakeFile {
+    let config = AutomakeConfig {
+        makefile_in: true,
+        ..Default::default()
+    };
+    let (nodes, top_ids) =
+        AutomakeNodeParser::new_with_automake_config(Lexer::new(src.chars()), config)
+            .try_parse_all()
+            .unwrap();
+    AutomakeFile { nodes, top_ids }
+}
+
+#[test]
+fn test_makefile_in_mode() {
+    let am = parse_makefile_in(
+        "CC = @CC@
+bin_PROGRAMS = demo$(EXEEXT)
+@DEBUG_TRUE@am__append_1 = -g \\
+@DEBUG_TRUE@\t-O0
+@DEBUG_FALSE@am__append_1 = -O2
+@DEBUG_TRUE@@WIN32_TRUE@LIBS = -lws2_32
+ifdef V
+Q =
+endif
+all: demo$(EXEEXT)
+\t$(MAKE) $(AM_MAKEFLAGS) all-recursive
+@DEBUG_TRUE@\techo debug
+@SET_MAKE@
+am__strip = for flg in $$MAKEFLAGS; do \\
+  case $$flg in *=*) ;; esac; echo \"$$1\"; \\
+done
+",
+    );
+    let lines = top(&am);
+    assert!(matches!(lines[0], AmLine::Assignment(a) if a.rhs[0].to_string() == "@CC@"));
+    let AmLine::Conditional(debug) = lines[2] else {
+        panic!("unexpected line: {:?}", lines[2]);
+    };
+    assert_eq!(debug.guard_var, "DEBUG");
+    assert_eq!(debug.then.len(), 1);
+    assert!(debug.otherwise.is_empty());
+    assert!(
+        matches!(lines[3], AmLine::Conditional(c) if c.then.is_empty() && c.otherwise.len() == 1)
+    );
+    assert!(matches!(lines[4], AmLine::Conditional(c) if c.guard_var == "DEBUG"));
+    assert!(matches!(lines[5], AmLine::MakeConditional(_)));
+    let AmLine::Rule(all) = lines[6] else {
+        panic!("unexpected line: {:?}", lines[6]);
+    };
+    assert_eq!(all.recipe.len(), 2);
+    assert!(
+        matches!(&am.nodes[all.recipe[1]].cmd, AmLine::Conditional(c) if c.guard_var == "DEBUG")
+    );
+
+    assert!(
+        matches!(lines[7], AmLine::Substitution(words) if words[0].to_string() == "@SET_MAKE@")
+    );
+    assert!(matches!(lines[8], AmLine::Assignment(a) if a.lhs == "am__strip"));
+
+    let eval = Evaluator::new(&am);
+    let values = eval.eval("am__append_1").unwrap();
+    let values = values
+        .iter()
+        .map(|(cond, words)| (cond.to_string(), words.join(" ")))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        vec![
+            ("DEBUG_FALSE".to_string(), "-O2".to_string()),
+            ("DEBUG_TRUE".to_string(), "-g -O0".to_string()),
+        ]
+    );
+    let libs = eval.eval("LIBS").unwrap();
+    assert_eq!(
+        libs.iter()
+            .filter(|(_, words)| !words.is_empty())
+            .map(|(c, _)| c.to_string())
+            .collect::<Vec<_>>(),
+        vec!["DEBUG_TRUE WIN32_TRUE"]
+    );
+}
+
+#[test]
+fn test_makefile_in_positions() {
+    // The `if`, `else` and `endif` lines read in place of the prefixes do not
+    // shift the lines of the statements after them.
+    let am = parse_makefile_in(
+        "@DEBUG_TRUE@A = 1
+@DEBUG_TRUE@@WIN32_TRUE@B = 2
+@DEBUG_FALSE@A = 3
+all:
+@DEBUG_TRUE@\techo debug
+\techo all
+",
+    );
+    let line = |id: usize| am.nodes[id].range_start().unwrap();
+    let AmLine::Rule(all) = top(&am)[2] else {
+        panic!("unexpected line: {:?}", top(&am)[2]);
+    };
+    let AmLine::Conditional(debug) = &am.nodes[all.recipe[0]].cmd else {
+        panic!("unexpected recipe: {:?}", am.nodes[all.recipe[0]].cmd);
+    };
+    assert_eq!(line(debug.then[0]), 5);
+    assert_eq!(line(all.recipe[1]), 6);
+
+    let config = AutomakeConfig {
+        makefile_in: true,
+        ..Default::default()
+    };
+    let err = AutomakeNodeParser::new_with_automake_config(
+        Lexer::new("@DEBUG_TRUE@A = 1\n@DEBUG_FALSE@B = 2\nC = 3\nendif\n".chars()),
+        config,
+    )
+    .try_parse_all()
+    .unwrap_err();
+    assert_eq!(err.pos().unwrap().line, 4);
+}
@@ -0,0 +1,136 @@
+#![deny(rust_2018_idioms)]
+use autotools_parser::ast::node::AutoconfPool;
+use autotools_parser::autoupdate::{autoupdate, Update};
+use autotools_parser::lexer::Lexer;
+use autotools_parser::parse::autoconf::NodeParser;
+
+fn update(src: &str) -> Update {
+    let (nodes, top_ids) = NodeParser::<_, ()>::new(Lexer::new(src.chars()))
+        .try_parse_all()
+        .unwrap();
+    let pool = AutoconfPool::new(nodes, None);
+    autoupdate(&pool, &top_ids, src)
+}
+
+/// The line and the names of each change.
+fn report(update: &Update) -> Vec<(usize, &str, &str)> {
+    update
+        .changes
+        .iter()
+        .map(|c| (c.line, c.from.as_str(), c.to.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_autoupdate_renames() {
+    let src = r#"AC_INIT([demo], [1.0])
+# Keep this comment.
+AC_STDC_HEADERS
+AC_GNU_SOURCE
+AC_HAVE_FUNCS([memcpy strdup])
+AC_OUTPUT
+"#;
+    let update = update(src);
+    assert_eq!(
+        update.source,
+        r#"AC_INIT([demo], [1.0])
+# Keep this comment.
+AC_HEADER_STDC
+AC_USE_SYSTEM_EXTENSIONS
+AC_CHECK_FUNCS([memcpy strdup])
+AC_OUTPUT
+"#
+    );
+    assert_eq!(
+        report(&update),
+        [
+            (3, "AC_STDC_HEADERS", "AC_HEADER_STDC"),
+            (4, "AC_GNU_SOURCE", "AC_USE_SYSTEM_EXTENSIONS"),
+            (5, "AC_HAVE_FUNCS", "AC_CHECK_FUNCS"),
+        ]
+    );
+    // Only the macro names are replaced.
+    let change = &update.changes[2];
+    assert_eq!(&src[change.span.clone()], "AC_HAVE_FUNCS");
+    assert_eq!(change.text, "AC_CHECK_FUNCS");
+}
+
+#[test]
+fn test_autoupdate_rewrites_calls() {
+    let update = update(
+        r#"AC_TRY_COMPILE([#include <stdio.h>],
+  [printf("hi");],
+  [have_printf=yes], [have_printf=no])
+AC_TRY_LINK(, [main();])
+AC_TRY_RUN([int main() { return 0; }], [run=yes], [run=no], [run=maybe])
+AC_HAVE_LIBRARY(-lm)
+AC_HAVE_LIBRARY([libz.a], [LIBS="-lz $LIBS"])
+AC_OUTPUT_COMMANDS([echo done], [x=1])
+AC_LIBTOOL_DLOPEN
+"#,
+    );
+    assert_eq!(
+        update.source,
+        r#"AC_COMPILE_IFELSE([AC_LANG_PROGRAM([[#include <stdio.h>]], [[printf("hi");]])], [have_printf=yes], [have_printf=no])
+AC_LINK_IFELSE([AC_LANG_PROGRAM([], [[main();]])])
+AC_RUN_IFELSE([AC_LANG_SOURCE([[int main() { return 0; }]])], [run=yes], [run=no], [run=maybe])
+AC_CHECK_LIB([m], [main])
+AC_CHECK_LIB([z], [main], [LIBS="-lz $LIBS"])
+AC_CONFIG_COMMANDS([default], [echo done], [x=1])
+LT_INIT([dlopen])
+"#
+    );
+    assert_eq!(
+        report(&update),
+        [
+            (1, "AC_TRY_COMPILE", "AC_COMPILE_IFELSE"),
+            (4, "AC_TRY_LINK", "AC_LINK_IFELSE"),
+            (5, "AC_TRY_RUN", "AC_RUN_IFELSE"),
+            (6, "AC_HAVE_LIBRARY", "AC_CHECK_LIB"),
+            (7, "AC_HAVE_LIBRARY", "AC_CHECK_LIB"),
+            (8, "AC_OUTPUT_COMMANDS", "AC_CONFIG_COMMANDS"),
+            (9, "AC_LIBTOOL_DLOPEN", "LT_INIT"),
+        ]
+    );
+}
+
+#[test]
+fn test_autoupdate_nested_calls() {
+    let src = r#"AC_CACHE_CHECK([for memcpy], [ac_cv_memcpy],
+  [AC_TRY_LINK([#include <string.h>], [memcpy(0, 0, 0);],
+     [ac_cv_memcpy=yes],
+     [AC_HAVE_FUNCS([bcopy])
+      ac_cv_memcpy=no])])
+"#;
+    let update = update(src);
+    assert_eq!(
+        update.source,
+        r#"AC_CACHE_CHECK([for memcpy], [ac_cv_memcpy],
+  [AC_LINK_IFELSE([AC_LANG_PROGRAM([[#include <string.h>]], [[memcpy(0, 0, 0);]])], [ac_cv_memcpy=yes], [AC_CHECK_FUNCS([bcopy])
+      ac_cv_memcpy=no])])
+"#
+    );
+    assert_eq!(
+        report(&update),
+        [
+            (2, "AC_TRY_LINK", "AC_LINK_IFELSE"),
+            (4, "AC_HAVE_FUNCS", "AC_CHECK_FUNCS"),
+        ]
+    );
+    // The rename of the nested call is part of the rewritten call.
+    let (outer, inner) = (&update.changes[0], &update.changes[1]);
+    assert!(outer.span.start < inner.span.start && inner.span.end < outer.span.end);
+    assert!(outer.text.contains("AC_CHECK_FUNCS([bcopy])"));
+}
+
+#[test]
+fn test_autoupdate_up_to_date() {
+    let src = r#"AC_INIT([demo], [1.0])
+AC_COMPILE_IFELSE([AC_LANG_PROGRAM([], [])], [ok=yes])
+echo "AC_TRY_COMPILE is obsolete"
+AC_OUTPUT
+"#;
+    let update = update(src);
+    assert_eq!(update.source, src);
+    assert!(update.changes.is_empty());
+}
@@ -0,0 +1,130 @@
+#![deny(rust_2018_idioms)]
+use autotools_parser::backend::build_script::BuildScript;
+use autotools_parser::project::Project;
+
+mod project_support;
+use project_support::*;
+
+fn translate(name: &str) -> BuildScript {
+    let project = Project::load(fixture(name)).unwrap();
+    BuildScript::new(project.file(project.configure())).unwrap()
+}
+
+/// The statements of `main`, after the helpers.
+fn main_body(script: &BuildScript) -> &str {
+    let start = script.text.find("\nfn main() {\n").unwrap();
+    &script.text[start + 1..]
+}
+
+#[test]
+fn test_build_script_checks() {
+    let script = translate("build_script");
+    assert!(script
+        .text
+        .starts_with("// Generated from configure.ac.\nuse std::env;\n"));
+    assert!(script.text.contains("struct Config {\n"));
+    assert_eq!(
+        main_body(&script),
+        r##"fn main() {
+    let mut config = Config::new();
+    config.define("PACKAGE_NAME", "\"zipper\"");
+    config.define("PACKAGE_TARNAME", "\"zipper\"");
+    config.define("PACKAGE_VERSION", "\"0.3\"");
+    config.define("PACKAGE_STRING", "\"zipper 0.3\"");
+    config.define("PACKAGE_BUGREPORT", "\"bugs@example.com\"");
+
+    // AC_CHECK_HEADERS([stdint.h zlib.h])
+    let found = config.check(&[DEFAULT_INCLUDES, "#include <stdint.h>\n"].concat(), Stage::Compile, &[]);
+    config.have("HAVE_STDINT_H", found);
+    let found = config.check(&[DEFAULT_INCLUDES, "#include <zlib.h>\n"].concat(), Stage::Compile, &[]);
+    config.have("HAVE_ZLIB_H", found);
+
+    // AC_CHECK_HEADER([fcntl.h], [], [AC_MSG_ERROR([fcntl.h is required])])
+    let found = config.check(&[DEFAULT_INCLUDES, "#include <fcntl.h>\n"].concat(), Stage::Compile, &[]);
+    if !found {
+        panic!("{}", "fcntl.h is required");
+    }
+
+    // AC_CHECK_FUNCS([mmap])
+    let found = config.check("#ifdef __cplusplus\nextern \"C\"\n#endif\nchar mmap (void);\nint\nmain (void)\n{\nreturn mmap ();\n  ;\n  return 0;\n}\n", Stage::Link, &[]);
+    config.have("HAVE_MMAP", found);
+
+    // AC_CHECK_TYPES([off_t])
+    let found = config.check(&[DEFAULT_INCLUDES, "int\nmain (void)\n{\nif (sizeof (off_t))\n  return 0;\n  ;\n  return 0;\n}\n"].concat(), Stage::Compile, &[]);
+    config.have("HAVE_OFF_T", found);
+
+    // AC_CHECK_SIZEOF([long])
+    let size = config.sizeof("long", DEFAULT_INCLUDES);
+    config.define("SIZEOF_LONG", &size.to_string());
+
+    // AC_CHECK_LIB([z], [inflate])
+    let found = config.check("#ifdef __cplusplus\nextern \"C\"\n#endif\nchar inflate (void);\nint\nmain (void)\n{\nreturn inflate ();\n  ;\n  return 0;\n}\n", Stage::Link, &["z"]);
+    config.have("HAVE_LIBZ", found);
+    if found {
+        config.libs.insert(0, "z".to_string());
+    }
+
+    // AC_COMPILE_IFELSE([AC_LANG_PROGRAM([[#include <stdint.h>]], [[uint64_t x = 0; (void) x;]])],
+    //   [AC_DEFINE([HAVE_UINT64], [1], [Define if uint64_t works.])])
+    let found = config.check("#include <stdint.h>\nint\nmain (void)\n{\nuint64_t x = 0; (void) x;\n  ;\n  return 0;\n}\n", Stage::Compile, &[]);
+    config.have("HAVE_UINT64", found);
+
+    // AC_RUN_IFELSE([AC_LANG_PROGRAM([], [[return 1;]])], [have_run=yes])
+    config.check("\nint\nmain (void)\n{\nreturn 1;\n  ;\n  return 0;\n}\n", Stage::Run, &[]);
+
+    // TODO configure.ac:12: the actions of the check are not translated
+    //   AC_RUN_IFELSE([AC_LANG_PROGRAM([], [[return 1;]])], [have_run=yes])
+
+    // AC_DEFINE([ZIP_LEVEL], [6], [Default compression level.])
+    config.define("ZIP_LEVEL", "6");
+
+    // TODO configure.ac:14: options are not translated
+    //   AC_ARG_ENABLE([asm], [AS_HELP_STRING([--enable-asm], [use assembly])])
+
+    // TODO configure.ac:15: shell code is not translated
+    //   if test "x$enable_asm" = xyes; then
+    //     CFLAGS="$CFLAGS -DASM"
+    //   fi
+
+    config.write("include/config.h");
+}
+"##
+    );
+    let diagnostics = script
+        .diagnostics
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diagnostics,
+        vec![
+            "configure.ac: line 12: the actions of the check are not translated",
+            "configure.ac: line 14: options are not translated",
+            "configure.ac: line 15: shell code is not translated",
+        ]
+    );
+}
+
+#[test]
+fn test_build_script_todo() {
+    let script = translate("cmake");
+    let body = main_body(&script);
+    assert!(body.contains(
+        "    let size = config.sizeof(\"long\", DEFAULT_INCLUDES);\n    config.define(\"SIZEOF_LONG\", &size.to_string());\n"
+    ));
+    assert!(body.contains(
+        "\
+    // TODO configure.ac:16: the value depends on the shell
+    //   AC_DEFINE_UNQUOTED([BUILD_HOST], [\"$host\"])
+"
+    ));
+    assert!(!body.contains("AC_SUBST"));
+    assert!(body.ends_with("    config.write(\"include/config.h\");\n}\n"));
+}
+
+#[test]
+fn test_build_script_makefile() {
+    let project = Project::load(fixture("demo")).unwrap();
+    let makefile = project.automake_files().next().unwrap();
+    assert!(BuildScript::new(project.file(makefile)).is_none());
+}
@@ -0,0 +1,275 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "cli")]
+use std::fs;
+use std::process::Command;
+
+use serde_json::{json, Value};
+
+mod project_support;
+use project_support::*;
+
+/// Runs the command in the fixtures directory, returning its exit code and
+/// output.
+fn run(args: &[&str]) -> (i32, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_autotools-parser"))
+        .args(args)
+        .current_dir(fixture(""))
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+fn run_json(args: &[&str]) -> (i32, Value) {
+    let mut args = args.to_vec();
+    args.extend(["--format", "json"]);
+    let (code, stdout, _) = run(&args);
+    (code, serde_json::from_str(&stdout).unwrap())
+}
+
+#[test]
+fn test_cli_usage() {
+    let (code, stdout, _) = run(&["--help"]);
+    assert_eq!(code, 0);
+    assert!(stdout.starts_with("usage: autotools-parser <command>"));
+
+    let (code, stdout, stderr) = run(&["frobnicate", "configure.ac"]);
+    assert_eq!(code, 2);
+    assert!(stdout.is_empty());
+    assert!(stderr.starts_with("error: unknown command `frobnicate`\n"));
+
+    assert_eq!(run(&["lint"]).0, 2);
+    assert_eq!(run(&["query", "demo/configure.ac"]).0, 2);
+    assert_eq!(run(&["dump", "--format", "yaml", "demo/configure.ac"]).0, 2);
+}
+
+#[test]
+fn test_cli_file_errors() {
+    let (code, _, stderr) = run(&["lint", "missing/configure.ac", "demo/configure.ac"]);
+    assert_eq!(code, 2);
+    assert!(stderr.starts_with("error: failed to read missing/configure.ac: "));
+
+    let (code, _, stderr) = run(&["lint", "dist/README"]);
+    assert_eq!(code, 2);
+    assert_eq!(
+        stderr,
+        "error: dist/README is neither an autoconf script nor a makefile\n"
+    );
+}
+
+#[test]
+fn test_cli_lint() {
+    let (code, stdout, _) = run(&["lint", "lint/configure.ac", "lint/Makefile.am"]);
+    assert_eq!(code, 1);
+    assert_eq!(
+        stdout,
+        "lint/configure.ac:2: warning[obsolete-macro]: \
+         `AC_OUTPUT_COMMANDS` is obsolete, use `AC_CONFIG_COMMANDS`
+lint/configure.ac:5: warning[unknown-macro]: `AX_MISSING_MACRO` is not a known macro
+lint/configure.ac: warning[missing-macro]: `AC_OUTPUT` is never called
+lint/Makefile.am: warning[rule-cycle]: rules depend on themselves: gen.h -> gen.in -> gen.h
+"
+    );
+
+    let (code, value) = run_json(&["lint", "lint/configure.ac"]);
+    assert_eq!(code, 1);
+    assert_eq!(
+        value[0]["problems"][1],
+        json!({
+            "line": 5,
+            "code": "unknown-macro",
+            "message": "`AX_MISSING_MACRO` is not a known macro",
+        })
+    );
+
+    let (code, stdout, _) = run(&["lint", "build_script/configure.ac"]);
+    assert_eq!(code, 0);
+    assert!(stdout.is_empty());
+}
+
+#[test]
+fn test_cli_probes() {
+    let (code, stdout, _) = run(&["probes", "build_script/configure.ac"]);
+    assert_eq!(code, 0);
+    assert_eq!(
+        stdout,
+        "build_script/configure.ac:4: AC_CHECK_HEADERS header stdint.h HAVE_STDINT_H
+build_script/configure.ac:4: AC_CHECK_HEADERS header zlib.h HAVE_ZLIB_H
+build_script/configure.ac:5: AC_CHECK_HEADER header fcntl.h
+build_script/configure.ac:6: AC_CHECK_FUNCS function mmap HAVE_MMAP
+build_script/configure.ac:7: AC_CHECK_TYPES type off_t HAVE_OFF_T
+build_script/configure.ac:8: AC_CHECK_SIZEOF sizeof long SIZEOF_LONG
+build_script/configure.ac:9: AC_CHECK_LIB library z HAVE_LIBZ
+"
+    );
+
+    let (_, value) = run_json(&["probes", "build_script/configure.ac"]);
+    assert_eq!(
+        value[0]["probes"][4],
+        json!({
+            "line": 7,
+            "macro": "AC_CHECK_TYPES",
+            "kind": "type",
+            "name": "off_t",
+            "symbol": "HAVE_OFF_T",
+            "cache_var": "ac_cv_type_off_t",
+        })
+    );
+}
+
+#[test]
+fn test_cli_options_and_deps() {
+    let (code, value) = run_json(&["options", "build_script/configure.ac"]);
+    assert_eq!(code, 0);
+    let option = &value[0]["options"][0];
+    assert_eq!(option["option"], json!("--enable-asm"));
+    assert_eq!(option["shell_var"], json!("enable_asm"));
+
+    let (code, stdout, _) = run(&["deps", "demo/configure.ac", "lint/Makefile.am"]);
+    assert_eq!(code, 0);
+    assert_eq!(
+        stdout,
+        "demo/configure.ac:9: library m
+demo/configure.ac:14: pkg-config glib-2.0>=2.40
+lint/Makefile.am: all-local: gen.h
+lint/Makefile.am: gen.h: gen.in
+lint/Makefile.am: gen.in: gen.h
+lint/Makefile.am: all: all-local
+"
+    );
+}
+
+#[test]
+fn test_cli_query() {
+    let (code, stdout, _) = run(&["query", "AC_CHECK_H*", "build_script/configure.ac"]);
+    assert_eq!(code, 0);
+    assert_eq!(
+        stdout,
+        "build_script/configure.ac:4: AC_CHECK_HEADERS([stdint.h zlib.h])
+build_script/configure.ac:5: AC_CHECK_HEADER([fcntl.h], [...], [...])
+"
+    );
+
+    let (_, value) = run_json(&["query", "app_SOURCES", "lint/Makefile.am"]);
+    assert_eq!(
+        value[0]["matches"],
+        json!([
+            {"line": null, "variable": "app_SOURCES", "op": "=", "words": ["main.c"]},
+            {"line": null, "variable": "app_SOURCES", "op": "+=", "words": ["extra.c"]},
+        ])
+    );
+}
+
+#[test]
+fn test_cli_select() {
+    let (code, stdout, _) = run(&[
+        "select",
+        "macro[name^=AC_CHECK_HEADER] > arg[0] word, if cond:test(=) var",
+        "build_script/configure.ac",
+        "lint/Makefile.am",
+    ]);
+    assert_eq!(code, 0);
+    assert_eq!(
+        stdout,
+        "build_script/configure.ac:4: word stdint.h
+build_script/configure.ac:4: word zlib.h
+build_script/configure.ac:5: word fcntl.h
+build_script/configure.ac:15: var enable_asm
+"
+    );
+
+    let (code, _, stderr) = run(&["select", "macro[", "build_script/configure.ac"]);
+    assert_eq!(code, 2);
+    assert!(stderr.contains("unexpected end of query"), "{}", stderr);
+}
+
+#[test]
+fn test_cli_dump() {
+    let (code, value) = run_json(&["dump", "lint/configure.ac", "lint/Makefile.am"]);
+    assert_eq!(code, 0);
+    assert_eq!(value[0]["kind"], json!("autoconf"));
+    assert_eq!(value[0]["version"], json!(1));
+    assert_eq!(
+        value[0]["nodes"][0]["cmd"]["Macro"]["name"],
+        json!("AC_INIT")
+    );
+    assert_eq!(value[1]["kind"], json!("automake"));
+    assert_eq!(
+        value[1]["nodes"][0]["cmd"]["Assignment"]["lhs"],
+        json!("bin_PROGRAMS")
+    );
+
+    let (_, stdout, _) = run(&["dump", "lint/configure.ac"]);
+    assert!(stdout.starts_with("0 1 AC_INIT("), "{}", stdout);
+}
+
+#[test]
+fn test_cli_fmt() {
+    let (code, formatted, _) = run(&["fmt", "lint/configure.ac"]);
+    assert_eq!(code, 0);
+    assert!(
+        formatted.contains("AC_CHECK_FUNCS(strdup)\n"),
+        "{}",
+        formatted
+    );
+
+    let (code, stdout, _) = run(&["fmt", "--check", "lint/configure.ac"]);
+    assert_eq!(code, 1);
+    assert_eq!(stdout, "lint/configure.ac: not formatted\n");
+
+    // The output of fmt is formatted.
+    let dir = std::env::temp_dir().join(format!("autotools-parser-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("configure.ac");
+    fs::write(&path, formatted).unwrap();
+    let (code, stdout, _) = run(&["fmt", "--check", path.to_str().unwrap()]);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(code, 0, "{}", stdout);
+    assert!(stdout.is_empty());
+
+    let (code, _, stderr) = run(&["fmt", "lint/Makefile.am"]);
+    assert_eq!(code, 2);
+    assert_eq!(
+        stderr,
+        "error: lint/Makefile.am: only autoconf scripts can be formatted\n"
+    );
+}
+
+#[test]
+fn test_cli_autoupdate() {
+    let (code, stdout, _) = run(&["autoupdate", "lint/configure.ac"]);
+    assert_eq!(code, 1);
+    assert_eq!(
+        stdout,
+        "lint/configure.ac:2: AC_OUTPUT_COMMANDS -> AC_CONFIG_COMMANDS\n"
+    );
+
+    let dir = std::env::temp_dir().join(format!(
+        "autotools-parser-autoupdate-{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("configure.ac");
+    fs::copy(fixture("lint/configure.ac"), &path).unwrap();
+    let (code, value) = run_json(&["autoupdate", "--write", path.to_str().unwrap()]);
+    let updated = fs::read_to_string(&path).unwrap();
+    let (again, stdout, _) = run(&["autoupdate", path.to_str().unwrap()]);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(code, 0);
+    assert_eq!(
+        value[0]["changes"],
+        json!([{
+            "line": 2,
+            "from": "AC_OUTPUT_COMMANDS",
+            "to": "AC_CONFIG_COMMANDS",
+            "text": "AC_CONFIG_COMMANDS([default], [echo done])",
+        }])
+    );
+    assert_eq!(value[0]["written"], json!(true));
+    assert!(updated.contains("\nAC_CONFIG_COMMANDS([default], [echo done])\n"));
+    assert_eq!(again, 0, "{}", stdout);
+    assert!(stdout.is_empty());
+}
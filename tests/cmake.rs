@@ -0,0 +1,170 @@
+#![deny(rust_2018_idioms)]
+use std::path::PathBuf;
+
+use autotools_parser::backend::cmake::CMakeLists;
+use autotools_parser::project::Project;
+
+mod project_support;
+use project_support::*;
+
+fn translate(name: &str) -> CMakeLists {
+    let project = Project::load(fixture(name)).unwrap();
+    CMakeLists::new(project.file(project.configure())).unwrap()
+}
+
+#[test]
+fn test_cmake_demo() {
+    let cmake = translate("demo");
+    assert_eq!(
+        cmake.text,
+        "\
+cmake_minimum_required(VERSION 3.10)
+project(demo VERSION 1.0 LANGUAGES C)
+
+include(CheckIncludeFile)
+include(CheckSymbolExists)
+include(CheckLibraryExists)
+find_package(PkgConfig REQUIRED)
+
+check_include_file(stdio.h HAVE_STDIO_H)
+check_include_file(unistd.h HAVE_UNISTD_H)
+check_symbol_exists(strdup \"stdio.h;unistd.h\" HAVE_STRDUP)
+check_library_exists(m cos \"\" HAVE_LIBM)
+if(HAVE_LIBM)
+  list(APPEND LIBS m)
+endif()
+option(ENABLE_X \"enable the x feature\" OFF)
+pkg_check_modules(GLIB REQUIRED glib-2.0>=2.40)
+
+configure_file(config.h.cmake config.h)
+include_directories(${CMAKE_CURRENT_BINARY_DIR})
+add_compile_definitions(HAVE_CONFIG_H)
+
+# TODO configure.ac: `src` has no CMakeLists.txt
+# add_subdirectory(src)
+# TODO configure.ac: `lib` has no CMakeLists.txt
+# add_subdirectory(lib)
+# TODO configure.ac: `sub` has no CMakeLists.txt
+# add_subdirectory(sub)
+"
+    );
+    let (header, template) = cmake.config_header.unwrap();
+    assert_eq!(header, PathBuf::from("config.h"));
+    assert_eq!(
+        template,
+        "\
+#define PACKAGE_NAME \"demo\"
+#define PACKAGE_TARNAME \"demo\"
+#define PACKAGE_VERSION \"1.0\"
+#define PACKAGE_STRING \"demo 1.0\"
+#define PACKAGE_BUGREPORT \"bug@example.com\"
+#define PACKAGE \"demo\"
+#define VERSION \"1.0\"
+#cmakedefine HAVE_STDIO_H 1
+#cmakedefine HAVE_UNISTD_H 1
+#cmakedefine HAVE_STRDUP 1
+#cmakedefine HAVE_LIBM 1
+"
+    );
+    let lines = cmake
+        .diagnostics
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            "configure.ac: `src` has no CMakeLists.txt",
+            "configure.ac: `lib` has no CMakeLists.txt",
+            "configure.ac: `sub` has no CMakeLists.txt",
+        ]
+    );
+}
+
+#[test]
+fn test_cmake_todo() {
+    let cmake = translate("cmake");
+    assert_eq!(
+        cmake.text,
+        "\
+cmake_minimum_required(VERSION 3.10)
+project(\"Foo Tools\" LANGUAGES CXX)
+
+include(CheckIncludeFile)
+find_package(PkgConfig REQUIRED)
+
+check_include_file(sys/types.h HAVE_SYS_TYPES_H)
+# TODO configure.ac:5: the actions of the check are not translated
+#   AC_CHECK_HEADERS([sys/types.h], [], [AC_MSG_ERROR([no sys/types.h])])
+option(WITH_ZLIB \"use zlib\" ON)
+option(ENABLE_DOCS \"do not build the documentation\" OFF)
+# TODO configure.ac:9: the actions of the option are not translated
+#   AC_ARG_ENABLE([docs],
+#     [AS_HELP_STRING([--disable-docs], [do not build the documentation])],
+#     [enable_docs=$enableval; docs_explicit=1])
+if(NOT WITH_ZLIB)
+  set(NO_ZLIB ON)
+else()
+  set(NO_ZLIB OFF)
+endif()
+set(ON_LINUX OFF)
+# TODO configure.ac:13: the condition is not translated
+#   AM_CONDITIONAL([ON_LINUX], [test \"$host_os\" = linux])
+pkg_check_modules(ZLIB zlib)
+# TODO configure.ac:14: the actions of the check are not translated
+#   PKG_CHECK_MODULES([ZLIB], [zlib], [], [have_zlib=no])
+set(USE_FOO 1)
+# TODO configure.ac:16: the value depends on the shell
+#   AC_DEFINE_UNQUOTED([BUILD_HOST], [\"$host\"])
+set(FOO_API 3)
+# TODO configure.ac:18: `AC_CHECK_SIZEOF` is not translated
+#   AC_CHECK_SIZEOF([long])
+# TODO configure.ac:19: shell code is not translated
+#   if test \"x$with_zlib\" = xyes; then
+#     LIBS=\"$LIBS -lz\"
+#   fi
+configure_file(foo.pc.in foo.pc @ONLY)
+
+configure_file(include/config.h.cmake include/config.h)
+include_directories(${CMAKE_CURRENT_BINARY_DIR})
+add_compile_definitions(HAVE_CONFIG_H)
+
+# TODO configure.ac: `lib` has no CMakeLists.txt
+# add_subdirectory(lib)
+"
+    );
+    let (header, template) = cmake.config_header.unwrap();
+    assert_eq!(header, PathBuf::from("include/config.h"));
+    assert!(template.contains("#define PACKAGE_TARNAME \"foo\"\n"));
+    assert!(template.ends_with(
+        "#cmakedefine HAVE_SYS_TYPES_H 1\n/* Define to use foo. */\n#cmakedefine USE_FOO @USE_FOO@\n"
+    ));
+
+    let lines = cmake
+        .diagnostics
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            "configure.ac: line 5: the actions of the check are not translated",
+            "configure.ac: line 9: the actions of the option are not translated",
+            "configure.ac: line 13: the condition is not translated",
+            "configure.ac: line 14: the actions of the check are not translated",
+            "configure.ac: line 16: the value depends on the shell",
+            "configure.ac: line 18: `AC_CHECK_SIZEOF` is not translated",
+            "configure.ac: line 19: shell code is not translated",
+            "configure.ac: `lib` has no CMakeLists.txt",
+        ]
+    );
+}
+
+#[test]
+fn test_cmake_function_without_header() {
+    let cmake = translate("lint");
+    assert!(cmake.text.contains("include(CheckFunctionExists)\n"));
+    assert!(cmake
+        .text
+        .contains("\ncheck_function_exists(strdup HAVE_STRDUP)\n"));
+}
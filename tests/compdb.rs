@@ -0,0 +1,147 @@
+#![deny(rust_2018_idioms)]
+use std::path::{Path, PathBuf};
+
+use autotools_parser::analysis::compdb::{CompdbOptions, CompilationDatabase, UnknownValue};
+use autotools_parser::project::Project;
+
+mod project_support;
+use project_support::*;
+
+fn words(s: &str) -> Vec<String> {
+    s.split_whitespace().map(String::from).collect()
+}
+
+fn options(conds: &[(&str, bool)], outputs: &[(&str, &str)]) -> CompdbOptions {
+    CompdbOptions {
+        conds: conds.iter().map(|(k, v)| (k.to_string(), *v)).collect(),
+        outputs: outputs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        build_dir: None,
+    }
+}
+
+#[test]
+fn test_compdb_in_tree() {
+    let root = fixture("demo");
+    let project = Project::load(&root).unwrap();
+    let db = CompilationDatabase::new(
+        &project,
+        &options(
+            &[("ENABLE_X", true)],
+            &[("CC", "cc"), ("CPPFLAGS", ""), ("CFLAGS", "-g")],
+        ),
+    );
+    let files = db
+        .entries
+        .iter()
+        .map(|e| e.file.strip_prefix(&root).unwrap().to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        files,
+        vec![
+            "lib/demo.c",
+            "src/main.c",
+            "src/util.c",
+            "src/x.c",
+            "sub/helper.c",
+        ]
+    );
+
+    let main = &db.entries[1];
+    assert_eq!(main.directory, root.join("src"));
+    assert_eq!(main.output, Path::new("demo-main.o"));
+    assert_eq!(
+        main.arguments,
+        words("cc -DHAVE_CONFIG_H -I. -I.. -I../lib $(GLIB_CFLAGS) -g -c -o demo-main.o main.c")
+    );
+    // The libtool wrapper is left out of library objects.
+    assert_eq!(
+        db.entries[0].arguments,
+        words("cc -DHAVE_CONFIG_H -I. -I.. -g -c -o demo.lo demo.c")
+    );
+    // The nested package has no config header.
+    assert_eq!(
+        db.entries[4].arguments,
+        words("cc $(DEFS) -I. -g -c -o helper.o helper.c")
+    );
+    assert_eq!(
+        db.unknown,
+        vec![
+            UnknownValue::Variable {
+                makefile: "src/Makefile.am".into(),
+                name: "GLIB_CFLAGS".to_string(),
+            },
+            UnknownValue::Variable {
+                makefile: "sub/Makefile.am".into(),
+                name: "DEFS".to_string(),
+            },
+        ]
+    );
+
+    let json = db.to_json();
+    assert!(json.starts_with("[\n  {\n    \"directory\": "));
+    assert!(json.contains("\"output\": \"demo-main.o\""));
+    assert!(json.contains("\"-DHAVE_CONFIG_H\", \"-I.\", \"-I..\""));
+}
+
+#[test]
+fn test_compdb_unknown_conditional_and_build_dir() {
+    let root = fixture("demo");
+    let project = Project::load(&root).unwrap();
+    let mut opts = options(&[], &[("CC", "cc"), ("GLIB_CFLAGS", "-I/usr/include/glib")]);
+    opts.build_dir = Some(PathBuf::from("/tmp/build"));
+    let db = CompilationDatabase::new(&project, &opts);
+    assert!(!db.entries.iter().any(|e| e.file.ends_with("x.c")));
+    assert!(!db.entries.iter().any(|e| e.file.ends_with("nox.c")));
+    assert!(db.unknown.contains(&UnknownValue::Conditional {
+        makefile: "src/Makefile.am".into(),
+        name: "ENABLE_X".to_string(),
+    }));
+
+    let main = db
+        .entries
+        .iter()
+        .find(|e| e.file == root.join("src/main.c"))
+        .unwrap();
+    assert_eq!(main.directory, Path::new("/tmp/build/src"));
+    let srcdir = root.join("src").display().to_string();
+    assert_eq!(
+        main.arguments[..4],
+        words(&format!("cc -DHAVE_CONFIG_H -I. -I{}", srcdir))[..]
+    );
+    assert_eq!(main.arguments[4], "-I..");
+    assert_eq!(
+        main.arguments.last().unwrap(),
+        &format!("{}/main.c", srcdir)
+    );
+}
+
+#[test]
+fn test_compdb_config_headers() {
+    let root = fixture("headers");
+    let project = Project::load(&root).unwrap();
+    let outputs = [("CC", "cc"), ("CPPFLAGS", ""), ("CFLAGS", "")];
+    let db = CompilationDatabase::new(&project, &options(&[], &outputs));
+    // `-I..` is not mistaken for a prefix of `-I../lib`.
+    assert_eq!(
+        db.entries[0].arguments,
+        words("cc -DHAVE_CONFIG_H -I. -I../lib -I.. -c -o app.o app.c")
+    );
+}
+
+#[test]
+fn test_compdb_make_conditional() {
+    let project = Project::load(fixture("make_conditionals")).unwrap();
+    let db = CompilationDatabase::new(&project, &options(&[], &[("CC", "cc")]));
+    assert_eq!(db.entries.len(), 1);
+    assert!(db.unknown.contains(&UnknownValue::MakeConditional {
+        makefile: "Makefile.am".into(),
+        test: "ifdef(DEBUG)".to_string(),
+    }));
+    assert!(!db
+        .unknown
+        .iter()
+        .any(|u| matches!(u, UnknownValue::Conditional { .. })));
+}
@@ -0,0 +1,169 @@
+#![deny(rust_2018_idioms)]
+use autotools_parser::analysis::dist::{DistManifest, DistOrigin};
+use autotools_parser::analysis::CondSet;
+use autotools_parser::project::Project;
+
+mod project_support;
+use project_support::*;
+
+fn manifest(name: &str) -> DistManifest {
+    DistManifest::new(&Project::load(fixture(name)).unwrap())
+}
+
+fn paths(manifest: &DistManifest, origin: fn(&DistOrigin) -> bool) -> Vec<&str> {
+    manifest
+        .files
+        .iter()
+        .filter(|f| origin(&f.origin))
+        .map(|f| f.path.to_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_dist_demo() {
+    let m = manifest("demo");
+    assert_eq!(
+        paths(&m, |o| matches!(
+            o,
+            DistOrigin::Source(_) | DistOrigin::Primary(_)
+        )),
+        vec![
+            "lib/demo.h",
+            "lib/demo.c",
+            "src/main.c",
+            "src/util.c",
+            "src/util.h",
+            "src/x.c",
+            "src/nox.c",
+        ]
+    );
+    assert_eq!(
+        m.get("lib/demo.h").unwrap().origin,
+        DistOrigin::Primary("include_HEADERS".to_string())
+    );
+    let x = m.get("src/x.c").unwrap();
+    assert_eq!(x.conds.len(), 1);
+    assert_eq!(x.conds[0].to_string(), "ENABLE_X_TRUE");
+    assert_eq!(m.get("README").unwrap().origin, DistOrigin::ExtraDist);
+    assert_eq!(
+        paths(&m, |o| *o == DistOrigin::Generated),
+        vec![
+            "configure",
+            "aclocal.m4",
+            "Makefile.in",
+            "src/Makefile.in",
+            "lib/Makefile.in",
+            "config.h.in",
+        ]
+    );
+    assert_eq!(
+        paths(&m, |o| *o == DistOrigin::AuxFile),
+        vec![
+            "build-aux/install-sh",
+            "build-aux/missing",
+            "build-aux/compile",
+            "build-aux/config.guess",
+            "build-aux/config.sub",
+            "build-aux/ltmain.sh",
+            "build-aux/depcomp",
+        ]
+    );
+    // The nested package is not listed in `DIST_SUBDIRS`.
+    assert!(m.get("sub/Makefile.am").is_none());
+    assert!(m.unresolved.is_empty());
+}
+
+#[test]
+fn test_dist_variables() {
+    let m = manifest("dist");
+    assert_eq!(
+        paths(&m, |o| matches!(
+            o,
+            DistOrigin::Source(_) | DistOrigin::Primary(_)
+        )),
+        vec![
+            "data.txt",
+            "pkg.1",
+            "internal.h",
+            "main.c",
+            "parse.y",
+            "doc/manual.txt",
+        ]
+    );
+    assert_eq!(
+        paths(&m, |o| *o == DistOrigin::ExtraDist),
+        vec!["debian", "notes", "ChangeLog"]
+    );
+    assert_eq!(m.get("debian").unwrap().conds, vec![CondSet::always()]);
+    assert_eq!(
+        m.get("notes").unwrap().conds[0].to_string(),
+        "WITH_DOC_TRUE"
+    );
+    assert_eq!(
+        paths(&m, |o| *o == DistOrigin::Common),
+        vec!["README", "doc/NEWS"]
+    );
+    assert_eq!(
+        paths(&m, |o| *o == DistOrigin::ConfigInput),
+        vec!["pkg.pc.in", "script.sh.in", "common.in"]
+    );
+    assert_eq!(
+        paths(&m, |o| *o == DistOrigin::AuxFile),
+        vec![
+            "install-sh",
+            "missing",
+            "compile",
+            "config.guess",
+            "config.sub",
+            "test-driver",
+            "ylwrap",
+        ]
+    );
+    // `tools` is built but not distributed.
+    assert!(m.get("tools/Makefile.am").is_none());
+    for excluded in ["config.c", "version.h", "generated.txt", "check.sh"] {
+        assert!(m.get(excluded).is_none(), "{} is distributed", excluded);
+    }
+    assert_eq!(m.unresolved.len(), 1);
+    assert_eq!(m.unresolved[0].word, "$(GENERATED)");
+
+    let tracked = [
+        "configure.ac",
+        "Makefile.am",
+        "doc/Makefile.am",
+        "README",
+        "debian/rules",
+    ];
+    let missing = m
+        .missing(tracked)
+        .into_iter()
+        .map(|f| f.path.to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        missing,
+        vec![
+            "pkg.pc.in",
+            "script.sh.in",
+            "common.in",
+            "notes",
+            "data.txt",
+            "pkg.1",
+            "internal.h",
+            "main.c",
+            "parse.y",
+            "doc/NEWS",
+            "ChangeLog",
+            "doc/manual.txt",
+        ]
+    );
+}
+
+#[test]
+fn test_dist_nested_package() {
+    let m = manifest("nested");
+    assert_eq!(
+        paths(&m, |o| *o == DistOrigin::ExtraDist),
+        vec!["pkg/HACKING"]
+    );
+    assert_eq!(m.get("pkg/common.am").unwrap().origin, DistOrigin::Input);
+}
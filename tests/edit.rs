@@ -0,0 +1,230 @@
+#![deny(rust_2018_idioms)]
+use autotools_parser::ast::edit::{EditError, Position};
+use autotools_parser::ast::minimal::{Condition, Operator, Word, WordFragment};
+use autotools_parser::ast::node::{
+    AcCommand, AcWord, AutoconfPool, DisplayNode, M4Argument, Node, NodeId, ShellCommand,
+};
+use autotools_parser::ast::{MayM4, Parameter};
+use autotools_parser::lexer::Lexer;
+use autotools_parser::parse::autoconf::NodeParser;
+
+fn parse_pool(src: &str) -> (AutoconfPool, Vec<NodeId>) {
+    let (nodes, top_ids) = NodeParser::<_, ()>::new(Lexer::new(src.chars()))
+        .try_parse_all()
+        .unwrap();
+    (AutoconfPool::new(nodes, None), top_ids)
+}
+
+fn print(pool: &AutoconfPool, top_ids: &[NodeId]) -> String {
+    top_ids
+        .iter()
+        .map(|id| pool.display_node(*id, 0))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parse `src` and print it back.
+fn reprint(src: &str) -> String {
+    let (pool, top_ids) = parse_pool(src);
+    print(&pool, &top_ids)
+}
+
+fn literal(s: &str) -> AcWord {
+    AcWord(Word::Single(MayM4::Shell(WordFragment::Literal(s.into()))))
+}
+
+fn cmd(words: &[&str]) -> Node<AcCommand, ()> {
+    let words = words.iter().map(|w| literal(w)).collect();
+    Node::new(None, None, AcCommand::new_cmd(ShellCommand::Cmd(words)), ())
+}
+
+// Nodes: 0 AC_INIT, 1 test, 2 echo a, 3 echo b, 4 AS_IF, 5 true, 6 echo c,
+// 7 `&&`, 8 echo $f, 9 for, 10 AC_OUTPUT.
+const SCRIPT: &str = r#"AC_INIT([demo], [1.0])
+AS_IF([test -n "$x"], [echo a; echo b])
+true && echo c
+for f in a b; do
+  echo $f
+done
+AC_OUTPUT
+"#;
+
+#[test]
+fn test_edit_parents() {
+    let (mut pool, mut top_ids) = parse_pool(SCRIPT);
+    assert_eq!(top_ids, [0, 4, 7, 9, 10]);
+    let editor = pool.edit(&mut top_ids);
+    assert_eq!(editor.children(4), [1, 2, 3]);
+    assert_eq!(editor.children(7), [5, 6]);
+    assert_eq!(editor.parent(2), Some(4));
+    assert_eq!(editor.parent(5), Some(7));
+    assert_eq!(editor.parent(8), Some(9));
+    assert_eq!(editor.parent(9), None);
+    assert_eq!(editor.ancestors(3).collect::<Vec<_>>(), [4]);
+    assert!(editor.is_attached(8));
+}
+
+#[test]
+fn test_edit_insert_remove_and_move() {
+    let (mut pool, mut top_ids) = parse_pool(SCRIPT);
+    let mut editor = pool.edit(&mut top_ids);
+    let x = editor.insert_after(2, cmd(&["echo", "x"])).unwrap();
+    assert_eq!(editor.parent(x), Some(4));
+    editor.insert_before(0, cmd(&["echo", "first"])).unwrap();
+    editor.remove(10).unwrap();
+    editor.move_subtree(3, Position::Before(8)).unwrap();
+    assert_eq!(editor.parent(3), Some(9));
+    assert!(!editor.is_attached(10));
+
+    assert_eq!(
+        print(&pool, &top_ids),
+        reprint(
+            r#"echo first
+AC_INIT([demo], [1.0])
+AS_IF([test -n "$x"], [echo a; echo x])
+true && echo c
+for f in a b; do
+  echo b
+  echo $f
+done
+"#
+        )
+    );
+}
+
+#[test]
+fn test_edit_errors() {
+    let (mut pool, mut top_ids) = parse_pool(SCRIPT);
+    let mut editor = pool.edit(&mut top_ids);
+    assert_eq!(editor.remove(6), Err(EditError::NotInList(6)));
+    assert_eq!(
+        editor.insert_after(5, cmd(&["echo"])),
+        Err(EditError::NotInList(5))
+    );
+    assert_eq!(
+        editor.move_subtree(9, Position::After(8)),
+        Err(EditError::Cycle(9))
+    );
+    assert_eq!(editor.remove(42), Err(EditError::UnknownNode(42)));
+
+    let brace = Node::new(
+        None,
+        None,
+        AcCommand::new_cmd(ShellCommand::Brace(vec![2])),
+        (),
+    );
+    assert_eq!(editor.insert_after(9, brace), Err(EditError::InUse(2)));
+    editor.remove(10).unwrap();
+    assert_eq!(editor.remove(10), Err(EditError::Detached(10)));
+    let brace = Node::new(
+        None,
+        None,
+        AcCommand::new_cmd(ShellCommand::Brace(vec![10])),
+        (),
+    );
+    let id = editor.insert_after(9, brace).unwrap();
+    assert_eq!(editor.parent(10), Some(id));
+
+    assert_eq!(editor.remove_argument(7, 0), Err(EditError::NotAMacro(7)));
+    assert_eq!(
+        editor.remove_argument(0, 2),
+        Err(EditError::ArgumentOutOfRange(0, 2))
+    );
+}
+
+#[test]
+fn test_edit_replace_and_wrap() {
+    let (mut pool, mut top_ids) = parse_pool(SCRIPT);
+    let mut editor = pool.edit(&mut top_ids);
+    let old = editor.replace(6, cmd(&["echo", "d"])).unwrap();
+    assert!(matches!(old.cmd.0, MayM4::Shell(ShellCommand::Cmd(_))));
+
+    let var = AcWord(Word::Single(MayM4::Shell(WordFragment::Param(
+        Parameter::Var("v".into()),
+    ))));
+    let wrapper = editor
+        .wrap_in_if(6, Condition::Cond(Operator::NonEmpty(var.clone())))
+        .unwrap();
+    assert_eq!(editor.parent(6), Some(wrapper));
+    assert_eq!(editor.parent(wrapper), Some(7));
+    let wrapper = editor
+        .wrap_in_if(4, Condition::Cond(Operator::Empty(var)))
+        .unwrap();
+    assert_eq!(editor.top_ids()[1], wrapper);
+    assert_eq!(editor.parent(4), Some(wrapper));
+
+    // The `for` keeps its body when its words are replaced.
+    let for_loop = Node::new(
+        None,
+        None,
+        AcCommand::new_cmd(ShellCommand::For {
+            var: "f".into(),
+            words: vec![literal("c")],
+            body: vec![8],
+        }),
+        (),
+    );
+    editor.replace(9, for_loop).unwrap();
+    assert_eq!(editor.parent(8), Some(9));
+
+    assert_eq!(
+        print(&pool, &top_ids),
+        reprint(
+            r#"AC_INIT([demo], [1.0])
+if test -z "$v"; then
+  AS_IF([test -n "$x"], [echo a; echo b])
+fi
+true && if test -n "$v"; then echo d; fi
+for f in c; do
+  echo $f
+done
+AC_OUTPUT
+"#
+        )
+    );
+}
+
+#[test]
+fn test_edit_macro_arguments_and_compact() {
+    let (mut pool, mut top_ids) = parse_pool(SCRIPT);
+    let mut editor = pool.edit(&mut top_ids);
+    let old = editor
+        .replace_argument(0, 1, M4Argument::Word(literal("2.0")))
+        .unwrap();
+    assert!(matches!(old, M4Argument::Word(_)));
+    editor
+        .insert_argument(0, 2, M4Argument::Literal("bugs@example.com".into()))
+        .unwrap();
+
+    // Dropping the branch of AS_IF detaches its commands, which may be
+    // given to another argument.
+    let branch = editor.remove_argument(4, 1).unwrap();
+    assert!(matches!(branch, M4Argument::Commands(ref ids) if ids == &[2, 3]));
+    assert_eq!(editor.parent(2), None);
+    editor
+        .insert_argument(4, 1, M4Argument::Commands(vec![3]))
+        .unwrap();
+    assert_eq!(editor.parent(3), Some(4));
+    editor.remove(7).unwrap();
+
+    let expected = reprint(
+        r#"AC_INIT([demo], [2.0], [bugs@example.com])
+AS_IF([test -n "$x"], [echo b])
+for f in a b; do
+  echo $f
+done
+AC_OUTPUT
+"#,
+    );
+    assert_eq!(print(editor.pool(), editor.top_ids()), expected);
+
+    let ids = editor.compact();
+    // The nodes of `echo a` and of `true && echo c` are dropped.
+    assert_eq!(ids.len(), 7);
+    assert_eq!(ids[&0], 0);
+    assert!(ids.values().all(|id| *id < 7));
+    assert_eq!(editor.parent(ids[&8]), Some(ids[&9]));
+    assert_eq!(pool.num_nodes(), 7);
+    assert_eq!(top_ids, [ids[&0], ids[&4], ids[&9], ids[&10]]);
+    assert_eq!(print(&pool, &top_ids), expected);
+}
@@ -0,0 +1,3 @@
+bin_PROGRAMS += %D%/tool
+%C%_tool_SOURCES = %reldir%/tool.c
+include %reldir%/nested.am
@@ -0,0 +1,161 @@
+#![deny(rust_2018_idioms)]
+use autotools_parser::analysis::flags::{BuildFlags, ConfigureOutputs, Language};
+use autotools_parser::analysis::targets::collect_targets;
+use autotools_parser::analysis::CondSet;
+
+mod project_support;
+use project_support::*;
+
+fn outputs() -> ConfigureOutputs {
+    [
+        ("CC", "gcc"),
+        ("CXX", "g++"),
+        ("DEFS", "-DHAVE_CONFIG_H"),
+        ("CPPFLAGS", ""),
+        ("CFLAGS", "-O2"),
+        ("CXXFLAGS", "-O2"),
+        ("LDFLAGS", ""),
+        ("LIBS", "-lm"),
+        ("LIBTOOL", "./libtool"),
+        ("AR", "ar"),
+        ("libdir", "/usr/lib"),
+    ]
+    .iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect()
+}
+
+fn words(s: &str) -> Vec<String> {
+    s.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn test_compile_flags_precedence() {
+    let am = parse_automake(
+        "\
+AM_CPPFLAGS = -I$(top_srcdir)/include
+AM_CFLAGS = -Wall
+bin_PROGRAMS = plain custom
+plain_SOURCES = main.c util.h
+custom_SOURCES = main.c sub/extra.c
+custom_CFLAGS = -DCUSTOM $(AM_CFLAGS)
+",
+    );
+    let targets = collect_targets(&am);
+    let flags = BuildFlags::new(&am, &outputs());
+
+    let plain = flags.compile_commands(&targets[0]);
+    assert_eq!(plain.len(), 1);
+    assert_eq!(plain[0].object, "main.o");
+    assert_eq!(plain[0].language, Language::C);
+    assert_eq!(
+        plain[0].template,
+        words("$(CC) $(DEFS) $(DEFAULT_INCLUDES) $(INCLUDES) $(AM_CPPFLAGS) $(CPPFLAGS) $(AM_CFLAGS) $(CFLAGS) -c -o main.o main.c")
+    );
+    assert_eq!(
+        plain[0].arguments,
+        words("gcc -DHAVE_CONFIG_H -I. -I$(top_srcdir)/include -Wall -O2 -c -o main.o main.c")
+    );
+
+    // Per-target flags replace the AM_ flags and rename the objects.
+    let custom = flags.compile_commands(&targets[1]);
+    assert_eq!(
+        custom.iter().map(|c| c.object.as_str()).collect::<Vec<_>>(),
+        vec!["custom-main.o", "custom-extra.o"]
+    );
+    assert_eq!(
+        custom[1].arguments,
+        words("gcc -DHAVE_CONFIG_H -I. -I$(top_srcdir)/include -DCUSTOM -Wall -O2 -c -o custom-extra.o sub/extra.c")
+    );
+    let custom = BuildFlags::new(&am, &outputs())
+        .subdir_objects(true)
+        .compile_commands(&targets[1]);
+    assert_eq!(custom[1].object, "sub/custom-extra.o");
+
+    let link = flags.link_commands(&targets[1]);
+    assert_eq!(link.len(), 1);
+    assert_eq!(
+        link[0].template,
+        words("$(CCLD) $(custom_CFLAGS) $(CFLAGS) $(AM_LDFLAGS) $(LDFLAGS) -o custom $(custom_OBJECTS) $(LDADD) $(LIBS)")
+    );
+    assert_eq!(
+        link[0].arguments,
+        words("gcc -DCUSTOM -Wall -O2 -o custom custom-main.o custom-extra.o -lm")
+    );
+}
+
+#[test]
+fn test_libtool_and_conditional_sources() {
+    let am = parse_automake(
+        "\
+lib_LTLIBRARIES = libfoo.la
+libfoo_la_SOURCES = foo.cc parse.y
+libfoo_la_LIBADD = $(ZLIB_LIBS)
+if WITH_X
+libfoo_la_SOURCES += x.c
+libfoo_la_CPPFLAGS = -DX
+endif
+",
+    );
+    let targets = collect_targets(&am);
+    let flags = BuildFlags::new(&am, &outputs());
+    let compiles = flags.compile_commands(&targets[0]);
+    let with_x = CondSet::always().with("WITH_X", true).unwrap();
+    let without_x = CondSet::always().with("WITH_X", false).unwrap();
+    let parse_c = compiles
+        .iter()
+        .find(|c| c.source == "libfoo_la-parse.c" && c.cond == with_x)
+        .unwrap();
+    assert_eq!(parse_c.object, "libfoo_la-parse.lo");
+    assert_eq!(
+        parse_c.arguments,
+        words("./libtool --tag=CC --mode=compile gcc -DHAVE_CONFIG_H -I. -DX -O2 -c -o libfoo_la-parse.lo libfoo_la-parse.c")
+    );
+
+    let link = flags.link_commands(&targets[0]);
+    assert_eq!(link.len(), 2);
+    // Objects are renamed as soon as a per-target flag is defined under any
+    // conditionals. C++ sources make the library link with the C++ linker.
+    assert_eq!(link[0].cond, without_x);
+    assert_eq!(
+        link[0].arguments,
+        words("./libtool --tag=CXX --mode=link g++ -O2 -o libfoo.la -rpath /usr/lib libfoo_la-foo.lo libfoo_la-parse.lo $(ZLIB_LIBS) -lm")
+    );
+    assert_eq!(link[1].cond, with_x);
+    assert_eq!(
+        link[1].arguments,
+        words("./libtool --tag=CXX --mode=link g++ -O2 -o libfoo.la -rpath /usr/lib libfoo_la-foo.lo libfoo_la-parse.lo libfoo_la-x.lo $(ZLIB_LIBS) -lm")
+    );
+}
+
+#[test]
+fn test_static_library() {
+    let am = parse_automake(
+        "\
+noinst_LIBRARIES = libutil.a
+libutil_a_SOURCES = a.c b.s
+",
+    );
+    let targets = collect_targets(&am);
+    let flags = BuildFlags::new(&am, &outputs());
+    let compiles = flags.compile_commands(&targets[0]);
+    assert_eq!(compiles[1].language, Language::Assembler);
+    assert_eq!(compiles[1].template[0], "$(CCAS)");
+    let link = flags.link_commands(&targets[0]);
+    assert_eq!(link[0].arguments, words("ar cru libutil.a a.o b.o"));
+}
+
+#[test]
+fn test_fortran_linker_before_objc() {
+    let am = parse_automake(
+        "\
+bin_PROGRAMS = solver
+solver_SOURCES = main.m solve.f
+",
+    );
+    let targets = collect_targets(&am);
+    let flags = BuildFlags::new(&am, &outputs());
+    let link = flags.link_commands(&targets[0]);
+    // Like automake's `resolve_linker`, Fortran 77 outranks Objective C.
+    assert_eq!(link[0].template[0], "$(F77LD)");
+}
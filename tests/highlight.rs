@@ -0,0 +1,167 @@
+#![deny(rust_2018_idioms)]
+use autotools_parser::highlight::{classify, ArgType, TokenKind};
+
+/// The text and kind of each token of a source.
+fn tokens(source: &str) -> Vec<(&str, TokenKind)> {
+    classify(source)
+        .into_iter()
+        .map(|t| (&source[t.span], t.kind))
+        .collect()
+}
+
+#[test]
+fn test_highlight_macro_arguments_by_type() {
+    use TokenKind::*;
+    let source = "AC_INIT([foo], [$VERSION])\nAC_CHECK_HEADERS([stdio.h unistd.h])\n";
+    assert_eq!(
+        tokens(source),
+        vec![
+            ("AC_INIT", Macro),
+            ("[", Quote),
+            ("foo", Argument(ArgType::Lit)),
+            ("]", Quote),
+            ("[", Quote),
+            ("$VERSION", Variable),
+            ("]", Quote),
+            ("AC_CHECK_HEADERS", Macro),
+            ("[", Quote),
+            ("stdio.h", Argument(ArgType::Paths)),
+            ("unistd.h", Argument(ArgType::Paths)),
+            ("]", Quote),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_repeated_argument_types() {
+    let source = "AS_IF([test -n \"$a\"], [x=1], [test -n \"$b\"], [x=2], [x=3])";
+    let types = tokens(source)
+        .into_iter()
+        .filter_map(|(text, kind)| match kind {
+            TokenKind::Variable => Some((text, None)),
+            TokenKind::Argument(ty) => Some((text, Some(ty))),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            ("test", Some(ArgType::Cmds)),
+            ("-n", Some(ArgType::Cmds)),
+            ("\"", Some(ArgType::Cmds)),
+            ("$a", None),
+            ("\"", Some(ArgType::Cmds)),
+            ("x", None),
+            ("=1", Some(ArgType::Cmds)),
+            ("test", Some(ArgType::Cmds)),
+            ("-n", Some(ArgType::Cmds)),
+            ("\"", Some(ArgType::Cmds)),
+            ("$b", None),
+            ("\"", Some(ArgType::Cmds)),
+            ("x", None),
+            ("=2", Some(ArgType::Cmds)),
+            ("x", None),
+            ("=3", Some(ArgType::Cmds)),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_user_macros() {
+    use TokenKind::*;
+    let source = "AC_DEFUN([MY_CHECK], [AC_MSG_CHECKING([for x])])\nMY_CHECK\nOTHER_CHECK([a])\nNOT_A_MACRO\n";
+    let macros = tokens(source)
+        .into_iter()
+        .filter(|(_, kind)| matches!(kind, Macro | UserMacro | Argument(_)))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        macros,
+        vec![
+            ("AC_DEFUN", Macro),
+            ("MY_CHECK", UserMacro),
+            ("AC_MSG_CHECKING", Macro),
+            ("for", Argument(ArgType::Lit)),
+            ("x", Argument(ArgType::Lit)),
+            ("MY_CHECK", UserMacro),
+            ("OTHER_CHECK", UserMacro),
+            ("a", Argument(ArgType::Unknown)),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_shell_keywords_and_variables() {
+    use TokenKind::*;
+    let source = "for f in a b; do echo \"$f ${#f}\" '$x' if; done\n\
+                  case $host in *linux*) os=linux ;; esac\n";
+    let shell = tokens(source)
+        .into_iter()
+        .filter(|(_, kind)| matches!(kind, Keyword | Variable | ParamOp))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        shell,
+        vec![
+            ("for", Keyword),
+            ("in", Keyword),
+            ("do", Keyword),
+            ("$f", Variable),
+            ("${", Variable),
+            ("#", ParamOp),
+            ("f", Variable),
+            ("}", Variable),
+            ("done", Keyword),
+            ("case", Keyword),
+            ("$host", Variable),
+            ("in", Keyword),
+            ("os", Variable),
+            ("esac", Keyword),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_parameter_substitutions() {
+    use TokenKind::*;
+    assert_eq!(
+        tokens("x=${with_foo:-$default}/${path%%/*}"),
+        vec![
+            ("x", Variable),
+            ("${with_foo", Variable),
+            (":-", ParamOp),
+            ("$default", Variable),
+            ("}", Variable),
+            ("${path", Variable),
+            ("%%", ParamOp),
+            ("}", Variable),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_comments_and_heredocs() {
+    use TokenKind::*;
+    let source =
+        "dnl [a] comment\n# shell comment\necho a#b\ncat <<-EOF >x.h\n#define X $x\n\tEOF\ndone\n";
+    assert_eq!(
+        tokens(source),
+        vec![
+            ("dnl [a] comment", Dnl),
+            ("# shell comment", Comment),
+            ("#define X $x", Heredoc),
+            ("done", Keyword),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_unparsable_source() {
+    use TokenKind::*;
+    assert_eq!(
+        tokens("AC_MSG_RESULT([yes"),
+        vec![
+            ("AC_MSG_RESULT", Macro),
+            ("[", Quote),
+            ("yes", Argument(ArgType::Lit)),
+        ]
+    );
+}
@@ -0,0 +1,243 @@
+#![deny(rust_2018_idioms)]
+use std::fs;
+
+use autotools_parser::ast::node::{AcCommand, DisplayNode, M4Argument, NodeId};
+use autotools_parser::ast::MayM4;
+use autotools_parser::project::{AutoconfFile, ProjectFile, TextEdit};
+
+mod project_support;
+use project_support::*;
+
+fn load() -> ProjectFile {
+    let path = fixture("incremental/configure.ac");
+    ProjectFile::parse("configure.ac", fs::read_to_string(path).unwrap()).unwrap()
+}
+
+/// Replace the first occurrence of `from` in the source with `to`.
+fn replace(file: &ProjectFile, from: &str, to: &str) -> TextEdit {
+    let start = file.source.find(from).unwrap();
+    TextEdit::new(start..start + from.len(), to)
+}
+
+/// Describe the commands of a script, with those in the arguments of macro calls.
+fn summary(ac: &AutoconfFile) -> Vec<(String, Vec<(usize, usize)>)> {
+    fn walk(ac: &AutoconfFile, ids: &[NodeId], ret: &mut Vec<(String, Vec<(usize, usize)>)>) {
+        for &id in ids {
+            let node = &ac.pool.nodes[id];
+            ret.push((ac.pool.display_node(id, 0), node.range.clone()));
+            if let AcCommand(MayM4::Macro(m4_macro)) = &node.cmd {
+                for arg in &m4_macro.args {
+                    if let M4Argument::Commands(cmds) = arg {
+                        walk(ac, cmds, ret);
+                    }
+                }
+            }
+        }
+    }
+    let mut ret = Vec::new();
+    walk(ac, &ac.top_ids, &mut ret);
+    ret
+}
+
+/// Check that an edited file is the same as its source parsed from scratch.
+fn assert_reparsed(file: &ProjectFile) {
+    let expected = ProjectFile::parse("configure.ac", file.source.clone()).unwrap();
+    let (ac, expected) = (file.as_autoconf().unwrap(), expected.as_autoconf().unwrap());
+    assert_eq!(summary(ac), summary(expected), "{}", file.source);
+    assert_eq!(
+        ac.pool.nodes.len(),
+        expected.pool.nodes.len(),
+        "{}",
+        file.source
+    );
+}
+
+#[test]
+fn test_edit_top_level_command() {
+    let mut file = load();
+    let top_ids = file.as_autoconf().unwrap().top_ids.clone();
+    let reparse = file.edit(&replace(&file, "/usr", "/opt")).unwrap();
+    assert!(!reparse.full);
+    assert_eq!(reparse.parent, None);
+    assert_eq!(reparse.lines, (12, 13));
+    assert_eq!(reparse.removed, vec![top_ids[4]]);
+    assert_eq!(reparse.inserted.len(), 1);
+
+    let ac = file.as_autoconf().unwrap();
+    let mut expected = top_ids.clone();
+    expected[4] = reparse.inserted[0];
+    assert_eq!(ac.top_ids, expected);
+    assert_eq!(
+        ac.pool.display_node(reparse.inserted[0], 0),
+        "prefix_dir=\"/opt\""
+    );
+    assert_reparsed(&file);
+}
+
+#[test]
+fn test_edit_in_macro_argument() {
+    let mut file = load();
+    let top_ids = file.as_autoconf().unwrap().top_ids.clone();
+    let reparse = file.edit(&replace(&file, "debug=yes", "debug=no")).unwrap();
+    assert!(!reparse.full);
+    assert_eq!(reparse.parent, Some(top_ids[3]));
+    assert_eq!(reparse.lines, (9, 10));
+    assert_eq!(reparse.removed.len(), 1);
+    // The top-level commands are kept.
+    assert_eq!(file.as_autoconf().unwrap().top_ids, top_ids);
+    assert_reparsed(&file);
+
+    // Adding lines shifts the commands which follow.
+    let reparse = file
+        .edit(&replace(
+            &file,
+            "echo debugging",
+            "echo debugging\n   echo more",
+        ))
+        .unwrap();
+    assert_eq!(reparse.parent, Some(top_ids[3]));
+    assert_eq!(reparse.lines, (10, 12));
+    let ac = file.as_autoconf().unwrap();
+    assert_eq!(ac.top_ids, top_ids);
+    assert_eq!(ac.pool.nodes[top_ids[3]].range, vec![(7, 13)]);
+    assert_eq!(ac.pool.nodes[top_ids[4]].range, vec![(13, 14)]);
+    assert_reparsed(&file);
+
+    // The first command of the argument shares its line with the quote.
+    let reparse = file.edit(&replace(&file, " -g", " -O0 -g")).unwrap();
+    assert_eq!(reparse.parent, None);
+    assert!(reparse.removed.contains(&top_ids[3]));
+    assert_reparsed(&file);
+}
+
+#[test]
+fn test_edit_in_nested_macro_argument() {
+    let mut file = load();
+    let top_ids = file.as_autoconf().unwrap().top_ids.clone();
+    let reparse = file.edit(&replace(&file, "found=1", "found=2")).unwrap();
+    let ac = file.as_autoconf().unwrap();
+    let parent = reparse.parent.unwrap();
+    assert!(ac
+        .pool
+        .display_node(parent, 0)
+        .starts_with("AS_IF([\n      test -r /dev/zero"));
+    assert_eq!(reparse.lines, (20, 21));
+    assert_eq!(ac.top_ids, top_ids);
+    assert_reparsed(&file);
+}
+
+#[test]
+fn test_edit_shared_line() {
+    let mut file = load();
+    let reparse = file.edit(&replace(&file, "b=2", "b=3")).unwrap();
+    assert_eq!(reparse.lines, (16, 17));
+    assert_eq!(reparse.inserted.len(), 2);
+    assert_reparsed(&file);
+}
+
+#[test]
+fn test_edit_runs_over_following_commands() {
+    let mut file = load();
+    // Continuing a line joins the command which follows.
+    let reparse = file.edit(&replace(&file, "/usr", "/usr \\")).unwrap();
+    assert!(!reparse.full);
+    assert_eq!(reparse.lines, (12, 27));
+    assert_reparsed(&file);
+
+    // So may a here document.
+    let mut file = load();
+    let reparse = file
+        .edit(&replace(&file, "a=1; b=2", "cat <<EOF\na=1; b=2\nEOF"))
+        .unwrap();
+    assert!(!reparse.full);
+    assert_eq!(reparse.lines, (16, 29));
+    assert_reparsed(&file);
+}
+
+#[test]
+fn test_edit_errors() {
+    let mut file = load();
+    let source = file.source.clone();
+    let top_ids = file.as_autoconf().unwrap().top_ids.clone();
+    assert!(file.edit(&replace(&file, "fi\n", "")).is_err());
+    assert_eq!(file.source, source);
+    assert_eq!(file.as_autoconf().unwrap().top_ids, top_ids);
+    assert_reparsed(&file);
+}
+
+#[test]
+fn test_edit_full_reparse() {
+    let mut file = load();
+    let reparse = file
+        .edit(&replace(
+            &file,
+            "AC_PROG_CC\n",
+            "changequote(<<, >>)\nAC_PROG_CC\n",
+        ))
+        .unwrap();
+    assert!(reparse.full);
+    assert_eq!(reparse.lines, (1, 28));
+
+    let mut makefile = ProjectFile::parse("Makefile.am", "bin_PROGRAMS = app\n".into()).unwrap();
+    let reparse = makefile.edit(&TextEdit::new(15..18, "demo")).unwrap();
+    assert!(reparse.full);
+    assert_eq!(makefile.source, "bin_PROGRAMS = demo\n");
+}
+
+/// Insert a letter, insert a newline and delete a byte at `byte`, checking
+/// each edit and its undo against a full parse.
+fn check_edits_at(original: &ProjectFile, byte: usize) {
+    let edits = [
+        TextEdit::new(byte..byte, "x"),
+        TextEdit::new(byte..byte, "\n"),
+        TextEdit::new(byte..(byte + 1).min(original.source.len()), ""),
+    ];
+    for edit in edits {
+        let mut file = load();
+        let mut source = file.source.clone();
+        source.replace_range(edit.range.clone(), &edit.text);
+        let expected = ProjectFile::parse("configure.ac", source);
+        match file.edit(&edit) {
+            Ok(_) => {
+                assert_reparsed(&file);
+                // Undo the edit.
+                let undo = TextEdit::new(
+                    edit.range.start..edit.range.start + edit.text.len(),
+                    &original.source[edit.range.clone()],
+                );
+                file.edit(&undo).unwrap();
+                assert_reparsed(&file);
+                assert_eq!(file.source, original.source);
+            }
+            Err(_) => assert!(expected.is_err(), "{:?}", edit),
+        }
+    }
+}
+
+#[test]
+fn test_edit_sampled_positions() {
+    let original = load();
+    // The starts of lines, the inner boundaries of quotes, and the end.
+    let mut bytes = vec![0, original.source.len()];
+    for (i, c) in original.source.char_indices() {
+        match c {
+            '\n' | '[' => bytes.push(i + 1),
+            ']' => bytes.push(i),
+            _ => {}
+        }
+    }
+    bytes.sort();
+    bytes.dedup();
+    for byte in bytes {
+        check_edits_at(&original, byte);
+    }
+}
+
+#[test]
+#[ignore = "slow: edits every byte of the fixture"]
+fn test_edit_every_position() {
+    let original = load();
+    for byte in 0..=original.source.len() {
+        check_edits_at(&original, byte);
+    }
+}
@@ -0,0 +1,123 @@
+#![deny(rust_2018_idioms)]
+use autotools_parser::analysis::install::{InstallDirs, InstallLayout};
+use autotools_parser::analysis::CondSet;
+use autotools_parser::project::{Project, ProjectFile};
+
+mod project_support;
+use project_support::*;
+
+fn layout(name: &str, dirs: &InstallDirs) -> InstallLayout {
+    InstallLayout::new(&Project::load(fixture(name)).unwrap(), dirs)
+}
+
+fn table(layout: &InstallLayout) -> Vec<(&str, &str, u32)> {
+    layout
+        .entries
+        .iter()
+        .map(|e| (e.source.to_str().unwrap(), e.dest.to_str().unwrap(), e.mode))
+        .collect()
+}
+
+#[test]
+fn test_install_dirs() {
+    let mut dirs = InstallDirs::with_prefix("/opt/x");
+    dirs.set("libdir", "$(exec_prefix)/lib64");
+    assert_eq!(dirs.get("bindir"), Some("${exec_prefix}/bin"));
+    assert_eq!(dirs.resolve("bindir").unwrap(), "/opt/x/bin");
+    assert_eq!(dirs.resolve("libdir").unwrap(), "/opt/x/lib64");
+    assert_eq!(dirs.resolve("man5dir").unwrap(), "/opt/x/share/man/man5");
+    // The package name is only known once configure.ac is read.
+    assert_eq!(
+        dirs.resolve("pkglibdir").unwrap(),
+        "/opt/x/lib64/${PACKAGE}"
+    );
+    assert_eq!(dirs.resolve("pkgconfigdir"), None);
+    dirs.set("a", "${b}").set("b", "${a}");
+    assert_eq!(dirs.resolve("a").unwrap(), "${a}");
+}
+
+#[test]
+fn test_install_dirs_tarname() {
+    let file = ProjectFile::parse(
+        "configure.ac",
+        "AC_INIT([GNU Hello for GNU Hurd], [1.0])\n".to_string(),
+    )
+    .unwrap();
+    let dirs = InstallDirs::default().for_package(file.as_autoconf().unwrap());
+    // Like `AC_INIT`, every `GNU ` is dropped from the tarball name.
+    assert_eq!(
+        dirs.resolve("pkgdatadir").unwrap(),
+        "/usr/local/share/hello-for-hurd"
+    );
+}
+
+#[test]
+fn test_install_demo() {
+    let l = layout("dist", &InstallDirs::default());
+    assert_eq!(
+        table(&l),
+        vec![
+            ("pkg", "/usr/local/bin/pkg", 0o755),
+            ("data.txt", "/usr/local/share/pkg/data.txt", 0o644),
+            ("generated.txt", "/usr/local/share/pkg/generated.txt", 0o644),
+            ("pkg.1", "/usr/local/share/man/man1/pkg.1", 0o644),
+            (
+                "doc/manual.txt",
+                "/usr/local/share/doc/pkg/manual.txt",
+                0o644
+            ),
+        ]
+    );
+    assert!(l.unresolved.is_empty());
+}
+
+#[test]
+fn test_install_layout() {
+    let mut dirs = InstallDirs::with_prefix("/usr");
+    dirs.set("libdir", "${prefix}/lib64");
+    let l = layout("install", &dirs);
+    assert_eq!(
+        table(&l),
+        vec![
+            ("libfoo.la", "/usr/lib64/libfoo.la", 0o644),
+            ("foo/foo.h", "/usr/include/foo/foo.h", 0o644),
+            ("foo/bits/types.h", "/usr/include/foo/bits/types.h", 0o644),
+            ("foo.pc", "/usr/lib64/pkgconfig/foo.pc", 0o644),
+            ("foo-tool", "/usr/bin/foo-tool", 0o755),
+            ("helper.sh", "/usr/libexec/helper.sh", 0o755),
+            ("foo-tool.1", "/usr/share/man/man1/foo-tool.1", 0o644),
+            ("libfoo.3", "/usr/share/man/man3/libfoo.3", 0o644),
+            ("doc/foo.info", "/usr/share/info/foo.info", 0o644),
+            (
+                "data/themes/dark.css",
+                "/usr/share/foo-bar/themes/dark.css",
+                0o644
+            ),
+            (
+                "data/icons/16/foo.png",
+                "/usr/share/foo-bar/icons/16/foo.png",
+                0o644
+            ),
+        ]
+    );
+    let tool = l.find("/usr/bin/foo-tool").unwrap();
+    assert_eq!(tool.var, "bin_PROGRAMS");
+    assert_eq!(
+        tool.cond,
+        CondSet::always().with("WITH_TOOLS", true).unwrap()
+    );
+
+    // pythondir comes from AM_PATH_PYTHON, which was not given a value.
+    assert_eq!(l.unresolved.len(), 1);
+    assert_eq!(l.unresolved[0].word, "$(pythondir)");
+
+    dirs.set("pythondir", "/usr/lib/python3/site-packages");
+    let l = layout("install", &dirs);
+    assert!(l.unresolved.is_empty());
+    assert_eq!(
+        l.find("/usr/lib/python3/site-packages/foo.py")
+            .unwrap()
+            .mode,
+        0o644
+    );
+}
@@ -0,0 +1,381 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "lsp")]
+use std::fs;
+use std::io::{BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use lsp_server::{Message, Notification, Request, RequestId};
+use serde_json::{json, Value};
+
+mod project_support;
+use project_support::*;
+
+fn uri(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+/// A client talking to `autotools-lsp` over its standard streams.
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i32,
+    notifications: Vec<Notification>,
+}
+
+impl Client {
+    /// Start the server and initialize it for the workspace in `root`.
+    fn start(root: &Path) -> (Self, Value) {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_autotools-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut client = Client {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+            notifications: Vec::new(),
+        };
+        let result = client.request(
+            "initialize",
+            json!({
+                "processId": null,
+                "rootUri": uri(root),
+                "capabilities": {},
+            }),
+        );
+        client.notify("initialized", json!({}));
+        (client, result)
+    }
+
+    fn send(&mut self, message: Message) {
+        message.write(&mut self.stdin).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Message {
+        Message::read(&mut self.stdout).unwrap().unwrap()
+    }
+
+    /// Send a request and wait for its result, keeping the notifications
+    /// received meanwhile.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        self.send(Request::new(id.clone(), method.to_string(), params).into());
+        loop {
+            match self.receive() {
+                Message::Response(response) if response.id == id => {
+                    assert!(response.error.is_none(), "{:?}", response.error);
+                    // A null result is read back as none.
+                    return response.result.unwrap_or(Value::Null);
+                }
+                Message::Notification(notification) => self.notifications.push(notification),
+                message => panic!("unexpected message {:?}", message),
+            }
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(Notification::new(method.to_string(), params).into());
+    }
+
+    /// Wait for the diagnostics of a document.
+    fn diagnostics(&mut self, uri: &str) -> Value {
+        loop {
+            let position = self.notifications.iter().position(|notification| {
+                notification.method == "textDocument/publishDiagnostics"
+                    && notification.params["uri"] == uri
+            });
+            if let Some(i) = position {
+                return self.notifications.remove(i).params["diagnostics"].clone();
+            }
+            match self.receive() {
+                Message::Notification(notification) => self.notifications.push(notification),
+                message => panic!("unexpected message {:?}", message),
+            }
+        }
+    }
+
+    /// Open a file of the fixture, returning its URI.
+    fn open(&mut self, path: &Path) -> String {
+        let uri = uri(path);
+        let text = fs::read_to_string(path).unwrap();
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": uri,
+                    "languageId": "autoconf",
+                    "version": 1,
+                    "text": text,
+                },
+            }),
+        );
+        uri
+    }
+
+    fn at(&mut self, method: &str, uri: &str, line: u32, character: u32) -> Value {
+        self.request(
+            method,
+            json!({
+                "textDocument": {"uri": uri},
+                "position": {"line": line, "character": character},
+                "context": {"includeDeclaration": true},
+            }),
+        )
+    }
+
+    /// Shut the server down, returning its exit code.
+    fn shutdown(mut self) -> i32 {
+        assert_eq!(self.request("shutdown", Value::Null), Value::Null);
+        self.notify("exit", Value::Null);
+        self.child.wait().unwrap().code().unwrap()
+    }
+}
+
+fn range(start: (u32, u32), end: (u32, u32)) -> Value {
+    json!({
+        "start": {"line": start.0, "character": start.1},
+        "end": {"line": end.0, "character": end.1},
+    })
+}
+
+#[test]
+fn test_lsp_initialize_and_shutdown() {
+    let (client, result) = Client::start(&fixture("lsp"));
+    let capabilities = &result["capabilities"];
+    assert_eq!(capabilities["textDocumentSync"], json!(1));
+    assert_eq!(capabilities["hoverProvider"], json!(true));
+    assert_eq!(capabilities["definitionProvider"], json!(true));
+    assert_eq!(capabilities["referencesProvider"], json!(true));
+    assert_eq!(capabilities["documentSymbolProvider"], json!(true));
+    assert_eq!(
+        capabilities["semanticTokensProvider"]["legend"]["tokenModifiers"],
+        json!(["defaultLibrary"])
+    );
+    assert_eq!(client.shutdown(), 0);
+}
+
+#[test]
+fn test_lsp_diagnostics() {
+    let root = fixture("lsp");
+    let (mut client, _) = Client::start(&root);
+    let uri = client.open(&root.join("configure.ac"));
+    assert_eq!(client.diagnostics(&uri), json!([]));
+
+    let change = |text: &str| {
+        json!({
+            "textDocument": {"uri": uri, "version": 2},
+            "contentChanges": [{"text": text}],
+        })
+    };
+    client.notify(
+        "textDocument/didChange",
+        change("AC_INIT([demo], [1.0])\nfoo=1\n(cd src; make\nAC_OUTPUT\n"),
+    );
+    let diagnostics = client.diagnostics(&uri);
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["severity"], json!(1));
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], json!(2));
+    assert!(
+        diagnostics[0]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("unmatched `(` starting on line 3:1"),
+        "{}",
+        diagnostics[0]
+    );
+
+    client.notify("textDocument/didChange", change("AC_INIT([demo], [1.0])\n"));
+    assert_eq!(client.diagnostics(&uri), json!([]));
+
+    client.notify(
+        "textDocument/didClose",
+        json!({"textDocument": {"uri": uri}}),
+    );
+    assert_eq!(client.diagnostics(&uri), json!([]));
+    assert_eq!(client.shutdown(), 0);
+}
+
+#[test]
+fn test_lsp_hover() {
+    let root = fixture("lsp");
+    let (mut client, _) = Client::start(&root);
+    let uri = client.open(&root.join("configure.ac"));
+
+    let hover = client.at("textDocument/hover", &uri, 2, 4);
+    assert_eq!(hover["range"], range((2, 0), (2, 10)));
+    assert_eq!(hover["contents"]["kind"], json!("markdown"));
+    let text = hover["contents"]["value"].as_str().unwrap();
+    assert!(
+        text.starts_with("```m4\nAC_PROG_CC([Arr])\n```\n"),
+        "{}",
+        text
+    );
+    assert!(text.contains("* `CC`: environment, set\n"), "{}", text);
+
+    // Inside the body of a macro definition.
+    let hover = client.at("textDocument/hover", &uri, 7, 31);
+    let text = hover["contents"]["value"].as_str().unwrap();
+    assert!(
+        text.starts_with("```m4\nAC_CHECK_LIB([Library], [Symbol], [Cmds], [Cmds], [Arr])\n```\n"),
+        "{}",
+        text
+    );
+    assert!(text.contains("* `LIBS`: output, appended to\n"), "{}", text);
+
+    // Not a macro.
+    assert_eq!(client.at("textDocument/hover", &uri, 9, 2), Value::Null);
+    assert_eq!(client.shutdown(), 0);
+}
+
+#[test]
+fn test_lsp_definition() {
+    let root = fixture("lsp");
+    let (mut client, _) = Client::start(&root);
+    let uri = client.open(&root.join("configure.ac"));
+    let location = |start, end| json!({"uri": uri, "range": range(start, end)});
+
+    // `$prefix_dir` in the `if` guard.
+    let definition = client.at("textDocument/definition", &uri, 12, 16);
+    assert_eq!(definition, location((9, 0), (9, 10)));
+    // `$CFLAGS` is set by `AC_PROG_CC`.
+    let definition = client.at("textDocument/definition", &uri, 13, 12);
+    assert_eq!(definition, location((2, 0), (2, 10)));
+    // The call of a macro defined with `AC_DEFUN`.
+    let definition = client.at("textDocument/definition", &uri, 8, 3);
+    assert_eq!(definition, location((7, 10), (7, 25)));
+    // `exit` is defined nowhere.
+    assert_eq!(
+        client.at("textDocument/definition", &uri, 0, 0),
+        Value::Null
+    );
+    assert_eq!(client.shutdown(), 0);
+}
+
+#[test]
+fn test_lsp_references() {
+    let root = fixture("lsp");
+    let (mut client, _) = Client::start(&root);
+    let configure = client.open(&root.join("configure.ac"));
+    let makefile = uri(&root.join("Makefile.am"));
+    let sub_makefile = uri(&root.join("src/Makefile.am"));
+
+    let references = client.at("textDocument/references", &configure, 10, 17);
+    assert_eq!(
+        references,
+        json!([
+            {"uri": makefile, "range": range((1, 3), (1, 8))},
+            {"uri": configure, "range": range((10, 16), (10, 21))},
+            {"uri": sub_makefile, "range": range((2, 3), (2, 8))},
+        ])
+    );
+
+    // From a makefile, without the declaration.
+    let sub_makefile = client.open(&root.join("src/Makefile.am"));
+    let references = client.request(
+        "textDocument/references",
+        json!({
+            "textDocument": {"uri": sub_makefile},
+            "position": {"line": 5, "character": 4},
+            "context": {"includeDeclaration": false},
+        }),
+    );
+    assert_eq!(
+        references,
+        json!([{"uri": sub_makefile, "range": range((5, 3), (5, 7))}])
+    );
+    assert_eq!(client.shutdown(), 0);
+}
+
+#[test]
+fn test_lsp_document_symbols() {
+    let root = fixture("lsp");
+    let (mut client, _) = Client::start(&root);
+    let uri = client.open(&root.join("configure.ac"));
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({"textDocument": {"uri": uri}}),
+    );
+    let summary = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| {
+            (
+                symbol["name"].as_str().unwrap().to_string(),
+                symbol["kind"].as_u64().unwrap(),
+                symbol["range"]["start"]["line"].as_u64().unwrap(),
+                symbol["range"]["end"]["line"].as_u64().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            ("--enable-debug".to_string(), 7, 3, 5),
+            ("--with-zlib".to_string(), 7, 5, 6),
+            ("PYTHON".to_string(), 13, 6, 7),
+            ("DEMO_CHECK_MATH".to_string(), 12, 7, 8),
+        ]
+    );
+    assert_eq!(symbols[0]["detail"], json!("build with debugging"));
+    assert_eq!(symbols[0]["selectionRange"], range((3, 15), (3, 20)));
+    assert_eq!(client.shutdown(), 0);
+}
+
+#[test]
+fn test_lsp_semantic_tokens() {
+    let root = fixture("lsp");
+    let (mut client, result) = Client::start(&root);
+    let legend = &result["capabilities"]["semanticTokensProvider"]["legend"];
+    let token_type = |name: &str| {
+        let types = legend["tokenTypes"].as_array().unwrap();
+        types.iter().position(|t| t == name).unwrap() as u64
+    };
+    let (macro_type, operator, string) = (
+        token_type("macro"),
+        token_type("operator"),
+        token_type("string"),
+    );
+    let uri = client.open(&root.join("configure.ac"));
+    let tokens = client.request(
+        "textDocument/semanticTokens/full",
+        json!({"textDocument": {"uri": uri}}),
+    );
+    let data = tokens["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|n| n.as_u64().unwrap())
+        .collect::<Vec<_>>();
+    // AC_INIT([lsp-demo], [1.0])
+    // AM_INIT_AUTOMAKE(...
+    assert_eq!(
+        data.chunks(5).take(8).collect::<Vec<_>>(),
+        [
+            &[0, 0, 7, macro_type, 1][..],
+            &[0, 8, 1, operator, 0],
+            &[0, 1, 8, string, 0],
+            &[0, 8, 1, operator, 0],
+            &[0, 3, 1, operator, 0],
+            &[0, 1, 3, string, 0],
+            &[0, 3, 1, operator, 0],
+            &[1, 0, 16, macro_type, 1],
+        ]
+    );
+
+    let makefile = client.open(&root.join("Makefile.am"));
+    let tokens = client.request(
+        "textDocument/semanticTokens/full",
+        json!({"textDocument": {"uri": makefile}}),
+    );
+    assert_eq!(tokens["data"], json!([]));
+    assert_eq!(client.shutdown(), 0);
+}
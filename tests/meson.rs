@@ -0,0 +1,278 @@
+#![deny(rust_2018_idioms)]
+use std::path::{Path, PathBuf};
+
+use autotools_parser::backend::meson::MesonBuild;
+use autotools_parser::project::Project;
+
+mod project_support;
+use project_support::*;
+
+fn translate(name: &str) -> MesonBuild {
+    MesonBuild::new(&Project::load(fixture(name)).unwrap())
+}
+
+fn file<'a>(meson: &'a MesonBuild, dir: &str) -> &'a str {
+    let (_, text) = meson
+        .files
+        .iter()
+        .find(|(d, _)| d == Path::new(dir))
+        .unwrap();
+    text
+}
+
+fn diagnostics(meson: &MesonBuild) -> Vec<String> {
+    meson.diagnostics.iter().map(|d| d.to_string()).collect()
+}
+
+#[test]
+fn test_meson_demo() {
+    let meson = translate("demo");
+    let dirs = meson
+        .files
+        .iter()
+        .map(|(d, _)| d.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        dirs,
+        vec![PathBuf::new(), PathBuf::from("lib"), PathBuf::from("src")]
+    );
+    assert_eq!(
+        file(&meson, ""),
+        "\
+project('demo', 'c', version : '1.0', meson_version : '>= 0.50')
+
+cc = meson.get_compiler('c')
+conf = configuration_data()
+conf.set_quoted('PACKAGE_NAME', 'demo')
+conf.set_quoted('PACKAGE_TARNAME', 'demo')
+conf.set_quoted('PACKAGE_VERSION', '1.0')
+conf.set_quoted('PACKAGE_STRING', 'demo 1.0')
+conf.set_quoted('PACKAGE_BUGREPORT', 'bug@example.com')
+conf.set_quoted('PACKAGE', 'demo')
+conf.set_quoted('VERSION', '1.0')
+libs = []
+
+if cc.has_header('stdio.h')
+  conf.set('HAVE_STDIO_H', 1)
+endif
+if cc.has_header('unistd.h')
+  conf.set('HAVE_UNISTD_H', 1)
+endif
+if cc.has_function('strdup')
+  conf.set('HAVE_STRDUP', 1)
+endif
+libm_dep = cc.find_library('m', required : false)
+if libm_dep.found()
+  conf.set('HAVE_LIBM', 1)
+endif
+libs += libm_dep
+enable_x = get_option('x')
+glib_dep = dependency('glib-2.0', version : '>=2.40')
+# TODO configure.ac:16: nested packages are not translated
+#   AC_CONFIG_SUBDIRS([sub])
+
+configure_file(output : 'config.h', configuration : conf)
+config_inc = include_directories('.')
+add_project_arguments('-DHAVE_CONFIG_H', language : ['c'])
+
+subdir('lib')
+subdir('src')
+"
+    );
+    assert_eq!(
+        file(&meson, "lib"),
+        "\
+# Generated from lib/Makefile.am.
+
+libdemo = library(
+  'demo',
+  files('demo.c', 'demo.h'),
+  include_directories : [config_inc],
+  dependencies : [libs],
+  install : true,
+)
+
+install_headers(files('demo.h'))
+"
+    );
+    assert_eq!(
+        file(&meson, "src"),
+        "\
+# Generated from src/Makefile.am.
+
+demo_sources = files('main.c', 'util.c', 'util.h')
+if enable_x
+  demo_sources += files('x.c')
+endif
+if not enable_x
+  demo_sources += files('nox.c')
+endif
+demo = executable(
+  'demo',
+  demo_sources,
+  include_directories : [config_inc, '../lib'],
+  dependencies : [glib_dep, libs],
+  link_with : [libdemo],
+  install : true,
+)
+"
+    );
+    assert_eq!(
+        meson.options,
+        "option('x', type : 'boolean', value : false, description : 'enable the x feature')\n"
+    );
+    assert_eq!(
+        diagnostics(&meson),
+        vec!["configure.ac: line 16: nested packages are not translated"]
+    );
+}
+
+#[test]
+fn test_meson_todo() {
+    let meson = translate("cmake");
+    let text = file(&meson, "");
+    assert!(text.starts_with(
+        "project('Foo Tools', 'cpp', version : '2.1-beta', meson_version : '>= 0.50')\n\n\
+         cc = meson.get_compiler('cpp')\n"
+    ));
+    assert!(text.contains("subst.set('PACKAGE_TARNAME', 'foo')\n"));
+    assert!(text.contains(
+        "\
+if cc.has_header('sys/types.h')
+  conf.set('HAVE_SYS_TYPES_H', 1)
+endif
+# TODO configure.ac:5: the actions of the check are not translated
+"
+    ));
+    assert!(text.contains(
+        "\
+no_zlib = not get_option('zlib')
+on_linux = false
+# TODO configure.ac:13: the condition is not translated
+#   AM_CONDITIONAL([ON_LINUX], [test \"$host_os\" = linux])
+zlib_dep = dependency('zlib', required : false)
+"
+    ));
+    assert!(text.contains(
+        "\
+conf.set('USE_FOO', 1, description : 'Define to use foo.')
+# TODO configure.ac:16: the value depends on the shell
+#   AC_DEFINE_UNQUOTED([BUILD_HOST], [\"$host\"])
+subst.set('FOO_API', '3')
+"
+    ));
+    assert!(text.contains(
+        "configure_file(input : 'foo.pc.in', output : 'foo.pc', configuration : subst)\n"
+    ));
+    assert!(text.ends_with(
+        "\
+# TODO configure.ac: `include/config.h` is generated as `config.h` in the top build directory
+configure_file(output : 'config.h', configuration : conf)
+config_inc = include_directories('.')
+add_project_arguments('-DHAVE_CONFIG_H', language : ['cpp'])
+"
+    ));
+    assert_eq!(
+        meson.options,
+        "\
+option('zlib', type : 'boolean', value : true, description : 'use zlib')
+option('docs', type : 'boolean', value : false, description : 'do not build the documentation')
+"
+    );
+    assert_eq!(
+        diagnostics(&meson),
+        vec![
+            "configure.ac: line 5: the actions of the check are not translated",
+            "configure.ac: line 9: the actions of the option are not translated",
+            "configure.ac: line 13: the condition is not translated",
+            "configure.ac: line 14: the actions of the check are not translated",
+            "configure.ac: line 16: the value depends on the shell",
+            "configure.ac: line 18: `AC_CHECK_SIZEOF` is not translated",
+            "configure.ac: line 19: shell code is not translated",
+            "configure.ac: `include/config.h` is generated as `config.h` in the top build directory",
+        ]
+    );
+}
+
+#[test]
+fn test_meson_targets() {
+    let meson = translate("ninja");
+    let text = file(&meson, "");
+    assert!(!text.contains("configure_file"));
+    assert!(text.contains(
+        "\
+libutil = static_library(
+  'util',
+  files('util.c'),
+)
+"
+    ));
+    assert!(text.contains(
+        "\
+libgen = library(
+  'gen',
+  files('gen.c'),
+  link_with : [libutil],
+  version : '1.0.0',
+  soversion : '1',
+  install : true,
+)
+"
+    ));
+    assert!(text.contains(
+        "\
+gen_c_args = []
+if debug
+  gen_c_args += ['-O0']
+endif
+gen = executable(
+  'gen',
+  files('main.c'),
+  c_args : gen_c_args,
+  link_with : [libgen],
+  install : true,
+)
+"
+    ));
+    assert_eq!(
+        diagnostics(&meson),
+        vec![
+            "configure.ac: line 6: the condition is not translated",
+            "Makefile.am: the built sources of `libgen.la` are not translated",
+            "Makefile.am: the rule of `version.h` is not translated",
+            "Makefile.am: the rule of `check-version` is not translated",
+            "Makefile.am: the rule of `install-data-local` is not translated",
+            "Makefile.am: the rule of `clean-local` is not translated",
+            "Makefile.am: the rule of `.in.out` is not translated",
+        ]
+    );
+}
+
+#[test]
+fn test_meson_make_conditional() {
+    let meson = translate("make_conditionals");
+    assert!(file(&meson, "").contains(
+        "\
+app_sources = files('app.c')
+if false
+  app_sources += files('debug.c')
+endif
+"
+    ));
+    assert_eq!(
+        diagnostics(&meson),
+        vec!["Makefile.am: GNU make conditional `ifdef(DEBUG)` is not translated"]
+    );
+}
+
+#[test]
+fn test_meson_write() {
+    let meson = translate("demo");
+    let dir = std::env::temp_dir().join(format!("meson-write-{}", std::process::id()));
+    meson.write(&dir).unwrap();
+    let read = |path: &str| std::fs::read_to_string(dir.join(path)).unwrap();
+    assert_eq!(read("meson.build"), file(&meson, ""));
+    assert_eq!(read("src/meson.build"), file(&meson, "src"));
+    assert_eq!(read("meson_options.txt"), meson.options);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -661,3 +661,22 @@ fn test_macro_patsubst_nested() {
         }
     }
 }
+
+#[test]
+fn test_condition_without_operator() {
+    let mut p = make_parser(r#"test -xn "$foo" && foo=1"#);
+    let cmd = p.complete_command().unwrap().unwrap();
+    // An unknown operator leaves the test as a command.
+    let Compound(Shell(CompoundCommand::And(cond, _))) = &*cmd.cmd else {
+        panic!("{:?}", cmd);
+    };
+    assert!(matches!(cond, Condition::ReturnZero(_)), "{:?}", cond);
+}
+
+#[test]
+fn test_unbalanced_quotes_in_nested_macro_argument() {
+    let mut p = make_parser(
+        "AC_CACHE_CHECK([for mmap], [ac_cv_mmap],\n  [AS_IF[test -r /dev/zero],\n     [ac_cv_mmap=yes],\n     [ac_cv_mmap=no])])\n",
+    );
+    assert!(p.complete_command().is_err());
+}
@@ -0,0 +1,200 @@
+#![deny(rust_2018_idioms)]
+use std::path::PathBuf;
+
+use autotools_parser::backend::{ninja, BackendOptions, BuildModel, Diagnostic, TargetKind};
+use autotools_parser::project::Project;
+
+mod project_support;
+use project_support::*;
+
+fn options(conds: &[(&str, bool)], srcdir: Option<&str>) -> BackendOptions {
+    let mut options = BackendOptions {
+        conds: conds.iter().map(|(k, v)| (k.to_string(), *v)).collect(),
+        srcdir: srcdir.map(PathBuf::from),
+        ..BackendOptions::default()
+    };
+    for (name, value) in [
+        ("CC", "cc"),
+        ("CPPFLAGS", ""),
+        ("CFLAGS", "-g"),
+        ("LDFLAGS", ""),
+        ("LIBS", ""),
+        ("AR", "ar"),
+        ("MKDIR_P", "mkdir -p"),
+    ] {
+        options.outputs.insert(name.to_string(), value.to_string());
+    }
+    options
+}
+
+fn diagnostic(makefile: &str, message: &str) -> Diagnostic {
+    Diagnostic {
+        makefile: PathBuf::from(makefile),
+        message: message.to_string(),
+    }
+}
+
+#[test]
+fn test_ninja_demo() {
+    let project = Project::load(fixture("demo")).unwrap();
+    let model = BuildModel::new(&project, &options(&[("ENABLE_X", true)], Some(".")));
+    let targets = model
+        .targets
+        .iter()
+        .map(|t| (t.path(), t.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        targets,
+        vec![
+            (PathBuf::from("lib/libdemo.so"), TargetKind::SharedLibrary),
+            (PathBuf::from("src/demo"), TargetKind::Program),
+            (PathBuf::from("sub/helper"), TargetKind::Program),
+        ]
+    );
+    assert_eq!(model.targets[1].links, vec![0]);
+
+    let build = ninja::generate(&model);
+    assert!(build.contains("\nsrcdir = .\n"));
+    assert!(build.contains(
+        "\nbuild lib/demo.lo: compile lib/demo.c\n  dir = lib\n  \
+         cmd = cc -fPIC -DHAVE_CONFIG_H -I. -I.. -g -c -o demo.lo demo.c\n"
+    ));
+    assert!(build.contains(
+        "\nbuild src/demo-x.o: compile src/x.c\n  dir = src\n  \
+         cmd = cc -DHAVE_CONFIG_H -I. -I.. -I../lib $$(GLIB_CFLAGS) -g -c -o demo-x.o x.c\n"
+    ));
+    assert!(!build.contains("nox.c"));
+    assert!(build.contains(
+        "\nbuild lib/libdemo.so: link lib/demo.lo\n  dir = lib\n  \
+         cmd = cc -shared -g -o libdemo.so demo.lo\n"
+    ));
+    assert!(build.contains(
+        "\nbuild src/demo: link src/demo-main.o src/demo-util.o src/demo-x.o | lib/libdemo.so\n  \
+         dir = src\n  \
+         cmd = cc -g -o demo demo-main.o demo-util.o demo-x.o ../lib/libdemo.so $$(GLIB_LIBS)\n"
+    ));
+    assert!(build.contains(
+        "\nbuild install_0: install lib/libdemo.so\n  \
+         dest = /usr/local/lib/libdemo.so\n  mode = 644\n"
+    ));
+    assert!(build.contains(
+        "\nbuild install_2: install src/demo\n  dest = /usr/local/bin/demo\n  mode = 755\n"
+    ));
+    assert!(build.contains("\nbuild install: phony all install_0 install_1 install_2\n"));
+    assert!(
+        build.ends_with("\nbuild all: phony lib/libdemo.so src/demo sub/helper\n\ndefault all\n")
+    );
+
+    assert_eq!(
+        model.diagnostics,
+        vec![
+            diagnostic("src/Makefile.am", "unknown value of `GLIB_CFLAGS`"),
+            diagnostic("src/Makefile.am", "unknown value of `GLIB_LIBS`"),
+            diagnostic("sub/Makefile.am", "unknown value of `DEFS`"),
+        ]
+    );
+}
+
+#[test]
+fn test_ninja_out_of_tree() {
+    let root = std::path::absolute(fixture("demo")).unwrap();
+    let project = Project::load(&root).unwrap();
+    let model = BuildModel::new(&project, &options(&[("ENABLE_X", false)], None));
+    assert_eq!(model.srcdir, root);
+
+    let build = ninja::generate(&model);
+    assert!(build.contains(&format!("\nsrcdir = {}\n", root.display())));
+    assert!(build.contains(&format!(
+        "\nbuild src/demo-nox.o: compile $srcdir/src/nox.c\n  dir = src\n  \
+         cmd = cc -DHAVE_CONFIG_H -I. -I{src} -I.. -I{root}/lib $$(GLIB_CFLAGS) -g -c \
+         -o demo-nox.o {src}/nox.c\n",
+        src = root.join("src").display(),
+        root = root.display(),
+    )));
+    assert!(build.contains("\nbuild install_1: install $srcdir/lib/demo.h\n"));
+}
+
+#[test]
+fn test_ninja_unchosen_conditional() {
+    let project = Project::load(fixture("demo")).unwrap();
+    let model = BuildModel::new(&project, &options(&[], Some(".")));
+    let objects = model.targets[1]
+        .objects
+        .iter()
+        .map(|o| o.source.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(objects, vec!["main.c", "util.c"]);
+    assert!(model.diagnostics.contains(&diagnostic(
+        "src/Makefile.am",
+        "no value chosen for conditional `ENABLE_X`"
+    )));
+}
+
+#[test]
+fn test_ninja_custom_rules() {
+    let project = Project::load(fixture("ninja")).unwrap();
+    let mut options = options(&[("DEBUG", true)], Some(".."));
+    options.outputs.insert("DEFS".to_string(), String::new());
+    let model = BuildModel::new(&project, &options);
+    let build = ninja::generate(&model);
+
+    assert!(build.contains(
+        "\nbuild gen.lo: compile $srcdir/gen.c || version.h\n  dir = .\n  \
+         cmd = cc -fPIC -I. -I.. -g -c -o gen.lo ../gen.c\n"
+    ));
+    assert!(build.contains(
+        "\nbuild libutil.a: archive util.o\n  dir = .\n  target = libutil.a\n  \
+         cmd = ar cru libutil.a util.o\n"
+    ));
+    assert!(build.contains(
+        "\nbuild libgen.so: link gen.lo | libutil.a\n  dir = .\n  \
+         cmd = cc -shared -g -Wl,--no-undefined -o libgen.so gen.lo libutil.a\n"
+    ));
+    assert!(build.contains(
+        "\nbuild gen: link gen-main.o | libgen.so\n  dir = .\n  \
+         cmd = cc -O0 -g -o gen gen-main.o libgen.so\n"
+    ));
+    assert!(build.contains(
+        "\nbuild version.h: custom $srcdir/version.h.in\n  dir = .\n  \
+         cmd = (sed -e s/@VERSION@/2.0/ version.h.in > version.h-t) && \
+         (mv version.h-t version.h)\n"
+    ));
+    assert!(build.contains(
+        "\nbuild check-version: custom version.h\n  dir = .\n  \
+         cmd = if test -f version.h; then echo ok; else exit 1; fi\n"
+    ));
+    assert!(build.contains(
+        "\nbuild install-data-local: custom\n  dir = .\n  \
+         cmd = mkdir -p $$DESTDIR/usr/local/share/gen || true\n"
+    ));
+    assert!(build.contains("\nbuild install: phony all install_0 install_1 install-data-local\n"));
+    assert!(build.contains("\nbuild all: phony libutil.a libgen.so gen version.h\n"));
+
+    assert_eq!(
+        model.diagnostics,
+        vec![
+            diagnostic(
+                "Makefile.am",
+                "libtool flag `-version-info` of `libgen.la` is dropped"
+            ),
+            diagnostic("Makefile.am", "inference rule `%.out` is not translated"),
+            diagnostic("Makefile.am", "`clean-local` is not translated"),
+        ]
+    );
+}
+
+#[test]
+fn test_ninja_make_conditional() {
+    let project = Project::load(fixture("make_conditionals")).unwrap();
+    let model = BuildModel::new(&project, &options(&[], Some(".")));
+    let objects = model.targets[0]
+        .objects
+        .iter()
+        .map(|o| o.source.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(objects, vec!["app.c"]);
+    assert!(model.diagnostics.contains(&diagnostic(
+        "Makefile.am",
+        "GNU make conditional `ifdef(DEBUG)` is not translated"
+    )));
+}
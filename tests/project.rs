@@ -0,0 +1,91 @@
+#![deny(rust_2018_idioms)]
+use std::error::Error;
+use std::path::Path;
+
+use autotools_parser::ast::am::AmLine;
+use autotools_parser::ast::builder::BuilderError;
+use autotools_parser::parse::ParseError;
+use autotools_parser::project::{Project, ProjectError, ProjectFile};
+
+mod project_support;
+use project_support::*;
+
+#[test]
+fn test_project_file_table() {
+    let project = Project::load(fixture("demo")).unwrap();
+    let paths = project
+        .files()
+        .iter()
+        .map(|f| f.path.to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            "configure.ac",
+            "Makefile.am",
+            "lib/Makefile.am",
+            "src/Makefile.am",
+            "sub/configure.ac",
+            "sub/Makefile.am",
+        ]
+    );
+    assert_eq!(project.autoconf_files().count(), 2);
+    assert_eq!(project.automake_files().count(), 4);
+    assert_eq!(project.find("./src/../src/Makefile.am"), Some(3));
+    assert_eq!(project.file(3).dir(), Path::new("src"));
+}
+
+#[test]
+fn test_project_keeps_pools() {
+    let project = Project::load(fixture("demo")).unwrap();
+    let configure = project.file(project.configure()).as_autoconf().unwrap();
+    assert_eq!(configure.top_ids.len(), 15);
+
+    let src = project.find("src/Makefile.am").unwrap();
+    let am = project.file(src).as_automake().unwrap();
+    let cond = am
+        .top_ids
+        .iter()
+        .find_map(|id| match &am.nodes[*id].cmd {
+            AmLine::Conditional(cond) => Some(cond),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(cond.guard_var, "ENABLE_X");
+    assert_eq!(cond.then.len(), 1);
+    assert_eq!(cond.otherwise.len(), 1);
+}
+
+#[test]
+fn test_project_without_configure() {
+    match Project::load(fixture("demo").join("src")) {
+        Err(ProjectError::Io(path, _)) => assert!(path.ends_with("src/configure.ac")),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_project_parse_error_source() {
+    let error = ProjectFile::parse("configure.ac", "if true; then\n".to_string()).unwrap_err();
+    assert!(matches!(error, ProjectError::Parse(..)));
+    assert_eq!(
+        error.source().unwrap().to_string(),
+        error
+            .to_string()
+            .trim_start_matches("failed to parse configure.ac: ")
+    );
+}
+
+#[test]
+fn test_project_nested_package_includes() {
+    let project = Project::load(fixture("nested")).unwrap();
+    let src = project.find("pkg/src/Makefile.am").unwrap();
+    assert_eq!(project.package_of(src), project.find("pkg/configure.ac"));
+    let am = project.file(src).as_automake().unwrap();
+    let included = match &am.nodes[am.top_ids[0]].cmd {
+        AmLine::Include(include) => include.included.as_ref().unwrap(),
+        other => panic!("unexpected line: {:?}", other),
+    };
+    assert_eq!(included.path, Path::new("common.am"));
+    assert_eq!(included.body.len(), 1);
+}
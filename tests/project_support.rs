@@ -0,0 +1,25 @@
+// Certain helpers may only be used by specific tests,
+// suppress dead_code warnings since the compiler can't
+// see our intent
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use autotools_parser::lexer::Lexer;
+use autotools_parser::parse::automake::AutomakeNodeParser;
+use autotools_parser::project::AutomakeFile;
+
+/// The path of a file or directory under `tests/fixtures`.
+pub fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// Parse the source of a `Makefile.am`.
+pub fn parse_automake(src: &str) -> AutomakeFile {
+    let (nodes, top_ids) = AutomakeNodeParser::new(Lexer::new(src.chars()))
+        .try_parse_all()
+        .unwrap();
+    AutomakeFile { nodes, top_ids }
+}
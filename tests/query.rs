@@ -0,0 +1,149 @@
+#![deny(rust_2018_idioms)]
+use autotools_parser::ast::node::{AutoconfPool, NodeId};
+use autotools_parser::lexer::Lexer;
+use autotools_parser::parse::autoconf::NodeParser;
+use autotools_parser::query::{Element, Query, QueryError};
+
+fn parse_pool(src: &str) -> (AutoconfPool, Vec<NodeId>) {
+    let (nodes, top_ids) = NodeParser::<_, ()>::new(Lexer::new(src.chars()))
+        .try_parse_all()
+        .unwrap();
+    (AutoconfPool::new(nodes, None), top_ids)
+}
+
+/// The kind and the `name` or `text` of each match.
+fn find(src: &str, query: &str) -> Vec<(&'static str, String)> {
+    let (pool, top_ids) = parse_pool(src);
+    let query = Query::parse(query).unwrap();
+    query
+        .find(&pool, &top_ids)
+        .into_iter()
+        .map(|m| {
+            let label = m
+                .element
+                .name(&pool)
+                .or_else(|| m.element.text(&pool))
+                .unwrap_or_default();
+            (m.element.kind(), label)
+        })
+        .collect()
+}
+
+const SCRIPT: &str = r#"AC_INIT([demo], [1.0])
+AC_CHECK_HEADERS([zlib.h stdio.h], [], [AC_MSG_ERROR([no zlib])])
+AC_CHECK_HEADERS([unistd.h])
+if test -z "$CC"; then
+  CC=`which gcc`
+elif test -n "$CXX" && test -z "${CC:-}"; then
+  CC=cc
+fi
+AS_IF([test -z "$LIBS"], [LIBS="-lm $LIBS"])
+"#;
+
+#[test]
+fn test_query_child_and_index() {
+    assert_eq!(
+        find(SCRIPT, "macro[name=AC_CHECK_HEADERS] > arg[0] word"),
+        [
+            ("word", "zlib.h".to_string()),
+            ("word", "stdio.h".to_string()),
+            ("word", "unistd.h".to_string()),
+        ]
+    );
+    assert_eq!(
+        find(SCRIPT, "macro[name^=AC_CHECK] > arg[2] macro"),
+        [("macro", "AC_MSG_ERROR".to_string())]
+    );
+}
+
+#[test]
+fn test_query_conditions() {
+    assert_eq!(
+        find(SCRIPT, "if > cond:test(-z) var[name=CC]"),
+        [("var", "CC".to_string()), ("var", "CC".to_string()),]
+    );
+    // A condition of a macro argument is not the condition of an `if`.
+    assert_eq!(
+        find(SCRIPT, "cond[op=-z] var").len(),
+        find(SCRIPT, "if cond[op=-z] var").len()
+    );
+    assert_eq!(
+        find(SCRIPT, "macro[name=AS_IF] cmd[name=test] var"),
+        [("var", "LIBS".to_string())]
+    );
+    assert_eq!(find(SCRIPT, "subst[op=:-]"), [("subst", "CC".to_string())]);
+}
+
+#[test]
+fn test_query_pseudo_classes_and_alternatives() {
+    assert_eq!(
+        find(SCRIPT, "assign[name=CC]:has(subst)"),
+        [("assign", "CC".to_string())]
+    );
+    assert_eq!(
+        find(SCRIPT, "if > assign:not([text=cc])"),
+        [("assign", "CC".to_string())]
+    );
+    assert_eq!(
+        find(SCRIPT, "macro:has(> arg macro)"),
+        [("macro", "AC_CHECK_HEADERS".to_string())]
+    );
+    assert_eq!(
+        find(
+            SCRIPT,
+            "macro[name~='^AC_(INIT|MSG_.*)$'], assign[name=LIBS]"
+        ),
+        [
+            ("macro", "AC_INIT".to_string()),
+            ("macro", "AC_MSG_ERROR".to_string()),
+            ("assign", "LIBS".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_query_returns_nodes() {
+    let (pool, top_ids) = parse_pool(SCRIPT);
+    let matches = Query::parse("if").unwrap().find(&pool, &top_ids);
+    assert_eq!(matches.len(), 1);
+    let node = &pool.nodes[top_ids[3]];
+    assert_eq!(matches[0].node, top_ids[3]);
+    assert_eq!(
+        matches[0].span,
+        Some((node.range_start().unwrap(), node.range_end().unwrap()))
+    );
+    assert!(matches!(matches[0].element, Element::Command(_)));
+}
+
+#[test]
+fn test_query_errors() {
+    assert!(matches!(Query::parse(""), Err(QueryError::UnexpectedEnd)));
+    assert!(matches!(
+        Query::parse("macro >"),
+        Err(QueryError::UnexpectedEnd)
+    ));
+    assert!(matches!(
+        Query::parse("macro[name=AC_INIT"),
+        Err(QueryError::UnexpectedEnd)
+    ));
+    assert!(matches!(
+        Query::parse("macro ) arg"),
+        Err(QueryError::UnexpectedChar(6, ')'))
+    ));
+    assert!(matches!(
+        Query::parse("command"),
+        Err(QueryError::UnknownKind(kind)) if kind == "command"
+    ));
+    assert!(matches!(
+        Query::parse("macro[args=1]"),
+        Err(QueryError::UnknownAttribute(attr)) if attr == "args"
+    ));
+    assert!(matches!(
+        Query::parse("macro:first"),
+        Err(QueryError::UnknownPseudoClass(name)) if name == "first"
+    ));
+    assert!(matches!(
+        Query::parse("macro[name~=(]"),
+        Err(QueryError::Regex(..))
+    ));
+}
@@ -0,0 +1,133 @@
+#![deny(rust_2018_idioms)]
+use autotools_parser::analysis::rules::{Extension, ExtensionKind, RuleGraph};
+use autotools_parser::lexer::Lexer;
+use autotools_parser::parse::automake::AutomakeNodeParser;
+use autotools_parser::project::AutomakeFile;
+
+fn graph(src: &str) -> RuleGraph {
+    let (nodes, top_ids) = AutomakeNodeParser::new(Lexer::new(src.chars()))
+        .try_parse_all()
+        .unwrap();
+    RuleGraph::new(&AutomakeFile { nodes, top_ids })
+}
+
+#[test]
+fn test_explicit_rules() {
+    let g = graph(
+        "OBJS = a.o b.o
+prog: $(OBJS)
+\tcc -o $@ $(OBJS)
+$(OBJS): config.h
+if DEBUG
+prog: debug.o
+endif
+gen.h:: gen.sh
+",
+    );
+    assert_eq!(g.prerequisites("prog"), vec!["a.o", "b.o", "debug.o"]);
+    assert_eq!(g.prerequisites("a.o"), vec!["config.h"]);
+    let prog = g.target("prog").unwrap();
+    assert_eq!(prog.recipe.len(), 1);
+    assert_eq!(
+        prog.conds.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+        vec!["TRUE", "DEBUG_TRUE"]
+    );
+    let gen = g.target("gen.h").unwrap();
+    assert!(gen.double_colon);
+    assert_eq!(gen.prerequisites, vec!["gen.sh"]);
+    let mut dependents = g.dependents("config.h");
+    dependents.sort_unstable();
+    assert_eq!(dependents, vec!["a.o", "b.o"]);
+}
+
+#[test]
+fn test_inference_rules() {
+    let g = graph(
+        "SUFFIXES = .y
+.y.c:
+\tbison -o $@ $<
+.c.o:
+\tcc -c $<
+%.html: %.md
+\tpandoc $< > $@
+prog: main.o parse.o
+doc: index.html
+index.html: index.md
+",
+    );
+    assert_eq!(g.suffixes(), &[".y", ".c", ".o"]);
+    let rules = g.inference_rules();
+    assert_eq!(rules.len(), 3);
+    assert_eq!(rules[0].target, "%.c");
+    assert_eq!(rules[0].prerequisites, vec!["%.y"]);
+    assert!(rules[0].suffix_rule);
+    assert!(!rules[2].suffix_rule);
+    assert_eq!(rules[1].apply("main.o"), Some(vec!["main.c".to_string()]));
+    assert!(g.target(".c.o").is_none());
+
+    // Inferred edges are added where the prerequisites are mentioned.
+    assert_eq!(g.prerequisites("index.html"), vec!["index.md"]);
+    assert!(g.prerequisites("main.o").is_empty());
+
+    // Inference rules are chained to find what rebuilds.
+    let rebuilt = g.rebuilt_by("parse.y");
+    assert_eq!(
+        rebuilt.into_iter().collect::<Vec<_>>(),
+        vec!["parse.c", "parse.o", "prog"]
+    );
+    assert_eq!(
+        g.rebuilt_by("index.md").into_iter().collect::<Vec<_>>(),
+        vec!["doc", "index.html"]
+    );
+}
+
+#[test]
+fn test_phony_and_extensions() {
+    let g = graph(
+        ".PHONY: docs
+docs:
+\tdoxygen
+clean-local:
+\trm -rf html
+install-data-hook: docs
+\tcp -r html $(DESTDIR)$(docdir)
+check-foo-local:
+",
+    );
+    assert!(g.is_phony("docs"));
+    assert!(g.is_phony("clean-local"));
+    assert!(g.is_phony("clean"));
+    assert!(!g.is_phony("html"));
+    assert_eq!(g.prerequisites("clean"), vec!["clean-local"]);
+    assert_eq!(g.prerequisites("install-data"), vec!["install-data-hook"]);
+    assert_eq!(
+        g.target("install-data-hook").unwrap().extension,
+        Some(Extension {
+            kind: ExtensionKind::Hook,
+            base: "install-data"
+        })
+    );
+    assert!(g.target("check-foo-local").unwrap().extension.is_none());
+    assert!(g.target(".PHONY").is_none());
+    assert_eq!(
+        g.rebuilt_by("docs").into_iter().collect::<Vec<_>>(),
+        vec!["install-data", "install-data-hook"]
+    );
+}
+
+#[test]
+fn test_order_and_cycles() {
+    let g = graph("all: b a\na: c\nb: c\n");
+    assert_eq!(g.topological_order().unwrap(), vec!["c", "a", "b", "all"]);
+    assert!(g.cycles().is_empty());
+
+    let g = graph("all: a\na: b\nb: c\nc: a\nself: self\n");
+    assert_eq!(
+        g.cycles(),
+        vec![
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            vec!["self".to_string()],
+        ]
+    );
+    assert_eq!(g.topological_order().unwrap_err(), vec!["a", "b", "c"]);
+}
@@ -0,0 +1,255 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "serde")]
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use autotools_parser::ast::am::AmLine;
+use autotools_parser::ast::node::{AutoconfPool, Node, POOL_SCHEMA_VERSION};
+use autotools_parser::lexer::Lexer;
+use autotools_parser::m4_macro::{get_macro, M4MacroSignature};
+use autotools_parser::parse::autoconf::NodeParser;
+use autotools_parser::parse::automake::AutomakeNodeParser;
+use autotools_parser::parse::SourcePos;
+use serde_json::{json, Value};
+
+const SCRIPT: &str = r#"AC_INIT([demo], [1.0])
+AC_CHECK_HEADERS([stdio.h], [], [AC_MSG_ERROR([no stdio.h])])
+AC_ARG_ENABLE([x], [AS_HELP_STRING([--enable-x], [x])])
+count=$((count + 1 * 2))
+name=${prefix:-/usr}
+base=${file%.c}
+for f in a b; do
+  echo "$f" > out.txt 2>&1
+done
+while test -n "$x"; do x=`echo`; done
+case $host in
+  *linux*) os=linux ;;
+  *) os=other ;;
+esac
+if test "x$enable_x" = xyes && test -f foo; then
+  CFLAGS="$CFLAGS -DX"
+else
+  ( cd sub; make ) | tee log &
+fi
+echo hi $1 $#
+AC_OUTPUT
+"#;
+
+fn pool(src: &str) -> AutoconfPool {
+    let (nodes, _) = NodeParser::<_, ()>::new(Lexer::new(src.chars()))
+        .try_parse_all()
+        .unwrap();
+    AutoconfPool::new(nodes, None)
+}
+
+fn schema() -> Value {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("schema/autoconf-pool.schema.json");
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+/// Check a value against the keywords of JSON Schema used by the schema,
+/// recording the definitions reached.
+fn validate(
+    root: &Value,
+    schema: &Value,
+    value: &Value,
+    path: &str,
+    seen: &mut BTreeSet<String>,
+) -> Result<(), String> {
+    let fail = |message: String| Err(format!("{}: {}", path, message));
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.strip_prefix("#/$defs/").unwrap();
+        seen.insert(name.to_string());
+        return validate(root, &root["$defs"][name], value, path, seen);
+    }
+    if let Some(expected) = schema.get("type").and_then(Value::as_str) {
+        let ok = match expected {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => return fail(format!("unknown type {}", expected)),
+        };
+        if !ok {
+            return fail(format!("expected {}, got {}", expected, value));
+        }
+    }
+    if let Some(minimum) = schema.get("minimum").and_then(Value::as_i64) {
+        if value.as_i64().is_some_and(|v| v < minimum) {
+            return fail(format!("{} is less than {}", value, minimum));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            return fail(format!("expected {}, got {}", expected, value));
+        }
+    }
+    if let Some(names) = schema.get("enum").and_then(Value::as_array) {
+        if !names.contains(value) {
+            return fail(format!("{} is not one of {:?}", value, names));
+        }
+    }
+    if let Some(alternatives) = schema.get("oneOf").and_then(Value::as_array) {
+        let matches = alternatives
+            .iter()
+            .filter(|alt| validate(root, alt, value, path, &mut BTreeSet::new()).is_ok())
+            .collect::<Vec<_>>();
+        if matches.len() != 1 {
+            return fail(format!("{} matches {} alternatives", value, matches.len()));
+        }
+        validate(root, matches[0], value, path, seen)?;
+    }
+    if let Some(object) = value.as_object() {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for name in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if !object.contains_key(name.as_str().unwrap()) {
+                return fail(format!("missing {}", name));
+            }
+        }
+        for (name, field) in object {
+            let field_path = format!("{}.{}", path, name);
+            match properties.and_then(|p| p.get(name)) {
+                Some(property) => validate(root, property, field, &field_path, seen)?,
+                None if schema.get("additionalProperties") == Some(&json!(false)) => {
+                    return fail(format!("unexpected {}", name))
+                }
+                None => {}
+            }
+        }
+    }
+    if let Some(items) = value.as_array() {
+        let length = items.len() as u64;
+        if schema["minItems"].as_u64().is_some_and(|min| length < min)
+            || schema["maxItems"].as_u64().is_some_and(|max| length > max)
+        {
+            return fail(format!("unexpected length {}", length));
+        }
+        let prefix = schema
+            .get("prefixItems")
+            .and_then(Value::as_array)
+            .map_or(&[][..], Vec::as_slice);
+        for (i, item) in items.iter().enumerate() {
+            let item_schema = prefix.get(i).or(schema.get("items"));
+            if let Some(item_schema) = item_schema {
+                validate(root, item_schema, item, &format!("{}[{}]", path, i), seen)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_pool_matches_schema() {
+    let schema = schema();
+    let value = serde_json::to_value(pool(SCRIPT)).unwrap();
+    assert_eq!(value["version"], json!(POOL_SCHEMA_VERSION));
+    let mut seen = BTreeSet::new();
+    validate(&schema, &schema, &value, "$", &mut seen).unwrap();
+    for name in [
+        "Arithmetic",
+        "Condition",
+        "GuardBodyPair",
+        "M4Argument",
+        "M4Macro",
+        "Operator",
+        "ParameterSubstitution",
+        "PatternBodyPair",
+        "Redirect",
+        "ShellCommand",
+        "SideEffect",
+        "Var",
+    ] {
+        assert!(seen.contains(name), "{} is not covered", name);
+    }
+}
+
+#[test]
+fn test_pool_node_layout() {
+    let value = serde_json::to_value(pool("AC_INIT([demo], [1.0])\nx=1\n")).unwrap();
+    assert_eq!(
+        value["nodes"][1],
+        json!({
+            "id": 1,
+            "comment": null,
+            "range": [[2, 3]],
+            "cmd": {"Shell": {"Assignment": ["x", {"Single": {"Shell": {"Literal": "1"}}}]}},
+            "info": null,
+        })
+    );
+    assert_eq!(value["nodes"][0]["cmd"]["Macro"]["name"], json!("AC_INIT"));
+    assert_eq!(
+        value["nodes"][0]["cmd"]["Macro"]["args"][0],
+        json!({"Literal": "demo"})
+    );
+}
+
+#[test]
+fn test_pool_round_trip() {
+    let original = pool(SCRIPT);
+    let json = serde_json::to_string(&original).unwrap();
+    let pool: AutoconfPool = serde_json::from_str(&json).unwrap();
+    assert_eq!(pool.num_nodes(), original.num_nodes());
+    for (id, node) in &original.nodes {
+        assert_eq!(pool.nodes[id].cmd, node.cmd);
+        assert_eq!(pool.nodes[id].range, node.range);
+        assert_eq!(pool.nodes[id].comment, node.comment);
+    }
+}
+
+#[test]
+fn test_pool_version_mismatch() {
+    let err = serde_json::from_str::<AutoconfPool>(r#"{"version": 0, "nodes": []}"#)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.starts_with("unsupported schema version 0, expected 1"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_automake_round_trip() {
+    let src =
+        "if DEBUG\nAM_CFLAGS = -O0\nendif\nbin_PROGRAMS = demo\ndemo_SOURCES = $(srcdir)/main.c\n";
+    let (nodes, _) = AutomakeNodeParser::new(Lexer::new(src.chars()))
+        .try_parse_all()
+        .unwrap();
+    for (_, node) in &nodes {
+        let json = serde_json::to_string(node).unwrap();
+        let back: Node<AmLine, ()> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.cmd, node.cmd);
+    }
+}
+
+#[test]
+fn test_signature_export_functions() {
+    let (_, signature, _) = get_macro("AC_CHECK_LIB").unwrap();
+    let value = serde_json::to_value(signature).unwrap();
+    assert_eq!(value["arg_types"][0], json!({"Library": true}));
+    assert_eq!(value["arg_types"][2], json!("Cmds"));
+    let back: M4MacroSignature = serde_json::from_value(value).unwrap();
+    assert_eq!(
+        format!("{:?}", back.arg_types),
+        format!("{:?}", signature.arg_types)
+    );
+}
+
+#[test]
+fn test_source_pos() {
+    let pos = SourcePos {
+        byte: 3,
+        line: 2,
+        col: 1,
+    };
+    let value = serde_json::to_value(pos).unwrap();
+    assert_eq!(value, json!({"byte": 3, "line": 2, "col": 1}));
+    assert_eq!(serde_json::from_value::<SourcePos>(value).unwrap(), pos);
+}
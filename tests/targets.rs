@@ -0,0 +1,126 @@
+#![deny(rust_2018_idioms)]
+use std::path::Path;
+
+use autotools_parser::analysis::targets::{canonicalize, collect_targets, Primary};
+use autotools_parser::analysis::CondSet;
+use autotools_parser::project::Project;
+
+mod project_support;
+use project_support::*;
+
+fn literals(words: &[autotools_parser::analysis::CondWord]) -> Vec<(String, String)> {
+    words
+        .iter()
+        .map(|w| (w.cond.to_string(), w.word.as_literal().unwrap()))
+        .collect()
+}
+
+#[test]
+fn test_canonicalize() {
+    assert_eq!(canonicalize("libfoo.la"), "libfoo_la");
+    assert_eq!(canonicalize("sub/libc++.a"), "sub_libc___a");
+    assert_eq!(canonicalize("@FOO@-bar"), "@FOO@_bar");
+}
+
+#[test]
+fn test_targets_from_fixture() {
+    let project =
+        Project::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/demo")).unwrap();
+    let src = project.find("src/Makefile.am").unwrap();
+    let targets = collect_targets(project.file(src).as_automake().unwrap());
+    assert_eq!(targets.len(), 1);
+    let demo = &targets[0];
+    assert_eq!(demo.name, "demo");
+    assert_eq!(demo.primary, Primary::Programs);
+    assert_eq!(demo.dir, "bin");
+    assert!(!demo.default_sources);
+    assert_eq!(
+        literals(&demo.sources),
+        vec![
+            ("TRUE".to_string(), "main.c".to_string()),
+            ("TRUE".to_string(), "util.c".to_string()),
+            ("TRUE".to_string(), "util.h".to_string()),
+            ("ENABLE_X_TRUE".to_string(), "x.c".to_string()),
+            ("ENABLE_X_FALSE".to_string(), "nox.c".to_string()),
+        ]
+    );
+    assert_eq!(demo.ldadd.as_ref().unwrap().len(), 2);
+    assert_eq!(demo.cppflags.as_ref().unwrap().len(), 2);
+    assert_eq!(demo.cflags, None);
+}
+
+#[test]
+fn test_library_targets() {
+    let am = parse_automake(
+        "lib_LTLIBRARIES = libfoo.la
+nobase_pkglib_LIBRARIES = sub/libbar.a
+libfoo_la_SOURCES = foo.c
+nodist_libfoo_la_SOURCES = gen.c
+libfoo_la_LIBADD = -lm
+if WITH_BAZ
+noinst_PROGRAMS = baz
+endif
+EXTRA_PROGRAMS = qux
+",
+    );
+    let targets = collect_targets(&am);
+    let names = targets.iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["libfoo.la", "sub/libbar.a", "baz", "qux"]);
+
+    let libfoo = &targets[0];
+    assert_eq!(libfoo.primary, Primary::LtLibraries);
+    assert_eq!(libfoo.canonical, "libfoo_la");
+    assert_eq!(literals(&libfoo.nodist_sources)[0].1, "gen.c");
+    assert_eq!(literals(libfoo.ldadd.as_ref().unwrap())[0].1, "-lm");
+
+    let libbar = &targets[1];
+    assert!(libbar.nobase);
+    assert_eq!(libbar.dir, "pkglib");
+    assert!(libbar.default_sources);
+    assert_eq!(literals(&libbar.sources)[0].1, "sub/libbar.c");
+
+    let baz = &targets[2];
+    assert_eq!(baz.dir, "noinst");
+    assert_eq!(
+        baz.conds,
+        vec![CondSet::always().with("WITH_BAZ", true).unwrap()]
+    );
+    assert_eq!(literals(&baz.sources)[0].1, "baz.c");
+    assert_eq!(targets[3].dir, "EXTRA");
+}
+
+#[test]
+fn test_dist_sources() {
+    let am = parse_automake(
+        "bin_PROGRAMS = foo bar baz
+foo_SOURCES = main.c
+dist_foo_SOURCES = util.c util.h
+dist_bar_SOURCES = bar.c
+nodist_baz_SOURCES = gen.c
+",
+    );
+    let targets = collect_targets(&am);
+    let sources = |i: usize| {
+        literals(&targets[i].sources)
+            .into_iter()
+            .map(|(_, source)| source)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(sources(0), vec!["main.c", "util.c", "util.h"]);
+    assert!(!targets[1].default_sources);
+    assert_eq!(sources(1), vec!["bar.c"]);
+    // Only the sources which are not distributed are built.
+    assert!(!targets[2].default_sources);
+    assert!(targets[2].sources.is_empty());
+}
+
+#[test]
+fn test_cond_set() {
+    let x = CondSet::always().with("X", true).unwrap();
+    let x_not_y = x.with("Y", false).unwrap();
+    assert_eq!(x.with("X", false), None);
+    assert!(x.is_implied_by(&x_not_y));
+    assert!(!x_not_y.is_implied_by(&x));
+    assert!(CondSet::always().is_implied_by(&x));
+    assert_eq!(x_not_y.to_string(), "X_TRUE Y_FALSE");
+}
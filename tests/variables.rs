@@ -0,0 +1,266 @@
+#![deny(rust_2018_idioms)]
+use autotools_parser::analysis::variables::{CondValues, Evaluator};
+use autotools_parser::analysis::CondSet;
+
+mod project_support;
+use project_support::*;
+
+fn cond(terms: &[(&str, bool)]) -> CondSet {
+    terms.iter().fold(CondSet::always(), |acc, (var, value)| {
+        acc.with(var, *value).unwrap()
+    })
+}
+
+fn values(entries: &[(CondSet, &[&str])]) -> CondValues {
+    entries
+        .iter()
+        .map(|(cond, words)| (cond.clone(), words.iter().map(|w| w.to_string()).collect()))
+        .collect()
+}
+
+#[test]
+fn test_eval_unconditional() {
+    let am = parse_automake(
+        "A = a1 a2
+B = $(A) b ${A}x
+C = pre-$(UNDEFINED) @SUBST@
+",
+    );
+    let eval = Evaluator::new(&am);
+    assert_eq!(
+        eval.eval("B"),
+        Some(values(&[(
+            CondSet::always(),
+            &["a1", "a2", "b", "a1", "a2x"]
+        )]))
+    );
+    assert_eq!(
+        eval.eval("C"),
+        Some(values(&[(
+            CondSet::always(),
+            &["pre-$(UNDEFINED)", "@SUBST@"]
+        )]))
+    );
+    assert_eq!(eval.eval("D"), None);
+    assert_eq!(eval.variables(), &["A", "B", "C"]);
+}
+
+#[test]
+fn test_eval_substitute() {
+    let am = parse_automake("C = $(top_srcdir)/include @LIBS@\n");
+    let mut eval = Evaluator::new(&am);
+    eval.substitute("top_srcdir", "..");
+    eval.substitute("LIBS", "-lm");
+    assert_eq!(
+        eval.eval("C"),
+        Some(values(&[(CondSet::always(), &["../include", "-lm"])]))
+    );
+}
+
+#[test]
+fn test_eval_lazy_and_instant() {
+    let am = parse_automake(
+        "X = one
+LAZY = $(X)
+INSTANT := $(X)
+X = two
+INSTANT += $(X)
+LAZY += $(X)
+",
+    );
+    let eval = Evaluator::new(&am);
+    assert_eq!(
+        eval.eval("LAZY"),
+        Some(values(&[(CondSet::always(), &["two", "two"])]))
+    );
+    assert_eq!(
+        eval.eval("INSTANT"),
+        Some(values(&[(CondSet::always(), &["one", "two"])]))
+    );
+}
+
+#[test]
+fn test_eval_conditionals() {
+    let am = parse_automake(
+        "SRCS = main.c
+if ENABLE_X
+SRCS += x.c
+if WIN32
+SRCS += win.c
+endif
+else
+SRCS += nox.c
+endif
+ALL = $(SRCS) $(EXTRA)
+if DEBUG
+EXTRA = debug.c
+endif
+",
+    );
+    let eval = Evaluator::new(&am);
+    assert_eq!(
+        eval.eval("SRCS"),
+        Some(values(&[
+            (cond(&[("ENABLE_X", false)]), &["main.c", "nox.c"]),
+            (
+                cond(&[("ENABLE_X", true), ("WIN32", false)]),
+                &["main.c", "x.c"]
+            ),
+            (
+                cond(&[("ENABLE_X", true), ("WIN32", true)]),
+                &["main.c", "x.c", "win.c"]
+            ),
+        ]))
+    );
+    let all = eval.eval("ALL").unwrap();
+    assert_eq!(all.len(), 6);
+    assert_eq!(
+        all[&cond(&[("DEBUG", true), ("ENABLE_X", false)])],
+        vec!["main.c", "nox.c", "debug.c"]
+    );
+}
+
+#[test]
+fn test_eval_make_conditionals() {
+    let am = parse_automake(
+        "ifeq (a,b)
+X = 1
+else
+X = 2
+endif
+ifndef DEBUG
+Y = release
+endif
+",
+    );
+    let eval = Evaluator::new(&am);
+    assert_eq!(
+        eval.eval("X"),
+        Some(values(&[
+            (cond(&[("ifeq(a,b)", true)]), &["1"]),
+            (cond(&[("ifeq(a,b)", false)]), &["2"]),
+        ]))
+    );
+    assert_eq!(
+        eval.eval("Y"),
+        Some(values(&[
+            (cond(&[("ifdef(DEBUG)", false)]), &["release"]),
+            (cond(&[("ifdef(DEBUG)", true)]), &[]),
+        ]))
+    );
+}
+
+#[test]
+fn test_eval_recursive_variable() {
+    let am = parse_automake("A = $(A) a\n");
+    let eval = Evaluator::new(&am);
+    assert_eq!(eval.eval("A"), Some(values(&[(CondSet::always(), &["a"])])));
+}
+
+#[test]
+fn test_eval_make_assign_ops() {
+    let am = parse_automake(
+        "A ?= a
+A ?= b
+B = b
+B ?= c
+C != echo c
+",
+    );
+    let eval = Evaluator::new(&am);
+    assert_eq!(eval.eval("A"), Some(values(&[(CondSet::always(), &["a"])])));
+    assert_eq!(eval.eval("B"), Some(values(&[(CondSet::always(), &["b"])])));
+    assert_eq!(
+        eval.eval("C"),
+        Some(values(&[(CondSet::always(), &["$(shell", "echo", "c)"])]))
+    );
+}
+
+#[test]
+fn test_eval_functions() {
+    let am = parse_automake(
+        "SRCS = main.c util.c
+if ENABLE_X
+SRCS += x.c
+endif
+OBJS = $(SRCS:.c=.o)
+DEPS = $(patsubst %.c,.deps/%.Po,$(filter-out main.c,$(SRCS)))
+prog_SOURCES = a.c
+NAME = prog
+SOURCES = $($(NAME)_SOURCES) $(addprefix src/,$(notdir lib/b.c)) $(words $(SRCS))
+FILES = $(wildcard *.h)
+SUFFIXED = $(subst ,.c,main) $(subst a,o,bar)
+",
+    );
+    let eval = Evaluator::new(&am);
+    assert_eq!(
+        eval.eval("OBJS"),
+        Some(values(&[
+            (cond(&[("ENABLE_X", false)]), &["main.o", "util.o"]),
+            (cond(&[("ENABLE_X", true)]), &["main.o", "util.o", "x.o"]),
+        ]))
+    );
+    assert_eq!(
+        eval.eval("DEPS"),
+        Some(values(&[
+            (cond(&[("ENABLE_X", false)]), &[".deps/util.Po"]),
+            (
+                cond(&[("ENABLE_X", true)]),
+                &[".deps/util.Po", ".deps/x.Po"]
+            ),
+        ]))
+    );
+    assert_eq!(
+        eval.eval("SOURCES"),
+        Some(values(&[
+            (cond(&[("ENABLE_X", false)]), &["a.c", "src/b.c", "2"]),
+            (cond(&[("ENABLE_X", true)]), &["a.c", "src/b.c", "3"]),
+        ]))
+    );
+    assert_eq!(
+        eval.eval("FILES"),
+        Some(values(&[(CondSet::always(), &["$(wildcard", "*.h)"])]))
+    );
+    assert_eq!(
+        eval.eval("SUFFIXED"),
+        Some(values(&[(CondSet::always(), &["main.c", "bor"])]))
+    );
+}
+
+#[test]
+fn test_eval_functions_of_unknown_values() {
+    let am = parse_automake(
+        "IF = $(if $(UNDEF),yes,no)
+FILTER = $(filter %.c,a.c $(UNDEF))
+WORDS = $(words $(UNDEF) b)
+OBJS = $(UNDEF:.c=.o)
+LIBS = $(strip @LIBS@)
+KNOWN = $(if $(EMPTY),yes,no) $(words @LIBS@)
+EMPTY =
+",
+    );
+    let mut eval = Evaluator::new(&am);
+    let value =
+        |eval: &Evaluator<'_>, name: &str| eval.eval(name).unwrap()[&CondSet::always()].join(" ");
+    assert_eq!(value(&eval, "IF"), "$(if $(UNDEF),yes,no)");
+    assert_eq!(value(&eval, "FILTER"), "$(filter %.c,a.c $(UNDEF))");
+    assert_eq!(value(&eval, "WORDS"), "$(words $(UNDEF) b)");
+    assert_eq!(value(&eval, "OBJS"), "$(UNDEF:.c=.o)");
+    assert_eq!(value(&eval, "LIBS"), "$(strip @LIBS@)");
+    eval.substitute("LIBS", "-lm -lz");
+    assert_eq!(value(&eval, "LIBS"), "-lm -lz");
+    assert_eq!(value(&eval, "KNOWN"), "no 2");
+}
+
+#[test]
+fn test_eval_command_substitutions() {
+    let am = parse_automake("VERSION = `git describe` $${TAG:-dev}\n");
+    let eval = Evaluator::new(&am);
+    assert_eq!(
+        eval.eval("VERSION"),
+        Some(values(&[(
+            CondSet::always(),
+            &["$(git", "describe)", "${TAG:-dev}"]
+        )]))
+    );
+}
@@ -0,0 +1,293 @@
+#![deny(rust_2018_idioms)]
+use autotools_parser::ast::am::{AmVar, MakeParameter, MayAm};
+use autotools_parser::ast::minimal::{self, MinimalCommand, WordFragment};
+use autotools_parser::ast::node::{self, AutoconfPool, DisplayNode, M4Macro, NodeId};
+use autotools_parser::ast::visit::{am, top_level};
+use autotools_parser::ast::visit::{minimal as minimal_visit, node as node_visit};
+use autotools_parser::ast::{MayM4, Parameter, SimpleWord, TopLevelCommand};
+use autotools_parser::lexer::Lexer;
+use autotools_parser::parse::autoconf::{DefaultParser, MinimalParser, NodeParser};
+use autotools_parser::parse::automake::AutomakeNodeParser;
+
+fn parse_pool(src: &str) -> (AutoconfPool, Vec<NodeId>) {
+    let (nodes, top_ids) = NodeParser::<_, ()>::new(Lexer::new(src.chars()))
+        .try_parse_all()
+        .unwrap();
+    (AutoconfPool::new(nodes, None), top_ids)
+}
+
+/// Collects the names of macro calls and of read variables.
+#[derive(Default)]
+struct Names {
+    macros: Vec<String>,
+    vars: Vec<String>,
+}
+
+impl<'a> node_visit::Visit<'a> for Names {
+    fn visit_macro(&mut self, pool: &'a AutoconfPool, m4_macro: &'a M4Macro) {
+        self.macros.push(m4_macro.name.clone());
+        node_visit::walk_macro(self, pool, m4_macro);
+    }
+
+    fn visit_shell_fragment(
+        &mut self,
+        pool: &'a AutoconfPool,
+        fragment: &'a node::WordFragment<node::AcWord>,
+    ) {
+        if let WordFragment::Param(Parameter::Var(name)) = fragment {
+            self.vars.push(name.clone());
+        }
+        node_visit::walk_shell_fragment(self, pool, fragment);
+    }
+}
+
+#[test]
+fn test_visit_pool_enters_macro_arguments_and_words() {
+    let src = r#"AC_CHECK_HEADERS([zlib.h], [LIBS="$LIBS -lz"], [AC_MSG_ERROR([no zlib])])
+if test -n "$CC"; then
+  version=`$CC --version`
+fi
+"#;
+    let (pool, top_ids) = parse_pool(src);
+    let mut names = Names::default();
+    for id in top_ids {
+        node_visit::Visit::visit_node(&mut names, &pool, id);
+    }
+    assert_eq!(names.macros, ["AC_CHECK_HEADERS", "AC_MSG_ERROR"]);
+    assert_eq!(names.vars, ["LIBS", "CC", "CC"]);
+}
+
+/// Renames every read of a variable.
+struct Rename(&'static str, &'static str);
+
+impl node_visit::VisitMut for Rename {
+    fn visit_shell_fragment_mut(
+        &mut self,
+        pool: &mut AutoconfPool,
+        fragment: &mut node::WordFragment<node::AcWord>,
+    ) {
+        if let WordFragment::Param(Parameter::Var(name)) = fragment {
+            if name == self.0 {
+                *name = self.1.to_string();
+            }
+        }
+        node_visit::walk_shell_fragment_mut(self, pool, fragment);
+    }
+}
+
+#[test]
+fn test_visit_mut_pool_edits_nested_nodes() {
+    let src = "AS_IF([test -n \"$CC\"], [echo $CC])\n";
+    let (mut pool, top_ids) = parse_pool(src);
+    for id in &top_ids {
+        node_visit::VisitMut::visit_node_mut(&mut Rename("CC", "CXX"), &mut pool, *id);
+    }
+    let printed = pool.display_node(top_ids[0], 0);
+    assert!(printed.contains("CXX"), "{}", printed);
+
+    let (expected, expected_ids) = parse_pool(&src.replace("CC", "CXX"));
+    assert_eq!(printed, expected.display_node(expected_ids[0], 0));
+}
+
+fn parse_minimal(src: &str) -> Vec<MinimalCommand<String>> {
+    MinimalParser::new_with_config(Lexer::new(src.chars()), true)
+        .into_iter()
+        .map(Result::unwrap)
+        .collect()
+}
+
+/// Replaces a literal everywhere, including inside double quotes.
+struct ReplaceLiteral(&'static str, &'static str);
+
+impl minimal_visit::Fold<String> for ReplaceLiteral {
+    fn fold_shell_fragment(
+        &mut self,
+        fragment: WordFragment<String, MinimalCommand<String>, minimal::AcWord<String>>,
+    ) -> WordFragment<String, MinimalCommand<String>, minimal::AcWord<String>> {
+        match fragment {
+            WordFragment::Literal(lit) if lit == self.0 => WordFragment::Literal(self.1.into()),
+            fragment => minimal_visit::fold_shell_fragment(self, fragment),
+        }
+    }
+}
+
+#[test]
+fn test_fold_minimal_rebuilds_commands() {
+    let src = "for f in a b; do\n  AS_IF([test -f $f], [echo \"$f\" | echo])\ndone\n";
+    let folded: Vec<_> = parse_minimal(src)
+        .into_iter()
+        .map(|cmd| minimal_visit::Fold::fold_command(&mut ReplaceLiteral("echo", "printf"), cmd))
+        .collect();
+    assert_eq!(folded, parse_minimal(&src.replace("echo", "printf")));
+}
+
+/// Counts the commands given to macro calls.
+#[derive(Default)]
+struct MacroCommands(usize);
+
+impl<'a> minimal_visit::Visit<'a, String> for MacroCommands {
+    fn visit_argument(
+        &mut self,
+        arg: &'a autotools_parser::m4_macro::M4Argument<
+            MinimalCommand<String>,
+            minimal::AcWord<String>,
+        >,
+    ) {
+        if let autotools_parser::m4_macro::M4Argument::Commands(cmds) = arg {
+            self.0 += cmds.len();
+        }
+        minimal_visit::walk_argument(self, arg);
+    }
+}
+
+#[test]
+fn test_visit_minimal_counts_macro_commands() {
+    let src = "AC_CACHE_VAL([x_cv_y], [AS_IF([true], [x_cv_y=yes; echo], [x_cv_y=no])])\n";
+    let mut count = MacroCommands::default();
+    for cmd in &parse_minimal(src) {
+        minimal_visit::Visit::visit_command(&mut count, cmd);
+    }
+    // The body of AC_CACHE_VAL, then the test and the branches of AS_IF.
+    assert_eq!(count.0, 5);
+}
+
+fn parse_default(src: &str) -> Vec<TopLevelCommand<String>> {
+    DefaultParser::new(Lexer::new(src.chars()))
+        .into_iter()
+        .map(Result::unwrap)
+        .collect()
+}
+
+/// Renames every read of a variable.
+struct RenameParam(&'static str, &'static str);
+
+impl top_level::VisitMut<String> for RenameParam {
+    fn visit_fragment_mut(&mut self, fragment: &mut autotools_parser::ast::DefaultWordFragment) {
+        if let MayM4::Shell(SimpleWord::Param(Parameter::Var(name))) = fragment {
+            if name == self.0 {
+                *name = self.1.to_string();
+            }
+        }
+        top_level::walk_fragment_mut(self, fragment);
+    }
+}
+
+impl top_level::Fold<String> for RenameParam {
+    fn fold_fragment(
+        &mut self,
+        fragment: autotools_parser::ast::DefaultWordFragment,
+    ) -> autotools_parser::ast::DefaultWordFragment {
+        match fragment {
+            MayM4::Shell(SimpleWord::Param(Parameter::Var(name))) if name == self.0 => {
+                MayM4::Shell(SimpleWord::Param(Parameter::Var(self.1.into())))
+            }
+            fragment => top_level::fold_fragment(self, fragment),
+        }
+    }
+}
+
+#[test]
+fn test_visit_mut_and_fold_default_ast() {
+    let src = r#"function foo { echo "$a" > $a.log; }
+case $a in
+  x) AS_IF([test -n "${b:-$a}"], [echo]) ;;
+esac
+b=$(cat $a) && echo $b
+"#;
+    let expected = parse_default(&src.replace("$a", "$z"));
+
+    let mut visited = parse_default(src);
+    for cmd in &mut visited {
+        top_level::VisitMut::visit_command_mut(&mut RenameParam("a", "z"), cmd);
+    }
+    assert_eq!(visited, expected);
+
+    let folded: Vec<_> = parse_default(src)
+        .into_iter()
+        .map(|cmd| top_level::Fold::fold_command(&mut RenameParam("a", "z"), cmd))
+        .collect();
+    assert_eq!(folded, expected);
+}
+
+/// Collects the names of referenced make variables.
+#[derive(Default)]
+struct MakeVars(Vec<String>);
+
+impl<'a> am::Visit<'a> for MakeVars {
+    fn visit_variable(&mut self, nodes: &'a am::AmNodes, var: &'a AmVar) {
+        if let AmVar::Param(MakeParameter::Var(name)) = var {
+            self.0.push(name.clone());
+        }
+        am::walk_variable(self, nodes, var);
+    }
+}
+
+/// Renames every reference to a make variable.
+struct RenameMakeVar(&'static str, &'static str);
+
+impl am::VisitMut for RenameMakeVar {
+    fn visit_fragment_mut(
+        &mut self,
+        nodes: &mut am::AmNodes,
+        fragment: &mut autotools_parser::ast::am::AmWordFragment,
+    ) {
+        if let MayAm::Automake(AmVar::Param(MakeParameter::Var(name))) = fragment {
+            if name == self.0 {
+                *name = self.1.to_string();
+            }
+        }
+        am::walk_fragment_mut(self, nodes, fragment);
+    }
+}
+
+#[test]
+fn test_visit_automake_lines() {
+    let src = "\
+if DEBUG
+AM_CFLAGS = $(DEBUG_FLAGS)
+else
+AM_CFLAGS = $(filter-out -g,$(OPT_FLAGS))
+endif
+foo: $(foo_SOURCES)
+\t$(CC) -o $@ $(foo_SOURCES)
+";
+    let (mut nodes, top_ids) = AutomakeNodeParser::new(Lexer::new(src.chars()))
+        .try_parse_all()
+        .unwrap();
+    let mut vars = MakeVars::default();
+    for id in &top_ids {
+        am::Visit::visit_node(&mut vars, &nodes, *id);
+    }
+    assert_eq!(
+        vars.0,
+        [
+            "DEBUG_FLAGS",
+            "OPT_FLAGS",
+            "foo_SOURCES",
+            "CC",
+            "foo_SOURCES"
+        ]
+    );
+
+    for id in &top_ids {
+        am::VisitMut::visit_node_mut(
+            &mut RenameMakeVar("foo_SOURCES", "bar_SOURCES"),
+            &mut nodes,
+            *id,
+        );
+    }
+    let mut vars = MakeVars::default();
+    for id in &top_ids {
+        am::Visit::visit_node(&mut vars, &nodes, *id);
+    }
+    assert_eq!(
+        vars.0,
+        [
+            "DEBUG_FLAGS",
+            "OPT_FLAGS",
+            "bar_SOURCES",
+            "CC",
+            "bar_SOURCES"
+        ]
+    );
+}